@@ -0,0 +1,52 @@
+//! Finding the neighbors of every chunk, which a cycle of processing does once for each of them,
+//! from the cache built with the directory against working them out from the coordinates every time
+//! Run it with `cargo bench --bench chunk_neighbors`
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use orbiting_sand::physics::fallingsand::data::element_directory::ElementGridDir;
+use orbiting_sand::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+use orbiting_sand::physics::fallingsand::util::vectors::ChunkIjkVector;
+use orbiting_sand::physics::orbits::components::Length;
+
+fn bench_chunk_neighbors(c: &mut Criterion) {
+    let coordinate_dir = CoordinateDirBuilder::new()
+        .cell_radius(Length(1.0))
+        .num_layers(9)
+        .first_num_radial_lines(6)
+        .second_num_concentric_circles(3)
+        .max_concentric_circles_per_chunk(64)
+        .max_radial_lines_per_chunk(64)
+        .build();
+    let element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+    let coords = element_grid_dir.get_coordinate_dir();
+    let chunks: Vec<ChunkIjkVector> = (0..coords.get_num_layers())
+        .flat_map(|i| {
+            let num_tangential_chunks = coords.get_layer_num_tangential_chunkss(i);
+            (0..coords.get_layer_num_concentric_chunks(i)).flat_map(move |j| {
+                (0..num_tangential_chunks).map(move |k| ChunkIjkVector::new(i, j, k))
+            })
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("neighbors of every chunk");
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            for &chunk in &chunks {
+                black_box(element_grid_dir.get_chunk_neighbors(black_box(chunk)));
+            }
+        })
+    });
+    group.bench_function("computed", |b| {
+        b.iter(|| {
+            for &chunk in &chunks {
+                black_box(element_grid_dir.calc_chunk_neighbors(black_box(chunk)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_chunk_neighbors);
+criterion_main!(benches);
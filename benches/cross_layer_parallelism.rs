@@ -0,0 +1,41 @@
+//! Full cycles of a nine layer planet with and without cross layer parallelism
+//! Run it with `cargo bench --bench cross_layer_parallelism`
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use orbiting_sand::physics::fallingsand::data::element_directory::ElementGridDir;
+use orbiting_sand::physics::fallingsand::elements::sand::Sand;
+use orbiting_sand::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+use orbiting_sand::physics::orbits::components::Length;
+
+/// Nine layers half full of sand, the lower layers only a chunk or two wide
+fn sandy_planet(cross_layer: bool) -> ElementGridDir {
+    let coordinate_dir = CoordinateDirBuilder::new()
+        .cell_radius(Length(1.0))
+        .num_layers(9)
+        .first_num_radial_lines(6)
+        .second_num_concentric_circles(3)
+        .max_concentric_circles_per_chunk(64)
+        .max_radial_lines_per_chunk(64)
+        .build();
+    let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+    element_grid_dir.set_cross_layer_parallelism(cross_layer);
+    let radius = element_grid_dir.get_coordinate_dir().get_radius().0;
+    element_grid_dir.fill_settled(&Sand::default(), radius / 2.0);
+    element_grid_dir
+}
+
+fn bench_cross_layer_parallelism(c: &mut Criterion) {
+    let mut group = c.benchmark_group("full cycle");
+    for (name, cross_layer) in [("cross layer", true), ("layer by layer", false)] {
+        let mut element_grid_dir = sandy_planet(cross_layer);
+        group.bench_function(name, |b| {
+            b.iter(|| element_grid_dir.step_full(Duration::from_millis(16)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cross_layer_parallelism);
+criterion_main!(benches);
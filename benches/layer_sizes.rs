@@ -0,0 +1,88 @@
+//! Looking up the size of every layer from the cache against summing up its chunks like it used to,
+//! then converting positions to chunks, which looks them up several times
+//! Run it with `cargo bench --bench layer_sizes`
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use orbiting_sand::physics::fallingsand::mesh::coordinate_directory::{
+    CoordinateDir, CoordinateDirBuilder,
+};
+use orbiting_sand::physics::fallingsand::util::vectors::ChunkIjkVector;
+use orbiting_sand::physics::orbits::components::Length;
+use orbiting_sand::physics::util::vectors::RelXyPoint;
+
+/// Twelve layers, enough for the outer ones to be split into plenty of chunks
+fn coordinate_dir() -> CoordinateDir {
+    CoordinateDirBuilder::new()
+        .cell_radius(Length(1.0))
+        .num_layers(12)
+        .first_num_radial_lines(6)
+        .second_num_concentric_circles(3)
+        .max_concentric_circles_per_chunk(64)
+        .max_radial_lines_per_chunk(64)
+        .build()
+}
+
+/// The radial lines and concentric circles of a layer, added up chunk by chunk
+fn summed_layer_size(coordinate_dir: &CoordinateDir, i: usize) -> (usize, usize) {
+    let radial_lines = (0..coordinate_dir.get_layer_num_tangential_chunkss(i))
+        .map(|k| {
+            coordinate_dir
+                .get_chunk_at_idx(ChunkIjkVector::new(i, 0, k))
+                .get_num_radial_lines()
+        })
+        .sum();
+    let concentric_circles = (0..coordinate_dir.get_layer_num_concentric_chunks(i))
+        .map(|j| {
+            coordinate_dir
+                .get_chunk_at_idx(ChunkIjkVector::new(i, j, 0))
+                .get_num_concentric_circles()
+        })
+        .sum();
+    (radial_lines, concentric_circles)
+}
+
+fn bench_layer_sizes(c: &mut Criterion) {
+    let coordinate_dir = coordinate_dir();
+    let num_layers = coordinate_dir.get_num_layers();
+    let mut group = c.benchmark_group("every layer size");
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            for i in 0..num_layers {
+                black_box(coordinate_dir.get_layer_num_radial_lines(black_box(i)));
+                black_box(coordinate_dir.get_layer_num_concentric_circles(black_box(i)));
+            }
+        })
+    });
+    group.bench_function("summed", |b| {
+        b.iter(|| {
+            for i in 0..num_layers {
+                black_box(summed_layer_size(&coordinate_dir, black_box(i)));
+            }
+        })
+    });
+    group.finish();
+
+    // Points spiralling out through every layer
+    let radius = coordinate_dir.get_layer_end_radius(num_layers - 1);
+    let points: Vec<RelXyPoint> = (0..1000)
+        .map(|n| {
+            let angle = n as f32 * 0.618;
+            let distance = radius * n as f32 / 1000.0;
+            RelXyPoint::new(distance * angle.cos(), distance * angle.sin())
+        })
+        .collect();
+    c.bench_function("position to chunk", |b| {
+        b.iter(|| {
+            for point in &points {
+                if let Ok(cell) = coordinate_dir.rel_pos_to_cell_idx(black_box(*point)) {
+                    black_box(coordinate_dir.cell_idx_to_chunk_idx(cell));
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_layer_sizes);
+criterion_main!(benches);
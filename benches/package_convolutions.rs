@@ -0,0 +1,137 @@
+//! How many allocations a pass of processing makes with the packaging buffers kept between passes,
+//! against throwing them away before every pass, which is how packaging used to allocate
+//! Run it with `cargo bench --bench package_convolutions`, the values are allocations per pass
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use criterion::measurement::{Measurement, ValueFormatter};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use orbiting_sand::physics::fallingsand::data::element_directory::ElementGridDir;
+use orbiting_sand::physics::fallingsand::elements::sand::Sand;
+use orbiting_sand::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+use orbiting_sand::physics::orbits::components::Length;
+
+/// Allocations made so far, from every thread
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting every allocation on the way through
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Measures in allocations rather than time
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> u64 {
+        ALLOCATIONS.load(Ordering::SeqCst)
+    }
+    fn end(&self, start: u64) -> u64 {
+        ALLOCATIONS.load(Ordering::SeqCst) - start
+    }
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        a + b
+    }
+    fn zero(&self) -> u64 {
+        0
+    }
+    fn to_f64(&self, value: &u64) -> f64 {
+        *value as f64
+    }
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationFormatter
+    }
+}
+
+/// Writes allocation counts as they are, there is no unit to scale them into
+struct AllocationFormatter;
+
+impl ValueFormatter for AllocationFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (per, unit) = match *throughput {
+            Throughput::Bytes(bytes) | Throughput::BytesDecimal(bytes) => (bytes, "allocs/byte"),
+            Throughput::Elements(elements) => (elements, "allocs/element"),
+        };
+        for value in values {
+            *value /= per as f64;
+        }
+        unit
+    }
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+/// A nine layer planet half full of sand, so every pass has chunks to package
+fn sandy_planet() -> ElementGridDir {
+    let coordinate_dir = CoordinateDirBuilder::new()
+        .cell_radius(Length(1.0))
+        .num_layers(9)
+        .first_num_radial_lines(6)
+        .second_num_concentric_circles(3)
+        .max_concentric_circles_per_chunk(64)
+        .max_radial_lines_per_chunk(64)
+        .build();
+    let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+    let radius = element_grid_dir.get_coordinate_dir().get_radius().0;
+    element_grid_dir.fill_settled(&Sand::default(), radius / 2.0);
+    element_grid_dir
+}
+
+fn bench_package_convolutions(c: &mut Criterion<Allocations>) {
+    let mut group = c.benchmark_group("allocations per pass");
+    for keep_scratch in [true, false] {
+        let name = if keep_scratch {
+            "scratch kept"
+        } else {
+            "scratch freed"
+        };
+        let mut element_grid_dir = sandy_planet();
+        // A cycle first, so the kept buffers have grown to fit every pass
+        element_grid_dir.step_full(Duration::from_millis(16));
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                if !keep_scratch {
+                    element_grid_dir.free_scratch();
+                }
+                element_grid_dir.step(Duration::from_millis(16));
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_measurement(Allocations);
+    targets = bench_package_convolutions
+}
+criterion_main!(benches);
@@ -5,22 +5,26 @@
 
 use bevy::app::{App, FixedUpdate, Plugin, Update};
 use bevy::asset::{AssetServer, Assets, Handle};
-use bevy::core::{FrameCount, Name};
+use bevy::core::Name;
+use bevy::ecs::change_detection::DetectChanges;
 use bevy::ecs::component::Component;
 
 use bevy::ecs::entity::Entity;
 
 use bevy::gizmos::gizmos::Gizmos;
+use bevy::log::{info, warn};
 
 use bevy::render::color::Color;
 use bevy::render::view::{ViewVisibility, Visibility, VisibilityBundle};
 use bevy_mod_picking::prelude::*;
 
 // use bevy_mod_picking::PickableBundle;
-use bevy::ecs::query::With;
-use bevy::ecs::system::{Commands, Query, Res, ResMut};
+use bevy::ecs::query::{With, Without};
+use bevy::ecs::schedule::IntoSystemConfigs;
+use bevy::ecs::system::{Commands, Query, Res, ResMut, Resource};
 
 use bevy::hierarchy::{BuildChildren, Parent};
+use bevy::input::{keyboard::KeyCode, Input};
 use bevy::math::Vec2;
 
 use bevy::prelude::SpatialBundle;
@@ -31,21 +35,32 @@ use bevy_mod_picking::events::Pointer;
 use bevy_mod_picking::PickableBundle;
 
 use bevy::sprite::{ColorMaterial, MaterialMesh2dBundle};
-use bevy::time::{Fixed, Time};
+use bevy::time::Time;
 
-use bevy::transform::components::Transform;
+use bevy::transform::components::{GlobalTransform, Transform};
 
 use hashbrown::HashMap;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
 
-use crate::gui::camera::{CelestialIdx, OverlayLayer2, OverlayLayer3, SelectCelestial};
+use crate::gui::camera::{CelestialIdx, MainCamera, OverlayLayer2, OverlayLayer3, SelectCelestial};
+use crate::physics::fallingsand::data::ejecta::{self, BoundaryFlux, BuriedCellError, Ejecta};
 use crate::physics::fallingsand::data::element_directory::{ElementGridDir, Textures};
+use crate::physics::fallingsand::data::replay::{Recorder, Recording};
+use crate::physics::fallingsand::elements::element::Element;
+use crate::physics::fallingsand::elements::registry::ElementRegistry;
 
 use crate::physics::fallingsand::mesh::chunk_coords::{VertexMode, VertexSettings};
+use crate::physics::fallingsand::util::heatmap::HeatGradient;
 use crate::physics::fallingsand::util::mesh::{GizmoDrawableGrid, GizmoDrawableLoop};
-use crate::physics::fallingsand::util::vectors::ChunkIjkVector;
-use crate::physics::orbits::components::{GravitationalField, Mass, Velocity};
-use crate::physics::util::clock::Clock;
-use crate::physics::PHYSICS_FRAME_RATE;
+use crate::physics::fallingsand::util::palette::ElementPalette;
+use crate::physics::fallingsand::util::pressuremap::PressureOverlay;
+use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector};
+use crate::physics::orbits::components::{
+    AngularVelocity, CenterOfMassOffset, GravitationalField, Mass, SurfaceGravity, Velocity,
+};
+use crate::physics::util::vectors::RelXyPoint;
 
 /// Identifies the mesh which draws the celestials chunk outlines
 #[derive(Component)]
@@ -55,26 +70,96 @@ pub struct CelestialOutline;
 #[derive(Component)]
 pub struct CelestialWireframe;
 
+/// The pass (0..9) of the nine pass convolution that the chunk is processed in
+/// Lives alongside the chunk outline so it can be tinted by pass
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ChunkPass(pub usize);
+
+/// Debug overlays for the celestial meshes
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct MeshDebugSettings {
+    /// Tint each chunk outline by the pass it is processed in
+    /// Makes the multithreading partition visible. Toggle with `P`
+    pub show_pass_coloring: bool,
+    /// Color the cells by their temperature instead of their element
+    /// Uses the [HeatGradient] resource. Toggle with `H`
+    pub show_heatmap: bool,
+    /// Color the cells by the pressure on them, with contour lines, over anything else
+    /// Uses the [PressureOverlay] resource. Toggle with `O`
+    pub show_pressure: bool,
+}
+
+/// Where `R` writes the recording of the focused celestial, see [CelestialDataPlugin::toggle_recording_system]
+const RECORDING_PATH: &str = "recording.orec";
+
+impl MeshDebugSettings {
+    /// A distinct color for each of the nine passes
+    pub fn pass_color(pass: usize) -> Color {
+        Color::hsl(pass as f32 * 360.0 / 9.0, 1.0, 0.5)
+    }
+}
+
+/// What crossed the top of every celestial's grid during the last physics frame, added up
+/// Elements should only leave by being launched, so mass that went missing any other way
+/// turns up in its [BoundaryFlux::unexplained_mass]
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct SpaceBoundaryFlux(pub BoundaryFlux);
+
 /// A component that represents a chunk by its index in the directory
 #[derive(Component, Debug, Clone, Copy)]
 pub struct CelestialChunkIdk(ChunkIjkVector);
 
+/// A loose body orbiting on its own, made of a single element that was thrown off a celestial
+#[derive(Component)]
+pub struct Asteroid(pub Box<dyn Element>);
+
+/// Marks the celestial that lights the others, see [CelestialDataPlugin::sunlight_system]
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Sun;
+
 /// Put this alongside the mesh that represents the falling sand itself
 #[derive(Component, Debug, Clone, Copy)]
 pub struct FallingSandMaterial;
 
+/// How many straight lines go around a celestial when drawing its glow, see [CelestialDataPlugin::draw_emission_system]
+const EMISSION_SEGMENTS: usize = 64;
+
 /// A plugin that adds the CelestialData system
 pub struct CelestialDataPlugin;
 
 impl Plugin for CelestialDataPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, Self::process_system);
-        app.insert_resource(Time::<Fixed>::from_seconds(1.0 / PHYSICS_FRAME_RATE));
+        app.add_systems(
+            FixedUpdate,
+            (
+                Self::sunlight_system.before(Self::process_system),
+                Self::ejecta_system.before(Self::process_system),
+                Self::accretion_system.before(Self::process_system),
+                Self::process_system,
+                Self::center_of_mass_system.after(Self::process_system),
+                Self::boundary_flux_system
+                    .after(Self::ejecta_system)
+                    .after(Self::accretion_system),
+            ),
+        );
+        app.init_resource::<MeshDebugSettings>();
+        app.init_resource::<SpaceBoundaryFlux>();
+        app.init_resource::<HeatGradient>();
+        app.init_resource::<PressureOverlay>();
+        app.init_resource::<ElementRegistry>();
+        app.init_resource::<ElementPalette>();
         app.add_systems(
             Update,
             (
                 CelestialDataPlugin::draw_wireframe_system,
                 CelestialDataPlugin::draw_outline_system,
+                CelestialDataPlugin::toggle_pass_coloring_system,
+                CelestialDataPlugin::toggle_heatmap_system,
+                CelestialDataPlugin::toggle_pressure_system,
+                CelestialDataPlugin::toggle_recording_system,
+                CelestialDataPlugin::draw_pass_coloring_system,
+                CelestialDataPlugin::draw_emission_system,
+                CelestialDataPlugin::coarse_heat_system,
             ),
         );
         app.add_event::<SelectCelestial>();
@@ -86,27 +171,169 @@ impl Plugin for CelestialDataPlugin {
 pub struct CelestialData {
     /// The elements in this celestial
     pub element_grid_dir: ElementGridDir,
+    /// Cells thrown this frame and how fast, see [Self::launch]
+    launches: Vec<(IjkVector, Velocity)>,
+    /// Writes down every change made through here while recording, see [Self::start_recording]
+    recorder: Option<Recorder>,
+    /// Recording was asked for partway through a cycle, it starts with the next one
+    recording_requested: bool,
 }
 
 impl CelestialData {
     /// Creates a new CelestialData
     pub fn new(mut element_grid_dir: ElementGridDir) -> Self {
         element_grid_dir.recalculate_everything();
-        Self { element_grid_dir }
+        Self {
+            element_grid_dir,
+            launches: Vec::new(),
+            recorder: None,
+            recording_requested: false,
+        }
     }
 
-    /// Something to call every frame
+    /// Starts writing down everything done to the directory through here, see [Recorder]
+    /// A recording can only start between full cycles, so it waits for the first frame that starts one
+    pub fn start_recording(&mut self) {
+        if self.recorder.is_none() {
+            self.recording_requested = true;
+        }
+    }
+
+    /// Whether changes are being written down, or will be from the next cycle
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some() || self.recording_requested
+    }
+
+    /// Stops recording, None if it never got going
+    pub fn finish_recording(&mut self) -> Option<Recording> {
+        self.recording_requested = false;
+        let recorder = self.recorder.take()?;
+        Some(recorder.finish(&self.element_grid_dir))
+    }
+
+    /// Starts the recorder if one was asked for and a cycle is about to start
+    fn start_requested_recording(&mut self) {
+        if !self.recording_requested {
+            return;
+        }
+        if let Ok(recorder) = Recorder::start(&mut self.element_grid_dir) {
+            self.recorder = Some(recorder);
+            self.recording_requested = false;
+        }
+    }
+
+    /// Paints a cell over with `element`, writing it down by name if recording
+    /// While recording, an element `registry` can't make by name isn't painted at all, since a
+    /// replay couldn't paint it either
+    pub fn set_element(
+        &mut self,
+        cell: IjkVector,
+        element: Box<dyn Element>,
+        registry: &ElementRegistry,
+    ) {
+        if let Some(recorder) = &mut self.recorder {
+            match recorder.set_element(
+                &mut self.element_grid_dir,
+                registry,
+                cell,
+                &element.get_name(),
+            ) {
+                Ok(()) => {}
+                Err(err) => warn!("{}, not painting it while recording", err),
+            }
+            return;
+        }
+        let current_time = self.element_grid_dir.clock();
+        self.element_grid_dir
+            .set_element(cell, element, current_time);
+    }
+
+    /// Lands `element` on the surface, see [ejecta::accrete], writing it down by name if recording
+    /// Like [Self::set_element], while recording one `registry` can't make doesn't land at all
+    pub fn impact(
+        &mut self,
+        element: Box<dyn Element>,
+        position: RelXyPoint,
+        registry: &ElementRegistry,
+    ) -> Option<IjkVector> {
+        if let Some(recorder) = &mut self.recorder {
+            match recorder.impact(
+                &mut self.element_grid_dir,
+                registry,
+                &element.get_name(),
+                position,
+            ) {
+                Ok(landed) => return landed,
+                Err(err) => {
+                    warn!("{}, not landing it while recording", err);
+                    return None;
+                }
+            }
+        }
+        let current_time = self.element_grid_dir.clock();
+        ejecta::accrete(&mut self.element_grid_dir, element, position, current_time)
+    }
+
+    /// Throws the element in a cell right now, see [ejecta::eject], writing it down if recording
+    fn eject(
+        &mut self,
+        cell: IjkVector,
+        velocity: Velocity,
+    ) -> Result<Option<Ejecta>, BuriedCellError> {
+        if let Some(recorder) = &mut self.recorder {
+            return recorder.eject(&mut self.element_grid_dir, cell, velocity);
+        }
+        let current_time = self.element_grid_dir.clock();
+        ejecta::eject(&mut self.element_grid_dir, cell, velocity, current_time)
+    }
+
+    /// Where the light comes from, see [ElementGridDir::set_sunlight], written down if recording
+    pub fn set_sunlight(&mut self, sunlight: Option<Vec2>) {
+        match &mut self.recorder {
+            Some(recorder) => recorder.set_sunlight(&mut self.element_grid_dir, sunlight),
+            None => self.element_grid_dir.set_sunlight(sunlight),
+        }
+    }
+
+    /// Throw the element in a cell with a velocity relative to the celestial
+    /// On the next physics frame it either escapes and becomes an [Asteroid],
+    /// or it was too slow or buried and stays put, see [ejecta::eject]
+    pub fn launch(&mut self, cell: IjkVector, velocity: Velocity) {
+        self.launches.push((cell, velocity));
+    }
+
+    /// Something to call every frame, with how much time passed since the last
     /// This calculates only 1/9th of the grid each frame
-    /// for maximum performance
-    pub fn process(&mut self, current_time: Clock) -> HashMap<ChunkIjkVector, Textures> {
-        self.element_grid_dir.process(current_time);
-        self.element_grid_dir.get_updated_target_textures()
+    /// for maximum performance, once per [ElementGridDir::set_sub_steps]
+    /// Only chunks that changed are drawn again, see [ElementGridDir::get_dirty_textures]
+    pub fn process(&mut self, delta: Duration) -> HashMap<ChunkIjkVector, Textures> {
+        self.start_requested_recording();
+        match &mut self.recorder {
+            Some(recorder) => recorder.step(&mut self.element_grid_dir, delta),
+            None => self.element_grid_dir.step(delta),
+        }
+        self.element_grid_dir
+            .get_dirty_textures()
+            .into_iter()
+            .map(|(coord, texture)| {
+                (
+                    coord,
+                    Textures {
+                        texture: Some(texture),
+                    },
+                )
+            })
+            .collect()
     }
 
     /// Something to call every frame
-    /// This is the same as process, but it processes the entire grid
-    pub fn process_full(&mut self, current_time: Clock) -> HashMap<ChunkIjkVector, Textures> {
-        self.element_grid_dir.process_full(current_time);
+    /// This is the same as process, but it processes the entire grid, see [ElementGridDir::set_sub_steps]
+    pub fn process_full(&mut self, delta: Duration) -> HashMap<ChunkIjkVector, Textures> {
+        self.start_requested_recording();
+        match &mut self.recorder {
+            Some(recorder) => recorder.step_full(&mut self.element_grid_dir, delta),
+            None => self.element_grid_dir.step_full(delta),
+        }
         self.element_grid_dir.get_textures()
     }
 
@@ -135,11 +362,28 @@ pub struct CelestialBuilder {
     celestial_idx: CelestialIdx,
     /// Whether the celestial has a gravitational field
     gravitational: bool,
+    /// The spin of the celestial
+    angular_velocity: AngularVelocity,
+    /// How much the spin deflects falling elements, see [ElementGridDir::set_coriolis]
+    coriolis_strength: f32,
+}
+
+/// Anything that knows how to lay out a kind of celestial and fill it with elements,
+/// like [EarthLikeBuilder](super::earthlike::EarthLikeBuilder) or [SunBuilder](super::sun::SunBuilder)
+/// Where it sits, how it moves and how it spins are up to the [CelestialBuilder] it is handed to
+pub trait CelestialRecipe {
+    /// Makes a fresh element directory for the celestial
+    fn build_element_dir(&self) -> ElementGridDir;
 }
 
 impl CelestialBuilder {
-    /// Create a new celestial builder
-    pub fn new(idx: &mut CelestialIdx, name: String, data: CelestialData) -> Self {
+    /// Create a new celestial builder, filled in by the recipe
+    pub fn new(idx: &mut CelestialIdx, name: String, recipe: &dyn CelestialRecipe) -> Self {
+        Self::from_data(idx, name, CelestialData::new(recipe.build_element_dir()))
+    }
+
+    /// Create a new celestial builder around elements that already exist
+    fn from_data(idx: &mut CelestialIdx, name: String, data: CelestialData) -> Self {
         let out = Self {
             name,
             celestial_data: data,
@@ -147,11 +391,29 @@ impl CelestialBuilder {
             velocity: Velocity(Vec2::new(0., 0.)),
             translation: Vec2::new(0., 0.),
             gravitational: true,
+            angular_velocity: AngularVelocity(0.0),
+            coriolis_strength: 0.1,
         };
         *idx = *idx + 1;
         out
     }
 
+    /// Start from a celestial saved with [save_to_file](crate::physics::fallingsand::data::save::save_to_file)
+    /// Only the elements are saved, so velocity, position and the rest are set on the builder as usual
+    pub fn from_save(
+        path: impl AsRef<Path>,
+        registry: &ElementRegistry,
+        idx: &mut CelestialIdx,
+        name: String,
+    ) -> io::Result<Self> {
+        let element_grid_dir = ElementGridDir::from_save(path, registry)?;
+        Ok(Self::from_data(
+            idx,
+            name,
+            CelestialData::new(element_grid_dir),
+        ))
+    }
+
     /// Set the velocity of the celestial
     pub fn velocity(mut self, velocity: Velocity) -> Self {
         self.velocity = velocity;
@@ -170,14 +432,34 @@ impl CelestialBuilder {
         self
     }
 
+    /// Set the spin of the celestial
+    pub fn angular_velocity(mut self, angular_velocity: AngularVelocity) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    /// Set how strongly the spin deflects falling elements
+    /// Defaults to something subtle, set to 0 to turn it off
+    pub fn coriolis_strength(mut self, coriolis_strength: f32) -> Self {
+        self.coriolis_strength = coriolis_strength;
+        self
+    }
+
     /// Build the celestial
     pub fn build(
-        self,
+        mut self,
         commands: &mut Commands,
         meshes: &mut ResMut<Assets<Mesh>>,
         materials: &mut ResMut<Assets<ColorMaterial>>,
         asset_server: &Res<AssetServer>,
     ) -> Entity {
+        self.celestial_data
+            .get_element_dir_mut()
+            .set_coriolis(self.angular_velocity, self.coriolis_strength);
+        self.celestial_data
+            .get_element_dir_mut()
+            .set_spin(self.angular_velocity);
+
         // Create all the chunk meshes as pairs of ChunkIjkVector and Mesh2dBundle
         let mut chunks = Vec::new();
         let mut wireframes = Vec::new();
@@ -212,6 +494,9 @@ impl CelestialBuilder {
                     let outline = coordinate_dir
                         .get_chunk_at_idx(chunk_ijk)
                         .calc_chunk_outline();
+                    let pass = element_dir
+                        .pass_of_chunk(chunk_ijk)
+                        .expect("Every chunk is in a pass");
 
                     let textures = textures.remove(&chunk_ijk).unwrap();
                     let sand_material = textures.texture.unwrap().to_bevy_image();
@@ -269,6 +554,7 @@ impl CelestialBuilder {
                                 ..Default::default()
                             },
                             CelestialOutline,
+                            ChunkPass(pass),
                             OverlayLayer3,
                         ))
                         .id();
@@ -292,7 +578,12 @@ impl CelestialBuilder {
                         .get_coordinate_dir()
                         .get_radius(),
                     self.celestial_data.get_element_dir().get_total_mass(),
+                    self.celestial_data.get_element_dir().surface_gravity(),
                     self.velocity,
+                    self.angular_velocity,
+                    CenterOfMassOffset(
+                        self.celestial_data.get_element_dir().get_center_of_mass().0,
+                    ),
                     self.celestial_data,
                     self.celestial_idx,
                     SpatialBundle {
@@ -348,9 +639,9 @@ impl CelestialBuilder {
 /// Bevy Systems
 impl CelestialDataPlugin {
     /// Run this system every frame to update the celestial
-    #[allow(clippy::type_complexity)]
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
     pub fn process_system(
-        mut celestial: Query<(Entity, &mut CelestialData, &mut Mass)>,
+        mut celestial: Query<(Entity, &mut CelestialData, &mut Mass, &mut SurfaceGravity)>,
         mut falling_sand_materials: Query<
             (&Parent, &mut Handle<ColorMaterial>, &CelestialChunkIdk),
             With<FallingSandMaterial>,
@@ -358,15 +649,31 @@ impl CelestialDataPlugin {
         mut materials: ResMut<Assets<ColorMaterial>>,
         asset_server: Res<AssetServer>,
         time: Res<Time>,
-        frame: Res<FrameCount>,
+        settings: Res<MeshDebugSettings>,
+        gradient: Res<HeatGradient>,
+        pressure_overlay: Res<PressureOverlay>,
+        palette: Res<ElementPalette>,
     ) {
-        for (celestial_id, mut celestial, mut mass) in celestial.iter_mut() {
+        // Only pass the display settings down when they change, or to a celestial that hasn't had them yet
+        let display_changed = settings.is_changed()
+            || gradient.is_changed()
+            || pressure_overlay.is_changed()
+            || palette.is_changed();
+        for (celestial_id, mut celestial, mut mass, mut surface_gravity) in celestial.iter_mut() {
+            if display_changed || celestial.is_added() {
+                let element_dir = celestial.get_element_dir_mut();
+                element_dir.set_heatmap(settings.show_heatmap.then(|| gradient.clone()));
+                element_dir
+                    .set_pressure_overlay(settings.show_pressure.then(|| pressure_overlay.clone()));
+                element_dir.set_palette(palette.clone());
+            }
             let mut new_textures: HashMap<ChunkIjkVector, Textures> =
-                celestial.process(Clock::new(time.as_generic(), frame.as_ref().to_owned()));
+                celestial.process(time.delta());
 
             // Update the mass of the celestial after processing, which
             // can affect its gravitational pull
             mass.0 = celestial.get_element_dir().get_total_mass().0;
+            *surface_gravity = celestial.get_element_dir().surface_gravity();
 
             // Update the falling sand materials
             for (parent, material_handle, chunk_ijk) in falling_sand_materials.iter_mut() {
@@ -384,6 +691,105 @@ impl CelestialDataPlugin {
             }
         }
     }
+    /// Turns every launched element that escapes its celestial into an [Asteroid]
+    /// The asteroid keeps the celestial's velocity on top of its own, so it starts out orbiting with it
+    /// It starts a cell above the surface, so it is clear of the celestial before it can be captured again
+    pub fn ejecta_system(
+        mut commands: Commands,
+        mut celestials: Query<(&mut CelestialData, &Transform, &Velocity)>,
+    ) {
+        for (mut celestial, transform, velocity) in celestials.iter_mut() {
+            let launches = std::mem::take(&mut celestial.launches);
+            for (cell, launch_velocity) in launches {
+                let ejecta = match celestial.eject(cell, launch_velocity) {
+                    Ok(Some(ejecta)) => ejecta,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!("{}", err);
+                        continue;
+                    }
+                };
+                let coord_dir = celestial.get_element_dir().get_coordinate_dir();
+                let clearance = coord_dir.get_radius().0
+                    + coord_dir
+                        .get_layer_cell_width(coord_dir.get_num_layers() - 1)
+                        .0;
+                let position = transform.translation.truncate()
+                    + ejecta.position.0.normalize_or_zero() * clearance;
+                commands.spawn((
+                    Name::new(format!("{} Asteroid", ejecta.element.get_name())),
+                    Asteroid(ejecta.element),
+                    ejecta.mass,
+                    Velocity(velocity.0 + ejecta.velocity.0),
+                    SpatialBundle {
+                        transform: Transform::from_translation(position.extend(0.0)),
+                        ..Default::default()
+                    },
+                ));
+            }
+        }
+    }
+    /// Collects what every celestial sent out to space or took in this frame into [SpaceBoundaryFlux]
+    pub fn boundary_flux_system(
+        mut flux: ResMut<SpaceBoundaryFlux>,
+        mut celestials: Query<&mut CelestialData>,
+    ) {
+        let mut total = BoundaryFlux::default();
+        for mut celestial in celestials.iter_mut() {
+            total += celestial.get_element_dir_mut().take_boundary_flux();
+        }
+        flux.0 = total;
+    }
+    /// Moves where each celestial pulls from to wherever its mass has shifted to
+    /// The directory only recalculates its center of mass once per full process,
+    /// so this changes at most that often
+    pub fn center_of_mass_system(mut celestials: Query<(&CelestialData, &mut CenterOfMassOffset)>) {
+        for (celestial, mut offset) in celestials.iter_mut() {
+            let center_of_mass =
+                CenterOfMassOffset(celestial.get_element_dir().get_center_of_mass().0);
+            // Only touch the component when it changes, so change detection stays quiet
+            if *offset != center_of_mass {
+                *offset = center_of_mass;
+            }
+        }
+    }
+    /// Any [Asteroid] that has fallen within a celestial's radius becomes part of its surface again
+    /// Only asteroids headed inwards are captured, anything on its way out is left to leave
+    /// Asteroids that find no room where they hit bounce off the surface instead
+    pub fn accretion_system(
+        mut commands: Commands,
+        mut asteroids: Query<(Entity, &Asteroid, &Transform, &mut Velocity)>,
+        mut celestials: Query<(&mut CelestialData, &Transform, &Velocity), Without<Asteroid>>,
+        registry: Res<ElementRegistry>,
+    ) {
+        for (asteroid_id, asteroid, asteroid_transform, mut asteroid_velocity) in
+            asteroids.iter_mut()
+        {
+            for (mut celestial, celestial_transform, celestial_velocity) in celestials.iter_mut() {
+                let impact =
+                    (asteroid_transform.translation - celestial_transform.translation).truncate();
+                let radius = celestial
+                    .get_element_dir()
+                    .get_coordinate_dir()
+                    .get_radius();
+                let relative_velocity = asteroid_velocity.0 - celestial_velocity.0;
+                if impact.length() > radius.0 || relative_velocity.dot(impact) >= 0.0 {
+                    continue;
+                }
+                let landed =
+                    celestial.impact(asteroid.0.box_clone(), RelXyPoint(impact), &registry);
+                if landed.is_some() {
+                    commands.entity(asteroid_id).despawn();
+                } else {
+                    // Turn the inward part of its velocity around, so it won't hit the same full column next frame
+                    let normal = impact.normalize_or_zero();
+                    let bounced = relative_velocity - 2.0 * relative_velocity.dot(normal) * normal;
+                    asteroid_velocity.0 = celestial_velocity.0 + bounced;
+                }
+                break;
+            }
+        }
+    }
     /// Draw the wireframe of the celestials cells
     pub fn draw_wireframe_system(
         mut gizmos: Gizmos,
@@ -406,4 +812,742 @@ impl CelestialDataPlugin {
             }
         }
     }
+    /// Outline each celestial in the color its surface glows, see [ElementGridDir::surface_emission_color]
+    /// Parts of the surface too cold to glow, or with nothing on them, aren't drawn
+    pub fn draw_emission_system(
+        mut gizmos: Gizmos,
+        celestials: Query<(&CelestialData, &Transform)>,
+    ) {
+        for (celestial, transform) in celestials.iter() {
+            let element_dir = celestial.get_element_dir();
+            for edge in element_dir.collision_outline(EMISSION_SEGMENTS).windows(2) {
+                let middle = (edge[0] + edge[1]) / 2.0;
+                let color = element_dir.surface_emission_color(middle.y.atan2(middle.x));
+                if color.a() == 0.0 || color == Color::BLACK {
+                    continue;
+                }
+                gizmos.line_2d(
+                    transform.transform_point(edge[0].extend(0.0)).truncate(),
+                    transform.transform_point(edge[1].extend(0.0)).truncate(),
+                    color,
+                );
+            }
+        }
+    }
+    /// Lights every celestial from the nearest [Sun], wherever the two are in the world right now
+    /// The direction is turned into the celestial's own frame, the spin of its surface is added on
+    /// top by the directory, see [ElementGridDir::set_sunlight]. With no sun there is no light
+    pub fn sunlight_system(
+        suns: Query<&GlobalTransform, With<Sun>>,
+        mut celestials: Query<(&mut CelestialData, &GlobalTransform), Without<Sun>>,
+    ) {
+        for (mut celestial, transform) in celestials.iter_mut() {
+            let position = transform.translation();
+            let sunlight = suns
+                .iter()
+                .map(|sun| sun.translation() - position)
+                .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+                .map(|to_sun| {
+                    let (_, rotation, _) = transform.to_scale_rotation_translation();
+                    (rotation.inverse() * to_sun).truncate()
+                });
+            celestial.set_sunlight(sunlight);
+        }
+    }
+    /// Switch the heatmap to a color per chunk once the camera is zoomed out far enough that
+    /// a pixel is bigger than a cell, see [ElementGridDir::set_coarse_heat]
+    pub fn coarse_heat_system(
+        camera: Query<&GlobalTransform, With<MainCamera>>,
+        mut celestials: Query<&mut CelestialData>,
+    ) {
+        let Ok(camera) = camera.get_single() else {
+            return;
+        };
+        // The camera zooms by scaling, so its scale is how much of the world a pixel covers
+        let pixel_width = camera.compute_transform().scale.x;
+        for mut celestial in celestials.iter_mut() {
+            // Once a pixel covers the outermost layer's cells, the widest, none can be made out
+            let coord_dir = celestial.get_element_dir().get_coordinate_dir();
+            let cell_width = coord_dir.get_layer_cell_width(coord_dir.get_num_layers() - 1);
+            let coarse = pixel_width > cell_width.0;
+            // Only borrow mutably when it flips, so change detection stays quiet
+            if celestial.get_element_dir().get_coarse_heat() != coarse {
+                celestial.get_element_dir_mut().set_coarse_heat(coarse);
+            }
+        }
+    }
+    /// Toggle the pass coloring debug overlay
+    pub fn toggle_pass_coloring_system(
+        keyboard_input: Res<Input<KeyCode>>,
+        mut settings: ResMut<MeshDebugSettings>,
+    ) {
+        if keyboard_input.just_pressed(KeyCode::P) {
+            settings.show_pass_coloring = !settings.show_pass_coloring;
+        }
+    }
+    /// Toggle drawing the temperature heatmap
+    pub fn toggle_heatmap_system(
+        keyboard_input: Res<Input<KeyCode>>,
+        mut settings: ResMut<MeshDebugSettings>,
+    ) {
+        if keyboard_input.just_pressed(KeyCode::H) {
+            settings.show_heatmap = !settings.show_heatmap;
+        }
+    }
+    /// Toggle drawing the pressure overlay
+    pub fn toggle_pressure_system(
+        keyboard_input: Res<Input<KeyCode>>,
+        mut settings: ResMut<MeshDebugSettings>,
+    ) {
+        if keyboard_input.just_pressed(KeyCode::O) {
+            settings.show_pressure = !settings.show_pressure;
+        }
+    }
+    /// Start recording the celestial the camera is on with `R`, and press it again to write the
+    /// recording to [RECORDING_PATH], to attach to a bug report and play back with [Recording::replay]
+    pub fn toggle_recording_system(
+        keyboard_input: Res<Input<KeyCode>>,
+        camera: Query<&Parent, With<MainCamera>>,
+        mut celestials: Query<&mut CelestialData>,
+    ) {
+        if !keyboard_input.just_pressed(KeyCode::R) {
+            return;
+        }
+        let Ok(parent) = camera.get_single() else {
+            return;
+        };
+        let Ok(mut celestial) = celestials.get_mut(parent.get()) else {
+            return;
+        };
+        if !celestial.is_recording() {
+            info!("Recording from the next cycle");
+            celestial.start_recording();
+            return;
+        }
+        let Some(recording) = celestial.finish_recording() else {
+            info!("Stopped recording before it started");
+            return;
+        };
+        match recording.write_to_file(RECORDING_PATH) {
+            Ok(()) => info!(
+                "Wrote {} events to {}",
+                recording.events().len(),
+                RECORDING_PATH
+            ),
+            Err(err) => warn!("Couldn't write the recording: {}", err),
+        }
+    }
+    /// Draw every chunk outline in the color of its pass, regardless of whether outlines are shown
+    pub fn draw_pass_coloring_system(
+        mut gizmos: Gizmos,
+        settings: Res<MeshDebugSettings>,
+        query: Query<(&GizmoDrawableLoop, &Transform, &ChunkPass), With<CelestialOutline>>,
+    ) {
+        if !settings.show_pass_coloring {
+            return;
+        }
+        for (drawable, transform, pass) in query.iter() {
+            drawable.draw_bevy_gizmo_loop_with_color(
+                &mut gizmos,
+                transform,
+                MeshDebugSettings::pass_color(pass.0),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+    use bevy::MinimalPlugins;
+
+    use super::*;
+    use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+    use crate::physics::fallingsand::data::element_grid::ElementGrid;
+    use crate::physics::fallingsand::data::save;
+    use crate::physics::fallingsand::elements::element::{
+        Density, ElementTakeOptions, ElementType, StateOfMatter,
+    };
+    use crate::physics::fallingsand::mesh::coordinate_directory::{
+        CoordinateDir, CoordinateDirBuilder,
+    };
+    use crate::physics::fallingsand::util::vectors::JkVector;
+    use crate::physics::orbits::components::Length;
+    use crate::physics::orbits::nbody::gravitational_position;
+    use crate::physics::util::clock::Clock;
+
+    fn get_celestial_data() -> CelestialData {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(5)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let stone = ElementType::Stone.get_element();
+        CelestialData::new(ElementGridDir::new_checkerboard(
+            coordinate_dir,
+            &*stone,
+            &*stone,
+        ))
+    }
+
+    #[test]
+    fn test_only_escaping_launches_become_asteroids() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.init_resource::<SpaceBoundaryFlux>();
+        app.add_systems(
+            Update,
+            (
+                CelestialDataPlugin::ejecta_system,
+                CelestialDataPlugin::boundary_flux_system,
+            )
+                .chain(),
+        );
+
+        let mut celestial_data = get_celestial_data();
+        let coordinate_dir = celestial_data.get_element_dir().get_coordinate_dir();
+        let layer = coordinate_dir.get_num_layers() - 1;
+        let top = coordinate_dir.get_layer_num_concentric_circles(layer) - 1;
+        let fast_cell = IjkVector::new(layer, top, 0);
+        let slow_cell = IjkVector::new(layer, top, 5);
+        let outward = |cell| coordinate_dir.cell_midpoint(cell).0.normalize();
+        let fast = Velocity(outward(fast_cell) * 1.0e6);
+        let slow = Velocity(outward(slow_cell) * 1.0e-3);
+        celestial_data.launch(fast_cell, fast);
+        celestial_data.launch(slow_cell, slow);
+
+        let celestial_velocity = Velocity(Vec2::new(0.0, 5.0));
+        let celestial = app
+            .world
+            .spawn((celestial_data, Transform::default(), celestial_velocity))
+            .id();
+        app.update();
+
+        let mut asteroids = app
+            .world
+            .query::<(&Asteroid, &Mass, &Velocity, &Transform)>();
+        let asteroids: Vec<_> = asteroids.iter(&app.world).collect();
+        assert_eq!(asteroids.len(), 1);
+        let (asteroid, mass, velocity, transform) = asteroids[0];
+        assert_eq!(asteroid.0.get_type(), ElementType::Stone);
+        assert!(mass.0 > 0.0);
+        assert_eq!(velocity.0, celestial_velocity.0 + fast.0);
+        let coordinate_dir = app
+            .world
+            .get::<CelestialData>(celestial)
+            .unwrap()
+            .get_element_dir()
+            .get_coordinate_dir();
+        let launched_from = coordinate_dir.cell_midpoint(fast_cell);
+        // Straight out from where it was launched, but clear of the surface
+        let position = transform.translation.truncate();
+        assert!(position.length() > coordinate_dir.get_radius().0);
+        assert!(position
+            .normalize()
+            .abs_diff_eq(launched_from.0.normalize(), 1e-5));
+        // Only the one that escaped counts as leaving
+        let flux = app.world.resource::<SpaceBoundaryFlux>().0;
+        assert_eq!((flux.outflux, flux.influx), (1, 0));
+        assert_eq!(flux.mass_out.0, mass.0);
+
+        let element_dir = app
+            .world
+            .get::<CelestialData>(celestial)
+            .unwrap()
+            .get_element_dir();
+        assert_eq!(
+            element_dir.get_element(fast_cell).get_type(),
+            ElementType::Vacuum
+        );
+        assert_eq!(
+            element_dir.get_element(slow_cell).get_type(),
+            ElementType::Stone
+        );
+    }
+
+    #[test]
+    fn test_falling_asteroid_lands_on_the_surface() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.init_resource::<ElementRegistry>();
+        app.add_systems(Update, CelestialDataPlugin::accretion_system);
+
+        // A stone planet with an empty outermost ring to land in
+        let mut celestial_data = get_celestial_data();
+        let element_dir = celestial_data.get_element_dir_mut();
+        let coordinate_dir = element_dir.get_coordinate_dir().clone();
+        let layer = coordinate_dir.get_num_layers() - 1;
+        let top = coordinate_dir.get_layer_num_concentric_circles(layer) - 1;
+        for k in 0..coordinate_dir.get_layer_num_radial_lines(layer) {
+            element_dir.set_element(
+                IjkVector::new(layer, top, k),
+                ElementType::Vacuum.get_element(),
+                Clock::default(),
+            );
+        }
+        let translation = Vec2::new(100.0, -50.0);
+        let celestial_velocity = Velocity(Vec2::new(3.0, 0.0));
+        let celestial = app
+            .world
+            .spawn((
+                celestial_data,
+                Transform::from_translation(translation.extend(0.0)),
+                celestial_velocity,
+            ))
+            .id();
+
+        let target = IjkVector::new(layer, top, 5);
+        let radius = coordinate_dir.get_radius().0;
+        let direction = coordinate_dir.cell_midpoint(target).0.normalize();
+        let falling = Velocity(celestial_velocity.0 - direction);
+        let landing = app
+            .world
+            .spawn((
+                Asteroid(ElementType::Sand.get_element()),
+                Transform::from_translation((translation + direction * (radius - 0.1)).extend(0.0)),
+                falling,
+            ))
+            .id();
+        let passing = app
+            .world
+            .spawn((
+                Asteroid(ElementType::Sand.get_element()),
+                Transform::from_translation((translation + direction * radius * 2.0).extend(0.0)),
+                falling,
+            ))
+            .id();
+        // Still on its way out, like something that was just thrown off
+        let leaving = app
+            .world
+            .spawn((
+                Asteroid(ElementType::Sand.get_element()),
+                Transform::from_translation((translation + direction * (radius - 0.1)).extend(0.0)),
+                Velocity(celestial_velocity.0 + direction),
+            ))
+            .id();
+        app.update();
+
+        assert!(app.world.get_entity(landing).is_none());
+        assert!(app.world.get_entity(passing).is_some());
+        assert!(app.world.get_entity(leaving).is_some());
+        let element_dir = app
+            .world
+            .get::<CelestialData>(celestial)
+            .unwrap()
+            .get_element_dir();
+        assert_eq!(
+            element_dir.get_element(target).get_type(),
+            ElementType::Sand
+        );
+        let sand_cells = (0..coordinate_dir.get_layer_num_radial_lines(layer))
+            .filter(|k| {
+                element_dir
+                    .get_element(IjkVector::new(layer, top, *k))
+                    .get_type()
+                    == ElementType::Sand
+            })
+            .count();
+        assert_eq!(sand_cells, 1);
+    }
+
+    #[test]
+    fn test_asteroid_bounces_off_a_full_column() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.init_resource::<ElementRegistry>();
+        app.add_systems(Update, CelestialDataPlugin::accretion_system);
+
+        // Solid stone all the way up, so there is nowhere to land
+        let celestial_data = get_celestial_data();
+        let coordinate_dir = celestial_data
+            .get_element_dir()
+            .get_coordinate_dir()
+            .clone();
+        app.world
+            .spawn((celestial_data, Transform::default(), Velocity(Vec2::ZERO)));
+
+        let direction = Vec2::new(0.6, 0.8);
+        let asteroid = app
+            .world
+            .spawn((
+                Asteroid(ElementType::Sand.get_element()),
+                Transform::from_translation(
+                    (direction * (coordinate_dir.get_radius().0 - 0.1)).extend(0.0),
+                ),
+                Velocity(-direction * 2.0 + direction.perp()),
+            ))
+            .id();
+        app.update();
+
+        // Same speed along the surface, but now headed out
+        let velocity = app.world.get::<Velocity>(asteroid).unwrap().0;
+        assert!(velocity.abs_diff_eq(direction * 2.0 + direction.perp(), 1e-5));
+        // Headed out, so the next frame leaves it alone
+        app.update();
+        assert_eq!(app.world.get::<Velocity>(asteroid).unwrap().0, velocity);
+    }
+
+    #[test]
+    fn test_sunlight_comes_from_the_nearest_sun() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_systems(Update, CelestialDataPlugin::sunlight_system);
+
+        let planet = app
+            .world
+            .spawn((
+                get_celestial_data(),
+                GlobalTransform::from_translation(Vec3::new(100.0, 0.0, 0.0)),
+            ))
+            .id();
+        app.update();
+        // Nothing to light it yet
+        let sunlight = |app: &App| {
+            app.world
+                .get::<CelestialData>(planet)
+                .unwrap()
+                .get_element_dir()
+                .get_sunlight()
+        };
+        assert_eq!(sunlight(&app), None);
+
+        app.world.spawn((
+            Sun,
+            GlobalTransform::from_translation(Vec3::new(100.0, 50.0, 0.0)),
+        ));
+        app.world.spawn((
+            Sun,
+            GlobalTransform::from_translation(Vec3::new(-1000.0, 0.0, 0.0)),
+        ));
+        app.update();
+        assert_eq!(sunlight(&app), Some(Vec2::Y));
+
+        // The planet moves around to the other side of its sun
+        *app.world.get_mut::<GlobalTransform>(planet).unwrap() =
+            GlobalTransform::from_translation(Vec3::new(100.0, 100.0, 0.0));
+        app.update();
+        assert_eq!(sunlight(&app), Some(Vec2::NEG_Y));
+    }
+
+    #[test]
+    fn test_gravity_pulls_from_the_center_of_mass() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_systems(Update, CelestialDataPlugin::center_of_mass_system);
+
+        // Everything but the right half of the outer layer is knocked away
+        let mut lopsided = get_celestial_data();
+        let element_dir = lopsided.get_element_dir_mut();
+        let coordinate_dir = element_dir.get_coordinate_dir().clone();
+        let layer = coordinate_dir.get_num_layers() - 1;
+        for j in 0..coordinate_dir.get_layer_num_concentric_circles(layer) {
+            for k in 0..coordinate_dir.get_layer_num_radial_lines(layer) {
+                let cell = IjkVector::new(layer, j, k);
+                if coordinate_dir.cell_midpoint(cell).0.x < 0.0 {
+                    element_dir.set_element(
+                        cell,
+                        ElementType::Vacuum.get_element(),
+                        Clock::default(),
+                    );
+                }
+            }
+        }
+        element_dir.recalculate_everything();
+        let expected = element_dir.get_center_of_mass().0;
+        assert!(expected.x > 0.0);
+
+        let translation = Vec2::new(100.0, -50.0).extend(0.0);
+        let symmetric = app
+            .world
+            .spawn((
+                get_celestial_data(),
+                CenterOfMassOffset::default(),
+                Transform::from_translation(translation),
+            ))
+            .id();
+        let lopsided = app
+            .world
+            .spawn((
+                lopsided,
+                CenterOfMassOffset::default(),
+                Transform::from_translation(translation),
+            ))
+            .id();
+        app.update();
+
+        let pulls_from = |entity| {
+            gravitational_position(
+                app.world.get::<Transform>(entity).unwrap(),
+                app.world.get::<CenterOfMassOffset>(entity),
+            )
+            .translation
+        };
+        assert!((pulls_from(symmetric) - translation).length() < 1e-2);
+        assert_eq!(
+            app.world.get::<CenterOfMassOffset>(lopsided).unwrap().0,
+            expected
+        );
+        assert!((pulls_from(lopsided) - (translation + expected.extend(0.0))).length() < 1e-4);
+    }
+
+    /// The smallest celestial there is, a ball of stone
+    struct Pebble;
+
+    impl CelestialRecipe for Pebble {
+        fn build_element_dir(&self) -> ElementGridDir {
+            get_celestial_data().element_grid_dir
+        }
+    }
+
+    #[test]
+    fn test_builds_from_any_recipe() {
+        let mut idx = CelestialIdx(3);
+        let builder = CelestialBuilder::new(&mut idx, "Pebble".to_string(), &Pebble);
+        assert_eq!(idx.0, 4);
+        assert_eq!(builder.celestial_idx.0, 3);
+        assert_eq!(builder.name, "Pebble");
+
+        let element_dir = builder.celestial_data.get_element_dir();
+        assert_eq!(element_dir.get_coordinate_dir().get_num_layers(), 5);
+        assert_eq!(
+            element_dir.cells_of_kind(ElementType::Stone).count(),
+            element_dir.get_total_num_cells()
+        );
+    }
+
+    #[test]
+    fn test_process_runs_a_pass_per_sub_step() {
+        let registry = ElementRegistry::default();
+        let frame = Duration::from_millis(48);
+        let falling_sand = || {
+            let mut celestial_data = get_celestial_data();
+            let coordinate_dir = celestial_data.get_element_dir().get_coordinate_dir();
+            let layer = coordinate_dir.get_num_layers() - 1;
+            let top = coordinate_dir.get_layer_num_concentric_circles(layer) - 1;
+            celestial_data.set_element(
+                IjkVector::new(layer, top, 0),
+                ElementType::Vacuum.get_element(),
+                &registry,
+            );
+            celestial_data.set_element(
+                IjkVector::new(layer, top - 1, 0),
+                ElementType::Vacuum.get_element(),
+                &registry,
+            );
+            celestial_data.set_element(
+                IjkVector::new(layer, top, 0),
+                ElementType::Sand.get_element(),
+                &registry,
+            );
+            celestial_data
+        };
+
+        // What the game calls every frame
+        let mut sub_stepped = falling_sand();
+        sub_stepped.get_element_dir_mut().set_sub_steps(3);
+        sub_stepped.process(frame);
+        let mut split = falling_sand();
+        for _ in 0..3 {
+            split.process(frame / 3);
+        }
+        assert_eq!(sub_stepped.get_element_dir().get_process_count(), 3);
+        assert_eq!(
+            sub_stepped.get_element_dir().state_hash(),
+            split.get_element_dir().state_hash()
+        );
+    }
+
+    #[test]
+    fn test_loads_a_saved_celestial_back() {
+        let registry = ElementRegistry::default();
+        let mut idx = CelestialIdx(0);
+        let mut builder = CelestialBuilder::new(&mut idx, "Pebble".to_string(), &Pebble);
+        let coordinate_dir = builder
+            .celestial_data
+            .get_element_dir()
+            .get_coordinate_dir();
+        let layer = coordinate_dir.get_num_layers() - 1;
+        let top = coordinate_dir.get_layer_num_concentric_circles(layer) - 1;
+        builder.celestial_data.set_element(
+            IjkVector::new(layer, top, 0),
+            ElementType::Sand.get_element(),
+            &registry,
+        );
+        let path = std::env::temp_dir().join(format!(
+            "orbiting_sand_celestial_{}.osnd",
+            std::process::id()
+        ));
+        save::save_to_file(builder.celestial_data.get_element_dir(), &path).unwrap();
+
+        let loaded =
+            CelestialBuilder::from_save(&path, &registry, &mut idx, "Loaded".to_string()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(idx.0, 2);
+        assert_eq!(loaded.celestial_idx.0, 1);
+        assert_eq!(loaded.name, "Loaded");
+        assert_eq!(
+            layout_and_histogram(loaded.celestial_data.get_element_dir()),
+            layout_and_histogram(builder.celestial_data.get_element_dir())
+        );
+    }
+
+    /// The shape of each layer and how many cells hold each element id
+    fn layout_and_histogram(
+        element_grid_dir: &ElementGridDir,
+    ) -> (Vec<(usize, usize)>, HashMap<u16, usize>) {
+        let coord_dir = element_grid_dir.get_coordinate_dir();
+        let mut layout = Vec::new();
+        let mut histogram = HashMap::new();
+        for layer in 0..coord_dir.get_num_layers() {
+            let array = element_grid_dir.layer_as_array(layer);
+            layout.push(array.dim());
+            for id in array.iter() {
+                *histogram.entry(*id).or_insert(0) += 1;
+            }
+        }
+        (layout, histogram)
+    }
+
+    #[test]
+    fn test_changes_made_while_recording_replay_the_same() {
+        let registry = ElementRegistry::default();
+        let mut celestial_data = get_celestial_data();
+        let frame = Duration::from_millis(16);
+        // Partway through a cycle, so recording waits for the next one
+        celestial_data.process(frame);
+        celestial_data.start_recording();
+        assert!(celestial_data.is_recording());
+        for _ in 0..8 {
+            celestial_data.process(frame);
+        }
+        assert!(celestial_data.recorder.is_none());
+        celestial_data.process(frame);
+        assert!(celestial_data.recorder.is_some());
+
+        let coordinate_dir = celestial_data.get_element_dir().get_coordinate_dir();
+        let layer = coordinate_dir.get_num_layers() - 1;
+        let top = coordinate_dir.get_layer_num_concentric_circles(layer) - 1;
+        let radius = coordinate_dir.get_radius().0;
+        celestial_data.set_element(
+            IjkVector::new(layer, top, 0),
+            ElementType::Vacuum.get_element(),
+            &registry,
+        );
+        celestial_data.set_element(
+            IjkVector::new(layer, top, 1),
+            ElementType::Sand.get_element(),
+            &registry,
+        );
+        celestial_data.set_sunlight(Some(Vec2::Y));
+        celestial_data.impact(
+            ElementType::Water.get_element(),
+            RelXyPoint(Vec2::new(radius, 0.0)),
+            &registry,
+        );
+        celestial_data.launch(IjkVector::new(layer, top, 2), Velocity(Vec2::ZERO));
+        let launches = std::mem::take(&mut celestial_data.launches);
+        for (cell, velocity) in launches {
+            celestial_data.eject(cell, velocity).unwrap();
+        }
+        celestial_data.process(frame);
+        celestial_data.process_full(frame);
+
+        let recording = celestial_data.finish_recording().unwrap();
+        assert!(!celestial_data.is_recording());
+        // The step of the frame it started on, then everything after
+        assert_eq!(recording.events().len(), 8);
+        let replayed = recording.replay(&registry).unwrap();
+        assert_eq!(replayed.state_hash(), recording.final_state_hash());
+        assert_eq!(
+            recording.final_state_hash(),
+            celestial_data.get_element_dir().state_hash()
+        );
+    }
+
+    /// An element no registry knows how to make, like one from a mod that isn't loaded
+    #[derive(Clone, Copy, Default)]
+    struct Homebrew {
+        last_processed: Clock,
+    }
+
+    impl Element for Homebrew {
+        fn get_type(&self) -> ElementType {
+            ElementType::Stone
+        }
+        fn get_name(&self) -> String {
+            "Homebrew".to_string()
+        }
+        fn get_last_processed(&self) -> Clock {
+            self.last_processed
+        }
+        fn _set_last_processed(&mut self, current_time: Clock) {
+            self.last_processed = current_time;
+        }
+        fn get_color(&self) -> Color {
+            Color::rgb_u8(1, 2, 3)
+        }
+        fn get_density(&self) -> Density {
+            Density(1.0)
+        }
+        fn get_state_of_matter(&self) -> StateOfMatter {
+            StateOfMatter::Solid
+        }
+        fn _process(
+            &mut self,
+            _pos: JkVector,
+            _coord_dir: &CoordinateDir,
+            _target_chunk: &mut ElementGrid,
+            _element_grid_conv: &mut ElementGridConvolutionNeighbors,
+            _current_time: Clock,
+        ) -> ElementTakeOptions {
+            ElementTakeOptions::PutBack
+        }
+        fn box_clone(&self) -> Box<dyn Element> {
+            Box::new(*self)
+        }
+    }
+
+    #[test]
+    fn test_unregistered_elements_are_not_painted_while_recording() {
+        let registry = ElementRegistry::default();
+        let mut celestial_data = get_celestial_data();
+        let frame = Duration::from_millis(16);
+        celestial_data.start_recording();
+        celestial_data.process(frame);
+        assert!(celestial_data.recorder.is_some());
+
+        let coordinate_dir = celestial_data.get_element_dir().get_coordinate_dir();
+        let layer = coordinate_dir.get_num_layers() - 1;
+        let top = coordinate_dir.get_layer_num_concentric_circles(layer) - 1;
+        let radius = coordinate_dir.get_radius().0;
+        let cell = IjkVector::new(layer, top, 0);
+        celestial_data.set_element(cell, Box::<Homebrew>::default(), &registry);
+        assert_eq!(
+            celestial_data
+                .get_element_dir()
+                .get_element(cell)
+                .get_name(),
+            "Stone"
+        );
+        assert_eq!(
+            celestial_data.impact(
+                Box::<Homebrew>::default(),
+                RelXyPoint(Vec2::new(radius, 0.0)),
+                &registry
+            ),
+            None
+        );
+        celestial_data.process(frame);
+
+        let recording = celestial_data.finish_recording().unwrap();
+        let replayed = recording.replay(&registry).unwrap();
+        let live = celestial_data.get_element_dir();
+        for layer in 0..live.get_coordinate_dir().get_num_layers() {
+            assert_eq!(replayed.layer_as_array(layer), live.layer_as_array(layer));
+        }
+        assert_eq!(replayed.state_hash(), live.state_hash());
+    }
 }
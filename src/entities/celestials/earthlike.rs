@@ -1,7 +1,7 @@
 use bevy::log::info;
 
 use crate::{
-    entities::celestials::celestial::CelestialData,
+    entities::celestials::celestial::CelestialRecipe,
     physics::{
         fallingsand::{
             data::element_directory::ElementGridDir, elements::element::ElementType,
@@ -78,8 +78,9 @@ impl EarthLikeBuilder {
         self
     }
 
-    pub fn build(&self) -> CelestialData {
-        let coordinate_dir = CoordinateDirBuilder::new()
+    /// The mesh this planet is built on, so other rocky planets can start from the same one
+    pub fn coordinate_dir_builder(&self) -> CoordinateDirBuilder {
+        CoordinateDirBuilder::new()
             .cell_radius(self.cell_radius)
             .num_layers(self.num_layers)
             .first_num_radial_lines(self.first_num_radial_lines)
@@ -87,7 +88,12 @@ impl EarthLikeBuilder {
             .first_num_tangential_chunkss(self.first_num_tangential_chunkss)
             .max_radial_lines_per_chunk(self.max_radial_lines_per_chunk)
             .max_concentric_circles_per_chunk(self.max_concentric_circles_per_chunk)
-            .build();
+    }
+}
+
+impl CelestialRecipe for EarthLikeBuilder {
+    fn build_element_dir(&self) -> ElementGridDir {
+        let coordinate_dir = self.coordinate_dir_builder().build();
         let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
         info!("Num elements: {}", element_grid_dir.get_total_num_cells());
 
@@ -132,6 +138,33 @@ impl EarthLikeBuilder {
                 total_j += 1;
             }
         }
-        CelestialData::new(element_grid_dir)
+        element_grid_dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::fallingsand::util::vectors::IjkVector;
+    use crate::physics::util::clock::Clock;
+
+    #[test]
+    fn test_planet_below_the_sand_band_is_angularly_symmetric() {
+        // The default top layer has sand in every other chunk on purpose, so it rains down
+        assert!(!EarthLikeBuilder::new()
+            .build_element_dir()
+            .is_angularly_symmetric());
+
+        // Everything under it is built in whole rings
+        let mut element_grid_dir = EarthLikeBuilder::new().num_layers(7).build_element_dir();
+        assert!(element_grid_dir.is_angularly_symmetric());
+
+        // A single grain out of place breaks it
+        element_grid_dir.set_element(
+            IjkVector::new(3, 1, 7),
+            ElementType::Sand.get_element(),
+            Clock::default(),
+        );
+        assert!(!element_grid_dir.is_angularly_symmetric());
     }
 }
@@ -0,0 +1,198 @@
+use bevy::log::info;
+
+use crate::{
+    entities::celestials::{celestial::CelestialRecipe, earthlike::EarthLikeBuilder},
+    physics::fallingsand::{
+        data::{element_directory::ElementGridDir, hydrostatic::HydrostaticProfile},
+        elements::element::ElementType,
+        mesh::coordinate_directory::{CoordinateDir, CoordinateDirBuilder},
+    },
+};
+
+/// A rocky core under a liquid ocean, frozen over by a crust of ice
+/// Each band reaches out to its own fraction of the radius of the whole mesh,
+/// and everything above the ice is vacuum
+pub struct IcePlanetBuilder {
+    coordinate_dir: CoordinateDirBuilder,
+    core_radius_fraction: f32,
+    ocean_radius_fraction: f32,
+    surface_radius_fraction: f32,
+}
+
+impl Default for IcePlanetBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IcePlanetBuilder {
+    /// Starts from the same mesh as an [EarthLikeBuilder]
+    pub fn new() -> Self {
+        Self {
+            coordinate_dir: EarthLikeBuilder::new().coordinate_dir_builder(),
+            core_radius_fraction: 0.5,
+            ocean_radius_fraction: 0.75,
+            surface_radius_fraction: 0.9,
+        }
+    }
+
+    /// The mesh to build the planet on
+    pub fn coordinate_dir(mut self, coordinate_dir: CoordinateDirBuilder) -> Self {
+        self.coordinate_dir = coordinate_dir;
+        self
+    }
+
+    /// How far out the rock goes, as a fraction of the total radius
+    pub fn core_radius_fraction(mut self, core_radius_fraction: f32) -> Self {
+        self.core_radius_fraction = core_radius_fraction;
+        self
+    }
+
+    /// How far out the liquid water goes, as a fraction of the total radius
+    pub fn ocean_radius_fraction(mut self, ocean_radius_fraction: f32) -> Self {
+        self.ocean_radius_fraction = ocean_radius_fraction;
+        self
+    }
+
+    /// How far out the ice goes, as a fraction of the total radius
+    pub fn surface_radius_fraction(mut self, surface_radius_fraction: f32) -> Self {
+        self.surface_radius_fraction = surface_radius_fraction;
+        self
+    }
+
+    /// How many cells of rock, water and ice the planet is made of
+    /// Every ring goes to the band its middle falls in, so the bands end on ring boundaries
+    fn composition(&self, coords: &CoordinateDir) -> Vec<(ElementType, usize)> {
+        let radius = coords.get_radius().0;
+        let mut composition = vec![
+            (ElementType::Stone, 0),
+            (ElementType::Water, 0),
+            (ElementType::Ice, 0),
+        ];
+        for layer_num in 0..coords.get_num_layers() {
+            let start_radius = coords.get_layer_start_radius(layer_num);
+            let cell_height = coords.get_layer_cell_height(layer_num);
+            for j in 0..coords.get_layer_num_concentric_circles(layer_num) {
+                let fraction = (start_radius + (j as f32 + 0.5) * cell_height) / radius;
+                let band = if fraction < self.core_radius_fraction {
+                    0
+                } else if fraction < self.ocean_radius_fraction {
+                    1
+                } else if fraction < self.surface_radius_fraction {
+                    2
+                } else {
+                    continue;
+                };
+                composition[band].1 += coords.get_layer_num_radial_lines(layer_num);
+            }
+        }
+        composition
+    }
+}
+
+impl CelestialRecipe for IcePlanetBuilder {
+    fn build_element_dir(&self) -> ElementGridDir {
+        debug_assert!(
+            self.core_radius_fraction <= self.ocean_radius_fraction
+                && self.ocean_radius_fraction <= self.surface_radius_fraction,
+            "The bands have to go core, ocean, then ice from the center out"
+        );
+        let coordinate_dir = self.coordinate_dir.clone().build();
+        let profile = HydrostaticProfile::new(&coordinate_dir, &self.composition(&coordinate_dir));
+        let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+        info!("Num elements: {}", element_grid_dir.get_total_num_cells());
+        // Ice is lighter than water, so the profile puts it on top of the ocean on its own
+        profile.fill(&mut element_grid_dir);
+        element_grid_dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::math::Vec2;
+
+    use super::*;
+    use crate::physics::fallingsand::data::element_grid::HeatConfig;
+    use crate::physics::fallingsand::util::vectors::IjkVector;
+    use crate::physics::util::clock::Clock;
+
+    fn get_builder() -> IcePlanetBuilder {
+        IcePlanetBuilder::new().coordinate_dir(
+            CoordinateDirBuilder::new()
+                .num_layers(6)
+                .first_num_radial_lines(6)
+                .second_num_concentric_circles(3)
+                .first_num_tangential_chunkss(3)
+                .max_radial_lines_per_chunk(64)
+                .max_concentric_circles_per_chunk(64),
+        )
+    }
+
+    #[test]
+    fn test_bands_are_where_they_should_be() {
+        let builder = get_builder();
+        let element_grid_dir = builder.build_element_dir();
+        assert!(element_grid_dir.is_angularly_symmetric());
+        let coords = element_grid_dir.get_coordinate_dir();
+        let profile = HydrostaticProfile::new(coords, &builder.composition(coords));
+        let kinds: Vec<_> = profile.bands().iter().map(|band| band.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![ElementType::Stone, ElementType::Water, ElementType::Ice]
+        );
+        for element_type in kinds {
+            let cells: Vec<_> = element_grid_dir.cells_of_kind(element_type).collect();
+            assert!(!cells.is_empty(), "No {:?}", element_type);
+            for cell in cells {
+                let midpoint = coords.cell_midpoint(cell).0.length();
+                assert_eq!(profile.kind_at(midpoint), element_type, "{:?}", cell);
+            }
+        }
+        // Nothing above the ice but space
+        let top = coords.get_num_layers() - 1;
+        let top_ring = coords.get_layer_num_concentric_circles(top) - 1;
+        assert_eq!(
+            element_grid_dir
+                .get_element(IjkVector::new(top, top_ring, 0))
+                .get_type(),
+            ElementType::Vacuum
+        );
+    }
+
+    #[test]
+    fn test_sunlight_melts_the_surface() {
+        let mut element_grid_dir = get_builder().build_element_dir();
+        let radius = element_grid_dir.get_coordinate_dir().get_radius().0;
+        let count = |element_grid_dir: &ElementGridDir, element_type| {
+            element_grid_dir.cells_of_kind(element_type).count()
+        };
+        let (ice, water) = (
+            count(&element_grid_dir, ElementType::Ice),
+            count(&element_grid_dir, ElementType::Water),
+        );
+
+        element_grid_dir.set_heat_config(HeatConfig {
+            solar_heating_rate: 20.0,
+            ..Default::default()
+        });
+        element_grid_dir.set_sunlight(Some(Vec2::X));
+        let mut clock = Clock::default();
+        for _ in 0..10 {
+            clock.update(Duration::from_secs(1));
+            element_grid_dir.process_full(clock);
+        }
+
+        let melted = ice - count(&element_grid_dir, ElementType::Ice);
+        assert!(melted > 0);
+        assert_eq!(count(&element_grid_dir, ElementType::Water), water + melted);
+        // The night side stays frozen
+        let coords = element_grid_dir.get_coordinate_dir();
+        let night_side_ice = element_grid_dir
+            .cells_of_kind(ElementType::Ice)
+            .filter(|cell| coords.cell_midpoint(*cell).0.x < -0.5 * radius)
+            .count();
+        assert!(night_side_ice > 0);
+    }
+}
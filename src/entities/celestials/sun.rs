@@ -1,7 +1,7 @@
 use bevy::log::info;
 
 use crate::{
-    entities::celestials::celestial::CelestialData,
+    entities::celestials::celestial::CelestialRecipe,
     physics::{
         fallingsand::{
             data::element_directory::ElementGridDir, elements::element::ElementType,
@@ -77,8 +77,10 @@ impl SunBuilder {
         self.max_concentric_circles_per_chunk = max_concentric_circles_per_chunk;
         self
     }
+}
 
-    pub fn build(&self) -> CelestialData {
+impl CelestialRecipe for SunBuilder {
+    fn build_element_dir(&self) -> ElementGridDir {
         let coordinate_dir = CoordinateDirBuilder::new()
             .cell_radius(self.cell_radius)
             .num_layers(self.num_layers)
@@ -107,6 +109,6 @@ impl SunBuilder {
                 }
             }
         }
-        CelestialData::new(element_grid_dir)
+        element_grid_dir
     }
 }
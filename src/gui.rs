@@ -10,7 +10,9 @@ use self::{brush::BrushPlugin, camera::CameraPlugin};
 
 pub mod brush;
 pub mod camera;
+pub mod cell_inspector;
 pub mod element_picker;
+pub mod neighbor_graph;
 
 pub struct GuiUnifiedPlugin;
 
@@ -34,7 +36,9 @@ impl PluginGroup for GuiPluginGroup {
         PluginGroupBuilder::start::<Self>()
             .add(camera::CameraPlugin)
             .add(brush::BrushPlugin)
+            .add(cell_inspector::CellInspectorPlugin)
             .add(element_picker::ElementPickerPlugin)
+            .add(neighbor_graph::NeighborGraphPlugin)
             .add(GuiUnifiedPlugin)
     }
 }
@@ -6,11 +6,11 @@
 
 use crate::entities::celestials::celestial::CelestialData;
 use crate::entities::utils::Radius;
+use crate::physics::fallingsand::elements::registry::ElementRegistry;
 use crate::physics::fallingsand::util::mesh::GizmoDrawableLoop;
-use crate::physics::util::clock::Clock;
+use crate::physics::orbits::components::Velocity;
 use crate::physics::util::vectors::{mouse_coord_to_world_coord, RelXyPoint};
 use bevy::app::{App, Plugin, Update};
-use bevy::core::FrameCount;
 use bevy::core_pipeline::core_2d::Camera2d;
 use bevy::ecs::entity::Entity;
 use bevy::ecs::query::Without;
@@ -19,12 +19,11 @@ use bevy::hierarchy::{BuildChildren, Parent};
 use bevy::input::keyboard::KeyCode;
 use bevy::input::mouse::MouseButton;
 use bevy::input::Input;
-use bevy::log::debug;
+use bevy::log::{debug, warn};
 use bevy::math::{Vec2, Vec3};
 use bevy::prelude::Window;
 use bevy::render::color::Color;
 
-use bevy::time::Time;
 use bevy::{
     ecs::{component::Component, event::EventReader, query::With, system::Query},
     gizmos::gizmos::Gizmos,
@@ -50,6 +49,7 @@ impl Plugin for BrushPlugin {
                 Self::move_brush_system,
                 Self::draw_brush_system,
                 Self::resize_brush_system,
+                Self::launch_brush_system,
                 // Self::apply_brush_system,
             ),
         );
@@ -125,6 +125,41 @@ impl BrushPlugin {
         }
     }
 
+    /// Throw the cell under the brush off the celestial with L, at twice its escape velocity
+    /// Only a cell on the surface leaves, see [CelestialData::launch]
+    #[allow(clippy::type_complexity)]
+    pub fn launch_brush_system(
+        keys: Res<Input<KeyCode>>,
+        brush: Query<(&Parent, &Transform), With<BrushComponent>>,
+        camera: Query<(&Parent, &Transform), (With<MainCamera>, Without<BrushComponent>)>,
+        mut celestial: Query<&mut CelestialData>,
+    ) {
+        if !keys.just_pressed(KeyCode::L) {
+            return;
+        }
+        let Ok((brush_parent, brush_transform)) = brush.get_single() else {
+            return;
+        };
+        let Ok((camera_parent, camera_transform)) = camera.get(brush_parent.get()) else {
+            return;
+        };
+        let Ok(mut celestial) = celestial.get_mut(camera_parent.get()) else {
+            return;
+        };
+        let pos = RelXyPoint::new(
+            brush_transform.translation.x + camera_transform.translation.x,
+            brush_transform.translation.y + camera_transform.translation.y,
+        );
+        let element_dir = &celestial.element_grid_dir;
+        let coord_dir = element_dir.get_coordinate_dir();
+        let Ok(cell) = coord_dir.rel_pos_to_cell_idx(pos) else {
+            return;
+        };
+        let outward = coord_dir.cell_midpoint(cell).0.normalize_or_zero();
+        let speed = 2.0 * element_dir.escape_velocity() as f32;
+        celestial.launch(cell, Velocity(outward * speed));
+    }
+
     /// Based on the brush radius and the celestial cell size, return a list of
     /// points in relative xy coordinates that the brush will affect.
     pub fn apply_brush_system(
@@ -136,8 +171,7 @@ impl BrushPlugin {
         >,
         mut celestial: Query<&mut CelestialData>,
         element_picker: Res<ElementSelection>,
-        current_time: Res<Time>,
-        frame_count: Res<FrameCount>,
+        registry: Res<ElementRegistry>,
     ) {
         if !mouse.pressed(MouseButton::Left) {
             return;
@@ -188,14 +222,19 @@ impl BrushPlugin {
                 .0;
         }
 
-        // Now apply the brush to the celestial
-        let current_time = Clock::new(current_time.as_generic(), frame_count.as_ref().to_owned());
+        // Now apply the brush to the celestial, through it so a recording sees the strokes
+        let element = match registry.create_kind(element_picker.0) {
+            Ok(element) => element,
+            Err(err) => {
+                warn!("Can't paint with the picked element: {}", err);
+                return;
+            }
+        };
         for pos in positions {
-            let element_dir = &mut celestial.element_grid_dir;
-            let coord_dir = element_dir.get_coordinate_dir();
+            let coord_dir = celestial.element_grid_dir.get_coordinate_dir();
             let conversion = coord_dir.rel_pos_to_cell_idx(pos);
             if let Ok(coords) = conversion {
-                element_dir.set_element(coords, element_picker.0.get_element(), current_time);
+                celestial.set_element(coords, element.box_clone(), &registry);
             }
         }
     }
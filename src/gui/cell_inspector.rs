@@ -0,0 +1,137 @@
+//! A small debug window describing whatever cell is under the cursor.
+
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        query::{With, Without},
+        system::{Query, Res, ResMut, Resource},
+    },
+    hierarchy::Parent,
+    transform::components::Transform,
+};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::entities::celestials::celestial::CelestialData;
+use crate::physics::fallingsand::elements::element::ElementType;
+use crate::physics::fallingsand::util::vectors::IjkVector;
+use crate::physics::util::vectors::RelXyPoint;
+
+use super::brush::BrushComponent;
+use super::camera::MainCamera;
+
+/// Shows the element under the cursor in a window
+pub struct CellInspectorPlugin;
+
+impl Plugin for CellInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CellUnderCursor>();
+        app.add_systems(
+            Update,
+            (
+                CellUnderCursor::find_cell_under_cursor_system,
+                CellUnderCursor::cell_inspector_system,
+            ),
+        );
+    }
+}
+
+/// A description of the cell under the cursor, None when the cursor isn't over a celestial
+#[derive(Resource, Default)]
+pub struct CellUnderCursor(pub Option<String>);
+
+/// Describes a cell in one line, for the cell inspector
+/// Cells with no temperature, like vacuum, have None
+pub fn format_cell_info(
+    element_type: ElementType,
+    cell: IjkVector,
+    radius: f32,
+    temperature: Option<f32>,
+) -> String {
+    let temperature = match temperature {
+        Some(temperature) => format!("{:.1}K", temperature),
+        None => "n/a".to_string(),
+    };
+    format!(
+        "{:?}, ({}, {}, {}), r={:.2}, T={}",
+        element_type, cell.i, cell.j, cell.k, radius, temperature
+    )
+}
+
+impl CellUnderCursor {
+    /// The brush follows the mouse, so the cell under the brush is the cell under the cursor
+    /// Like the brush, this works in the frame of the celestial the camera is parented to
+    #[allow(clippy::type_complexity)]
+    pub fn find_cell_under_cursor_system(
+        brush: Query<(&Parent, &Transform), With<BrushComponent>>,
+        camera: Query<(&Parent, &Transform), (With<MainCamera>, Without<BrushComponent>)>,
+        celestial: Query<&CelestialData>,
+        mut cell_under_cursor: ResMut<CellUnderCursor>,
+    ) {
+        cell_under_cursor.0 = None;
+        let Ok((brush_parent, brush_transform)) = brush.get_single() else {
+            return;
+        };
+        let Ok((camera_parent, camera_transform)) = camera.get(brush_parent.get()) else {
+            return;
+        };
+        let Ok(celestial) = celestial.get(camera_parent.get()) else {
+            return;
+        };
+        let pos = RelXyPoint::new(
+            brush_transform.translation.x + camera_transform.translation.x,
+            brush_transform.translation.y + camera_transform.translation.y,
+        );
+        let element_grid_dir = &celestial.element_grid_dir;
+        let coord_dir = element_grid_dir.get_coordinate_dir();
+        if let Ok(cell) = coord_dir.rel_pos_to_cell_idx(pos) {
+            let radius = coord_dir.cell_midpoint(cell).0.length();
+            let element_type = element_grid_dir.get_element(cell).get_type();
+            let temperature = element_grid_dir.get_temperature(cell).0;
+            let mut info = format_cell_info(
+                element_type,
+                cell,
+                radius,
+                (temperature != 0.0).then_some(temperature),
+            );
+            // A cell's temperature jumps from one cell to the next, this follows the cursor smoothly
+            if let Some(blended) = element_grid_dir.sample_temperature_bilinear(pos) {
+                info.push_str(&format!("\nT at cursor={:.1}K", blended));
+            }
+            cell_under_cursor.0 = Some(info);
+        }
+    }
+
+    /// Show the description in a window
+    pub fn cell_inspector_system(
+        mut contexts: EguiContexts,
+        cell_under_cursor: Res<CellUnderCursor>,
+    ) {
+        egui::Window::new("Cell Inspector").show(contexts.ctx_mut(), |ui| {
+            match &cell_under_cursor.0 {
+                Some(info) => ui.label(info),
+                None => ui.label("Nothing under the cursor"),
+            };
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_cell_info() {
+        let cell = IjkVector::new(3, 2, 17);
+        assert_eq!(
+            format_cell_info(ElementType::Sand, cell, 12.5, Some(300.0)),
+            "Sand, (3, 2, 17), r=12.50, T=300.0K"
+        );
+        assert_eq!(
+            format_cell_info(ElementType::Vacuum, cell, 1.0, None),
+            "Vacuum, (3, 2, 17), r=1.00, T=n/a"
+        );
+    }
+}
@@ -54,6 +54,16 @@ impl ElementSelection {
             ui.radio_value(&mut element_selection.0, ElementType::Stone, "Stone");
             ui.radio_value(&mut element_selection.0, ElementType::Lava, "Lava");
             ui.radio_value(&mut element_selection.0, ElementType::Water, "Water");
+            ui.radio_value(&mut element_selection.0, ElementType::Conveyor, "Conveyor");
+            ui.radio_value(&mut element_selection.0, ElementType::Ice, "Ice");
+            ui.radio_value(&mut element_selection.0, ElementType::Plant, "Plant");
+            ui.radio_value(&mut element_selection.0, ElementType::Fire, "Fire");
+            ui.radio_value(&mut element_selection.0, ElementType::Ash, "Ash");
+            ui.separator();
+            ui.label("Circuits");
+            ui.radio_value(&mut element_selection.0, ElementType::Metal, "Metal");
+            ui.radio_value(&mut element_selection.0, ElementType::Battery, "Battery");
+            ui.radio_value(&mut element_selection.0, ElementType::Lamp, "Lamp");
         });
     }
 }
@@ -0,0 +1,257 @@
+//! A debug overlay drawing an arrow from the chunk under the cursor to each of its neighbors.
+//! The neighbors are the chunks a convolution takes out of the directory along with it,
+//! so this shows how they fan out where the layers double.
+
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        entity::Entity,
+        query::{With, Without},
+        system::{Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    hierarchy::Parent,
+    input::{keyboard::KeyCode, Input},
+    math::Vec2,
+    render::color::Color,
+    transform::components::Transform,
+};
+
+use crate::entities::celestials::celestial::CelestialData;
+use crate::physics::fallingsand::convolution::neighbor_indexes::LeftRightNeighborIdxs;
+use crate::physics::fallingsand::data::element_directory::ElementGridDir;
+use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector};
+use crate::physics::util::vectors::RelXyPoint;
+
+use super::brush::BrushComponent;
+use super::camera::MainCamera;
+
+/// Toggled with G, draws the neighbor graph of the chunk under the cursor
+pub struct NeighborGraphPlugin;
+
+impl Plugin for NeighborGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NeighborGraph>();
+        app.add_systems(
+            Update,
+            (
+                NeighborGraph::toggle_system,
+                NeighborGraph::pick_chunk_system,
+                NeighborGraph::draw_system,
+            ),
+        );
+    }
+}
+
+/// Which side of a chunk a neighbor is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NeighborDirection {
+    /// Further from the core
+    Top,
+    /// Closer to the core
+    Bottom,
+    /// The next chunk in the ring with a greater k
+    Left,
+    /// The next chunk in the ring with a smaller k
+    Right,
+}
+
+impl NeighborDirection {
+    /// The color arrows in this direction are drawn in
+    pub fn color(&self) -> Color {
+        match self {
+            NeighborDirection::Top => Color::GREEN,
+            NeighborDirection::Bottom => Color::RED,
+            NeighborDirection::Left => Color::CYAN,
+            NeighborDirection::Right => Color::YELLOW,
+        }
+    }
+}
+
+/// An arrow from the middle of a chunk to the middle of one of its neighbors
+/// Positions are relative to the center of the celestial
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeighborArrow {
+    /// The neighbor being pointed at
+    pub target: ChunkIjkVector,
+    /// Which side of the chunk it is on
+    pub direction: NeighborDirection,
+    /// The middle of the chunk
+    pub start: Vec2,
+    /// The middle of the neighbor
+    pub end: Vec2,
+}
+
+/// The middle cell of a chunk, which is near the middle of the chunk even when it wraps all the way around
+fn chunk_center(element_grid_dir: &ElementGridDir, chunk: ChunkIjkVector) -> Vec2 {
+    let coord_dir = element_grid_dir.get_coordinate_dir();
+    let coords = coord_dir.get_chunk_at_idx(chunk);
+    let middle = IjkVector::new(
+        chunk.i,
+        coords.get_start_concentric_circle_layer_relative()
+            + coords.get_num_concentric_circles() / 2,
+        coords.get_start_radial_line() + coords.get_num_radial_lines() / 2,
+    );
+    coord_dir.cell_midpoint(middle).0
+}
+
+/// An arrow for every neighbor [ElementGridDir::get_chunk_neighbors] finds for the chunk
+pub fn neighbor_arrows(
+    element_grid_dir: &ElementGridDir,
+    chunk: ChunkIjkVector,
+) -> Vec<NeighborArrow> {
+    let neighbors = element_grid_dir.get_chunk_neighbors(chunk);
+    let LeftRightNeighborIdxs::LR { l, r } = neighbors.left_right;
+    let start = chunk_center(element_grid_dir, chunk);
+    neighbors
+        .top
+        .iter()
+        .map(|target| (target, NeighborDirection::Top))
+        .chain(
+            neighbors
+                .bottom
+                .iter()
+                .map(|target| (target, NeighborDirection::Bottom)),
+        )
+        .chain([(l, NeighborDirection::Left), (r, NeighborDirection::Right)])
+        .map(|(target, direction)| NeighborArrow {
+            target,
+            direction,
+            start,
+            end: chunk_center(element_grid_dir, target),
+        })
+        .collect()
+}
+
+/// Whether the overlay is on, and the chunk it is drawn for
+#[derive(Resource, Default)]
+pub struct NeighborGraph {
+    /// Flipped by [Self::toggle_system]
+    pub enabled: bool,
+    /// The celestial and chunk under the cursor, None when the cursor isn't over one
+    pub picked: Option<(Entity, ChunkIjkVector)>,
+}
+
+impl NeighborGraph {
+    /// Turns the overlay on and off with G
+    pub fn toggle_system(keys: Res<Input<KeyCode>>, mut graph: ResMut<NeighborGraph>) {
+        if keys.just_pressed(KeyCode::G) {
+            graph.enabled = !graph.enabled;
+        }
+    }
+
+    /// Picks the chunk under the brush, the same way the cell inspector finds its cell
+    #[allow(clippy::type_complexity)]
+    pub fn pick_chunk_system(
+        brush: Query<(&Parent, &Transform), With<BrushComponent>>,
+        camera: Query<(&Parent, &Transform), (With<MainCamera>, Without<BrushComponent>)>,
+        celestial: Query<&CelestialData>,
+        mut graph: ResMut<NeighborGraph>,
+    ) {
+        graph.picked = None;
+        if !graph.enabled {
+            return;
+        }
+        let Ok((brush_parent, brush_transform)) = brush.get_single() else {
+            return;
+        };
+        let Ok((camera_parent, camera_transform)) = camera.get(brush_parent.get()) else {
+            return;
+        };
+        let Ok(data) = celestial.get(camera_parent.get()) else {
+            return;
+        };
+        let pos = RelXyPoint::new(
+            brush_transform.translation.x + camera_transform.translation.x,
+            brush_transform.translation.y + camera_transform.translation.y,
+        );
+        let coord_dir = data.element_grid_dir.get_coordinate_dir();
+        if let Ok(cell) = coord_dir.rel_pos_to_cell_idx(pos) {
+            graph.picked = Some((camera_parent.get(), coord_dir.cell_idx_to_chunk_idx(cell).0));
+        }
+    }
+
+    /// Draws the arrows of the picked chunk over the celestial
+    pub fn draw_system(
+        graph: Res<NeighborGraph>,
+        celestial: Query<(&CelestialData, &Transform)>,
+        mut gizmos: Gizmos,
+    ) {
+        let Some((entity, chunk)) = graph.picked else {
+            return;
+        };
+        let Ok((data, transform)) = celestial.get(entity) else {
+            return;
+        };
+        let to_world = |point: Vec2| transform.transform_point(point.extend(0.0)).truncate();
+        for arrow in neighbor_arrows(&data.element_grid_dir, chunk) {
+            let (start, end) = (to_world(arrow.start), to_world(arrow.end));
+            let color = arrow.direction.color();
+            gizmos.line_2d(start, end, color);
+            // Two short barbs at the end, turned back 30 degrees either side
+            let back = (start - end).normalize_or_zero() * (end - start).length().min(4.0) * 0.25;
+            for barb in [Vec2::from_angle(0.5), Vec2::from_angle(-0.5)] {
+                gizmos.line_2d(end, end + barb.rotate(back), color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashSet;
+
+    use super::*;
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::orbits::components::Length;
+
+    #[test]
+    fn test_arrows_point_at_every_neighbor() {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(8)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+        let coord_dir = element_grid_dir.get_coordinate_dir();
+        // A chunk right under a layer where the chunks double
+        let chunk = (0..coord_dir.get_num_layers() - 1)
+            .map(|i| ChunkIjkVector::new(i, coord_dir.get_layer_num_concentric_chunks(i) - 1, 1))
+            .find(|chunk| {
+                coord_dir.get_layer_num_tangential_chunkss(chunk.i + 1)
+                    == 2 * coord_dir.get_layer_num_tangential_chunkss(chunk.i)
+            })
+            .expect("Some layer should double its chunks");
+
+        let arrows = neighbor_arrows(&element_grid_dir, chunk);
+        let targets: HashSet<ChunkIjkVector> = arrows.iter().map(|arrow| arrow.target).collect();
+        let neighbors: HashSet<ChunkIjkVector> =
+            element_grid_dir.get_chunk_neighbors(chunk).iter().collect();
+        assert_eq!(targets, neighbors);
+        assert_eq!(arrows.len(), neighbors.len());
+
+        // Four chunks fan out above, and each arrow goes out or in the way it says
+        let count = |direction| {
+            arrows
+                .iter()
+                .filter(|arrow| arrow.direction == direction)
+                .count()
+        };
+        assert_eq!(count(NeighborDirection::Top), 4);
+        assert_eq!(count(NeighborDirection::Left), 1);
+        assert_eq!(count(NeighborDirection::Right), 1);
+        for arrow in arrows {
+            match arrow.direction {
+                NeighborDirection::Top => assert!(arrow.end.length() > arrow.start.length()),
+                NeighborDirection::Bottom => assert!(arrow.end.length() < arrow.start.length()),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! The simulation, celestials and interface the game is built from, see the binary for the scenes
+//! Split out of the game so the benchmarks in `benches` can drive the simulation directly
+pub mod entities;
+pub mod gui;
+pub mod physics;
@@ -2,29 +2,31 @@
 //! For players, we will eventually create a mdbook describing gameplay.
 //! This is the entry point for the game. It installs the plugins and contains
 //! a couple of setup functions for creating different scenes.
-pub mod entities;
-pub mod gui;
-pub mod physics;
 
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-use crate::entities::celestials::celestial::CelestialBuilder;
-use crate::entities::celestials::earthlike::EarthLikeBuilder;
-use crate::entities::celestials::sun::SunBuilder;
-use crate::entities::EntitiesPluginGroup;
 use bevy::sprite::MaterialMesh2dBundle;
 use bevy::{log::LogPlugin, prelude::*};
 use bevy_egui::EguiPlugin;
 use bevy_mod_picking::low_latency_window_plugin;
 use bevy_mod_picking::DefaultPickingPlugins;
-use gui::camera::MainCamera;
+use orbiting_sand::entities::celestials::celestial::{CelestialBuilder, Sun};
+use orbiting_sand::entities::celestials::earthlike::EarthLikeBuilder;
+use orbiting_sand::entities::celestials::sun::SunBuilder;
+use orbiting_sand::entities::EntitiesPluginGroup;
+use orbiting_sand::gui::camera::MainCamera;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-use crate::gui::camera::{BackgroundLayer1, CelestialIdx};
-use crate::gui::GuiPluginGroup;
-use crate::physics::orbits::components::{Mass, Velocity};
+use orbiting_sand::gui::camera::{BackgroundLayer1, CelestialIdx};
+use orbiting_sand::gui::GuiPluginGroup;
+use orbiting_sand::physics::orbits::components::{Mass, Velocity};
 
-use crate::physics::PhysicsPluginGroup;
+use orbiting_sand::physics::PhysicsPluginGroup;
+
+/// Seeds everything random about the scene, so every run starts out the same
+const WORLD_SEED: u64 = 0;
 
 /// Create the bevy app
 fn main() {
@@ -62,36 +64,35 @@ fn solar_system_setup(
     let mut idx = CelestialIdx(0);
 
     // Create earth
-    let planet_data = EarthLikeBuilder::new().build();
-    CelestialBuilder::new(&mut idx, "Earth1".to_string(), planet_data)
+    CelestialBuilder::new(&mut idx, "Earth1".to_string(), &EarthLikeBuilder::new())
         .translation(Vec2::new(-10000., 0.))
         .velocity(Velocity(Vec2::new(0., 1200.)))
         .build(&mut commands, &mut meshes, &mut materials, &asset_server);
 
     // Create earth2
-    let planet_data = EarthLikeBuilder::new().build();
-    CelestialBuilder::new(&mut idx, "Earth2".to_string(), planet_data)
+    CelestialBuilder::new(&mut idx, "Earth2".to_string(), &EarthLikeBuilder::new())
         .translation(Vec2::new(10000., 0.))
         .velocity(Velocity(Vec2::new(0., -1200.)))
         .build(&mut commands, &mut meshes, &mut materials, &asset_server);
 
-    // Create a sun
-    let sun_data = SunBuilder::new().build();
-    CelestialBuilder::new(&mut idx, "Sun".to_string(), sun_data).build(
+    // Create a sun, which lights the planets
+    let sun = CelestialBuilder::new(&mut idx, "Sun".to_string(), &SunBuilder::new()).build(
         &mut commands,
         &mut meshes,
         &mut materials,
         &asset_server,
     );
+    commands.entity(sun).insert(Sun);
 
     // Create a bunch of asteroids
     const NUM_ASTEROIDS: usize = 10000;
+    let mut rng = StdRng::seed_from_u64(WORLD_SEED);
     for i in 0..NUM_ASTEROIDS {
         // Put them in a circle around the sun
         // at radius 5000 with a tangent velocity of 600
         let angle = (i as f32 / NUM_ASTEROIDS as f32) * 2.0 * std::f32::consts::PI;
         // random radius between 5000.0 and 6000.0
-        let r = 5000.0 + 1000.0 * rand::random::<f32>();
+        let r = 5000.0 + 1000.0 * rng.gen::<f32>();
         let pos = r * Vec2::new(angle.cos(), angle.sin());
         let vel = Vec2::new(angle.sin(), -angle.cos()) * 2000.0;
         commands.spawn((
@@ -118,9 +119,12 @@ fn planet_only_setup(
     asset_server: Res<AssetServer>,
 ) {
     // Create earth
-    let planet_data = EarthLikeBuilder::new().build();
-    let planet_id = CelestialBuilder::new(&mut CelestialIdx(0), "Earth".to_string(), planet_data)
-        .build(&mut commands, &mut meshes, &mut materials, &asset_server);
+    let planet_id = CelestialBuilder::new(
+        &mut CelestialIdx(0),
+        "Earth".to_string(),
+        &EarthLikeBuilder::new(),
+    )
+    .build(&mut commands, &mut meshes, &mut materials, &asset_server);
 
     // Parent the camera to the sun
     commands.entity(planet_id).push_children(&[camera.single()]);
@@ -1,18 +1,137 @@
 //! This module contains all the physics related code.
 
-use bevy::app::{PluginGroup, PluginGroupBuilder};
+use bevy::app::{App, Plugin, PluginGroup, PluginGroupBuilder, PreUpdate};
+use bevy::ecs::change_detection::DetectChanges;
+use bevy::ecs::system::{Res, ResMut, Resource};
+use bevy::time::{Fixed, Time};
 
 pub mod fallingsand;
 pub mod orbits;
 pub mod util;
 
-/// The number of physics frames per second.
+/// The default number of physics frames per second.
 pub const PHYSICS_FRAME_RATE: f64 = 30.0;
 
+/// How many times per second everything in FixedUpdate runs, no matter the render framerate
+/// Leftover time between renders is carried over to the next one by bevy's fixed timestep
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct TickRate(pub f64);
+
+impl Default for TickRate {
+    fn default() -> Self {
+        Self(PHYSICS_FRAME_RATE)
+    }
+}
+
+/// Keeps the fixed timestep in line with the [TickRate] resource
+pub struct TickRatePlugin;
+
+impl Plugin for TickRatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TickRate>();
+        app.insert_resource(Time::<Fixed>::from_hz(PHYSICS_FRAME_RATE));
+        app.add_systems(PreUpdate, Self::apply_tick_rate_system);
+    }
+}
+
+impl TickRatePlugin {
+    /// Runs before the fixed update loop, so a new rate takes effect the same frame it is set
+    pub fn apply_tick_rate_system(tick_rate: Res<TickRate>, mut fixed_time: ResMut<Time<Fixed>>) {
+        if tick_rate.is_changed() {
+            fixed_time.set_timestep_hz(tick_rate.0);
+        }
+    }
+}
+
 pub struct PhysicsPluginGroup;
 
 impl PluginGroup for PhysicsPluginGroup {
     fn build(self) -> PluginGroupBuilder {
-        PluginGroupBuilder::start::<Self>().add(orbits::nbody::NBodyPlugin)
+        PluginGroupBuilder::start::<Self>()
+            .add(TickRatePlugin)
+            .add(orbits::nbody::NBodyPlugin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::app::FixedUpdate;
+    use bevy::asset::{AssetApp, AssetPlugin};
+    use bevy::sprite::ColorMaterial;
+    use bevy::time::TimeUpdateStrategy;
+    use bevy::MinimalPlugins;
+
+    use super::*;
+    use crate::entities::celestials::celestial::{
+        CelestialData, CelestialDataPlugin, MeshDebugSettings,
+    };
+    use crate::physics::fallingsand::data::element_directory::ElementGridDir;
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::fallingsand::util::heatmap::HeatGradient;
+    use crate::physics::fallingsand::util::palette::ElementPalette;
+    use crate::physics::fallingsand::util::pressuremap::PressureOverlay;
+    use crate::physics::orbits::components::{Length, Mass, SurfaceGravity};
+
+    #[derive(Resource, Default)]
+    struct Ticks(u32);
+
+    /// One second of rendering at 144 fps runs a 60 Hz simulation 60 times, one process of the
+    /// celestial per tick
+    #[test]
+    fn test_celestials_process_at_the_tick_rate() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), TickRatePlugin));
+        app.init_asset::<ColorMaterial>();
+        app.init_resource::<MeshDebugSettings>();
+        app.init_resource::<HeatGradient>();
+        app.init_resource::<PressureOverlay>();
+        app.init_resource::<ElementPalette>();
+        app.insert_resource(TickRate(60.0));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / 144.0,
+        )));
+        app.init_resource::<Ticks>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                CelestialDataPlugin::process_system,
+                |mut ticks: ResMut<Ticks>| ticks.0 += 1,
+            ),
+        );
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(3)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .build();
+        let celestial = app
+            .world
+            .spawn((
+                CelestialData::new(ElementGridDir::new_empty(coordinate_dir)),
+                Mass(0.0),
+                SurfaceGravity(0.0),
+            ))
+            .id();
+
+        // The first update only starts the clock
+        app.update();
+        for _ in 0..144 {
+            app.update();
+        }
+        let ticks = app.world.resource::<Ticks>().0;
+        let processed = app
+            .world
+            .get::<CelestialData>(celestial)
+            .unwrap()
+            .get_element_dir()
+            .get_process_count();
+        assert_eq!(processed, ticks as usize);
+        assert!(
+            (processed as i64 - 60).abs() <= 1,
+            "Expected about 60 processes, got {}",
+            processed
+        );
     }
 }
@@ -64,3 +64,4 @@ pub mod behaviors;
 pub mod neighbor_grids;
 pub mod neighbor_identifiers;
 pub mod neighbor_indexes;
+pub mod read_only;
@@ -27,9 +27,10 @@ use crate::physics::{
         mesh::coordinate_directory::CoordinateDir,
         util::{
             functions::modulo,
-            vectors::{ChunkIjkVector, JkVector},
+            vectors::{ChunkIjkVector, IjkVector, JkVector},
         },
     },
+    orbits::components::ThermodynamicTemperature,
     util::clock::Clock,
 };
 
@@ -44,10 +45,7 @@ use super::{
         LeftRightNeighborIdentifier, TopNeighborIdentifier, TopNeighborIdentifierChunkDoubling,
         TopNeighborIdentifierNormal,
     },
-    neighbor_indexes::{
-        BottomNeighborIdxs, ElementGridConvolutionNeighborIdxs,
-        ElementGridConvolutionNeighborIdxsIter,
-    },
+    neighbor_indexes::{BottomNeighborIdxs, ElementGridConvolutionNeighborIdxs},
 };
 
 /// This is the main struct exported by this module
@@ -64,13 +62,15 @@ pub struct ElementGridConvolutionNeighbors {
 /// Instantiation
 impl ElementGridConvolutionNeighbors {
     /// Create a new ElementGridConvolutionNeighbors
+    /// Takes the neighbors out of `grids`, leaving it empty but with its capacity, so the same map
+    /// can be filled again for the next convolution
     pub fn new(
         chunk_idxs: ElementGridConvolutionNeighborIdxs,
-        mut grids: HashMap<ChunkIjkVector, ElementGrid>,
+        grids: &mut HashMap<ChunkIjkVector, ElementGrid>,
     ) -> Self {
-        let lr_neighbors = LeftRightNeighborGrids::from_hashmap(&chunk_idxs.left_right, &mut grids);
-        let top_neighbors = TopNeighborGrids::from_hashmap(&chunk_idxs.top, &mut grids);
-        let bottom_neighbors = BottomNeighborGrids::from_hashmap(&chunk_idxs.bottom, &mut grids);
+        let lr_neighbors = LeftRightNeighborGrids::from_hashmap(&chunk_idxs.left_right, grids);
+        let top_neighbors = TopNeighborGrids::from_hashmap(&chunk_idxs.top, grids);
+        let bottom_neighbors = BottomNeighborGrids::from_hashmap(&chunk_idxs.bottom, grids);
         ElementGridConvolutionNeighbors {
             chunk_idxs,
             grids: ElementGridConvolutionNeighborGrids {
@@ -93,26 +93,18 @@ impl ElementGridConvolutionNeighbors {
 
 /// Iteration
 /// We are going to implement into interation on the Neighbors so that unpackaging is easier
-/// To do this we will use the into_hashmap method on the neighbor grids
-/// and the iter method on the neighbor indexes
-/// taking from the hashmap on each iteration of the iter
+/// The grids are laid out in a fixed size array by [ElementGridConvolutionNeighborGrids::into_grids],
+/// so giving them back doesn't allocate anything
 pub struct ElementGridConvolutionNeighborsIntoIter {
-    /// The iterator for the neighbor indexes
-    chunk_idxs_iter: ElementGridConvolutionNeighborIdxsIter,
-    /// The grids of the neighbors
-    grids: HashMap<ChunkIjkVector, ElementGrid>,
+    /// The grids of the neighbors, None where the convolution has fewer
+    grids: std::array::IntoIter<Option<ElementGrid>, 9>,
 }
 
 impl Iterator for ElementGridConvolutionNeighborsIntoIter {
     type Item = (ChunkIjkVector, ElementGrid);
     fn next(&mut self) -> Option<Self::Item> {
-        match self.chunk_idxs_iter.next() {
-            Some(chunk_idx) => {
-                let grid = self.grids.remove(&chunk_idx)?;
-                Some((chunk_idx, grid))
-            }
-            None => None,
-        }
+        let grid = self.grids.by_ref().flatten().next()?;
+        Some((grid.get_chunk_coords().get_chunk_idx(), grid))
     }
 }
 
@@ -121,8 +113,7 @@ impl IntoIterator for ElementGridConvolutionNeighbors {
     type IntoIter = ElementGridConvolutionNeighborsIntoIter;
     fn into_iter(self) -> Self::IntoIter {
         ElementGridConvolutionNeighborsIntoIter {
-            chunk_idxs_iter: self.chunk_idxs.iter(),
-            grids: self.grids.into_hashmap(),
+            grids: self.grids.into_grids().into_iter(),
         }
     }
 }
@@ -136,7 +127,7 @@ impl ElementGridConvolutionNeighbors {
     pub fn get_below_idx_from_center(
         &self,
         target_chunk: &ElementGrid,
-        _coord_dir: &CoordinateDir,
+        coord_dir: &CoordinateDir,
         pos: &JkVector,
         n: usize,
     ) -> Result<ConvolutionIdx, ConvOutOfBoundsError> {
@@ -186,20 +177,16 @@ impl ElementGridConvolutionNeighbors {
                     )),
                 ))
             }
-            BottomNeighborIdxs::Normal { .. } => {
-                let mut new_coords = JkVector {
-                    j: pos.j + b_concentric_circles - n,
-                    k: pos.k,
-                };
+            BottomNeighborIdxs::Normal { b, .. } => {
                 // Sometimes a "Normal" bottom index is actually on a different layer
                 // just with the same number of tangential chunkss
-                // If there are the same number of radial lines (not a layer transition) we dont
-                // need to divide k by 2
-                let this_radial_lines = target_chunk.get_chunk_coords().get_num_radial_lines();
-                let b_radial_lines = self.grids.bottom.get_num_radial_lines();
-                if this_radial_lines != b_radial_lines {
-                    new_coords.k = pos.k / 2;
-                }
+                // in which case the radial lines halve, otherwise they match
+                let ratio = coord_dir
+                    .radial_resolution_ratio(target_chunk.get_chunk_coords().get_layer_num(), b.i);
+                let new_coords = JkVector {
+                    j: pos.j + b_concentric_circles - n,
+                    k: (pos.k as f32 * ratio) as usize,
+                };
                 Ok(ConvolutionIdx(
                     new_coords,
                     ConvolutionIdentifier::Bottom(BottomNeighborIdentifier::Normal(
@@ -210,8 +197,102 @@ impl ElementGridConvolutionNeighbors {
         }
     }
 
-    /// Positive k is left, counter clockwise
-    /// Negative k is right, clockwise
+    /// Gets the cell directly above an absolute cell index in the target chunk
+    /// the target chunk is the chunk in the center of the convolution
+    ///
+    /// When the origin is on the top row of its chunk, the layer above may be split
+    /// into more chunks than this one, and may have double the radial lines.
+    /// We use the origin's angle to pick which of the top chunks it sits under,
+    /// and which cell of that chunk is directly above it.
+    /// Returns None if the origin isn't in the target chunk, or there is nothing above it.
+    pub fn above_cell_for(
+        &self,
+        target_chunk: &ElementGrid,
+        origin: IjkVector,
+    ) -> Option<ConvolutionIdx> {
+        let coords = target_chunk.get_chunk_coords();
+        let pos = coords.absolute_cell_idx_to_in_chunk_cell_idx(origin).ok()?;
+
+        // Handle naive case where you don't change your chunk
+        if pos.j + 1 < coords.get_num_concentric_circles() {
+            return Some(ConvolutionIdx(
+                JkVector::new(pos.j + 1, pos.k),
+                ConvolutionIdentifier::Center,
+            ));
+        }
+
+        let candidates = match &self.grids.top {
+            TopNeighborGrids::Normal { tl, t, tr } => vec![
+                (
+                    TopNeighborIdentifier::Normal(TopNeighborIdentifierNormal::TopLeft),
+                    tl,
+                ),
+                (
+                    TopNeighborIdentifier::Normal(TopNeighborIdentifierNormal::Top),
+                    t,
+                ),
+                (
+                    TopNeighborIdentifier::Normal(TopNeighborIdentifierNormal::TopRight),
+                    tr,
+                ),
+            ],
+            TopNeighborGrids::ChunkDoubling { tl, t1, t0, tr } => vec![
+                (
+                    TopNeighborIdentifier::ChunkDoubling(
+                        TopNeighborIdentifierChunkDoubling::TopLeft,
+                    ),
+                    tl,
+                ),
+                (
+                    TopNeighborIdentifier::ChunkDoubling(TopNeighborIdentifierChunkDoubling::Top1),
+                    t1,
+                ),
+                (
+                    TopNeighborIdentifier::ChunkDoubling(TopNeighborIdentifierChunkDoubling::Top0),
+                    t0,
+                ),
+                (
+                    TopNeighborIdentifier::ChunkDoubling(
+                        TopNeighborIdentifierChunkDoubling::TopRight,
+                    ),
+                    tr,
+                ),
+            ],
+            TopNeighborGrids::TopOfGrid => return None,
+        };
+
+        // Map the origin's radial line onto the layer above
+        // If the radial lines double, the cell above is the first of the two covering our angle
+        let this_layer_radial_lines = coords.get_layer_num_radial_lines();
+        let (_, first_top) = candidates.first()?;
+        let top_layer_radial_lines = first_top.get_chunk_coords().get_layer_num_radial_lines();
+        let top_k = origin.k * top_layer_radial_lines / this_layer_radial_lines;
+
+        candidates.into_iter().find_map(|(id, grid)| {
+            let top_coords = grid.get_chunk_coords();
+            if top_k >= top_coords.get_start_radial_line()
+                && top_k < top_coords.get_end_radial_line()
+            {
+                Some(ConvolutionIdx(
+                    JkVector::new(0, top_k - top_coords.get_start_radial_line()),
+                    ConvolutionIdentifier::Top(id),
+                ))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The layer of the chunk that the index points into
+    pub fn get_layer_num(&self, target_chunk: &ElementGrid, idx: ConvolutionIdx) -> usize {
+        match self.get_chunk(idx.1) {
+            Ok(chunk) => chunk.get_chunk_coords().get_layer_num(),
+            Err(GetChunkErr::CenterChunk) => target_chunk.get_chunk_coords().get_layer_num(),
+        }
+    }
+
+    /// Positive k is left, clockwise with the default [Winding](crate::physics::fallingsand::util::vectors::Winding)
+    /// Negative k is right, counter clockwise with the default winding
     pub fn get_left_right_idx_from_center(
         &self,
         target_chunk: &ElementGrid,
@@ -513,7 +594,20 @@ impl ElementGridConvolutionNeighbors {
         }
     }
 
+    /// Whether the given index is in a static or resting neighbor, whose cells can be read but not replaced
+    /// See [ElementGrid::set_static] and [ElementGrid::set_resting]
+    pub fn is_read_only(&self, idx: ConvolutionIdx) -> bool {
+        match idx.1 {
+            ConvolutionIdentifier::Center => false,
+            id => self
+                .get_chunk(id)
+                .is_ok_and(|chunk| chunk.is_write_protected()),
+        }
+    }
+
     /// Get the element at the given index
+    /// Cells of a static neighbor can be looked at like any other, but check
+    /// [Self::is_read_only] before moving into one
     pub fn get(
         &self,
         target_grid: &ElementGrid,
@@ -536,10 +630,134 @@ impl ElementGridConvolutionNeighbors {
         }
     }
 
-    /// Replace the element at the given index
+    /// The pressure on the cell at the given index, see [ElementGrid::get_pressure]
+    pub fn get_pressure(
+        &self,
+        target_grid: &ElementGrid,
+        idx: ConvolutionIdx,
+    ) -> Result<f32, ConvOutOfBoundsError> {
+        let grid = match idx.1 {
+            ConvolutionIdentifier::Center => target_grid,
+            id => self.get_chunk(id).map_err(|_| ConvOutOfBoundsError(idx))?,
+        };
+        grid.checked_get_pressure(idx.0)
+            .map_err(|_| ConvOutOfBoundsError(idx))
+    }
+
+    /// The temperature of the cell at the given index, see [ElementGrid::get_temperature]
+    /// Heat still flows in and out of a static neighbor
+    pub fn get_temperature(
+        &self,
+        target_grid: &ElementGrid,
+        idx: ConvolutionIdx,
+    ) -> Result<ThermodynamicTemperature, ConvOutOfBoundsError> {
+        let grid = match idx.1 {
+            ConvolutionIdentifier::Center => target_grid,
+            id => self.get_chunk(id).map_err(|_| ConvOutOfBoundsError(idx))?,
+        };
+        grid.checked_get_temperature(idx.0)
+            .map_err(|_| ConvOutOfBoundsError(idx))
+    }
+
+    /// Borrows the element at the given index, None if it is out of bounds
+    fn get_ref<'a>(
+        &'a self,
+        target_grid: &'a ElementGrid,
+        idx: ConvolutionIdx,
+    ) -> Option<&'a dyn Element> {
+        let grid = match idx.1 {
+            ConvolutionIdentifier::Center => target_grid,
+            id => self.get_chunk(id).ok()?,
+        };
+        grid.checked_get(idx.0).ok().map(|element| element.as_ref())
+    }
+
+    /// The cells around an absolute cell index in the target chunk, and the cell itself in the middle
+    /// The rows are the ring above, the cell's own ring, and the ring below, and the columns are
+    /// k - 1, k and k + 1, the same as [Self::get_left_right_idx_from_center] with -1, 0 and 1
+    /// so a stencil can be written without matching on [ConvolutionIdentifier]s
+    /// Off the edge of the grid is None, and so is a corner that would be above or below a chunk
+    /// beside the target chunk, since the convolution doesn't hold those.
+    /// Everything is None if the origin isn't in the target chunk
+    pub fn moore_neighborhood<'a>(
+        &'a self,
+        target_chunk: &'a ElementGrid,
+        coord_dir: &CoordinateDir,
+        origin: IjkVector,
+    ) -> [[Option<&'a dyn Element>; 3]; 3] {
+        let mut out = [[None; 3]; 3];
+        let coords = target_chunk.get_chunk_coords();
+        let Ok(pos) = coords.absolute_cell_idx_to_in_chunk_cell_idx(origin) else {
+            return out;
+        };
+        for (col, rk) in [-1, 0, 1].into_iter().enumerate() {
+            let side = if rk == 0 {
+                ConvolutionIdx(pos, ConvolutionIdentifier::Center)
+            } else {
+                match self.get_left_right_idx_from_center(target_chunk, &pos, rk) {
+                    Ok(side) => side,
+                    Err(_) => continue,
+                }
+            };
+            let (above, below) = match side.1 {
+                ConvolutionIdentifier::Center => {
+                    let side_origin = IjkVector::new(
+                        origin.i,
+                        coords.get_start_concentric_circle_layer_relative() + side.0.j,
+                        coords.get_start_radial_line() + side.0.k,
+                    );
+                    (
+                        self.above_cell_for(target_chunk, side_origin),
+                        self.get_below_idx_from_center(target_chunk, coord_dir, &side.0, 1)
+                            .ok(),
+                    )
+                }
+                // Only the rings inside the chunk beside us can be reached from it
+                id => (
+                    Some(ConvolutionIdx(JkVector::new(side.0.j + 1, side.0.k), id)),
+                    side.0
+                        .j
+                        .checked_sub(1)
+                        .map(|j| ConvolutionIdx(JkVector::new(j, side.0.k), id)),
+                ),
+            };
+            for (row, idx) in [above, Some(side), below].into_iter().enumerate() {
+                out[row][col] = idx.and_then(|idx| self.get_ref(target_chunk, idx));
+            }
+        }
+        out
+    }
+
+    /// Moves an element into the given index along with its temperature, see [ElementGrid::replace_with_temperature]
+    /// Returns what was there and its temperature, or an error like [Self::replace]
+    pub fn replace_with_temperature(
+        &mut self,
+        target_grid: &mut ElementGrid,
+        idx: ConvolutionIdx,
+        element: Box<dyn Element>,
+        temperature: ThermodynamicTemperature,
+        current_time: Clock,
+    ) -> Result<(Box<dyn Element>, ThermodynamicTemperature), ConvOutOfBoundsError> {
+        match idx.1 {
+            ConvolutionIdentifier::Center => {
+                Ok(target_grid.replace_with_temperature(idx.0, element, temperature, current_time))
+            }
+            _ => match self.get_chunk_mut(idx.1) {
+                Ok(chunk) if chunk.is_write_protected() => Err(ConvOutOfBoundsError(idx)),
+                Ok(chunk) => {
+                    Ok(chunk.replace_with_temperature(idx.0, element, temperature, current_time))
+                }
+                Err(GetChunkErr::CenterChunk) => {
+                    unreachable!("This should never happen because we are checking for it in the match idx.1 statement")
+                }
+            },
+        }
+    }
+
+    /// Replace the element at the given index, the new element takes on the cell's temperature
     /// Great for taking ownership of the element
     /// Can also be used to give ownership back
-    /// Returns an error if the index is out of bounds
+    /// Returns an error if the index is out of bounds, or in a static or resting neighbor
     pub fn replace(
         &mut self,
         target_grid: &mut ElementGrid,
@@ -553,6 +771,7 @@ impl ElementGridConvolutionNeighbors {
                 Ok(out)
             }
             _ => match self.get_chunk_mut(idx.1) {
+                Ok(chunk) if chunk.is_write_protected() => Err(ConvOutOfBoundsError(idx)),
                 Ok(chunk) => {
                     let out = chunk.replace(idx.0, element, current_time);
                     Ok(out)
@@ -673,6 +892,83 @@ mod tests {
         );
     }
 
+    mod above_cell_for {
+        use super::*;
+        use crate::physics::{fallingsand::util::vectors::IjkVector, orbits::components::Length};
+
+        /// The default element grid directory for testing
+        /// Layer 2 -> 3 keeps the same number of chunks, layer 4 -> 5 doubles them
+        fn get_element_grid_dir() -> ElementGridDir {
+            let coordinate_dir = CoordinateDirBuilder::new()
+                .cell_radius(Length(1.0))
+                .num_layers(6)
+                .first_num_radial_lines(6)
+                .second_num_concentric_circles(3)
+                .max_concentric_circles_per_chunk(128)
+                .max_radial_lines_per_chunk(128)
+                .build();
+            ElementGridDir::new_empty(coordinate_dir)
+        }
+
+        /// Check every cell on the top row of layer i maps to the cell at double its k in layer i + 1
+        fn _test_above_cell_for_layer(i: usize) {
+            let coord_dir = get_element_grid_dir().get_coordinate_dir().clone();
+            let top_j = coord_dir.get_layer_num_concentric_circles(i) - 1;
+            for ck in 0..coord_dir.get_layer_num_tangential_chunkss(i) {
+                let mut element_dir = get_element_grid_dir();
+                let chunk_idx = coord_dir
+                    .cell_idx_to_chunk_idx(IjkVector::new(
+                        i,
+                        top_j,
+                        ck * coord_dir.get_layer_chunk_num_radial_lines(i),
+                    ))
+                    .0;
+                let package = element_dir.package_coordinate_neighbors(chunk_idx).unwrap();
+                let chunk = element_dir.get_chunk_by_chunk_ijk(chunk_idx);
+                let coords = chunk.get_chunk_coords();
+                for k in coords.get_start_radial_line()..coords.get_end_radial_line() {
+                    let expected = coord_dir.cell_idx_to_chunk_idx(IjkVector::new(i + 1, 0, 2 * k));
+                    let above = package
+                        .above_cell_for(chunk, IjkVector::new(i, top_j, k))
+                        .unwrap();
+                    assert_eq!(above.0, expected.1, "Wrong cell above k: {}", k);
+                    let above_chunk = package
+                        .get_chunk(above.1)
+                        .unwrap()
+                        .get_chunk_coords()
+                        .get_chunk_idx();
+                    assert_eq!(above_chunk, expected.0, "Wrong chunk above k: {}", k);
+                }
+            }
+        }
+
+        #[test]
+        fn test_above_cell_for_normal() {
+            _test_above_cell_for_layer(2);
+        }
+
+        #[test]
+        fn test_above_cell_for_chunk_doubling() {
+            _test_above_cell_for_layer(4);
+        }
+
+        #[test]
+        fn test_above_cell_for_inside_chunk() {
+            let mut element_dir = get_element_grid_dir();
+            let origin = IjkVector::new(2, 1, 5);
+            let chunk_idx = element_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(origin);
+            let package = element_dir
+                .package_coordinate_neighbors(chunk_idx.0)
+                .unwrap();
+            let chunk = element_dir.get_chunk_by_chunk_ijk(chunk_idx.0);
+            let above = package.above_cell_for(chunk, origin).unwrap();
+            assert_eq!(above.0, JkVector::new(2, chunk_idx.1.k));
+            assert!(matches!(above.1, ConvolutionIdentifier::Center));
+        }
+    }
+
     mod get_left_right_idx_from_center {
         use super::*;
         use crate::physics::{fallingsand::util::vectors::IjkVector, orbits::components::Length};
@@ -783,4 +1079,81 @@ mod tests {
             (5, 21, 383)
         );
     }
+
+    mod moore_neighborhood {
+        use super::*;
+        use crate::physics::fallingsand::elements::element::ElementType;
+        use crate::physics::{fallingsand::util::vectors::IjkVector, orbits::components::Length};
+
+        fn get_element_grid_dir() -> ElementGridDir {
+            let coordinate_dir = CoordinateDirBuilder::new()
+                .cell_radius(Length(1.0))
+                .num_layers(5)
+                .first_num_radial_lines(6)
+                .second_num_concentric_circles(3)
+                .max_concentric_circles_per_chunk(64)
+                .max_radial_lines_per_chunk(64)
+                .build();
+            let stone = ElementType::Stone.get_element();
+            ElementGridDir::new_checkerboard(coordinate_dir, &*stone, &*stone)
+        }
+
+        /// The kinds of element in the neighborhood of a cell, None where there is no cell
+        fn kinds_around(
+            element_grid_dir: &mut ElementGridDir,
+            origin: IjkVector,
+        ) -> [[Option<ElementType>; 3]; 3] {
+            let chunk_idx = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(origin)
+                .0;
+            let package = element_grid_dir
+                .package_coordinate_neighbors(chunk_idx)
+                .unwrap();
+            let chunk = element_grid_dir.get_chunk_by_chunk_ijk(chunk_idx);
+            package
+                .moore_neighborhood(chunk, element_grid_dir.get_coordinate_dir(), origin)
+                .map(|row| row.map(|element| element.map(|element| element.get_type())))
+        }
+
+        #[test]
+        fn test_middle_of_chunk_has_all_eight() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let origin = IjkVector::new(3, 5, 20);
+            for (cell, kind) in [
+                (IjkVector::new(3, 6, 19), ElementType::Sand),
+                (IjkVector::new(3, 5, 21), ElementType::Water),
+                (IjkVector::new(3, 4, 20), ElementType::Ice),
+            ] {
+                element_grid_dir.set_element(cell, kind.get_element(), Clock::default());
+            }
+            let stone = Some(ElementType::Stone);
+            assert_eq!(
+                kinds_around(&mut element_grid_dir, origin),
+                [
+                    [Some(ElementType::Sand), stone, stone],
+                    [stone, stone, Some(ElementType::Water)],
+                    [stone, Some(ElementType::Ice), stone],
+                ]
+            );
+        }
+
+        #[test]
+        fn test_surface_has_nothing_above() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir();
+            let layer = coords.get_num_layers() - 1;
+            let top = coords.get_layer_num_concentric_circles(layer) - 1;
+            let origin = IjkVector::new(layer, top, 30);
+            let stone = Some(ElementType::Stone);
+            assert_eq!(
+                kinds_around(&mut element_grid_dir, origin),
+                [
+                    [None, None, None],
+                    [stone, stone, stone],
+                    [stone, stone, stone],
+                ]
+            );
+        }
+    }
 }
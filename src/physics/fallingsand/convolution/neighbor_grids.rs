@@ -33,6 +33,45 @@ impl ElementGridConvolutionNeighborGrids {
         map.extend(self.bottom.to_hashmap());
         map
     }
+
+    /// Every grid in the convolution, without building a hashmap to put them in
+    /// There are at most four above, two beside and three below, the rest of the slots are None
+    pub fn into_grids(self) -> [Option<ElementGrid>; 9] {
+        let mut out = [None, None, None, None, None, None, None, None, None];
+        match self.top {
+            TopNeighborGrids::Normal { tl, t, tr } => {
+                out[0] = Some(tl);
+                out[1] = Some(t);
+                out[2] = Some(tr);
+            }
+            TopNeighborGrids::ChunkDoubling { tl, t1, t0, tr } => {
+                out[0] = Some(tl);
+                out[1] = Some(t1);
+                out[2] = Some(t0);
+                out[3] = Some(tr);
+            }
+            TopNeighborGrids::TopOfGrid => {}
+        }
+        match self.left_right {
+            LeftRightNeighborGrids::LR { l, r } => {
+                out[4] = Some(l);
+                out[5] = Some(r);
+            }
+        }
+        match self.bottom {
+            BottomNeighborGrids::Normal { bl, b, br } => {
+                out[6] = Some(bl);
+                out[7] = Some(b);
+                out[8] = Some(br);
+            }
+            BottomNeighborGrids::ChunkDoubling { bl, br } => {
+                out[6] = Some(bl);
+                out[7] = Some(br);
+            }
+            BottomNeighborGrids::BottomOfGrid => {}
+        }
+        out
+    }
 }
 
 /// Defines when the user has simply exceeded the bounds of the convolution
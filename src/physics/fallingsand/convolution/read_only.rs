@@ -0,0 +1,95 @@
+//! A read only counterpart to [super::behaviors::ElementGridConvolutionNeighbors]
+//!
+//! Processing has to take ownership of a chunk and its neighbors so that it can mutate them,
+//! which means nobody else can look at them until they are put back.
+//! Rendering only ever needs to look, so this view just borrows the chunks of the convolution
+//! from the [crate::physics::fallingsand::data::element_directory::ElementGridDir] immutably.
+//! Any number of these can exist at once, and they can be read from in parallel.
+
+use hashbrown::HashMap;
+
+use crate::physics::fallingsand::{
+    data::element_grid::ElementGrid,
+    elements::element::Element,
+    mesh::coordinate_directory::CoordinateDir,
+    util::vectors::{ChunkIjkVector, IjkVector},
+};
+use crate::physics::orbits::components::ThermodynamicTemperature;
+
+use super::neighbor_indexes::ElementGridConvolutionNeighborIdxs;
+
+/// A chunk and all its neighbors, borrowed immutably
+/// Cells are addressed by their global [IjkVector], so reading across a chunk boundary
+/// is no different from reading inside the center chunk
+pub struct ReadOnlyConvolution<'a> {
+    /// The indexes of the neighbors, the same ones processing would package
+    pub chunk_idxs: ElementGridConvolutionNeighborIdxs,
+    /// The coordinate directory, used to find which chunk a cell belongs to
+    coord_dir: &'a CoordinateDir,
+    /// The chunk in the middle of the convolution
+    center: &'a ElementGrid,
+    /// The neighbors of the center chunk
+    neighbors: HashMap<ChunkIjkVector, &'a ElementGrid>,
+}
+
+impl<'a> ReadOnlyConvolution<'a> {
+    /// Create a new ReadOnlyConvolution
+    pub fn new(
+        coord_dir: &'a CoordinateDir,
+        center: &'a ElementGrid,
+        chunk_idxs: ElementGridConvolutionNeighborIdxs,
+        neighbors: HashMap<ChunkIjkVector, &'a ElementGrid>,
+    ) -> Self {
+        ReadOnlyConvolution {
+            chunk_idxs,
+            coord_dir,
+            center,
+            neighbors,
+        }
+    }
+
+    /// The chunk in the middle of the convolution
+    pub fn get_center(&self) -> &'a ElementGrid {
+        self.center
+    }
+
+    /// Gets the chunk with the given index, if it is the center or one of its neighbors
+    pub fn get_chunk(&self, idx: ChunkIjkVector) -> Option<&'a ElementGrid> {
+        if self.center.get_chunk_coords().get_chunk_idx() == idx {
+            return Some(self.center);
+        }
+        self.neighbors.get(&idx).copied()
+    }
+
+    /// Gets the element at the given cell
+    /// Returns None if the cell is not in the center chunk or any of its neighbors
+    #[allow(clippy::borrowed_box)]
+    pub fn get(&self, coord: IjkVector) -> Option<&'a Box<dyn Element>> {
+        if coord.i >= self.coord_dir.get_num_layers() {
+            return None;
+        }
+        let (chunk_idx, pos) = self.coord_dir.cell_idx_to_chunk_idx(coord);
+        self.get_chunk(chunk_idx).map(|chunk| chunk.get(pos))
+    }
+
+    /// Gets the temperature of the given cell
+    /// Returns None if the cell is not in the center chunk or any of its neighbors
+    pub fn get_temperature(&self, coord: IjkVector) -> Option<ThermodynamicTemperature> {
+        if coord.i >= self.coord_dir.get_num_layers() {
+            return None;
+        }
+        let (chunk_idx, pos) = self.coord_dir.cell_idx_to_chunk_idx(coord);
+        self.get_chunk(chunk_idx)
+            .map(|chunk| chunk.get_temperature(pos))
+    }
+
+    /// Get the number of chunks, including the center
+    pub fn len(&self) -> usize {
+        self.neighbors.len() + 1
+    }
+
+    /// Always false, there is always a center chunk
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
@@ -1,5 +1,9 @@
 //! The data module contains the data structures for the falling sand physics engine.
 //! These are where the actual grids of elements are stored.
 
+pub mod ejecta;
 pub mod element_directory;
 pub mod element_grid;
+pub mod hydrostatic;
+pub mod replay;
+pub mod save;
@@ -0,0 +1,365 @@
+//! Elements thrown off a celestial fast enough to leave it
+//!
+//! Cells don't carry a velocity of their own, so whatever throws them, an impact or an explosion,
+//! says how fast with [eject]. Only cells on the surface can be thrown, anything buried is in the way.
+//! Anything slower than the escape velocity stays where it is and falls back like any other element.
+//! Anything faster leaves the grid as [Ejecta], which the celestial turns into an orbiting body.
+//! When an orbiting body comes back down, [accrete] puts it back into the grid.
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::fmt;
+use std::ops::AddAssign;
+
+use itertools::iproduct;
+
+use crate::physics::fallingsand::elements::element::{Element, ElementType, StateOfMatter};
+use crate::physics::fallingsand::util::vectors::IjkVector;
+use crate::physics::orbits::components::{Mass, Velocity};
+use crate::physics::util::clock::Clock;
+use crate::physics::util::vectors::RelXyPoint;
+
+use super::element_directory::ElementGridDir;
+
+/// An element that has left its celestial, and what it left with
+pub struct Ejecta {
+    /// What the cell held, as it was when it left
+    pub element: Box<dyn Element>,
+    /// The mass of the cell
+    pub mass: Mass,
+    /// Where the cell was, relative to the center of the celestial
+    pub position: RelXyPoint,
+    /// How fast it was launched, relative to the celestial
+    pub velocity: Velocity,
+}
+
+/// What crossed the top of a celestial's grid since it was last taken, see
+/// [ElementGridDir::take_boundary_flux]
+/// Nothing crosses it while the grid processes, there is nothing above the top of the grid,
+/// so only [eject] and [accrete] are counted. Whatever else the mass did over the same time is
+/// checked against that, and what they can't account for is [Self::unexplained_mass]
+#[derive(Debug, Clone, Copy)]
+pub struct BoundaryFlux {
+    /// How many cells left for space
+    pub outflux: usize,
+    /// How many cells came back down from it
+    pub influx: usize,
+    /// The mass of the cells that left
+    pub mass_out: Mass,
+    /// The mass of the cells that came back
+    pub mass_in: Mass,
+    /// How much the total mass changed while processing, which the flux and the directory's
+    /// own edits don't account for. Elements turning into something lighter or heavier,
+    /// like ice melting, show up here. Anything else is mass silently going missing
+    pub unexplained_mass: Mass,
+}
+
+impl Default for BoundaryFlux {
+    fn default() -> Self {
+        Self {
+            outflux: 0,
+            influx: 0,
+            mass_out: Mass(0.0),
+            mass_in: Mass(0.0),
+            unexplained_mass: Mass(0.0),
+        }
+    }
+}
+
+impl BoundaryFlux {
+    /// The mass gained, negative when more left than came back
+    pub fn net_mass(&self) -> Mass {
+        self.mass_in - self.mass_out
+    }
+}
+
+impl AddAssign for BoundaryFlux {
+    fn add_assign(&mut self, other: Self) {
+        self.outflux += other.outflux;
+        self.influx += other.influx;
+        self.mass_out += other.mass_out;
+        self.mass_in += other.mass_in;
+        self.unexplained_mass += other.unexplained_mass;
+    }
+}
+
+/// A cell with something solid or liquid between it and space, which can't be thrown off, see [eject]
+#[derive(Debug, Clone, Copy)]
+pub struct BuriedCellError(pub IjkVector);
+impl fmt::Display for BuriedCellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Cell {:?} is buried, only cells on the surface can be thrown off",
+            self.0
+        )
+    }
+}
+
+/// Whether nothing solid or liquid is between `cell` and the top of the grid
+/// Checks every cell over it, in the layers above that means all the radial lines it splits into
+/// Chunks that haven't been generated yet have nothing in them
+fn is_on_surface(element_grid_dir: &ElementGridDir, cell: IjkVector) -> bool {
+    let coord_dir = element_grid_dir.get_coordinate_dir();
+    let num_radial_lines = coord_dir.get_layer_num_radial_lines(cell.i);
+    (cell.i..coord_dir.get_num_layers()).all(|i| {
+        let split = coord_dir.get_layer_num_radial_lines(i) / num_radial_lines;
+        let start_j = if i == cell.i { cell.j + 1 } else { 0 };
+        iproduct!(
+            start_j..coord_dir.get_layer_num_concentric_circles(i),
+            cell.k * split..(cell.k + 1) * split
+        )
+        .all(|(j, k)| {
+            element_grid_dir
+                .try_get_element(IjkVector { i, j, k })
+                .is_none_or(|element| {
+                    !matches!(
+                        element.get_state_of_matter(),
+                        StateOfMatter::Solid | StateOfMatter::Liquid
+                    )
+                })
+        })
+    })
+}
+
+/// Launches the element in `cell` with `velocity`, relative to the celestial
+/// If its outward speed beats the escape velocity the cell is emptied and what left is returned,
+/// otherwise nothing changes
+/// Vacuum has nothing to launch, and a cell that isn't on the surface is an error
+pub fn eject(
+    element_grid_dir: &mut ElementGridDir,
+    cell: IjkVector,
+    velocity: Velocity,
+    current_time: Clock,
+) -> Result<Option<Ejecta>, BuriedCellError> {
+    if element_grid_dir.get_element(cell).get_type() == ElementType::Vacuum {
+        return Ok(None);
+    }
+    if !is_on_surface(element_grid_dir, cell) {
+        return Err(BuriedCellError(cell));
+    }
+    let position = element_grid_dir.get_coordinate_dir().cell_midpoint(cell);
+    let outward_speed = velocity.0.dot(position.0.normalize_or_zero());
+    if (outward_speed as f64) < element_grid_dir.escape_velocity() {
+        return Ok(None);
+    }
+    let cell_width = element_grid_dir.get_coordinate_dir().get_cell_width();
+    let element = element_grid_dir.get_element(cell).box_clone();
+    let mass = element.get_mass(cell_width);
+    element_grid_dir.set_element(cell, ElementType::Vacuum.get_element(), current_time);
+    let flux = element_grid_dir.boundary_flux_mut();
+    flux.outflux += 1;
+    flux.mass_out += mass;
+    Ok(Some(Ejecta {
+        element,
+        mass,
+        position,
+        velocity,
+    }))
+}
+
+/// Lands an element that hit the celestial at `impact`, relative to its center
+/// The cell under the impact point is found with
+/// [rel_pos_to_cell_idx](crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir::rel_pos_to_cell_idx),
+/// then the element climbs out of anything already there and settles onto whatever is below it,
+/// without leaving the layer it hit
+/// Returns the cell it landed in, or None if the column was full to the top
+pub fn accrete(
+    element_grid_dir: &mut ElementGridDir,
+    element: Box<dyn Element>,
+    impact: RelXyPoint,
+    current_time: Clock,
+) -> Option<IjkVector> {
+    let coord_dir = element_grid_dir.get_coordinate_dir();
+    let mut cell = coord_dir
+        .rel_pos_to_cell_idx(impact)
+        .unwrap_or_else(|outermost| outermost);
+    let num_concentric_circles = coord_dir.get_layer_num_concentric_circles(cell.i);
+    let is_vacuum = |element_grid_dir: &ElementGridDir, cell: IjkVector| {
+        element_grid_dir.get_element(cell).get_type() == ElementType::Vacuum
+    };
+    while !is_vacuum(element_grid_dir, cell) {
+        if cell.j + 1 >= num_concentric_circles {
+            return None;
+        }
+        cell.j += 1;
+    }
+    while cell.j > 0
+        && is_vacuum(
+            element_grid_dir,
+            IjkVector {
+                j: cell.j - 1,
+                ..cell
+            },
+        )
+    {
+        cell.j -= 1;
+    }
+    let mass = element.get_mass(element_grid_dir.get_coordinate_dir().get_cell_width());
+    element_grid_dir.set_element(cell, element, current_time);
+    let flux = element_grid_dir.boundary_flux_mut();
+    flux.influx += 1;
+    flux.mass_in += mass;
+    Some(cell)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::orbits::components::Length;
+
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(5)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let stone = ElementType::Stone.get_element();
+        let mut element_grid_dir =
+            ElementGridDir::new_checkerboard(coordinate_dir, &*stone, &*stone);
+        element_grid_dir.recalculate_everything();
+        element_grid_dir
+    }
+
+    #[test]
+    fn test_only_fast_outward_launches_escape() {
+        let mut element_grid_dir = get_element_grid_dir();
+        let escape = element_grid_dir.escape_velocity() as f32;
+        let layer = element_grid_dir.get_coordinate_dir().get_num_layers() - 1;
+        let top = element_grid_dir
+            .get_coordinate_dir()
+            .get_layer_num_concentric_circles(layer)
+            - 1;
+        let cell = IjkVector::new(layer, top, 0);
+        let outward = element_grid_dir
+            .get_coordinate_dir()
+            .cell_midpoint(cell)
+            .0
+            .normalize();
+
+        let slow = Velocity(outward * escape * 0.5);
+        assert!(eject(&mut element_grid_dir, cell, slow, Clock::default())
+            .unwrap()
+            .is_none());
+        // Fast, but sideways
+        let sideways = Velocity(outward.perp() * escape * 2.0);
+        assert!(
+            eject(&mut element_grid_dir, cell, sideways, Clock::default())
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            element_grid_dir.get_element(cell).get_type(),
+            ElementType::Stone
+        );
+
+        let fast = Velocity(outward * escape * 2.0);
+        // The cell under it has the surface in the way
+        let buried = IjkVector::new(layer, top - 1, 0);
+        assert!(eject(&mut element_grid_dir, buried, fast, Clock::default()).is_err());
+        assert_eq!(
+            element_grid_dir.get_element(buried).get_type(),
+            ElementType::Stone
+        );
+
+        let ejecta = eject(&mut element_grid_dir, cell, fast, Clock::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(ejecta.element.get_type(), ElementType::Stone);
+        assert!(ejecta.mass.0 > 0.0);
+        assert_eq!(
+            element_grid_dir.get_element(cell).get_type(),
+            ElementType::Vacuum
+        );
+        // Nothing is left to throw, but now the cell under it is on the surface
+        assert!(eject(&mut element_grid_dir, cell, fast, Clock::default())
+            .unwrap()
+            .is_none());
+        assert!(eject(&mut element_grid_dir, buried, fast, Clock::default())
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_flux_counts_only_what_crosses_the_top() {
+        let mut element_grid_dir = get_element_grid_dir();
+        // A settled planet left alone loses nothing
+        let mut clock = Clock::default();
+        for _ in 0..3 {
+            clock.update(std::time::Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+        }
+        let flux = element_grid_dir.take_boundary_flux();
+        assert_eq!((flux.outflux, flux.influx), (0, 0));
+        assert_eq!(flux.net_mass().0, 0.0);
+        assert_eq!(flux.unexplained_mass.0, 0.0);
+
+        let layer = element_grid_dir.get_coordinate_dir().get_num_layers() - 1;
+        let top = element_grid_dir
+            .get_coordinate_dir()
+            .get_layer_num_concentric_circles(layer)
+            - 1;
+        let cell = IjkVector::new(layer, top, 0);
+        let midpoint = element_grid_dir.get_coordinate_dir().cell_midpoint(cell);
+        let fast =
+            Velocity(midpoint.0.normalize() * element_grid_dir.escape_velocity() as f32 * 2.0);
+        let ejecta = eject(&mut element_grid_dir, cell, fast, clock)
+            .unwrap()
+            .unwrap();
+        let flux = element_grid_dir.take_boundary_flux();
+        assert_eq!((flux.outflux, flux.influx), (1, 0));
+        assert_eq!(flux.mass_out.0, ejecta.mass.0);
+        assert!(flux.net_mass().0 < 0.0);
+
+        // Taking it starts the count over, and landing it again evens things out
+        assert_eq!(element_grid_dir.take_boundary_flux().outflux, 0);
+        accrete(&mut element_grid_dir, ejecta.element, midpoint, clock).unwrap();
+        let flux = element_grid_dir.take_boundary_flux();
+        assert_eq!((flux.outflux, flux.influx), (0, 1));
+        assert_eq!(flux.mass_in.0, ejecta.mass.0);
+    }
+
+    #[test]
+    fn test_mass_lost_behind_the_directorys_back_is_unexplained() {
+        let mut element_grid_dir = get_element_grid_dir();
+        let cell_width = element_grid_dir.get_coordinate_dir().get_cell_width();
+        let stone_mass = ElementType::Stone.get_element().get_mass(cell_width);
+
+        // Changing a chunk directly skips the directory's mass bookkeeping, like a bug in processing would
+        let (chunk_idx, pos) = element_grid_dir
+            .get_coordinate_dir()
+            .cell_idx_to_chunk_idx(IjkVector::new(2, 1, 0));
+        element_grid_dir.get_chunk_by_chunk_ijk_mut(chunk_idx).set(
+            pos,
+            ElementType::Vacuum.get_element(),
+            Clock::default(),
+        );
+        let mut clock = Clock::default();
+        clock.update(std::time::Duration::from_millis(100));
+        element_grid_dir.process_full(clock);
+
+        let flux = element_grid_dir.take_boundary_flux();
+        assert_eq!((flux.outflux, flux.influx), (0, 0));
+        assert!((flux.unexplained_mass.0 + stone_mass.0).abs() < 1e-3 * stone_mass.0);
+        // Edits through the directory are accounted for
+        element_grid_dir.set_element(
+            IjkVector::new(2, 1, 1),
+            ElementType::Vacuum.get_element(),
+            clock,
+        );
+        clock.update(std::time::Duration::from_millis(100));
+        element_grid_dir.process_full(clock);
+        assert!(
+            element_grid_dir
+                .take_boundary_flux()
+                .unexplained_mass
+                .0
+                .abs()
+                < 1e-3 * stone_mass.0
+        );
+    }
+}
@@ -1,26 +1,123 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::log::{debug, error, trace};
+use bevy::math::{Rect, Vec2};
+use bevy::render::color::Color;
+use bevy::transform::components::{GlobalTransform, Transform};
 use hashbrown::{HashMap, HashSet};
 
-use crate::physics::orbits::components::Mass;
+use crate::physics::orbits::components::{
+    AngularVelocity, GravitationalAcceleration, Mass, SurfaceGravity, ThermodynamicTemperature,
+};
+use crate::physics::orbits::nbody::G;
 use crate::physics::util::clock::Clock;
+use crate::physics::util::vectors::RelXyPoint;
 
 use super::super::convolution::behaviors::ElementGridConvolutionNeighbors;
 
 use super::super::convolution::neighbor_indexes::{
     BottomNeighborIdxs, ElementGridConvolutionNeighborIdxs, LeftRightNeighborIdxs, TopNeighborIdxs,
 };
-use super::super::elements::element::Element;
-use super::super::mesh::coordinate_directory::CoordinateDir;
+use super::super::convolution::read_only::ReadOnlyConvolution;
+use super::super::elements::element::{Conduction, Element, ElementType, FieldKind, StateOfMatter};
+use super::super::elements::registry::ElementRegistry;
+use super::super::mesh::coordinate_directory::{CoordinateDir, RelativeDir};
 use super::super::util::functions::modulo;
 use super::super::util::grid::Grid;
+use super::super::util::heatmap::{black_body_color, HeatGradient};
 use super::super::util::image::RawImage;
+use super::super::util::palette::ElementPalette;
+use super::super::util::pressuremap::PressureOverlay;
 use super::super::util::vectors::{ChunkIjkVector, IjkVector, JkVector};
-use super::element_grid::ElementGrid;
+use super::ejecta::BoundaryFlux;
+use super::element_grid::{ChunkGenerator, ElementGrid, HeatConfig, MotionStats, RNG_STATE_LEN};
 
+use ndarray::Array2;
 use rayon::prelude::*;
 
 /// The number of frames it takes to fully process the directory
 const FRAMES_PER_FULL_PROCESS: usize = 9;
 
+/// Defines when a chunk has been taken out of the directory by a convolution
+/// and so can not be read until it is put back
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkBorrowError(pub ChunkIjkVector);
+impl fmt::Display for ChunkBorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Chunk {:?} is currently borrowed by a convolution",
+            self.0
+        )
+    }
+}
+
+/// Chunks that were taken out of the directory by a convolution and never put back
+/// Only found at the end of a full cycle of [ElementGridDir::process], see [ElementGridDir::check_chunks_returned]
+#[derive(Debug, Clone)]
+pub struct ChunkLeakError(pub Vec<ChunkIjkVector>);
+impl fmt::Display for ChunkLeakError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Chunks {:?} were borrowed by a convolution and never returned",
+            self.0
+        )
+    }
+}
+
+/// A chunk sitting in another chunk's slot of the directory, the slot then the chunk's own coordinates
+/// See [ElementGridDir::check_chunk_coords_consistent]
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkCoordsMismatch(pub ChunkIjkVector, pub ChunkIjkVector);
+impl fmt::Display for ChunkCoordsMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Chunk {:?} is in the slot of chunk {:?}", self.1, self.0)
+    }
+}
+
+/// A heat field from [ElementGridDir::serialize_heat] that doesn't fit the directory it is loaded into
+#[derive(Debug, Clone)]
+pub struct HeatLayoutError(pub String);
+impl fmt::Display for HeatLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Heat field does not match this directory: {}", self.0)
+    }
+}
+
+/// Random streams from [ElementGridDir::serialize_rngs] for a directory split into a different
+/// number of chunks, the byte count then the one expected
+#[derive(Debug, Clone, Copy)]
+pub struct RngLayoutError(pub usize, pub usize);
+impl fmt::Display for RngLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Random streams take {} bytes, this directory's take {}",
+            self.0, self.1
+        )
+    }
+}
+
+/// A layer holding custom elements, which [ElementGridDir::merge_chunks_in_layer] can't merge
+/// The coarse grid only keeps each cell's [ElementType], and custom kinds can't be made again from that
+/// without the registry they came from
+#[derive(Debug, Clone, Copy)]
+pub struct CustomElementsInLayerError(pub usize);
+impl fmt::Display for CustomElementsInLayerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Layer {} holds custom elements, which can't be merged",
+            self.0
+        )
+    }
+}
+
 /// A struct of textures for use in rendering
 /// These are options so you can take them out of the struct and use them elsewhere
 pub struct Textures {
@@ -28,6 +125,112 @@ pub struct Textures {
     pub texture: Option<RawImage>,
 }
 
+/// A chunk's texture this frame and last frame, see [ElementGridDir::get_textures_double_buffered]
+#[derive(Clone, Copy)]
+pub struct DoubleBufferedTexture<'a> {
+    /// Drawn this call
+    pub current: &'a RawImage,
+    /// Drawn the call before, the same as [Self::current] on the first
+    pub previous: &'a RawImage,
+}
+
+impl DoubleBufferedTexture<'_> {
+    /// Blends from the previous texture at 0.0 to the current one at 1.0, pixel by pixel
+    ///
+    /// # Panics
+    /// If the two textures aren't the same size
+    pub fn interpolated(&self, t: f32) -> RawImage {
+        assert_eq!(
+            self.previous.pixels.len(),
+            self.current.pixels.len(),
+            "Can only blend textures of the same size"
+        );
+        let t = t.clamp(0.0, 1.0);
+        let pixels = self
+            .previous
+            .pixels
+            .iter()
+            .zip(self.current.pixels.iter())
+            .map(|(&a, &b)| (a as f32 + (b as f32 - a as f32) * t).round() as u8)
+            .collect();
+        RawImage {
+            bounds: self.current.bounds,
+            pixels,
+        }
+    }
+}
+
+/// A whole layer of the directory at a lower resolution
+/// Made by [ElementGridDir::merge_chunks_in_layer] and restored by [ElementGridDir::split_layer]
+pub struct CoarseLayer {
+    layer: usize,
+    factor: usize,
+    /// Every chunk of the layer downsampled and stitched together, in layer relative coordinates
+    cells: Grid<ElementType>,
+}
+
+impl CoarseLayer {
+    /// One step of a much cheaper simulation than the full one, run once a cycle while the layer is merged
+    /// Loose elements fall one coarse cell into empty space below them, and that is all
+    fn settle(&mut self) {
+        let mut kinds: HashMap<ElementType, (bool, bool)> = HashMap::new();
+        let mut kind = |element_type: ElementType| {
+            *kinds.entry(element_type).or_insert_with(|| {
+                // Layers with custom elements aren't merged, but if one got in it stays put
+                element_type
+                    .try_get_element()
+                    .map_or((false, false), |element| {
+                        (
+                            element.is_loose(),
+                            element.get_state_of_matter() == StateOfMatter::Empty,
+                        )
+                    })
+            })
+        };
+        for k in 0..self.cells.get_width() {
+            for j in 1..self.cells.get_height() {
+                let here = JkVector { j, k };
+                let below = JkVector { j: j - 1, k };
+                let (loose, _) = kind(*self.cells.get(here));
+                let (_, below_empty) = kind(*self.cells.get(below));
+                if loose && below_empty {
+                    let fell = self.cells.replace(below, *self.cells.get(here));
+                    self.cells.set(here, fell);
+                }
+            }
+        }
+    }
+
+    pub fn get_layer(&self) -> usize {
+        self.layer
+    }
+    pub fn get_factor(&self) -> usize {
+        self.factor
+    }
+    pub fn get_cells(&self) -> &Grid<ElementType> {
+        &self.cells
+    }
+}
+
+/// 64 bit FNV-1a, for [ElementGridDir::state_hash]
+/// The std hashers are only promised to be stable within a single build, and a state hash has to
+/// match between two builds talking over the network
+pub struct StateHasher(u64);
+
+impl StateHasher {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    /// Mixes the bytes into the hash, write numbers in little endian so every machine agrees
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
 /// Useful for indicating at compile time that an iterable should be ran in parallel
 #[derive(Clone, Default)]
 struct Parallel<T>(T);
@@ -223,6 +426,51 @@ fn pregen_process_targets(coords: &CoordinateDir) -> ProcessTargets {
     }
 }
 
+/// Buffers reused by [ElementGridDir::package_convolutions] between passes
+/// They are handed out when packaging and given back, emptied but with their capacity intact,
+/// when unpackaging, so we aren't reallocating them nine times per cycle
+#[derive(Default)]
+struct PackageScratch {
+    convolutions: Vec<ElementGridConvolutionNeighbors>,
+    target_chunks: Vec<ElementGrid>,
+    /// Holds the neighbors of one convolution while they are taken out, and is always left empty
+    neighbor_grids: HashMap<ChunkIjkVector, ElementGrid>,
+    /// The targets of a pass that aren't resting, see [ElementGridDir::rest_layers]
+    due: HashSet<ChunkIjkVector>,
+}
+
+/// The pressure overlay of the whole directory, see [ElementGridDir::calc_pressure_snapshot]
+struct PressureSnapshot {
+    field: Vec<Array2<f32>>,
+    contours: Vec<Array2<bool>>,
+    /// The highest pressure anywhere, the hot end of the gradient
+    max: f32,
+}
+
+/// How gravity is computed at a radius within the directory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GravityModel {
+    /// All the mass acts as if it were at the center, $g = \frac{G M}{r^2}$ at every radius
+    #[default]
+    PointMass,
+    /// By the shell theorem only the mass inside the radius pulls on it
+    /// so the core is effectively weightless
+    Shell,
+}
+
+/// How much [ElementGridDir::process] logs about what it is doing
+/// Logging every chunk every pass is a lot of output, so it is off by default
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProcessDiagnostics {
+    /// Log nothing
+    #[default]
+    Off,
+    /// A debug line for every pass
+    PerPass,
+    /// A trace line for every chunk packaged, on top of every pass
+    PerChunk,
+}
+
 /* Main Struct */
 /// An element grid directory is like a coordinate directory, but for element grids
 /// It follow the same layer structure
@@ -232,8 +480,59 @@ pub struct ElementGridDir {
     coords: CoordinateDir,
     chunks: Vec<Grid<Option<ElementGrid>>>,
     process_targets: ProcessTargets,
+    /// See [Self::get_chunk_neighbors]
+    chunk_neighbors: HashMap<ChunkIjkVector, ElementGridConvolutionNeighborIdxs>,
     process_count: usize,
     total_mass: Mass,
+    gravity_model: GravityModel,
+    diagnostics: ProcessDiagnostics,
+    /// See [Self::set_seed]
+    seed: u64,
+    /// See [Self::set_cross_layer_parallelism], empty when it is off
+    merged_parallel: Vec<Option<Parallel<HashSet<ChunkIjkVector>>>>,
+    /// See [Self::set_layer_cadence], empty when every layer runs every cycle
+    layer_cadence: Vec<usize>,
+    /// See [Self::set_sub_steps]
+    sub_steps: usize,
+    /// See [Self::set_chunk_static]
+    static_chunks: HashSet<ChunkIjkVector>,
+    /// See [Self::merge_chunks_in_layer]
+    merged_layers: HashMap<usize, CoarseLayer>,
+    /// See [Self::enclosed_mass]
+    radial_profile: Vec<(f32, Mass)>,
+    /// See [Self::get_center_of_mass]
+    center_of_mass: RelXyPoint,
+    scratch: PackageScratch,
+    /// See [Self::set_heatmap]
+    heatmap: Option<HeatGradient>,
+    /// See [Self::set_coarse_heat]
+    coarse_heat: bool,
+    /// See [Self::set_pressure_overlay]
+    pressure_overlay: Option<PressureOverlay>,
+    /// The pressure overlay as of the end of the last cycle, None while it is off
+    pressure: Option<PressureSnapshot>,
+    /// The textures handed out by the last call to [Self::get_textures_double_buffered]
+    front_textures: HashMap<ChunkIjkVector, RawImage>,
+    /// The textures from the call before that, swapped with [Self::front_textures] every call
+    back_textures: HashMap<ChunkIjkVector, RawImage>,
+    /// The chunks redrawn by the last call to [Self::get_textures_double_buffered]
+    redrawn_textures: HashSet<ChunkIjkVector>,
+    /// See [Self::propagate_power]
+    powered: HashSet<IjkVector>,
+    /// See [Self::set_palette]
+    palette: ElementPalette,
+    /// See [Self::new_lazy], None when every chunk was made up front
+    generator: Option<ChunkGenerator>,
+    /// See [Self::clock]
+    clock: Clock,
+    /// See [Self::set_sunlight]
+    sunlight: Option<Vec2>,
+    /// See [Self::set_spin]
+    spin: AngularVelocity,
+    /// See [Self::motion_stats]
+    motion: MotionStats,
+    /// See [Self::take_boundary_flux]
+    boundary_flux: BoundaryFlux,
     // max_temp: ThermodynamicTemperature,
     // min_temp: ThermodynamicTemperature,
 }
@@ -255,17 +554,7 @@ impl ElementGridDir {
             }
             chunks.push(layer);
         }
-        let process_targets = pregen_process_targets(&coords);
-        // let (max_temp, min_temp) = Self::calc_max_min_temp(&mut chunks);
-        Self {
-            coords,
-            process_targets,
-            process_count: 0,
-            total_mass: Self::calc_total_mass(&mut chunks),
-            // max_temp,
-            // min_temp,
-            chunks,
-        }
+        Self::from_chunks(coords, chunks, None, 0)
     }
 
     pub fn new_checkerboard(
@@ -291,17 +580,182 @@ impl ElementGridDir {
             }
             chunks.push(layer);
         }
+        Self::from_chunks(coords, chunks, None, 0)
+    }
+
+    /// Creates a directory whose chunks are only generated the first time they are accessed mutably
+    /// Until then a chunk holds no elements, isn't processed or drawn, and acts like a wall to its neighbors,
+    /// so a huge world only pays for the parts that have been explored.
+    /// The generator is given the world seed, so the same seed always grows the same world
+    pub fn new_lazy(coords: CoordinateDir, seed: u64, generator: ChunkGenerator) -> Self {
+        let mut chunks: Vec<Grid<Option<ElementGrid>>> =
+            Vec::with_capacity(coords.get_num_layers());
+        for i in 0..coords.get_num_layers() {
+            let j_size = coords.get_layer_num_concentric_chunks(i);
+            let k_size = coords.get_layer_num_tangential_chunkss(i);
+            let mut layer = Grid::new_empty(k_size, j_size);
+            for j in 0..j_size {
+                for k in 0..k_size {
+                    let element_grid =
+                        ElementGrid::new_ungenerated(coords.get_chunk_at_idx(ChunkIjkVector {
+                            i,
+                            j,
+                            k,
+                        }));
+                    layer.replace(JkVector { j, k }, Some(element_grid));
+                }
+            }
+            chunks.push(layer);
+        }
+        Self::from_chunks(coords, chunks, Some(generator), seed)
+    }
+
+    /// Wraps freshly made chunks into a directory, working out the totals from whatever they hold
+    /// Every chunk's random stream is started from `seed`
+    fn from_chunks(
+        coords: CoordinateDir,
+        mut chunks: Vec<Grid<Option<ElementGrid>>>,
+        generator: Option<ChunkGenerator>,
+        seed: u64,
+    ) -> Self {
         let process_targets = pregen_process_targets(&coords);
+        for layer in &mut chunks {
+            for chunk in layer.iter_mut().flatten() {
+                chunk.reseed(seed);
+            }
+        }
         // let (max_temp, min_temp) = Self::calc_max_min_temp(&mut chunks);
-        Self {
+        let mut out = Self {
             coords,
             process_targets,
+            chunk_neighbors: HashMap::new(),
             process_count: 0,
             total_mass: Self::calc_total_mass(&mut chunks),
+            gravity_model: GravityModel::default(),
+            diagnostics: ProcessDiagnostics::default(),
+            seed,
+            merged_parallel: Vec::new(),
+            layer_cadence: Vec::new(),
+            sub_steps: 1,
+            radial_profile: Self::calc_radial_profile(&chunks),
+            center_of_mass: Self::calc_center_of_mass(&chunks),
+            scratch: PackageScratch::default(),
+            heatmap: None,
+            coarse_heat: false,
+            pressure_overlay: None,
+            pressure: None,
+            static_chunks: HashSet::new(),
+            merged_layers: HashMap::new(),
+            front_textures: HashMap::new(),
+            back_textures: HashMap::new(),
+            redrawn_textures: HashSet::new(),
+            powered: HashSet::new(),
+            palette: ElementPalette::default(),
+            generator,
+            clock: Clock::default(),
+            sunlight: None,
+            spin: AngularVelocity(0.0),
+            motion: MotionStats::default(),
+            boundary_flux: BoundaryFlux::default(),
             // max_temp,
             // min_temp,
             chunks,
+        };
+        out.chunk_neighbors = out.calc_all_chunk_neighbors();
+        out
+    }
+
+    /// Loads a directory saved with [save_to_file](super::save::save_to_file),
+    /// making its elements with `registry`
+    pub fn from_save(path: impl AsRef<Path>, registry: &ElementRegistry) -> io::Result<Self> {
+        super::save::load_from_file(path, registry)
+    }
+
+    /// Whether the chunk has been generated yet, always true unless the directory is [Self::new_lazy]
+    pub fn is_chunk_generated(&self, coord: ChunkIjkVector) -> bool {
+        self.get_chunk_by_chunk_ijk(coord).is_generated()
+    }
+
+    /// Generates the chunk if it hasn't been already, then returns it
+    pub fn get_or_generate_chunk(&mut self, coord: ChunkIjkVector) -> &ElementGrid {
+        self.generate_chunk(coord);
+        self.get_chunk_by_chunk_ijk(coord)
+    }
+
+    /// Runs the generator on the chunk, if it has one and the chunk is still a placeholder
+    fn generate_chunk(&mut self, coord: ChunkIjkVector) {
+        let Some(generator) = &self.generator else {
+            return;
+        };
+        let placeholder = self.chunks[coord.i]
+            .get(coord.to_jk_vector())
+            .as_ref()
+            .expect("Can't generate a chunk while it is borrowed");
+        if placeholder.is_generated() {
+            return;
+        }
+        let mut chunk = generator(placeholder.get_chunk_coords(), self.seed);
+        debug_assert_eq!(chunk.get_chunk_coords().get_chunk_idx(), coord);
+        chunk.reseed(self.seed);
+        chunk.set_coriolis_bias(placeholder.get_coriolis_bias());
+        chunk.set_heat_config(placeholder.get_heat_config());
+        chunk.set_static(placeholder.is_static());
+        chunk.set_resting(placeholder.is_resting());
+        self.add_to_totals(&chunk);
+        self.chunks[coord.i].replace(coord.to_jk_vector(), Some(chunk));
+    }
+
+    /// Counts a freshly generated chunk into the mass totals, the radial profile and the center of mass
+    /// The placeholder it replaces held nothing, so this is all that changed, no need to go over every chunk
+    fn add_to_totals(&mut self, chunk: &ElementGrid) {
+        let coords = *chunk.get_chunk_coords();
+        let cell_width = coords.get_cell_width();
+        let mut moment = Vec2::ZERO;
+        let mut added = Mass(0.0);
+        for j in 0..coords.get_num_concentric_circles() {
+            let mid_radius = coords.get_start_radius() + (j as f32 + 0.5) * cell_width.0;
+            let mut ring = Mass(0.0);
+            for k in 0..coords.get_num_radial_lines() {
+                let mass = chunk.get(JkVector { j, k }).get_mass(cell_width);
+                let angle = coords.get_winding().angle(
+                    (coords.get_start_radial_line() + k) as f32 + 0.5,
+                    coords.get_layer_num_radial_lines(),
+                );
+                moment += Vec2::from_angle(angle) * mid_radius * mass.0;
+                ring += mass;
+            }
+            self.add_to_radial_profile(mid_radius, ring);
+            added += ring;
+        }
+        let before = self.total_mass.0;
+        self.total_mass += added;
+        if self.total_mass.0 > 0.0 {
+            self.center_of_mass =
+                RelXyPoint((self.center_of_mass.0 * before + moment) / self.total_mass.0);
+        }
+    }
+
+    /// Fills every cell whose middle is within the radius with the element, skipping the simulation
+    /// Whole concentric circles are filled from the center out, so there are no gaps
+    /// for anything to fall into and the result is already settled
+    /// Much faster than processing until sand settles when setting up tests or pre-formed worlds
+    pub fn fill_settled(&mut self, fill: &dyn Element, up_to_radius: f32) {
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut().flatten() {
+                let coords = *chunk.get_chunk_coords();
+                let cell_width = coords.get_cell_width().0;
+                for j in 0..coords.get_num_concentric_circles() {
+                    let mid_radius = coords.get_start_radius() + (j as f32 + 0.5) * cell_width;
+                    if mid_radius > up_to_radius {
+                        break;
+                    }
+                    for k in 0..coords.get_num_radial_lines() {
+                        chunk.set(JkVector { j, k }, fill.box_clone(), Clock::default());
+                    }
+                }
+            }
         }
+        self.recalculate_everything();
     }
 
     #[cfg(test)]
@@ -439,7 +893,22 @@ impl ElementGridDir {
         }
     }
 
-    fn get_chunk_neighbors(&self, coord: ChunkIjkVector) -> ElementGridConvolutionNeighborIdxs {
+    /// Every chunk a convolution around `coord` takes out of the directory, by where it is
+    /// Looked up from the neighbors worked out when the directory was made, see [Self::calc_chunk_neighbors]
+    pub fn get_chunk_neighbors(&self, coord: ChunkIjkVector) -> ElementGridConvolutionNeighborIdxs {
+        self.chunk_neighbors
+            .get(&coord)
+            .cloned()
+            .unwrap_or_else(|| self.calc_chunk_neighbors(coord))
+    }
+
+    /// Works out the neighbors of a chunk from scratch
+    /// They only depend on the coordinate directory, which never changes, so [Self::get_chunk_neighbors]
+    /// doesn't have to go through all the edge cases again every pass
+    pub fn calc_chunk_neighbors(
+        &self,
+        coord: ChunkIjkVector,
+    ) -> ElementGridConvolutionNeighborIdxs {
         let top = self.get_chunk_top_neighbors(coord);
         let left_right = self.get_chunk_left_right_neighbors(coord);
         let bottom = self.get_chunk_bottom_neighbors(coord);
@@ -450,44 +919,162 @@ impl ElementGridDir {
         }
     }
 
+    /// [Self::calc_chunk_neighbors] for every chunk, run once when the directory is made
+    fn calc_all_chunk_neighbors(
+        &self,
+    ) -> HashMap<ChunkIjkVector, ElementGridConvolutionNeighborIdxs> {
+        let mut out = HashMap::with_capacity(self.coords.get_num_chunks());
+        for i in 0..self.coords.get_num_layers() {
+            for j in 0..self.coords.get_layer_num_concentric_chunks(i) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(i) {
+                    let coord = ChunkIjkVector { i, j, k };
+                    out.insert(coord, self.calc_chunk_neighbors(coord));
+                }
+            }
+        }
+        out
+    }
+
+    /// Checks that every neighbor relationship goes both ways across the whole directory
+    /// If B is a top neighbor of A then A must be a bottom neighbor of B, and the same for left and right,
+    /// otherwise an element could read a cell it could never be written back from
+    #[cfg(test)]
+    pub fn assert_neighbor_symmetry(&self) {
+        for i in 0..self.coords.get_num_layers() {
+            for j in 0..self.coords.get_layer_num_concentric_chunks(i) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(i) {
+                    let coord = ChunkIjkVector { i, j, k };
+                    for top in self.get_chunk_top_neighbors(coord).iter() {
+                        assert!(
+                            self.get_chunk_bottom_neighbors(top)
+                                .iter()
+                                .any(|c| c == coord),
+                            "{:?} has {:?} as a top neighbor, but not the other way around",
+                            coord,
+                            top
+                        );
+                    }
+                    for bottom in self.get_chunk_bottom_neighbors(coord).iter() {
+                        assert!(
+                            self.get_chunk_top_neighbors(bottom)
+                                .iter()
+                                .any(|c| c == coord),
+                            "{:?} has {:?} as a bottom neighbor, but not the other way around",
+                            coord,
+                            bottom
+                        );
+                    }
+                    for side in self.get_chunk_left_right_neighbors(coord).iter() {
+                        assert!(
+                            self.get_chunk_left_right_neighbors(side)
+                                .iter()
+                                .any(|c| c == coord),
+                            "{:?} has {:?} as a left or right neighbor, but not the other way around",
+                            coord,
+                            side
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks that every chunk is in the slot its own [ChunkCoords](crate::physics::fallingsand::mesh::chunk_coords::ChunkCoords) say it belongs in
+    /// Unpackaging puts chunks back by their own coordinates, so one in the wrong slot would
+    /// quietly overwrite another. Borrowed chunks are skipped, see [Self::check_chunks_returned] for those
+    pub fn check_chunk_coords_consistent(&self) -> Result<(), ChunkCoordsMismatch> {
+        for (i, layer) in self.chunks.iter().enumerate() {
+            for j in 0..layer.get_height() {
+                for k in 0..layer.get_width() {
+                    let Some(chunk) = layer.get(JkVector { j, k }) else {
+                        continue;
+                    };
+                    let slot = ChunkIjkVector { i, j, k };
+                    let found = chunk.get_chunk_coords().get_chunk_idx();
+                    if found != slot {
+                        return Err(ChunkCoordsMismatch(slot, found));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Panics if any chunk is out of place, see [Self::check_chunk_coords_consistent]
+    pub fn assert_chunk_coords_consistent(&self) {
+        if let Err(err) = self.check_chunk_coords_consistent() {
+            panic!("{}", err);
+        }
+    }
+
+    /// Borrows a chunk and its neighbors without taking them out of the directory
+    /// Unlike [Self::package_coordinate_neighbors] nothing can be mutated, but nothing
+    /// has to be put back either, so it is safe to call from render systems
+    pub fn read_only_convolution(
+        &self,
+        coord: ChunkIjkVector,
+    ) -> Result<ReadOnlyConvolution<'_>, ChunkBorrowError> {
+        let center = self.try_get_chunk(coord)?;
+        let chunk_idxs = self.get_chunk_neighbors(coord);
+        let mut neighbors = HashMap::new();
+        for neighbor in chunk_idxs.iter() {
+            neighbors.insert(neighbor, self.try_get_chunk(neighbor)?);
+        }
+        Ok(ReadOnlyConvolution::new(
+            &self.coords,
+            center,
+            chunk_idxs,
+            neighbors,
+        ))
+    }
+
     pub fn package_coordinate_neighbors(
         &mut self,
         coord: ChunkIjkVector,
     ) -> Result<ElementGridConvolutionNeighbors, String> {
+        if self.diagnostics >= ProcessDiagnostics::PerChunk {
+            trace!("Packaging convolution for {:?}", coord);
+        }
         let neighbors = self.get_chunk_neighbors(coord);
-        let mut out = HashMap::new();
+        let mut out = std::mem::take(&mut self.scratch.neighbor_grids);
+        debug_assert!(out.is_empty());
         for neighbor in neighbors.iter() {
             if let Some(chunk) = self.chunks[neighbor.i].replace(neighbor.to_jk_vector(), None) {
                 out.insert(neighbor, chunk);
             } else {
                 // In this case we need to unpackage the convolutions we have already packaged
                 // and put the chunks back where they came from
-                for (neighbor_idx, neighbor) in out.into_iter() {
+                for (neighbor_idx, neighbor) in out.drain() {
                     let prev = self.chunks[neighbor_idx.i]
                         .replace(neighbor_idx.to_jk_vector(), Some(neighbor));
                     debug_assert!(prev.is_none(), "Somehow this chunk was already replaced.");
                 }
+                self.scratch.neighbor_grids = out;
                 return Err(format!(
                     "Chunk {:?} is already borrowed by another convolution.",
                     neighbor
                 ));
             }
         }
-        Ok(ElementGridConvolutionNeighbors::new(neighbors, out))
+        let conv = ElementGridConvolutionNeighbors::new(neighbors, &mut out);
+        self.scratch.neighbor_grids = out;
+        Ok(conv)
     }
 
     // This takes ownership of the chunk and all its neighbors from the directory
     // and puts them into a target vector and a vector of convolutions
     // The taget vector and convolution vectors will then be iterated on in parallel
+    // The vectors are borrowed from the scratch buffers, and are returned to them by unpackage_convolutions
     fn package_convolutions(
         &mut self,
-        target_chunk_coords: HashSet<ChunkIjkVector>,
+        target_chunk_coords: &HashSet<ChunkIjkVector>,
     ) -> Result<(Vec<ElementGridConvolutionNeighbors>, Vec<ElementGrid>), Vec<ChunkIjkVector>> {
-        let mut convolutions = Vec::new();
-        let mut target_chunks = Vec::new();
+        let mut convolutions = std::mem::take(&mut self.scratch.convolutions);
+        let mut target_chunks = std::mem::take(&mut self.scratch.target_chunks);
+        debug_assert!(convolutions.is_empty() && target_chunks.is_empty());
         let mut failed_coords = Vec::new();
 
-        for coord in &target_chunk_coords {
+        for coord in target_chunk_coords {
             let conv = self.package_coordinate_neighbors(*coord);
             let chunk = self.chunks[coord.i].replace(coord.to_jk_vector(), None);
             match (conv, chunk) {
@@ -532,14 +1119,23 @@ impl ElementGridDir {
     }
 
     /// Unpackages more than one convolution at a time
+    /// The emptied vectors are kept as scratch buffers for the next call to package_convolutions
     fn unpackage_convolutions(
         &mut self,
-        convolutions: Vec<ElementGridConvolutionNeighbors>,
-        target_chunks: Vec<ElementGrid>,
+        mut convolutions: Vec<ElementGridConvolutionNeighbors>,
+        mut target_chunks: Vec<ElementGrid>,
     ) {
-        for (target_chunk, this_conv) in target_chunks.into_iter().zip(convolutions.into_iter()) {
+        for (target_chunk, this_conv) in target_chunks.drain(..).zip(convolutions.drain(..)) {
             self.unpackage_convolution(target_chunk, this_conv);
         }
+        self.scratch.convolutions = convolutions;
+        self.scratch.target_chunks = target_chunks;
+    }
+
+    /// Gives back the memory of the buffers [Self::process] keeps between passes
+    /// They grow back over the next cycle, so this only helps a directory that is going to sit idle
+    pub fn free_scratch(&mut self) {
+        self.scratch = PackageScratch::default();
     }
 
     /// Using the already_processed flag, get all the chunks that have not been processed yet
@@ -562,16 +1158,11 @@ impl ElementGridDir {
 
     /// Sets the already_processed flag to false for all chunks
     fn unlock_all_chunks(&mut self) {
-        for i in 0..self.coords.get_num_layers() {
-            let j_size = self.coords.get_layer_num_concentric_chunks(i);
-            let k_size = self.coords.get_layer_num_tangential_chunkss(i);
-            for j in 0..j_size {
-                for k in 0..k_size {
-                    let coord = ChunkIjkVector { i, j, k };
-                    // Doesn't even matter if this fails
-                    self.get_chunk_by_chunk_ijk_mut(coord)
-                        .set_already_processed(false);
-                }
+        // Goes through the chunks directly so lazy chunks aren't generated just to be unlocked
+        // Leaked chunks are skipped, process has already reported them
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut().flatten() {
+                chunk.set_already_processed(false);
             }
         }
     }
@@ -582,37 +1173,135 @@ impl ElementGridDir {
     /// This is important because elementgrids can effect one another at a maximum range of
     /// the size of one elementgrid.
     pub fn process(&mut self, current_time: Clock) {
-        self.process_parallel(
-            self.process_targets.standard_convolution[self.process_count % 9].clone(),
-            current_time,
-        );
-        self.process_sequence(
-            self.process_targets.has_single_bottom_neighbor[self.process_count % 9].clone(),
-            current_time,
-        );
-        self.process_parallel(
-            self.process_targets.has_multi_bottom_neighbor[self.process_count % 9].clone(),
-            current_time,
-        );
+        self.clock = current_time;
+        // The targets are taken out and put back rather than cloned, so we can borrow self mutably
+        let idx = self.process_count % 9;
+        if let Some(targets) = self.merged_parallel.get_mut(idx).and_then(Option::take) {
+            // Both parallel sets of this pass fit into one batch
+            let due = self.rest_layers(&targets.0).map(Parallel);
+            self.process_parallel(due.as_ref().unwrap_or(&targets), current_time);
+            self.return_due(due.map(|due| due.0));
+            self.merged_parallel[idx] = Some(targets);
+            // None of the multi bottom neighbor convolutions touch a sequential chunk, so running
+            // them before the sequential targets instead of after changes nothing
+            self.process_single_bottom_targets(idx, current_time);
+        } else {
+            let targets = std::mem::take(&mut self.process_targets.standard_convolution[idx]);
+            let due = self.rest_layers(&targets.0).map(Parallel);
+            self.process_parallel(due.as_ref().unwrap_or(&targets), current_time);
+            self.return_due(due.map(|due| due.0));
+            self.process_targets.standard_convolution[idx] = targets;
+            self.process_single_bottom_targets(idx, current_time);
+            let targets = std::mem::take(&mut self.process_targets.has_multi_bottom_neighbor[idx]);
+            let due = self.rest_layers(&targets.0).map(Parallel);
+            self.process_parallel(due.as_ref().unwrap_or(&targets), current_time);
+            self.return_due(due.map(|due| due.0));
+            self.process_targets.has_multi_bottom_neighbor[idx] = targets;
+        }
         self.process_count += 1;
+        if self.diagnostics >= ProcessDiagnostics::PerPass {
+            debug!("Processed pass {} of {}", idx, FRAMES_PER_FULL_PROCESS);
+        }
 
         // Check for errors and unlock all chunks every 9 iterations
         if self.process_count % FRAMES_PER_FULL_PROCESS == 0 {
-            debug_assert_eq!(
-                self.get_unprocessed_chunk_idxs().len(),
-                0,
-                "After 9 iterations not all chunks are processed. Missing {:?}",
-                self.get_unprocessed_chunk_idxs()
-            );
+            // A lost chunk can't be recovered, but everything below skips missing chunks,
+            // so the rest of the grid still gets unlocked and its totals stay current
+            if let Err(err) = self.check_chunks_returned() {
+                error!("{}", err);
+            } else {
+                debug_assert_eq!(
+                    self.get_unprocessed_chunk_idxs().len(),
+                    0,
+                    "After 9 iterations not all chunks are processed. Missing {:?}",
+                    self.get_unprocessed_chunk_idxs()
+                );
+                // Walks every chunk, so like the assert above it stays out of release builds
+                #[cfg(debug_assertions)]
+                self.assert_chunk_coords_consistent();
+            }
             self.unlock_all_chunks();
+            self.update_resting_chunks();
+            for coarse in self.merged_layers.values_mut() {
+                coarse.settle();
+            }
+            // Every edit made through the directory has already been added to the total,
+            // so whatever the recount disagrees by happened while processing
+            let counted = self.total_mass;
             self.recalculate_everything();
+            self.boundary_flux.unexplained_mass += self.total_mass - counted;
+            self.update_pressure();
+            if self.take_circuit_dirty() {
+                self.propagate_power();
+            }
+            self.motion = self.calc_motion_stats();
+        }
+    }
+
+    /// Makes sure every chunk taken out by a convolution was put back
+    /// unpackage_convolutions only catches a chunk being put back twice, not one never coming back,
+    /// so this is checked after every full cycle of [Self::process]
+    pub fn check_chunks_returned(&self) -> Result<(), ChunkLeakError> {
+        let mut missing = Vec::new();
+        for (i, layer) in self.chunks.iter().enumerate() {
+            for j in 0..layer.get_height() {
+                for k in 0..layer.get_width() {
+                    if layer.get(JkVector::new(j, k)).is_none() {
+                        missing.push(ChunkIjkVector::new(i, j, k));
+                    }
+                }
+            }
+        }
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(ChunkLeakError(missing))
+        }
+    }
+
+    /// Everything that left for space or came back from it since the last call, and starts counting again
+    /// Meant to be taken once a frame, see [BoundaryFlux]
+    pub fn take_boundary_flux(&mut self) -> BoundaryFlux {
+        std::mem::take(&mut self.boundary_flux)
+    }
+
+    /// Where [eject](super::ejecta::eject) and [accrete](super::ejecta::accrete) count what they move
+    pub(super) fn boundary_flux_mut(&mut self) -> &mut BoundaryFlux {
+        &mut self.boundary_flux
+    }
+
+    /// How many elements moved and how many stayed put over the last full cycle of [Self::process]
+    /// Falls to zero as the grid settles, if it levels off above zero something is moving forever
+    pub fn motion_stats(&self) -> MotionStats {
+        self.motion
+    }
+
+    /// Adds up what every chunk did the last time it was processed
+    fn calc_motion_stats(&self) -> MotionStats {
+        let mut out = MotionStats::default();
+        for layer in &self.chunks {
+            for chunk in layer.iter().flatten() {
+                out += chunk.get_motion_stats();
+            }
         }
+        out
+    }
+
+    /// The sequential part of pass `idx`, see [Self::process]
+    fn process_single_bottom_targets(&mut self, idx: usize, current_time: Clock) {
+        let targets = std::mem::take(&mut self.process_targets.has_single_bottom_neighbor[idx]);
+        let due = self.rest_layers(&targets.0).map(Sequential);
+        self.process_sequence(due.as_ref().unwrap_or(&targets), current_time);
+        self.return_due(due.map(|due| due.0));
+        self.process_targets.has_single_bottom_neighbor[idx] = targets;
     }
 
     /// Recalculates all the saved values
     pub fn recalculate_everything(&mut self) {
         // self.recalculate_max_min_temp();
         self.recalculate_total_mass();
+        self.radial_profile = Self::calc_radial_profile(&self.chunks);
+        self.center_of_mass = Self::calc_center_of_mass(&self.chunks);
     }
 
     /// Run process FRAMES_PER_FULL_PROCESS times
@@ -631,69 +1320,282 @@ impl ElementGridDir {
         let mut chunk = self.chunks[coord.i]
             .replace(coord.to_jk_vector(), None)
             .expect("Should not have been replaced already.");
+        chunk.set_light_direction(self.light_direction());
         chunk.process(self.get_coordinate_dir(), &mut conv, current_time);
         // Unpackage the convolution
         self.unpackage_convolution(chunk, conv);
     }
 
-    /// Gets the textures of the targets updated in the last call to process
-    pub fn get_updated_target_textures(&self) -> HashMap<ChunkIjkVector, Textures> {
-        // You should call this function only AFTER calling process
-        let process_count = self.process_count - 1;
-        let targets1 = self.process_targets.standard_convolution[process_count % 9].clone();
-        let targets2 = self.process_targets.has_single_bottom_neighbor[process_count % 9].clone();
-        let targets3 = self.process_targets.has_multi_bottom_neighbor[process_count % 9].clone();
-        let all_targets: Vec<ChunkIjkVector> = targets1
-            .0
-            .into_iter()
-            .chain(targets2.0)
-            .chain(targets3.0)
+    /// Process only the given chunks, for example right after they were edited
+    /// Chunks whose convolutions would share a chunk are split into separate batches,
+    /// and each batch is processed in parallel.
+    /// This doesn't advance the passes of [Self::process], and leaves their already processed flags as they were
+    /// Static chunks are skipped, see [Self::set_chunk_static]
+    pub fn process_chunks(&mut self, current_time: Clock, chunks: &HashSet<ChunkIjkVector>) {
+        // Sorted so that the batches come out the same every time
+        let mut coords: Vec<ChunkIjkVector> = chunks
+            .iter()
+            .copied()
+            .filter(|coord| !self.static_chunks.contains(coord))
             .collect();
-        // let (max_temp, min_temp) = self.get_max_min_temp();
-        all_targets
-            .into_par_iter()
-            .map(|target| {
-                let chunk = self.get_chunk_by_chunk_ijk(target);
-                (
-                    target,
-                    Textures {
-                        texture: Some(chunk.get_texture()),
-                    },
-                )
+        coords.sort_by_key(|coord| (coord.i, coord.j, coord.k));
+
+        // Greedily put each chunk in the first batch its convolution doesn't overlap with
+        let mut batches: Vec<(HashSet<ChunkIjkVector>, HashSet<ChunkIjkVector>)> = Vec::new();
+        for coord in coords {
+            let footprint: Vec<ChunkIjkVector> = std::iter::once(coord)
+                .chain(self.get_chunk_neighbors(coord).iter())
+                .collect();
+            match batches
+                .iter_mut()
+                .find(|(_, taken)| footprint.iter().all(|chunk| !taken.contains(chunk)))
+            {
+                Some((targets, taken)) => {
+                    targets.insert(coord);
+                    taken.extend(footprint);
+                }
+                None => {
+                    batches.push((HashSet::from([coord]), footprint.into_iter().collect()));
+                }
+            }
+        }
+
+        for (targets, _) in batches {
+            let already_processed: Vec<(ChunkIjkVector, bool)> = targets
+                .iter()
+                .map(|&coord| {
+                    let chunk = self.get_chunk_by_chunk_ijk_mut(coord);
+                    let was = chunk.get_already_processed();
+                    chunk.set_already_processed(false);
+                    (coord, was)
+                })
+                .collect();
+            self.process_parallel(&Parallel(targets), current_time);
+            for (coord, was) in already_processed {
+                self.get_chunk_by_chunk_ijk_mut(coord)
+                    .set_already_processed(was);
+            }
+        }
+    }
+
+    /// Which of the nine passes a chunk is processed in
+    /// Only returns None if the chunk is not in the directory
+    pub fn pass_of_chunk(&self, coord: ChunkIjkVector) -> Option<usize> {
+        (0..FRAMES_PER_FULL_PROCESS).find(|&pass| {
+            self.process_targets.standard_convolution[pass]
+                .0
+                .contains(&coord)
+                || self.process_targets.has_single_bottom_neighbor[pass]
+                    .0
+                    .contains(&coord)
+                || self.process_targets.has_multi_bottom_neighbor[pass]
+                    .0
+                    .contains(&coord)
+        })
+    }
+
+    /// A rough guess at how many passes a chunk needs before its loose elements stop moving
+    /// In every column, this is how far the highest loose element sits above the lowest empty
+    /// cell under it, since that whole stack has to fall through the gap one cell at a time
+    /// Zero if nothing loose is hanging over a gap, like a chunk of solid stone
+    /// Only the chunk itself is looked at, so a gap in the chunk below doesn't count
+    pub fn estimated_passes_to_settle(&self, coord: ChunkIjkVector) -> usize {
+        let chunk = self.get_chunk_by_chunk_ijk(coord);
+        let chunk_coords = chunk.get_chunk_coords();
+        let num_concentric_circles = chunk_coords.get_num_concentric_circles();
+        (0..chunk_coords.get_num_radial_lines())
+            .map(|k| {
+                let column = (0..num_concentric_circles).map(|j| chunk.get(JkVector::new(j, k)));
+                let Some(gap) = column
+                    .clone()
+                    .position(|element| element.get_state_of_matter() == StateOfMatter::Empty)
+                else {
+                    return 0;
+                };
+                column
+                    .enumerate()
+                    .skip(gap + 1)
+                    .filter(|(_, element)| element.is_loose())
+                    .map(|(j, _)| j - gap)
+                    .next_back()
+                    .unwrap_or(0)
             })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Every chunk processed by the last call to [Self::process], empty if it was never called
+    fn last_pass_targets(&self) -> Vec<ChunkIjkVector> {
+        let Some(process_count) = self.process_count.checked_sub(1) else {
+            return Vec::new();
+        };
+        let pass = process_count % FRAMES_PER_FULL_PROCESS;
+        self.process_targets.standard_convolution[pass]
+            .0
+            .iter()
+            .chain(
+                self.process_targets.has_single_bottom_neighbor[pass]
+                    .0
+                    .iter(),
+            )
+            .chain(
+                self.process_targets.has_multi_bottom_neighbor[pass]
+                    .0
+                    .iter(),
+            )
+            .copied()
+            .collect()
+    }
+
+    /// Draws only the chunks whose elements changed since they were last drawn by this,
+    /// and marks them clean, see [ElementGrid::is_texture_dirty]
+    /// A planet where nothing is moving draws nothing at all
+    /// Temperatures drift every pass, so with the heatmap on the chunks from the last pass are redrawn too
+    /// and the same goes for pressure with the pressure overlay on, using the pressure from the end of the last cycle
+    pub fn get_dirty_textures(&mut self) -> Vec<(ChunkIjkVector, RawImage)> {
+        let mut dirty: HashSet<ChunkIjkVector> = HashSet::new();
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut().flatten() {
+                if chunk.take_texture_dirty() {
+                    dirty.insert(chunk.get_chunk_coords().get_chunk_idx());
+                }
+            }
+        }
+        if self.heatmap.is_some() || self.pressure_overlay.is_some() {
+            dirty.extend(self.last_pass_targets());
+        }
+        let temp_range = self.heatmap.as_ref().map(|_| self.calc_max_min_temp());
+        let pressure = self.pressure.as_ref();
+        dirty
+            .into_par_iter()
+            .map(|coord| (coord, self.get_chunk_by_chunk_ijk(coord)))
+            .filter(|(_, chunk)| chunk.is_generated())
+            .map(|(coord, chunk)| (coord, self.draw_chunk(chunk, temp_range, pressure)))
             .collect()
     }
 
+    /// Draw a chunk by its elements, as a heatmap, or as a pressure map
+    /// The pressure overlay wins over the heatmap when both are on, see [Self::set_pressure_overlay]
+    /// temp_range is the (max, min) temperature of the directory, only needed for the heatmap
+    /// pressure is from [Self::calc_pressure_snapshot], only needed for the pressure overlay
+    fn draw_chunk(
+        &self,
+        chunk: &ElementGrid,
+        temp_range: Option<(ThermodynamicTemperature, ThermodynamicTemperature)>,
+        pressure: Option<&PressureSnapshot>,
+    ) -> RawImage {
+        if let (Some(overlay), Some(snapshot)) = (&self.pressure_overlay, pressure) {
+            let layer = chunk.get_chunk_coords().get_layer_num();
+            return chunk.get_pressure_texture(
+                overlay,
+                &snapshot.field[layer],
+                &snapshot.contours[layer],
+                snapshot.max,
+            );
+        }
+        match (&self.heatmap, temp_range) {
+            (Some(gradient), Some((max, min))) if self.coarse_heat => {
+                chunk.get_coarse_heat_texture(gradient, min, max)
+            }
+            (Some(gradient), Some((max, min))) => chunk.get_heat_texture(gradient, min, max),
+            _ => chunk.get_texture(&self.palette),
+        }
+    }
+
+    /// Liquids flow by pressure, see [ElementGrid::set_pressure], and the overlay draws it
+    /// Only chunks that may hold a liquid, and their neighbors which the liquid looks into, are handed
+    /// their part of the field, and the field stops at the lowest of them unless the overlay is on.
+    /// The rest keep whatever pressure they had, nothing reads it until a liquid gets there
+    fn update_pressure(&mut self) {
+        let mut wet = HashSet::new();
+        for layer in &self.chunks {
+            for chunk in layer.iter().flatten() {
+                if chunk.may_contain_liquid() {
+                    let coord = chunk.get_chunk_coords().get_chunk_idx();
+                    wet.insert(coord);
+                    wet.extend(self.get_chunk_neighbors(coord).iter());
+                }
+            }
+        }
+        let lowest_layer = if self.pressure_overlay.is_some() {
+            0
+        } else if let Some(lowest) = wet.iter().map(|coord| coord.i).min() {
+            lowest
+        } else {
+            self.pressure = None;
+            return;
+        };
+        let field = self.pressure_field_down_to(lowest_layer);
+        for coord in wet {
+            if let Some(chunk) = self.chunks[coord.i].get_mut(coord.to_jk_vector()) {
+                chunk.set_pressure(&field[coord.i]);
+            }
+        }
+        if self.pressure_overlay.is_some() {
+            self.pressure = self.calc_pressure_snapshot(field);
+        }
+    }
+
+    /// Everything needed to draw the pressure overlay from a [Self::pressure_field], worked out once a cycle
+    /// for all the chunks being drawn. None when the overlay is off
+    fn calc_pressure_snapshot(&self, field: Vec<Array2<f32>>) -> Option<PressureSnapshot> {
+        let overlay = self.pressure_overlay.as_ref()?;
+        let contours = field
+            .iter()
+            .map(|layer| overlay.contour_cells(layer))
+            .collect();
+        let max = field
+            .iter()
+            .flat_map(|layer| layer.iter().copied())
+            .fold(0.0, f32::max);
+        Some(PressureSnapshot {
+            field,
+            contours,
+            max,
+        })
+    }
+
     fn process_sequence(
         &mut self,
-        targets: Sequential<HashSet<ChunkIjkVector>>,
+        targets: &Sequential<HashSet<ChunkIjkVector>>,
         current_time: Clock,
     ) {
-        for target in targets.0 {
+        // These share neighbors, so the order they go in changes what happens and has to be
+        // the same every run for a seed to replay, see [Self::state_hash]
+        let mut ordered: Vec<ChunkIjkVector> = targets.0.iter().copied().collect();
+        ordered.sort_by_key(|coord| (coord.i, coord.j, coord.k));
+        let light_direction = self.light_direction();
+        for target in ordered {
             let mut conv = self
                 .package_coordinate_neighbors(target)
                 .expect("In runtime, this should never fail.");
             let mut chunk = self.chunks[target.i]
                 .replace(target.to_jk_vector(), None)
                 .expect("Should not have been replaced already.");
-            chunk.process(self.get_coordinate_dir(), &mut conv, current_time);
+            let layer_time = self.layer_clock(target.i, current_time);
+            chunk.set_light_direction(light_direction);
+            chunk.process(self.get_coordinate_dir(), &mut conv, layer_time);
             // Unpackage the convolution
             self.unpackage_convolution(chunk, conv);
         }
     }
     fn process_parallel(
         &mut self,
-        targets: Parallel<HashSet<ChunkIjkVector>>,
+        targets: &Parallel<HashSet<ChunkIjkVector>>,
         current_time: Clock,
     ) {
         let (mut convolutions, mut target_chunks) = self
-            .package_convolutions(targets.0)
+            .package_convolutions(&targets.0)
             .expect("In runtime, this should never fail.");
+        let light_direction = self.light_direction();
         convolutions
             .par_iter_mut()
             .zip(target_chunks.par_iter_mut())
             .for_each(|(convolution, target_chunk)| {
-                target_chunk.process(self.get_coordinate_dir(), convolution, current_time);
+                let layer = target_chunk.get_chunk_coords().get_layer_num();
+                let layer_time = self.layer_clock(layer, current_time);
+                target_chunk.set_light_direction(light_direction);
+                target_chunk.process(self.get_coordinate_dir(), convolution, layer_time);
             });
         self.unpackage_convolutions(convolutions, target_chunks);
     }
@@ -720,12 +1622,656 @@ impl ElementGridDir {
         out
     }
 
-    /// Get the total mass of the directory
-    pub fn get_total_mass(&self) -> Mass {
-        self.total_mass
-    }
-
-    /// Recalculate the total mass of the directory
+    /// Sums [ElementGrid::memory_bytes] over every chunk
+    /// A chunk borrowed by a convolution can't be measured, so that is an error
+    pub fn total_memory_bytes(&self) -> Result<usize, ChunkBorrowError> {
+        let mut out = std::mem::size_of::<Self>();
+        for i in 0..self.coords.get_num_layers() {
+            for j in 0..self.coords.get_layer_num_concentric_chunks(i) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(i) {
+                    out += self
+                        .try_get_chunk(ChunkIjkVector { i, j, k })?
+                        .memory_bytes();
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Get the total mass of the directory
+    pub fn get_total_mass(&self) -> Mass {
+        self.total_mass
+    }
+
+    /// The gravitational acceleration felt at the outer radius of the directory
+    /// Treats the whole directory as a point mass, $g = \frac{G M}{r^2}$
+    pub fn surface_gravity(&self) -> SurfaceGravity {
+        let radius = self.coords.get_radius().0;
+        SurfaceGravity(G * self.total_mass.0 / (radius * radius))
+    }
+
+    /// How far out the ground reaches at `angle`, counterclockwise from the x axis in radians
+    /// The ground is the outer edge of the outermost solid or liquid cell in that direction,
+    /// gases and vacuum are passed through. 0 if there is nothing at all
+    pub fn surface_radius_at_angle(&self, angle: f32) -> f32 {
+        let winding = self.coords.get_winding();
+        for i in (0..self.coords.get_num_layers()).rev() {
+            let k = winding.radial_line_at(angle, self.coords.get_layer_num_radial_lines(i));
+            let num_concentric_circles = self.coords.get_layer_num_concentric_circles(i);
+            for j in (0..num_concentric_circles).rev() {
+                let state = self
+                    .get_element(IjkVector { i, j, k })
+                    .get_state_of_matter();
+                if state == StateOfMatter::Solid || state == StateOfMatter::Liquid {
+                    let start = self.coords.get_layer_start_radius(i);
+                    let end = self.coords.get_layer_end_radius(i);
+                    return start + (end - start) * (j + 1) as f32 / num_concentric_circles as f32;
+                }
+            }
+        }
+        0.0
+    }
+
+    /// The color the outside of the directory glows at `angle`, counterclockwise from the x axis in radians
+    /// Takes the outermost cell in that direction that has a temperature, gases included since that is
+    /// what a star's surface is made of, and gives the [black_body_color] of it. Transparent if there is
+    /// nothing there, and black if it is too cold to glow
+    pub fn surface_emission_color(&self, angle: f32) -> Color {
+        let winding = self.coords.get_winding();
+        for i in (0..self.coords.get_num_layers()).rev() {
+            let k = winding.radial_line_at(angle, self.coords.get_layer_num_radial_lines(i));
+            for j in (0..self.coords.get_layer_num_concentric_circles(i)).rev() {
+                // Nothing has been generated here to glow yet
+                let Some(temperature) = self.try_get_temperature(IjkVector { i, j, k }) else {
+                    continue;
+                };
+                if temperature.0 > 0.0 {
+                    return black_body_color(temperature);
+                }
+            }
+        }
+        Color::NONE
+    }
+
+    /// A closed polygon around the ground, for colliding with instead of every cell
+    /// Samples [Self::surface_radius_at_angle] at `segments` evenly spaced angles,
+    /// counterclockwise from the x axis, and repeats the first point at the end
+    pub fn collision_outline(&self, segments: usize) -> Vec<Vec2> {
+        debug_assert!(segments >= 3, "A polygon needs 3 sides, got {}", segments);
+        let mut outline: Vec<Vec2> = (0..segments)
+            .map(|n| {
+                let angle = 2.0 * PI * n as f32 / segments as f32;
+                Vec2::from_angle(angle) * self.surface_radius_at_angle(angle)
+            })
+            .collect();
+        outline.push(outline[0]);
+        outline
+    }
+
+    /// When on, the standard and multi bottom neighbor targets of a pass are processed
+    /// in a single parallel batch, as long as none of their convolutions overlap.
+    /// This keeps more threads busy on the passes which only touch a few chunks per layer.
+    /// Passes where they do overlap are still processed one set after the other.
+    pub fn set_cross_layer_parallelism(&mut self, enabled: bool) {
+        self.merged_parallel = if enabled {
+            (0..FRAMES_PER_FULL_PROCESS)
+                .map(|idx| self.calc_merged_parallel(idx))
+                .collect()
+        } else {
+            Vec::new()
+        };
+    }
+
+    pub fn get_cross_layer_parallelism(&self) -> bool {
+        !self.merged_parallel.is_empty()
+    }
+
+    /// Processes the chunks of `layer` only once every `every` full cycles, with their time step
+    /// stretched to cover the cycles they sat out. Usually the core, which barely changes.
+    /// While a layer rests its chunks are treated like static ones, the layers next to it can look
+    /// at them but not move anything across the seam, so it presents a stable boundary until it runs again
+    pub fn set_layer_cadence(&mut self, layer: usize, every: usize) {
+        debug_assert!(every > 0, "A layer has to run at least sometimes");
+        if self.layer_cadence.is_empty() {
+            self.layer_cadence = vec![1; self.coords.get_num_layers()];
+        }
+        self.layer_cadence[layer] = every.max(1);
+        if self.layer_cadence.iter().all(|&every| every == 1) {
+            self.layer_cadence.clear();
+        }
+        self.update_resting_chunks();
+    }
+
+    /// Marks the chunks of every layer sitting out the current cycle as resting, and the rest as not
+    /// See [ElementGrid::set_resting]
+    fn update_resting_chunks(&mut self) {
+        let cycle = self.process_count / FRAMES_PER_FULL_PROCESS;
+        for (layer_num, layer) in self.chunks.iter_mut().enumerate() {
+            let is_resting = match self.layer_cadence.get(layer_num) {
+                Some(&every) => cycle % every != 0,
+                None => false,
+            };
+            for chunk in layer.iter_mut().flatten() {
+                chunk.set_resting(is_resting);
+            }
+        }
+    }
+
+    /// Pins a chunk as bedrock, or frees it again. A static chunk is never processed, and the chunks
+    /// around it can't move anything into or out of it, so its elements stay put even with nothing under them.
+    /// Its neighbors can still look at what it holds, so sand lands on it like on any other floor
+    /// and gets wet lying on static water
+    pub fn set_chunk_static(&mut self, coord: ChunkIjkVector, is_static: bool) {
+        if is_static {
+            self.static_chunks.insert(coord);
+        } else {
+            self.static_chunks.remove(&coord);
+        }
+        self.get_chunk_by_chunk_ijk_mut(coord).set_static(is_static);
+    }
+
+    /// See [Self::set_chunk_static]
+    pub fn is_chunk_static(&self, coord: ChunkIjkVector) -> bool {
+        self.static_chunks.contains(&coord)
+    }
+
+    /// Every chunk pinned with [Self::set_chunk_static], in no particular order
+    pub fn get_static_chunks(&self) -> impl Iterator<Item = ChunkIjkVector> + '_ {
+        self.static_chunks.iter().copied()
+    }
+
+    /// How many full cycles apart `layer` is processed, see [Self::set_layer_cadence]
+    pub fn get_layer_cadence(&self, layer: usize) -> usize {
+        self.layer_cadence.get(layer).copied().unwrap_or(1)
+    }
+
+    /// The clock a chunk in `layer` is processed with, the delta covering all of its cycles
+    fn layer_clock(&self, layer: usize, current_time: Clock) -> Clock {
+        match self.get_layer_cadence(layer) {
+            1 => current_time,
+            every => current_time.with_delta_scaled(every as u32),
+        }
+    }
+
+    /// Takes the chunks of layers resting this cycle, merged layers and static chunks, out of `targets`,
+    /// marking them as processed so the end of the cycle doesn't miss them. None when every chunk is due.
+    /// The set is the scratch buffer, hand it back with [Self::return_due] once the pass is done with it
+    fn rest_layers(
+        &mut self,
+        targets: &HashSet<ChunkIjkVector>,
+    ) -> Option<HashSet<ChunkIjkVector>> {
+        if self.layer_cadence.is_empty()
+            && self.static_chunks.is_empty()
+            && self.merged_layers.is_empty()
+        {
+            return None;
+        }
+        let cycle = self.process_count / FRAMES_PER_FULL_PROCESS;
+        let mut due = std::mem::take(&mut self.scratch.due);
+        debug_assert!(due.is_empty());
+        for &chunk in targets {
+            if cycle % self.get_layer_cadence(chunk.i) == 0
+                && !self.static_chunks.contains(&chunk)
+                && !self.merged_layers.contains_key(&chunk.i)
+            {
+                due.insert(chunk);
+            } else {
+                self.get_chunk_by_chunk_ijk_mut(chunk)
+                    .set_already_processed(true);
+            }
+        }
+        Some(due)
+    }
+
+    /// Empties the set [Self::rest_layers] handed out and keeps it for the next pass
+    fn return_due(&mut self, due: Option<HashSet<ChunkIjkVector>>) {
+        if let Some(mut due) = due {
+            due.clear();
+            self.scratch.due = due;
+        }
+    }
+
+    /// Every chunk the convolution of `coord` needs, itself included
+    fn convolution_footprint(&self, coord: ChunkIjkVector) -> Vec<ChunkIjkVector> {
+        std::iter::once(coord)
+            .chain(self.get_chunk_neighbors(coord).iter())
+            .collect()
+    }
+
+    /// Joins the parallel targets of a pass together
+    /// Returns None if any two of their convolutions would need the same chunk, or if a multi
+    /// bottom neighbor convolution needs a chunk the sequential targets use, since merging moves
+    /// those ahead of the sequential targets and they have to stay independent of them
+    fn calc_merged_parallel(&self, idx: usize) -> Option<Parallel<HashSet<ChunkIjkVector>>> {
+        let mut footprint = HashSet::new();
+        let mut out = HashSet::new();
+        let targets = self.process_targets.standard_convolution[idx]
+            .0
+            .iter()
+            .chain(self.process_targets.has_multi_bottom_neighbor[idx].0.iter());
+        for &coord in targets {
+            for chunk in self.convolution_footprint(coord) {
+                if !footprint.insert(chunk) {
+                    return None;
+                }
+            }
+            out.insert(coord);
+        }
+        let multi_footprint: HashSet<ChunkIjkVector> =
+            self.process_targets.has_multi_bottom_neighbor[idx]
+                .0
+                .iter()
+                .flat_map(|&coord| self.convolution_footprint(coord))
+                .collect();
+        let sequential_overlaps = self.process_targets.has_single_bottom_neighbor[idx]
+            .0
+            .iter()
+            .flat_map(|&coord| self.convolution_footprint(coord))
+            .any(|chunk| multi_footprint.contains(&chunk));
+        if sequential_overlaps {
+            return None;
+        }
+        Some(Parallel(out))
+    }
+
+    pub fn get_diagnostics(&self) -> ProcessDiagnostics {
+        self.diagnostics
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: ProcessDiagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn get_gravity_model(&self) -> GravityModel {
+        self.gravity_model
+    }
+
+    pub fn set_gravity_model(&mut self, gravity_model: GravityModel) {
+        self.gravity_model = gravity_model;
+    }
+
+    /// The gravitational acceleration at a distance from the center of the directory
+    /// depending on the [GravityModel]
+    pub fn gravity_at_radius(&self, radius: f32) -> GravitationalAcceleration {
+        if radius <= 0.0 {
+            return GravitationalAcceleration(0.0);
+        }
+        let mass = match self.gravity_model {
+            GravityModel::PointMass => self.total_mass,
+            GravityModel::Shell => self.enclosed_mass(radius),
+        };
+        GravitationalAcceleration(G * mass.0 / (radius * radius))
+    }
+
+    /// The mass of every cell whose middle is within the radius
+    /// Uses the radial profile cached by [Self::recalculate_everything], which [Self::set_element] keeps up to date
+    pub fn enclosed_mass(&self, radius: f32) -> Mass {
+        let idx = self
+            .radial_profile
+            .partition_point(|(mid_radius, _)| *mid_radius <= radius);
+        if idx == 0 {
+            Mass(0.0)
+        } else {
+            self.radial_profile[idx - 1].1
+        }
+    }
+
+    /// The energy it would take to pull the whole planet apart to infinity, in joules
+    /// Built up one concentric circle at a time, each ring falling onto the mass already inside it,
+    /// $U = \sum_{rings} \frac{G M_{inside} m_{ring}}{r}$, with half of the ring counted as inside itself
+    /// This is also the heat released when the planet formed, so it is a fair start for the core temperature
+    /// Uses the radial profile cached by [Self::recalculate_everything]
+    pub fn gravitational_binding_energy(&self) -> f64 {
+        let mut energy = 0.0;
+        let mut inside = 0.0;
+        for &(radius, enclosed) in &self.radial_profile {
+            let ring = enclosed.0 as f64 - inside;
+            energy += G as f64 * (inside + ring / 2.0) * ring / radius as f64;
+            inside = enclosed.0 as f64;
+        }
+        energy
+    }
+
+    /// The speed needed to leave from the outer edge of the mesh and never fall back, $\sqrt{\frac{2 G M}{R}}$
+    /// Treats all of the mass as inside the outer radius, which it always is
+    pub fn escape_velocity(&self) -> f64 {
+        let radius = self.coords.get_radius().0 as f64;
+        (2.0 * G as f64 * self.total_mass.0 as f64 / radius).sqrt()
+    }
+
+    /// Adds mass to the ring whose middle is at `mid_radius` and everything outside it,
+    /// so [Self::enclosed_mass] sees cells set between full recalculations
+    fn add_to_radial_profile(&mut self, mid_radius: f32, added: Mass) {
+        if added.0 == 0.0 {
+            return;
+        }
+        let idx = self
+            .radial_profile
+            .partition_point(|(radius, _)| *radius < mid_radius);
+        // The first cell set in a ring of a chunk that was never generated
+        if self
+            .radial_profile
+            .get(idx)
+            .is_none_or(|(radius, _)| *radius != mid_radius)
+        {
+            let inside = idx
+                .checked_sub(1)
+                .map_or(Mass(0.0), |below| self.radial_profile[below].1);
+            self.radial_profile.insert(idx, (mid_radius, inside));
+        }
+        for ring in &mut self.radial_profile[idx..] {
+            ring.1 += added;
+        }
+    }
+
+    /// The cumulative mass out to the middle of each concentric circle, sorted by radius
+    fn calc_radial_profile(chunks: &[Grid<Option<ElementGrid>>]) -> Vec<(f32, Mass)> {
+        let mut rings: HashMap<usize, (f32, Mass)> = HashMap::new();
+        for layer in chunks {
+            for chunk in layer.iter().flatten().filter(|chunk| chunk.is_generated()) {
+                let coords = chunk.get_chunk_coords();
+                let cell_width = coords.get_cell_width();
+                for j in 0..coords.get_num_concentric_circles() {
+                    let mid_radius = coords.get_start_radius() + (j as f32 + 0.5) * cell_width.0;
+                    let ring = rings
+                        .entry(coords.get_start_concentric_circle_absolute() + j)
+                        .or_insert((mid_radius, Mass(0.0)));
+                    for k in 0..coords.get_num_radial_lines() {
+                        ring.1 += chunk.get(JkVector { j, k }).get_mass(cell_width);
+                    }
+                }
+            }
+        }
+        let mut profile: Vec<(f32, Mass)> = rings.into_values().collect();
+        profile.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut total = Mass(0.0);
+        for ring in profile.iter_mut() {
+            total += ring.1;
+            ring.1 = total;
+        }
+        profile
+    }
+
+    /// Where the mass of the directory balances, relative to its center
+    /// Stays at the center while the mass is spread evenly, and drifts off it as impacts and
+    /// erosion pile mass up on one side. Recalculated every full process, with everything else
+    /// in [Self::recalculate_everything]
+    pub fn get_center_of_mass(&self) -> RelXyPoint {
+        self.center_of_mass
+    }
+
+    /// The mass weighted average of every cell's midpoint, the center when there is no mass
+    fn calc_center_of_mass(chunks: &[Grid<Option<ElementGrid>>]) -> RelXyPoint {
+        let mut moment = Vec2::ZERO;
+        let mut total = 0.0;
+        for layer in chunks {
+            for chunk in layer.iter().flatten().filter(|chunk| chunk.is_generated()) {
+                let coords = chunk.get_chunk_coords();
+                let cell_width = coords.get_cell_width();
+                for k in 0..coords.get_num_radial_lines() {
+                    let angle = coords.get_winding().angle(
+                        (coords.get_start_radial_line() + k) as f32 + 0.5,
+                        coords.get_layer_num_radial_lines(),
+                    );
+                    let direction = Vec2::new(angle.cos(), angle.sin());
+                    for j in 0..coords.get_num_concentric_circles() {
+                        let mass = chunk.get(JkVector { j, k }).get_mass(cell_width).0;
+                        let radius = coords.get_start_radius() + (j as f32 + 0.5) * cell_width.0;
+                        moment += direction * radius * mass;
+                        total += mass;
+                    }
+                }
+            }
+        }
+        if total > 0.0 {
+            RelXyPoint(moment / total)
+        } else {
+            RelXyPoint(Vec2::ZERO)
+        }
+    }
+
+    /// Deflect moving elements sideways, like the coriolis effect
+    /// The `coriolis_strength` scales the angular velocity into the chance an element is pushed a cell
+    /// for every cell it falls, so things falling fast drift more and things rising drift against the spin.
+    /// 0 turns it off
+    pub fn set_coriolis(&mut self, angular_velocity: AngularVelocity, coriolis_strength: f32) {
+        self.set_coriolis_bias((angular_velocity.0 * coriolis_strength).clamp(-1.0, 1.0));
+    }
+
+    /// Sets the chance every chunk pushes a falling element sideways, as worked out by [Self::set_coriolis]
+    pub fn set_coriolis_bias(&mut self, bias: f32) {
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut().flatten() {
+                chunk.set_coriolis_bias(bias);
+            }
+        }
+    }
+
+    /// See [Self::set_coriolis_bias], every chunk has the same one
+    pub fn get_coriolis_bias(&self) -> f32 {
+        self.get_chunk_by_chunk_ijk(ChunkIjkVector::new(0, 0, 0))
+            .get_coriolis_bias()
+    }
+
+    /// Sets how every chunk loses heat to space, see [HeatConfig]
+    pub fn set_heat_config(&mut self, heat_config: HeatConfig) {
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut().flatten() {
+                chunk.set_heat_config(heat_config);
+            }
+        }
+    }
+
+    /// See [Self::set_heat_config], every chunk has the same one
+    pub fn get_heat_config(&self) -> HeatConfig {
+        self.get_chunk_by_chunk_ijk(ChunkIjkVector::new(0, 0, 0))
+            .get_heat_config()
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reseeds the random stream of every chunk from one global seed
+    /// Each chunk mixes in its own index, so processing is reproducible without
+    /// neighboring chunks making the same choices
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut().flatten() {
+                chunk.reseed(seed);
+            }
+        }
+    }
+
+    /// The simulation time of this directory
+    /// Elements are handed this as they are processed, instead of reading the engine's time,
+    /// so replays and tests control exactly how much time passes. Set by every call to [Self::process]
+    pub fn clock(&self) -> Clock {
+        self.clock
+    }
+
+    /// Jumps the simulation time to `clock`, the next [Self::step] picks up from there
+    /// Going backwards rewinds the elements too, see [ElementGrid::rewind]
+    pub fn set_clock(&mut self, clock: Clock) {
+        if clock.get_current_frame() < self.clock.get_current_frame() {
+            for layer in &mut self.chunks {
+                for chunk in layer.iter_mut().flatten() {
+                    chunk.rewind(clock);
+                }
+            }
+        }
+        self.clock = clock;
+    }
+
+    /// How many passes of [Self::process] have run, a full cycle is [FRAMES_PER_FULL_PROCESS] of them
+    pub fn get_process_count(&self) -> usize {
+        self.process_count
+    }
+
+    /// Picks up the pass count from a replay, the next pass is chosen from it
+    pub(super) fn set_process_count(&mut self, process_count: usize) {
+        self.process_count = process_count;
+        self.update_resting_chunks();
+    }
+
+    /// True when no cycle has been started, so every chunk is about to be processed fresh
+    pub fn is_between_cycles(&self) -> bool {
+        self.process_count % FRAMES_PER_FULL_PROCESS == 0
+    }
+
+    /// Moves the simulation time forward by `delta` and one frame, without processing anything
+    pub fn advance_clock(&mut self, delta: Duration) {
+        self.clock.update(delta);
+    }
+
+    /// Starts the simulation time over from zero
+    pub fn reset_clock(&mut self) {
+        self.set_clock(Clock::default());
+    }
+
+    /// Does [Self::set_sub_steps] passes of [Self::process], splitting `delta` evenly between them
+    /// Each pass advances the clock by its share and a frame first
+    pub fn step(&mut self, delta: Duration) {
+        for share in Self::shares(delta, self.sub_steps) {
+            self.advance_clock(share);
+            self.process(self.clock);
+        }
+    }
+
+    /// Runs the whole nine pass cycle [Self::set_sub_steps] times, splitting `delta` evenly between them
+    /// Each sub step advances the clock by its share and a frame, so every element gets to move once per sub step
+    pub fn step_full(&mut self, delta: Duration) {
+        for share in Self::shares(delta, self.sub_steps) {
+            self.advance_clock(share);
+            self.process_full(self.clock);
+        }
+    }
+
+    /// `delta` split into `steps` shares
+    /// Cut at the same points every time so the shares add back up to delta exactly
+    fn shares(delta: Duration, steps: usize) -> impl Iterator<Item = Duration> {
+        let steps = steps as u32;
+        (0..steps).map(move |step| delta * (step + 1) / steps - delta * step / steps)
+    }
+
+    /// How many times [Self::step] and [Self::step_full] repeat their work per call, a pass or a
+    /// full cycle each. 1 by default
+    /// More settles things faster for the same framerate, at the cost of that many times the work
+    pub fn set_sub_steps(&mut self, sub_steps: usize) {
+        debug_assert!(sub_steps > 0, "There has to be at least one step");
+        self.sub_steps = sub_steps.max(1);
+    }
+
+    /// See [Self::set_sub_steps]
+    pub fn get_sub_steps(&self) -> usize {
+        self.sub_steps
+    }
+
+    /// Where the light the surface heats up in comes from, a direction in world space from the
+    /// center of the celestial. None for no light at all, see [HeatConfig::solar_heating_rate]
+    pub fn set_sunlight(&mut self, sunlight: Option<Vec2>) {
+        self.sunlight = sunlight.map(Vec2::normalize_or_zero);
+    }
+
+    /// See [Self::set_sunlight]
+    pub fn get_sunlight(&self) -> Option<Vec2> {
+        self.sunlight
+    }
+
+    /// How fast the celestial turns under its light, counterclockwise positive
+    /// The day side moves around the surface as the clock runs, see [Self::rotation]
+    pub fn set_spin(&mut self, spin: AngularVelocity) {
+        self.spin = spin;
+    }
+
+    /// See [Self::set_spin]
+    pub fn get_spin(&self) -> AngularVelocity {
+        self.spin
+    }
+
+    /// How far the celestial has turned since the clock started, in radians
+    pub fn rotation(&self) -> f32 {
+        self.spin.0 * self.clock.get_current_time().as_secs_f32()
+    }
+
+    /// Where the light is in the celestial's own frame, at the current rotation
+    /// Handed to each chunk just before it is processed, see [ElementGrid::set_light_direction]
+    fn light_direction(&self) -> Vec2 {
+        match self.sunlight {
+            Some(sunlight) => Vec2::from_angle(-self.rotation()).rotate(sunlight),
+            None => Vec2::ZERO,
+        }
+    }
+
+    /// Draw textures as a heatmap of the temperature field instead of the element colors
+    /// None goes back to drawing the elements
+    pub fn set_heatmap(&mut self, gradient: Option<HeatGradient>) {
+        // Every texture was drawn the other way, or in other colors, see Self::get_dirty_textures
+        if self.heatmap != gradient {
+            for layer in &mut self.chunks {
+                for chunk in layer.iter_mut().flatten() {
+                    chunk.mark_texture_dirty();
+                }
+            }
+            self.heatmap = gradient;
+        }
+    }
+
+    /// Draw the heatmap a chunk at a time, each in the color of its average temperature,
+    /// see [ElementGrid::get_coarse_heat_texture]. Only matters while the heatmap is on
+    pub fn set_coarse_heat(&mut self, coarse: bool) {
+        if self.coarse_heat != coarse {
+            if self.heatmap.is_some() {
+                for layer in &mut self.chunks {
+                    for chunk in layer.iter_mut().flatten() {
+                        chunk.mark_texture_dirty();
+                    }
+                }
+            }
+            self.coarse_heat = coarse;
+        }
+    }
+
+    /// See [Self::set_coarse_heat]
+    pub fn get_coarse_heat(&self) -> bool {
+        self.coarse_heat
+    }
+
+    /// Draw textures as the pressure on each cell, with contour lines every so often,
+    /// see [Self::pressure_field]. This is drawn over the heatmap if both are on
+    /// None goes back to whatever was drawn before
+    pub fn set_pressure_overlay(&mut self, overlay: Option<PressureOverlay>) {
+        if self.pressure_overlay != overlay {
+            for layer in &mut self.chunks {
+                for chunk in layer.iter_mut().flatten() {
+                    chunk.mark_texture_dirty();
+                }
+            }
+            self.pressure_overlay = overlay;
+            self.pressure = self.calc_pressure_snapshot(self.pressure_field());
+        }
+    }
+
+    /// Which colors the elements are drawn in
+    /// Changing it redraws every chunk the next time dirty textures are asked for
+    pub fn set_palette(&mut self, palette: ElementPalette) {
+        if self.palette != palette {
+            for layer in &mut self.chunks {
+                for chunk in layer.iter_mut().flatten() {
+                    chunk.mark_texture_dirty();
+                }
+            }
+            self.palette = palette;
+        }
+    }
+
+    /// See [Self::set_palette]
+    pub fn get_palette(&self) -> &ElementPalette {
+        &self.palette
+    }
+
+    /// Recalculate the total mass of the directory
     fn recalculate_total_mass(&mut self) {
         self.total_mass = Self::calc_total_mass(&mut self.chunks);
     }
@@ -751,34 +2297,82 @@ impl ElementGridDir {
     //     (self.max_temp, self.min_temp) = Self::calc_max_min_temp(&mut self.chunks);
     // }
 
-    // /// Calculate the maximum temperature in the directory
-    // pub fn calc_max_min_temp(
-    //     chunks: &mut Vec<Grid<Option<ElementGrid>>>,
-    // ) -> (ThermodynamicTemperature, ThermodynamicTemperature) {
-    //     let mut max = ThermodynamicTemperature(0.0);
-    //     let mut min = ThermodynamicTemperature(f32::INFINITY);
-    //     for layer in chunks {
-    //         for chunk in layer.into_iter().flatten() {
-    //             let (max_temp, min_temp) = chunk.get_max_min_temp();
-    //             if max_temp > max {
-    //                 max = max_temp;
-    //             }
-    //             // Min temperature should not equal exactly 0 because thats usually vacuum
-    //             if min_temp < min && min_temp != ThermodynamicTemperature(0.0) {
-    //                 min = min_temp;
-    //             }
-    //         }
-    //     }
-    //     // Because we exclude 0, if we get nothing but zero, we will get min==INF
-    //     // So we should fix that
-    //     if min == ThermodynamicTemperature(f32::INFINITY) {
-    //         min = ThermodynamicTemperature(0.0);
-    //     }
-    //     (max, min)
-    // }
+    /// Calculate the maximum and minimum temperature in the directory
+    /// The minimum leaves out 0K, as that is usually vacuum
+    pub fn calc_max_min_temp(&self) -> (ThermodynamicTemperature, ThermodynamicTemperature) {
+        let mut max = ThermodynamicTemperature(0.0);
+        let mut min = ThermodynamicTemperature(f32::INFINITY);
+        for layer in &self.chunks {
+            for chunk in layer.get_data_slice().iter().flatten() {
+                let (max_temp, min_temp) = chunk.calc_max_min_temp();
+                if max_temp > max {
+                    max = max_temp;
+                }
+                if min_temp < min {
+                    min = min_temp;
+                }
+            }
+        }
+        // Because we exclude 0, if we get nothing but zero, we will get min==INF
+        // So we should fix that
+        if min == ThermodynamicTemperature(f32::INFINITY) {
+            min = ThermodynamicTemperature(0.0);
+        }
+        (max, min)
+    }
+
+    /// The average temperature of every chunk, laid out like the chunks of each layer
+    /// For drawing heat coarsely when the planet is far away, see [ElementGrid::average_temperature]
+    /// Chunks that are borrowed for processing read as 0
+    pub fn chunk_average_temperatures(&self) -> Vec<Grid<f32>> {
+        self.chunks
+            .iter()
+            .map(|layer| {
+                let mut out = Grid::new_fill(layer.get_width(), layer.get_height(), 0.0);
+                for j in 0..layer.get_height() {
+                    for k in 0..layer.get_width() {
+                        let pos = JkVector { j, k };
+                        if let Some(chunk) = layer.get(pos) {
+                            out.set(pos, chunk.average_temperature());
+                        }
+                    }
+                }
+                out
+            })
+            .collect()
+    }
+
+    /// Every cell in the directory holding this kind of element
+    /// Chunks keep a record of which kinds they might hold, so chunks without any aren't scanned
+    /// Chunks that are borrowed for processing are skipped
+    pub fn cells_of_kind(&self, kind: ElementType) -> impl Iterator<Item = IjkVector> + '_ {
+        self.chunks
+            .iter()
+            .flat_map(|layer| layer.get_data_slice().iter().flatten())
+            .flat_map(move |chunk| chunk.cells_of_kind(kind))
+    }
 
+    /// Scans every cell for NaN or infinite fields
+    /// When something blows up, this says where it started instead of just where it crashed
+    pub fn find_non_finite_cells(&self) -> Vec<(IjkVector, FieldKind)> {
+        self.chunks
+            .iter()
+            .flat_map(|layer| layer.get_data_slice().iter().flatten())
+            .flat_map(|chunk| chunk.find_non_finite_cells())
+            .collect()
+    }
+
+    /// Gets the chunk at the given index
+    /// Returns an error instead of panicking if it is currently borrowed
+    /// Useful for tools which might query the directory mid process
+    pub fn try_get_chunk(&self, coord: ChunkIjkVector) -> Result<&ElementGrid, ChunkBorrowError> {
+        self.chunks[coord.i]
+            .get(coord.to_jk_vector())
+            .as_ref()
+            .ok_or(ChunkBorrowError(coord))
+    }
     /// Gets the chunk at the given index
-    /// Errors if it is currently borrowed
+    /// Panics if it is currently borrowed, see [Self::try_get_chunk]
     pub fn get_chunk_by_chunk_ijk(&self, coord: ChunkIjkVector) -> &ElementGrid {
         self.chunks[coord.i]
             .get(coord.to_jk_vector())
@@ -786,19 +2380,86 @@ impl ElementGridDir {
             .unwrap()
     }
     /// Gets the chunk at the given index mutably
-    /// Errors if it is currently borrowed
+    /// Panics if it is currently borrowed
+    /// Generates it first if the directory is lazy, see [Self::new_lazy]
     pub fn get_chunk_by_chunk_ijk_mut(&mut self, coord: ChunkIjkVector) -> &mut ElementGrid {
+        self.generate_chunk(coord);
         self.chunks[coord.i]
             .get_mut(coord.to_jk_vector())
             .as_mut()
             .unwrap()
     }
 
+    /// Panics if the cell's chunk is borrowed, or hasn't been generated yet, see [Self::try_get_element]
     #[allow(clippy::borrowed_box)]
     pub fn get_element(&self, coord: IjkVector) -> &Box<dyn Element> {
+        self.try_get_element(coord)
+            .expect("The chunk should be generated and not borrowed")
+    }
+
+    /// The element in a cell, None if its chunk is borrowed or hasn't been generated yet
+    #[allow(clippy::borrowed_box)]
+    pub fn try_get_element(&self, coord: IjkVector) -> Option<&Box<dyn Element>> {
+        let chunk_idx = self.get_coordinate_dir().cell_idx_to_chunk_idx(coord);
+        let chunk = self.try_get_chunk(chunk_idx.0).ok()?;
+        chunk.is_generated().then(|| chunk.get(chunk_idx.1))
+    }
+
+    /// The temperature of a cell, which stays with the cell rather than the element
+    /// Panics like [Self::get_element], see [Self::try_get_temperature]
+    pub fn get_temperature(&self, coord: IjkVector) -> ThermodynamicTemperature {
+        self.try_get_temperature(coord)
+            .expect("The chunk should be generated and not borrowed")
+    }
+
+    /// The temperature of a cell, None if its chunk is borrowed or hasn't been generated yet
+    pub fn try_get_temperature(&self, coord: IjkVector) -> Option<ThermodynamicTemperature> {
         let chunk_idx = self.get_coordinate_dir().cell_idx_to_chunk_idx(coord);
-        let chunk = self.get_chunk_by_chunk_ijk(chunk_idx.0);
-        chunk.get(chunk_idx.1)
+        let chunk = self.try_get_chunk(chunk_idx.0).ok()?;
+        chunk
+            .is_generated()
+            .then(|| chunk.get_temperature(chunk_idx.1))
+    }
+
+    /// The temperature at any point on the celestial in Kelvin, blended from the four nearest cell centers
+    /// The blend is bilinear in polar space, along the rings and along the radial lines, and reads
+    /// across chunk boundaries through [Self::read_only_convolution]
+    /// It doesn't blend across the boundary between two layers, the nearest ring of the layer is used twice instead
+    /// None outside of the mesh, or if the chunk under the point is borrowed for processing
+    pub fn sample_temperature_bilinear(&self, pos: RelXyPoint) -> Option<f32> {
+        let coord_dir = self.get_coordinate_dir();
+        let cell = coord_dir.rel_pos_to_cell_idx(pos).ok()?;
+        let num_radial_lines = coord_dir.get_layer_num_radial_lines(cell.i);
+        let num_concentric_circles = coord_dir.get_layer_num_concentric_circles(cell.i);
+        let start_r = coord_dir.get_layer_start_radius(cell.i);
+        let separation = coord_dir.get_layer_cell_height(cell.i);
+
+        // Continuous cell coordinates, with the cell centers on the integers
+        let radius = pos.0.length();
+        let angle = pos.0.y.atan2(pos.0.x);
+        let j_f =
+            ((radius - start_r) / separation - 0.5).clamp(0.0, (num_concentric_circles - 1) as f32);
+        let k_f = (coord_dir
+            .get_winding()
+            .fractional_radial_line(angle, num_radial_lines)
+            - 0.5)
+            .rem_euclid(num_radial_lines as f32);
+
+        let j0 = (j_f.floor() as usize).min(num_concentric_circles - 1);
+        let j1 = (j0 + 1).min(num_concentric_circles - 1);
+        let k0 = (k_f.floor() as usize) % num_radial_lines;
+        let k1 = (k0 + 1) % num_radial_lines;
+        let (fj, fk) = (j_f - j0 as f32, k_f - k_f.floor());
+
+        let view = self
+            .read_only_convolution(coord_dir.cell_idx_to_chunk_idx(cell).0)
+            .ok()?;
+        let temperature = |j: usize, k: usize| -> Option<f32> {
+            Some(view.get_temperature(IjkVector::new(cell.i, j, k))?.0)
+        };
+        let inner = temperature(j0, k0)? * (1.0 - fk) + temperature(j0, k1)? * fk;
+        let outer = temperature(j1, k0)? * (1.0 - fk) + temperature(j1, k1)? * fk;
+        Some(inner * (1.0 - fj) + outer * fj)
     }
 
     pub fn set_element(
@@ -809,15 +2470,782 @@ impl ElementGridDir {
     ) {
         let chunk_idx = self.get_coordinate_dir().cell_idx_to_chunk_idx(coord);
         let chunk = self.get_chunk_by_chunk_ijk_mut(chunk_idx.0);
+        let coords = chunk.get_chunk_coords();
+        let cell_width = coords.get_cell_width();
+        let mid_radius = coords.get_start_radius() + (chunk_idx.1.j as f32 + 0.5) * cell_width.0;
+        let added = element.get_mass(cell_width) - chunk.get(chunk_idx.1).get_mass(cell_width);
         chunk.set(chunk_idx.1, element, current_time);
+        self.total_mass += added;
+        self.add_to_radial_profile(mid_radius, added);
     }
 
-    pub fn get_coordinate_dir(&self) -> &CoordinateDir {
-        &self.coords
-    }
-    pub fn len(&self) -> usize {
-        self.chunks.len()
-    }
+    /// Paints an image into a layer, setting each cell to the kind of element the palette draws in its pixel's color
+    /// The image is laid out like a chunk texture, so its rows go out through the concentric circles
+    /// starting at j0 and its columns go along the radial lines starting at k0, wrapping around the ring
+    /// Rows past the top of the layer are cut off, and transparent pixels or pixels in a color no element is drawn in
+    /// leave their cell alone. So do custom elements, which only their registry knows how to make
+    pub fn stamp_image(
+        &mut self,
+        layer: usize,
+        j0: usize,
+        k0: usize,
+        image: RawImage,
+        palette: &ElementPalette,
+    ) {
+        let kinds = palette.kinds_by_color();
+        let width = image.bounds.width() as usize;
+        let num_concentric_circles = self.coords.get_layer_num_concentric_circles(layer);
+        let num_radial_lines = self.coords.get_layer_num_radial_lines(layer);
+        let current_time = self.clock;
+        for (idx, pixel) in image.pixels.chunks_exact(4).enumerate() {
+            let j = j0 + idx / width;
+            if j >= num_concentric_circles {
+                break;
+            }
+            let pixel: [u8; 4] = pixel.try_into().unwrap();
+            // Vacuum is drawn fully transparent, but a transparent background means nothing was painted
+            if pixel[3] == 0 {
+                continue;
+            }
+            let Some(element) = kinds
+                .get(&pixel)
+                .and_then(|kind| kind.try_get_element().ok())
+            else {
+                continue;
+            };
+            let k = (k0 + idx % width) % num_radial_lines;
+            let (chunk_idx, pos) = self
+                .coords
+                .cell_idx_to_chunk_idx(IjkVector::new(layer, j, k));
+            self.generate_chunk(chunk_idx);
+            self.get_chunk_by_chunk_ijk_mut(chunk_idx)
+                .set(pos, element, current_time);
+        }
+        self.recalculate_everything();
+    }
+
+    /// Stitches every chunk in a layer together into one array of element ids
+    /// Indexed `[j, k]` in layer relative coordinates, with each id being the
+    /// [ElementType::id](super::super::elements::element::ElementType::id)
+    /// Chunks that haven't been generated yet read as vacuum
+    pub fn layer_as_array(&self, layer: usize) -> Array2<u16> {
+        let mut out = Array2::zeros((
+            self.coords.get_layer_num_concentric_circles(layer),
+            self.coords.get_layer_num_radial_lines(layer),
+        ));
+        for j in 0..self.coords.get_layer_num_concentric_chunks(layer) {
+            for k in 0..self.coords.get_layer_num_tangential_chunkss(layer) {
+                let chunk = self.get_chunk_by_chunk_ijk(ChunkIjkVector { i: layer, j, k });
+                if !chunk.is_generated() {
+                    continue;
+                }
+                let chunk_coords = chunk.get_chunk_coords();
+                let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+                let start_k = chunk_coords.get_start_radial_line();
+                for cj in 0..chunk_coords.get_num_concentric_circles() {
+                    for ck in 0..chunk_coords.get_num_radial_lines() {
+                        out[[start_j + cj, start_k + ck]] =
+                            chunk.get(JkVector { j: cj, k: ck }).get_type().id();
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// The pressure on every cell, from the weight of everything above it in its column, in pascals
+    /// Indexed `[layer][[j, k]]` like [Self::layer_as_array]. A cell's own weight isn't counted,
+    /// so the surface is at 0. Where a layer has twice the radial lines of the one below it,
+    /// each column below carries the average of the two above it
+    /// Chunks that haven't been generated yet weigh nothing
+    pub fn pressure_field(&self) -> Vec<Array2<f32>> {
+        self.pressure_field_down_to(0)
+    }
+
+    /// Like [Self::pressure_field], but the layers under `lowest_layer` are left empty
+    /// Weight only presses down, so the layers that are worked out come out the same
+    fn pressure_field_down_to(&self, lowest_layer: usize) -> Vec<Array2<f32>> {
+        let num_layers = self.coords.get_num_layers();
+        let mut out = Vec::with_capacity(num_layers);
+        // The pressure under the bottom ring of the layer above, one per radial line
+        let mut carried: Vec<f32> = Vec::new();
+        for layer in (lowest_layer..num_layers).rev() {
+            let num_concentric_circles = self.coords.get_layer_num_concentric_circles(layer);
+            let num_radial_lines = self.coords.get_layer_num_radial_lines(layer);
+            carried = if carried.is_empty() {
+                vec![0.0; num_radial_lines]
+            } else {
+                (0..num_radial_lines)
+                    .map(|k| {
+                        let start = k * carried.len() / num_radial_lines;
+                        let end = ((k + 1) * carried.len() / num_radial_lines).max(start + 1);
+                        carried[start..end].iter().sum::<f32>() / (end - start) as f32
+                    })
+                    .collect()
+            };
+            let mut densities = Array2::<f32>::zeros((num_concentric_circles, num_radial_lines));
+            for j in 0..self.coords.get_layer_num_concentric_chunks(layer) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(layer) {
+                    // Missing and ungenerated chunks count as empty
+                    let Some(chunk) = self.chunks[layer].get(JkVector { j, k }) else {
+                        continue;
+                    };
+                    if !chunk.is_generated() {
+                        continue;
+                    }
+                    let chunk_coords = chunk.get_chunk_coords();
+                    let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+                    let start_k = chunk_coords.get_start_radial_line();
+                    for cj in 0..chunk_coords.get_num_concentric_circles() {
+                        for ck in 0..chunk_coords.get_num_radial_lines() {
+                            densities[[start_j + cj, start_k + ck]] =
+                                chunk.get(JkVector { j: cj, k: ck }).get_density().0;
+                        }
+                    }
+                }
+            }
+            let cell_height = self.coords.get_layer_cell_height(layer);
+            let start_radius = self.coords.get_layer_start_radius(layer);
+            let mut pressures = Array2::<f32>::zeros((num_concentric_circles, num_radial_lines));
+            for j in (0..num_concentric_circles).rev() {
+                let gravity = self
+                    .gravity_at_radius(start_radius + (j as f32 + 0.5) * cell_height)
+                    .0;
+                for k in 0..num_radial_lines {
+                    pressures[[j, k]] = carried[k];
+                    carried[k] += densities[[j, k]] * cell_height * gravity;
+                }
+            }
+            out.push(pressures);
+        }
+        out.extend((0..lowest_layer).map(|_| Array2::zeros((0, 0))));
+        out.reverse();
+        out
+    }
+
+    /// The corners of a cell as it is drawn on screen, for picking and decals
+    /// Its [ChunkCoords::cell_quad](crate::physics::fallingsand::mesh::chunk_coords::ChunkCoords::cell_quad)
+    /// moved by the celestial's transform
+    pub fn cell_world_quad(&self, cell: IjkVector, transform: &GlobalTransform) -> [Vec2; 4] {
+        let (chunk_idx, local) = self.coords.cell_idx_to_chunk_idx(cell);
+        self.coords
+            .get_chunk_at_idx(chunk_idx)
+            .cell_quad(local)
+            .map(|corner| transform.transform_point(corner.extend(0.0)).truncate())
+    }
+
+    /// Every cell whose midpoint is inside the rect, for selecting a region, in `[i, j, k]` order
+    /// The rect is relative to the center of the celestial, like [CoordinateDir::cell_midpoint]
+    /// Chunks whose bounding box misses the rect are skipped without looking at their cells
+    pub fn cells_in_rect(&self, rect: Rect) -> Vec<IjkVector> {
+        let mut out = Vec::new();
+        for (chunk_idx, bounding_box) in self.coords.get_chunk_bounding_boxes() {
+            // The box is drawn through the outline's vertices, and the arcs between them bulge past it a little
+            let cell_width = self.coords.get_layer_cell_width(chunk_idx.i).0;
+            if bounding_box.inset(cell_width).intersect(rect).is_empty() {
+                continue;
+            }
+            let chunk_coords = self.coords.get_chunk_at_idx(chunk_idx);
+            let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+            let start_k = chunk_coords.get_start_radial_line();
+            for j in start_j..start_j + chunk_coords.get_num_concentric_circles() {
+                for k in start_k..start_k + chunk_coords.get_num_radial_lines() {
+                    let cell = IjkVector::new(chunk_idx.i, j, k);
+                    if rect.contains(self.coords.cell_midpoint(cell).0) {
+                        out.push(cell);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Every cell sharing an edge with this one, for flood fills
+    /// [CoordinateDir::relative_direction] only goes up to the first of the cells above where the
+    /// layer above has more radial lines, so the rest of them are added here
+    fn edge_neighbors(&self, cell: IjkVector) -> Vec<IjkVector> {
+        let mut out: Vec<IjkVector> = [
+            RelativeDir::Up,
+            RelativeDir::Down,
+            RelativeDir::Spinward,
+            RelativeDir::Antispinward,
+        ]
+        .into_iter()
+        .filter_map(|dir| self.coords.relative_direction(cell, dir))
+        .collect();
+        if cell.j + 1 == self.coords.get_layer_num_concentric_circles(cell.i)
+            && cell.i + 1 < self.coords.get_num_layers()
+        {
+            let below = self.coords.get_layer_num_radial_lines(cell.i);
+            let above = self.coords.get_layer_num_radial_lines(cell.i + 1);
+            let start = cell.k * above / below;
+            let end = ((cell.k + 1) * above / below).max(start + 1);
+            out.extend((start + 1..end).map(|k| IjkVector::new(cell.i + 1, 0, k)));
+        }
+        out
+    }
+
+    /// Carries power out from every [Conduction::Source] through the conductors connected to it
+    /// A cellular automaton on top of the physics, run at the end of a cycle of [Self::process]
+    /// if any cell that can carry power changed, see [ElementGrid::take_circuit_dirty]
+    /// Power reaches the whole circuit at once. Sinks like lamps are powered when they touch it
+    /// but don't pass it on, and insulators, which is nearly everything, block it
+    /// Every element in the circuit hears about it through [Element::set_powered], and the chunks
+    /// of cells that gained or lost power are drawn again
+    pub fn propagate_power(&mut self) {
+        let mut frontier = Vec::new();
+        for layer in &self.chunks {
+            for chunk in layer.iter().flatten() {
+                // Ungenerated chunks hold nothing, so they can't hold a source either
+                if !chunk.may_contain_source() {
+                    continue;
+                }
+                let chunk_coords = chunk.get_chunk_coords();
+                let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+                let start_k = chunk_coords.get_start_radial_line();
+                for j in 0..chunk_coords.get_num_concentric_circles() {
+                    for k in 0..chunk_coords.get_num_radial_lines() {
+                        if chunk.get(JkVector { j, k }).conduction() == Conduction::Source {
+                            frontier.push(IjkVector::new(
+                                chunk_coords.get_layer_num(),
+                                start_j + j,
+                                start_k + k,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        let mut powered: HashSet<IjkVector> = frontier.iter().copied().collect();
+        while let Some(cell) = frontier.pop() {
+            for neighbor in self.edge_neighbors(cell) {
+                if powered.contains(&neighbor) {
+                    continue;
+                }
+                // Ungenerated chunks hold nothing to carry power yet
+                let Some(element) = self.try_get_element(neighbor) else {
+                    continue;
+                };
+                match element.conduction() {
+                    Conduction::Conductor => {
+                        powered.insert(neighbor);
+                        frontier.push(neighbor);
+                    }
+                    Conduction::Sink => {
+                        powered.insert(neighbor);
+                    }
+                    Conduction::Source | Conduction::Insulator => {}
+                }
+            }
+        }
+        let previous = std::mem::replace(&mut self.powered, powered);
+        for &cell in self.powered.union(&previous) {
+            let (chunk_idx, pos) = self.coords.cell_idx_to_chunk_idx(cell);
+            // Skips a chunk that was leaked, process has already reported it
+            let Some(chunk) = self.chunks[chunk_idx.i]
+                .get_mut(chunk_idx.to_jk_vector())
+                .as_mut()
+            else {
+                continue;
+            };
+            let is_powered = self.powered.contains(&cell);
+            chunk.get_mut(pos).set_powered(is_powered);
+            if is_powered != previous.contains(&cell) {
+                chunk.mark_texture_dirty();
+            }
+        }
+    }
+
+    /// Whether any chunk's circuit changed, clearing every chunk's flag
+    fn take_circuit_dirty(&mut self) -> bool {
+        let mut dirty = false;
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut().flatten() {
+                dirty |= chunk.take_circuit_dirty();
+            }
+        }
+        dirty
+    }
+
+    /// Whether a cell had power the last time [Self::propagate_power] ran
+    pub fn is_powered(&self, cell: IjkVector) -> bool {
+        self.powered.contains(&cell)
+    }
+
+    /// Every cell with power, see [Self::is_powered]
+    pub fn powered_cells(&self) -> &HashSet<IjkVector> {
+        &self.powered
+    }
+
+    /// Every empty or gas cell that can't reach space without passing through a liquid or solid,
+    /// like a bubble under water or a cave sealed in rock, in `[i, j, k]` order
+    /// Flood fills from the open cells of the top ring through empty and gas cells, whatever isn't
+    /// reached is trapped. Chunks that haven't been generated yet count as open, like vacuum
+    pub fn find_trapped_gas(&self) -> Vec<IjkVector> {
+        let num_layers = self.coords.get_num_layers();
+        let mut open: Vec<Array2<bool>> = Vec::with_capacity(num_layers);
+        for layer in 0..num_layers {
+            let mut layer_open = Array2::from_elem(
+                (
+                    self.coords.get_layer_num_concentric_circles(layer),
+                    self.coords.get_layer_num_radial_lines(layer),
+                ),
+                true,
+            );
+            for j in 0..self.coords.get_layer_num_concentric_chunks(layer) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(layer) {
+                    let chunk = self.get_chunk_by_chunk_ijk(ChunkIjkVector { i: layer, j, k });
+                    if !chunk.is_generated() {
+                        continue;
+                    }
+                    let chunk_coords = chunk.get_chunk_coords();
+                    let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+                    let start_k = chunk_coords.get_start_radial_line();
+                    for cj in 0..chunk_coords.get_num_concentric_circles() {
+                        for ck in 0..chunk_coords.get_num_radial_lines() {
+                            let state = chunk.get(JkVector { j: cj, k: ck }).get_state_of_matter();
+                            layer_open[[start_j + cj, start_k + ck]] =
+                                state == StateOfMatter::Empty || state == StateOfMatter::Gas;
+                        }
+                    }
+                }
+            }
+            open.push(layer_open);
+        }
+
+        let mut reached: Vec<Array2<bool>> = open
+            .iter()
+            .map(|layer| Array2::from_elem(layer.dim(), false))
+            .collect();
+        let top = num_layers - 1;
+        let top_ring = self.coords.get_layer_num_concentric_circles(top) - 1;
+        let mut frontier: Vec<IjkVector> = (0..self.coords.get_layer_num_radial_lines(top))
+            .map(|k| IjkVector::new(top, top_ring, k))
+            .filter(|cell| open[top][[cell.j, cell.k]])
+            .collect();
+        for cell in &frontier {
+            reached[top][[cell.j, cell.k]] = true;
+        }
+        while let Some(cell) = frontier.pop() {
+            for neighbor in self.edge_neighbors(cell) {
+                let idx = [neighbor.j, neighbor.k];
+                if open[neighbor.i][idx] && !reached[neighbor.i][idx] {
+                    reached[neighbor.i][idx] = true;
+                    frontier.push(neighbor);
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        for (i, (layer_open, layer_reached)) in open.iter().zip(reached.iter()).enumerate() {
+            for ((j, k), &is_open) in layer_open.indexed_iter() {
+                if is_open && !layer_reached[[j, k]] {
+                    out.push(IjkVector::new(i, j, k));
+                }
+            }
+        }
+        out
+    }
+
+    /// A fingerprint of everything the simulation does, for golden files and for checking two copies
+    /// of a world haven't drifted apart. Goes through the chunks one at a time in `[i, j, k]` order,
+    /// hashing each cell's element, whatever state it keeps (see [Element::hash_state]) and its temperature,
+    /// then the seed and pass, so it comes out the same however rayon split up the processing.
+    /// Pressure is worked out from the elements, so it is covered without hashing it too.
+    /// Chunks that haven't been generated yet are hashed as such, rather than as what they would hold
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        for i in 0..self.coords.get_num_layers() {
+            for j in 0..self.coords.get_layer_num_concentric_chunks(i) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(i) {
+                    let chunk = self.get_chunk_by_chunk_ijk(ChunkIjkVector { i, j, k });
+                    hasher.write(&[chunk.is_generated() as u8]);
+                    if !chunk.is_generated() {
+                        continue;
+                    }
+                    let chunk_coords = chunk.get_chunk_coords();
+                    for cj in 0..chunk_coords.get_num_concentric_circles() {
+                        for ck in 0..chunk_coords.get_num_radial_lines() {
+                            let pos = JkVector { j: cj, k: ck };
+                            let element = chunk.get(pos);
+                            hasher.write(&element.get_type().id().to_le_bytes());
+                            element.hash_state(&mut hasher);
+                            hasher.write(&chunk.get_temperature(pos).0.to_bits().to_le_bytes());
+                        }
+                    }
+                }
+            }
+        }
+        hasher.write(&self.seed.to_le_bytes());
+        hasher.write(&(self.process_count as u64).to_le_bytes());
+        hasher.0
+    }
+
+    /// Every cell's temperature and nothing else, for resuming a thermal simulation or sending it
+    /// over the network without the elements
+    /// The layer count, then each layer's concentric circles and radial lines as u32s, then every
+    /// temperature in Kelvin as an f32, layer by layer in `[j, k]` order like [Self::layer_as_array],
+    /// all little endian. Chunks that haven't been generated yet read as 0K, like vacuum
+    pub fn serialize_heat(&self) -> Vec<u8> {
+        let num_layers = self.coords.get_num_layers();
+        let mut out = Vec::new();
+        out.extend((num_layers as u32).to_le_bytes());
+        for layer in 0..num_layers {
+            out.extend((self.coords.get_layer_num_concentric_circles(layer) as u32).to_le_bytes());
+            out.extend((self.coords.get_layer_num_radial_lines(layer) as u32).to_le_bytes());
+        }
+        for layer in 0..num_layers {
+            let mut temperatures = Array2::<f32>::zeros((
+                self.coords.get_layer_num_concentric_circles(layer),
+                self.coords.get_layer_num_radial_lines(layer),
+            ));
+            for j in 0..self.coords.get_layer_num_concentric_chunks(layer) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(layer) {
+                    let chunk = self.get_chunk_by_chunk_ijk(ChunkIjkVector { i: layer, j, k });
+                    if !chunk.is_generated() {
+                        continue;
+                    }
+                    let chunk_coords = chunk.get_chunk_coords();
+                    let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+                    let start_k = chunk_coords.get_start_radial_line();
+                    for cj in 0..chunk_coords.get_num_concentric_circles() {
+                        for ck in 0..chunk_coords.get_num_radial_lines() {
+                            temperatures[[start_j + cj, start_k + ck]] =
+                                chunk.get_temperature(JkVector { j: cj, k: ck }).0;
+                        }
+                    }
+                }
+            }
+            for temperature in temperatures.iter() {
+                out.extend(temperature.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    /// Puts back a heat field written by [Self::serialize_heat], leaving the elements alone
+    /// The layers have to be laid out exactly like this directory's, how they are split into
+    /// chunks doesn't matter. Nothing is changed if they aren't
+    pub fn load_heat(&mut self, bytes: &[u8]) -> Result<(), HeatLayoutError> {
+        let mut words = bytes
+            .chunks_exact(4)
+            .map(|word| -> [u8; 4] { word.try_into().unwrap() });
+        let mut next_u32 = || -> Result<usize, HeatLayoutError> {
+            words
+                .next()
+                .map(|word| u32::from_le_bytes(word) as usize)
+                .ok_or_else(|| HeatLayoutError("ran out of bytes in the header".to_string()))
+        };
+        let num_layers = next_u32()?;
+        if num_layers != self.coords.get_num_layers() {
+            return Err(HeatLayoutError(format!(
+                "{} layers, expected {}",
+                num_layers,
+                self.coords.get_num_layers()
+            )));
+        }
+        let mut num_cells = 0;
+        for layer in 0..num_layers {
+            let shape = (next_u32()?, next_u32()?);
+            let expected = (
+                self.coords.get_layer_num_concentric_circles(layer),
+                self.coords.get_layer_num_radial_lines(layer),
+            );
+            num_cells += expected.0 * expected.1;
+            if shape != expected {
+                return Err(HeatLayoutError(format!(
+                    "layer {} is {:?}, expected {:?}",
+                    layer, shape, expected
+                )));
+            }
+        }
+        let header_len = 4 * (1 + 2 * num_layers);
+        let expected_len = header_len + 4 * num_cells;
+        if bytes.len() != expected_len {
+            return Err(HeatLayoutError(format!(
+                "{} bytes, expected {}",
+                bytes.len(),
+                expected_len
+            )));
+        }
+
+        let mut temperatures = bytes[header_len..]
+            .chunks_exact(4)
+            .map(|word| f32::from_le_bytes(word.try_into().unwrap()));
+        let current_time = self.clock;
+        for layer in 0..num_layers {
+            let shape = (
+                self.coords.get_layer_num_concentric_circles(layer),
+                self.coords.get_layer_num_radial_lines(layer),
+            );
+            let layer_temperatures = Array2::from_shape_vec(
+                shape,
+                temperatures.by_ref().take(shape.0 * shape.1).collect(),
+            )
+            .expect("The length was checked above");
+            for j in 0..self.coords.get_layer_num_concentric_chunks(layer) {
+                for k in 0..self.coords.get_layer_num_tangential_chunkss(layer) {
+                    let chunk = self.get_chunk_by_chunk_ijk_mut(ChunkIjkVector { i: layer, j, k });
+                    let chunk_coords = chunk.get_chunk_coords();
+                    let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+                    let start_k = chunk_coords.get_start_radial_line();
+                    let num_concentric_circles = chunk_coords.get_num_concentric_circles();
+                    let num_radial_lines = chunk_coords.get_num_radial_lines();
+                    for cj in 0..num_concentric_circles {
+                        for ck in 0..num_radial_lines {
+                            let temperature = layer_temperatures[[start_j + cj, start_k + ck]];
+                            chunk.set_temperature(
+                                JkVector { j: cj, k: ck },
+                                ThermodynamicTemperature(temperature),
+                                current_time,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Where every chunk's random stream is, see [ElementGrid::get_rng_state]
+    /// One after another, layer by layer in `[j, k]` chunk order, including chunks that haven't
+    /// been generated yet, so a replay draws the same numbers without reseeding anything
+    pub fn serialize_rngs(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.get_num_chunks() * RNG_STATE_LEN);
+        for layer in &self.chunks {
+            for chunk in layer.iter() {
+                let chunk = chunk.as_ref().expect("Chunk is borrowed");
+                out.extend(chunk.get_rng_state());
+            }
+        }
+        out
+    }
+
+    /// Puts back the random streams written by [Self::serialize_rngs]
+    /// The directory has to be split into the same chunks. Nothing is changed if it isn't
+    pub fn load_rngs(&mut self, bytes: &[u8]) -> Result<(), RngLayoutError> {
+        let expected_len = self.get_num_chunks() * RNG_STATE_LEN;
+        if bytes.len() != expected_len {
+            return Err(RngLayoutError(bytes.len(), expected_len));
+        }
+        let mut states = bytes
+            .chunks_exact(RNG_STATE_LEN)
+            .map(|state| -> &[u8; RNG_STATE_LEN] { state.try_into().unwrap() });
+        for layer in &mut self.chunks {
+            for chunk in layer.iter_mut() {
+                let chunk = chunk.as_mut().expect("Chunk is borrowed");
+                chunk.set_rng_state(states.next().expect("The length was checked above"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether every ring holds the same element all the way around
+    /// A freshly built planet should be, so this catches a build loop putting cells in the wrong
+    /// place, or a winding mixup, before the simulation gets a chance to stir things up
+    pub fn is_angularly_symmetric(&self) -> bool {
+        (0..self.coords.get_num_layers()).all(|layer| {
+            self.layer_as_array(layer)
+                .rows()
+                .into_iter()
+                .all(|ring| ring.iter().all(|id| *id == ring[0]))
+        })
+    }
+
+    /// Downsamples every chunk in a layer by `factor` and stitches them into one coarse grid
+    /// Meant for far away planets. While merged, [Self::process] leaves the layer's chunks alone and
+    /// runs [CoarseLayer]'s much cheaper settling on the coarse grid once a cycle instead.
+    /// The chunks keep showing what the layer looked like when it was merged until [Self::split_layer].
+    /// The chunk layout is left alone, the convolution neighbor logic depends on it, so splitting back
+    /// always lands in the same chunks with the same neighbors
+    /// Custom elements would come back as something else, so a layer that may hold any is an error
+    pub fn merge_chunks_in_layer(
+        &mut self,
+        layer: usize,
+        factor: usize,
+    ) -> Result<(), CustomElementsInLayerError> {
+        let has_custom = (0..self.coords.get_layer_num_concentric_chunks(layer)).any(|j| {
+            (0..self.coords.get_layer_num_tangential_chunkss(layer)).any(|k| {
+                self.get_chunk_by_chunk_ijk(ChunkIjkVector { i: layer, j, k })
+                    .may_contain_custom()
+            })
+        });
+        if has_custom {
+            return Err(CustomElementsInLayerError(layer));
+        }
+        let mut cells = Grid::new_fill(
+            self.coords.get_layer_num_radial_lines(layer) / factor,
+            self.coords.get_layer_num_concentric_circles(layer) / factor,
+            ElementType::default(),
+        );
+        for j in 0..self.coords.get_layer_num_concentric_chunks(layer) {
+            for k in 0..self.coords.get_layer_num_tangential_chunkss(layer) {
+                let chunk = self.get_chunk_by_chunk_ijk(ChunkIjkVector { i: layer, j, k });
+                let chunk_coords = chunk.get_chunk_coords();
+                let start_j = chunk_coords.get_start_concentric_circle_layer_relative() / factor;
+                let start_k = chunk_coords.get_start_radial_line() / factor;
+                let coarse = chunk.downsample(factor);
+                for cj in 0..coarse.get_height() {
+                    for ck in 0..coarse.get_width() {
+                        cells.set(
+                            JkVector {
+                                j: start_j + cj,
+                                k: start_k + ck,
+                            },
+                            *coarse.get(JkVector { j: cj, k: ck }),
+                        );
+                    }
+                }
+            }
+        }
+        self.merged_layers.insert(
+            layer,
+            CoarseLayer {
+                layer,
+                factor,
+                cells,
+            },
+        );
+        Ok(())
+    }
+
+    /// The coarse grid a layer is being processed as, if it is merged, see [Self::merge_chunks_in_layer]
+    pub fn get_coarse_layer(&self, layer: usize) -> Option<&CoarseLayer> {
+        self.merged_layers.get(&layer)
+    }
+
+    /// Restores the detail of a merged layer from its [CoarseLayer], and processes it normally again
+    /// Does nothing if the layer isn't merged, see [Self::merge_chunks_in_layer]
+    pub fn split_layer(&mut self, layer: usize, current_time: Clock) {
+        let Some(coarse) = self.merged_layers.remove(&layer) else {
+            return;
+        };
+        let factor = coarse.factor;
+        for j in 0..self.coords.get_layer_num_concentric_chunks(layer) {
+            for k in 0..self.coords.get_layer_num_tangential_chunkss(layer) {
+                let chunk = self.get_chunk_by_chunk_ijk_mut(ChunkIjkVector { i: layer, j, k });
+                let chunk_coords = chunk.get_chunk_coords();
+                let start_j = chunk_coords.get_start_concentric_circle_layer_relative() / factor;
+                let start_k = chunk_coords.get_start_radial_line() / factor;
+                let mut chunk_cells = Grid::new_fill(
+                    chunk_coords.get_num_radial_lines() / factor,
+                    chunk_coords.get_num_concentric_circles() / factor,
+                    ElementType::default(),
+                );
+                for cj in 0..chunk_cells.get_height() {
+                    for ck in 0..chunk_cells.get_width() {
+                        chunk_cells.set(
+                            JkVector { j: cj, k: ck },
+                            *coarse.cells.get(JkVector {
+                                j: start_j + cj,
+                                k: start_k + ck,
+                            }),
+                        );
+                    }
+                }
+                chunk.upsample(&chunk_cells, factor, current_time);
+            }
+        }
+        self.recalculate_everything();
+    }
+
+    /// Pours another celestial into this one, for when two of them collide and become one
+    /// `relative_transform` places the other celestial in this one's frame. Every cell of the other
+    /// lands in whichever of our cells its middle falls in. Where the cell is already taken the denser
+    /// of the two keeps it, ties keeping what was here, and the other is pushed out to the first free
+    /// cell above it. Parts of the other nobody has looked at yet have nothing to bring.
+    /// Returns whatever didn't fit, either landing outside of this mesh or finding no room above it
+    pub fn merge_from(
+        &mut self,
+        other: &ElementGridDir,
+        relative_transform: Transform,
+    ) -> Vec<Box<dyn Element>> {
+        let to_self = relative_transform.compute_affine();
+        let mut leftovers = Vec::new();
+        let mut landing = Vec::new();
+        for i in 0..other.coords.get_num_layers() {
+            for j in 0..other.coords.get_layer_num_concentric_circles(i) {
+                for k in 0..other.coords.get_layer_num_radial_lines(i) {
+                    let theirs = IjkVector::new(i, j, k);
+                    let (Some(element), Some(temperature)) = (
+                        other.try_get_element(theirs),
+                        other.try_get_temperature(theirs),
+                    ) else {
+                        continue;
+                    };
+                    if element.get_type() == ElementType::Vacuum {
+                        continue;
+                    }
+                    let midpoint = other.coords.cell_midpoint(theirs).0.extend(0.0);
+                    let pos = RelXyPoint(to_self.transform_point3(midpoint).truncate());
+                    match self.coords.rel_pos_to_cell_idx(pos) {
+                        Ok(cell) => landing.push((cell, element.box_clone(), temperature)),
+                        Err(_) => leftovers.push(element.box_clone()),
+                    }
+                }
+            }
+        }
+
+        let current_time = self.clock;
+        let mut pushed_out = Vec::new();
+        for (cell, element, temperature) in landing {
+            let (chunk_idx, pos) = self.coords.cell_idx_to_chunk_idx(cell);
+            // Getting a chunk mutably generates it, so what lands is compared against what would be there
+            let chunk = self.get_chunk_by_chunk_ijk_mut(chunk_idx);
+            if element.get_density().0 > chunk.get(pos).get_density().0 {
+                let (displaced, displaced_temperature) =
+                    chunk.replace_with_temperature(pos, element, temperature, current_time);
+                if displaced.get_type() != ElementType::Vacuum {
+                    pushed_out.push((cell, displaced, displaced_temperature));
+                }
+            } else {
+                pushed_out.push((cell, element, temperature));
+            }
+        }
+        for (cell, element, temperature) in pushed_out {
+            match self.first_vacuum_above(cell) {
+                Some(free) => {
+                    let (chunk_idx, pos) = self.coords.cell_idx_to_chunk_idx(free);
+                    self.get_chunk_by_chunk_ijk_mut(chunk_idx)
+                        .replace_with_temperature(pos, element, temperature, current_time);
+                }
+                None => leftovers.push(element),
+            }
+        }
+        self.recalculate_everything();
+        leftovers
+    }
+
+    /// The nearest vacuum cell straight up from `cell`, looking over the columns it splits into in the layers above
+    fn first_vacuum_above(&mut self, cell: IjkVector) -> Option<IjkVector> {
+        let num_radial_lines = self.coords.get_layer_num_radial_lines(cell.i);
+        for i in cell.i..self.coords.get_num_layers() {
+            let split = self.coords.get_layer_num_radial_lines(i) / num_radial_lines;
+            let start_j = if i == cell.i { cell.j + 1 } else { 0 };
+            for j in start_j..self.coords.get_layer_num_concentric_circles(i) {
+                for k in cell.k * split..(cell.k + 1) * split {
+                    let above = IjkVector { i, j, k };
+                    let (chunk_idx, pos) = self.coords.cell_idx_to_chunk_idx(above);
+                    if self
+                        .get_chunk_by_chunk_ijk_mut(chunk_idx)
+                        .get(pos)
+                        .get_type()
+                        == ElementType::Vacuum
+                    {
+                        return Some(above);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn get_coordinate_dir(&self) -> &CoordinateDir {
+        &self.coords
+    }
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
     pub fn is_empty(&self) -> bool {
         self.chunks.is_empty()
     }
@@ -837,10 +3265,51 @@ impl ElementGridDir {
         self.get_textures_filtered(&filter)
     }
 
+    /// Every chunk's texture along with the one it had the last time this was called, so a
+    /// renderer can cross-fade between them for motion blur
+    /// Chunks keep their index from frame to frame, which is what ties the two together.
+    /// Only dirty chunks are drawn, the same as [Self::get_dirty_textures] which this takes the place of,
+    /// and the two frames are kept in buffers that swap every call rather than being copied.
+    /// On the first call, the first time a chunk is drawn, or when its size changed since,
+    /// there is nothing to fade from and the previous texture is the current one
+    pub fn get_textures_double_buffered(
+        &mut self,
+    ) -> HashMap<ChunkIjkVector, DoubleBufferedTexture<'_>> {
+        std::mem::swap(&mut self.front_textures, &mut self.back_textures);
+        let dirty = self.get_dirty_textures();
+        let redrawn: HashSet<ChunkIjkVector> = dirty.iter().map(|(coord, _)| *coord).collect();
+        // Everything else looks the same as last call, but the front buffer still holds the call before
+        // for the chunks that changed then
+        for coord in self.redrawn_textures.difference(&redrawn) {
+            if let Some(texture) = self.back_textures.get(coord) {
+                self.front_textures.insert(*coord, texture.clone());
+            }
+        }
+        for (coord, texture) in dirty {
+            let fades = self
+                .back_textures
+                .get(&coord)
+                .is_some_and(|previous| previous.pixels.len() == texture.pixels.len());
+            if !fades {
+                self.back_textures.insert(coord, texture.clone());
+            }
+            self.front_textures.insert(coord, texture);
+        }
+        self.redrawn_textures = redrawn;
+        self.front_textures
+            .iter()
+            .map(|(coord, current)| {
+                let previous = self.back_textures.get(coord).unwrap_or(current);
+                (*coord, DoubleBufferedTexture { current, previous })
+            })
+            .collect()
+    }
+
     /// Where filter is true, get the textures
     fn get_textures_filtered(&self, filter: &[Grid<bool>]) -> HashMap<ChunkIjkVector, Textures> {
         let mut out = HashMap::new();
-        // let (max_temp, min_temp) = self.get_max_min_temp();
+        let temp_range = self.heatmap.as_ref().map(|_| self.calc_max_min_temp());
+        let pressure = self.pressure.as_ref();
         for (i, item) in filter.iter().enumerate() {
             let j_size = self.coords.get_layer_num_concentric_chunks(i);
             let k_size = self.coords.get_layer_num_tangential_chunkss(i);
@@ -850,7 +3319,11 @@ impl ElementGridDir {
                         continue;
                     }
                     let coord = ChunkIjkVector { i, j, k };
-                    let tex = self.get_chunk_by_chunk_ijk(coord).get_texture();
+                    let chunk = self.get_chunk_by_chunk_ijk(coord);
+                    if !chunk.is_generated() {
+                        continue;
+                    }
+                    let tex = self.draw_chunk(chunk, temp_range, pressure);
                     out.insert(coord, Textures { texture: Some(tex) });
                 }
             }
@@ -867,17 +3340,21 @@ mod tests {
 
     use super::*;
 
-    /// The default element grid directory for testing
-    fn get_element_grid_dir() -> ElementGridDir {
-        let coordinate_dir = CoordinateDirBuilder::new()
+    /// The coordinates the tests are built on, with `num_layers` layers
+    fn get_coordinate_dir(num_layers: usize) -> CoordinateDir {
+        CoordinateDirBuilder::new()
             .cell_radius(Length(1.0))
-            .num_layers(9)
+            .num_layers(num_layers)
             .first_num_radial_lines(6)
             .second_num_concentric_circles(3)
             .max_concentric_circles_per_chunk(64)
             .max_radial_lines_per_chunk(64)
-            .build();
-        ElementGridDir::new_empty(coordinate_dir)
+            .build()
+    }
+
+    /// The default element grid directory for testing
+    fn get_element_grid_dir() -> ElementGridDir {
+        ElementGridDir::new_empty(get_coordinate_dir(9))
     }
 
     mod neighbors {
@@ -1036,6 +3513,12 @@ mod tests {
 
     mod get_next_targets {
         use super::*;
+        use crate::physics::fallingsand::elements::sand::Sand;
+        use bevy::utils::tracing::{span, subscriber, Event, Metadata, Subscriber};
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
 
         /// Going to verify the chunk grid sizes before we start testing, and so we can know if they change
         #[test]
@@ -1080,6 +3563,33 @@ mod tests {
             assert_eq!(element_grid_dir.chunks[8].get_width(), 96);
         }
 
+        #[test]
+        fn test_try_get_chunk_while_borrowed() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coord = ChunkIjkVector { i: 1, j: 0, k: 0 };
+            let neighbor = ChunkIjkVector { i: 2, j: 0, k: 0 };
+            assert!(element_grid_dir.try_get_chunk(neighbor).is_ok());
+
+            // Packaging a convolution takes all of the neighbors out of the directory
+            let conv = element_grid_dir
+                .package_coordinate_neighbors(coord)
+                .unwrap();
+            let err = element_grid_dir
+                .try_get_chunk(neighbor)
+                .err()
+                .expect("The neighbor is in the convolution");
+            assert_eq!(err.0, neighbor);
+            // The target itself is not part of its own convolution
+            assert!(element_grid_dir.try_get_chunk(coord).is_ok());
+
+            // Put them back and it can be read again
+            let target = element_grid_dir.chunks[coord.i]
+                .replace(coord.to_jk_vector(), None)
+                .unwrap();
+            element_grid_dir.unpackage_convolution(target, conv);
+            assert!(element_grid_dir.try_get_chunk(neighbor).is_ok());
+        }
+
         fn get_next_targets(this: &mut ElementGridDir) -> HashSet<ChunkIjkVector> {
             let out1 = this.process_targets.standard_convolution[this.process_count % 9].clone();
             let out2 =
@@ -1143,7 +3653,7 @@ mod tests {
             let process_targets = element_grid_dir.get_process_targets();
             for frame_nb in 0..9 {
                 let res = element_grid_dir
-                    .package_convolutions(process_targets.standard_convolution[frame_nb].0.clone());
+                    .package_convolutions(&process_targets.standard_convolution[frame_nb].0);
                 match res {
                     Ok((convolutions, target_chunks)) => {
                         assert_eq!(convolutions.len(), target_chunks.len());
@@ -1164,11 +3674,8 @@ mod tests {
             let mut element_grid_dir = get_element_grid_dir();
             let process_targets = element_grid_dir.get_process_targets();
             for frame_nb in 0..9 {
-                let res = element_grid_dir.package_convolutions(
-                    process_targets.has_multi_bottom_neighbor[frame_nb]
-                        .0
-                        .clone(),
-                );
+                let res = element_grid_dir
+                    .package_convolutions(&process_targets.has_multi_bottom_neighbor[frame_nb].0);
                 match res {
                     Ok((convolutions, target_chunks)) => {
                         assert_eq!(convolutions.len(), target_chunks.len());
@@ -1184,41 +3691,320 @@ mod tests {
             }
         }
 
-        /// Not really necessary since this is done in sequence but good to have a test anyway
+        /// The scratch buffers should be reused between passes, and give back the same convolutions
         #[test]
-        fn test_has_single_bottom_neighbor_packaging() {
+        fn test_package_convolutions_reuses_scratch() {
             let mut element_grid_dir = get_element_grid_dir();
             let process_targets = element_grid_dir.get_process_targets();
-            for frame_nb in 0..9 {
-                for chunk_coord in process_targets.has_single_bottom_neighbor[frame_nb]
-                    .0
-                    .iter()
-                {
-                    let conv = element_grid_dir
-                        .package_coordinate_neighbors(*chunk_coord)
-                        .unwrap();
-                    let chunk = element_grid_dir.chunks[chunk_coord.i]
-                        .replace(chunk_coord.to_jk_vector(), None)
-                        .unwrap();
-                    element_grid_dir.unpackage_convolution(chunk, conv);
-                }
+            let targets = &process_targets.standard_convolution[0].0;
+
+            let (convolutions, target_chunks) =
+                element_grid_dir.package_convolutions(targets).unwrap();
+            let first_ptrs = (convolutions.as_ptr(), target_chunks.as_ptr());
+            let first_idxs: HashSet<ChunkIjkVector> = target_chunks
+                .iter()
+                .map(|chunk| chunk.get_chunk_coords().get_chunk_idx())
+                .collect();
+            element_grid_dir.unpackage_convolutions(convolutions, target_chunks);
+            element_grid_dir.unlock_all_chunks();
+
+            let (convolutions, target_chunks) =
+                element_grid_dir.package_convolutions(targets).unwrap();
+            let second_idxs: HashSet<ChunkIjkVector> = target_chunks
+                .iter()
+                .map(|chunk| chunk.get_chunk_coords().get_chunk_idx())
+                .collect();
+            assert_eq!(first_ptrs, (convolutions.as_ptr(), target_chunks.as_ptr()));
+            assert_eq!(&first_idxs, targets);
+            assert_eq!(first_idxs, second_idxs);
+            element_grid_dir.unpackage_convolutions(convolutions, target_chunks);
+        }
+
+        /// Counts every log event sent to it, from the thread it is installed on
+        struct CountEvents(Arc<AtomicUsize>);
+
+        impl Subscriber for CountEvents {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
             }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, _: &Event<'_>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
         }
 
-        #[test]
-        #[warn(unused_variables)]
-        fn test_get_next_targets_manual_j_dim() {
+        /// How many lines a full cycle of processing logs with the given diagnostics
+        /// Packaging and the per pass lines both happen on the calling thread
+        fn count_logged(diagnostics: ProcessDiagnostics) -> usize {
             let mut element_grid_dir = get_element_grid_dir();
-            let all_targets_1 = get_next_targets(&mut element_grid_dir);
+            element_grid_dir.set_diagnostics(diagnostics);
+            let count = Arc::new(AtomicUsize::new(0));
+            subscriber::with_default(CountEvents(count.clone()), || {
+                element_grid_dir.process_full(Clock::default());
+            });
+            count.load(Ordering::Relaxed)
+        }
 
-            // For every j step by 3 we should
-            assert!(all_targets_1.contains(&ChunkIjkVector { i: 0, j: 0, k: 0 }));
-            assert!(all_targets_1.contains(&ChunkIjkVector { i: 3, j: 0, k: 0 }));
-            assert!(all_targets_1.contains(&ChunkIjkVector { i: 6, j: 3, k: 0 }));
-            // assert!(all_targets_1.contains(&ChunkIjkVector { i: 7, j: 0, k: 0 }));
-            assert!(all_targets_1.contains(&ChunkIjkVector { i: 7, j: 3, k: 0 }));
-            // assert!(all_targets_1.contains(&ChunkIjkVector { i: 8, j: 0, k: 0 }));
-            assert!(all_targets_1.contains(&ChunkIjkVector { i: 8, j: 3, k: 0 }));
+        /// Nothing should be logged per chunk unless asked for
+        #[test]
+        fn test_diagnostics_default_off() {
+            let element_grid_dir = get_element_grid_dir();
+            assert_eq!(element_grid_dir.get_diagnostics(), ProcessDiagnostics::Off);
+            assert!(element_grid_dir.get_diagnostics() < ProcessDiagnostics::PerPass);
+            assert!(ProcessDiagnostics::PerPass < ProcessDiagnostics::PerChunk);
+
+            assert_eq!(count_logged(ProcessDiagnostics::Off), 0);
+            let per_pass = count_logged(ProcessDiagnostics::PerPass);
+            assert_eq!(per_pass, FRAMES_PER_FULL_PROCESS);
+            assert!(count_logged(ProcessDiagnostics::PerChunk) > per_pass);
+        }
+
+        fn assert_geometric_neighbors(cell: IjkVector, expected: &[IjkVector]) {
+            let element_grid_dir = get_element_grid_dir();
+            let neighbors = element_grid_dir
+                .get_coordinate_dir()
+                .geometric_cell_neighbors(cell);
+            assert_eq!(neighbors.len(), expected.len(), "{:?}", neighbors);
+            for neighbor in expected {
+                assert!(
+                    neighbors.contains(neighbor),
+                    "{:?} missing from {:?}",
+                    neighbor,
+                    neighbors
+                );
+            }
+        }
+
+        #[test]
+        fn test_geometric_cell_neighbors_interior() {
+            assert_geometric_neighbors(
+                IjkVector::new(3, 5, 10),
+                &[
+                    IjkVector::new(3, 4, 10),
+                    IjkVector::new(3, 6, 10),
+                    IjkVector::new(3, 5, 9),
+                    IjkVector::new(3, 5, 11),
+                ],
+            );
+        }
+
+        #[test]
+        fn test_geometric_cell_neighbors_wraps_k() {
+            assert_geometric_neighbors(
+                IjkVector::new(3, 5, 0),
+                &[
+                    IjkVector::new(3, 4, 0),
+                    IjkVector::new(3, 6, 0),
+                    IjkVector::new(3, 5, 47),
+                    IjkVector::new(3, 5, 1),
+                ],
+            );
+        }
+
+        /// The top of layer 2 has twice as many cells above it in layer 3
+        #[test]
+        fn test_geometric_cell_neighbors_layer_transition() {
+            assert_geometric_neighbors(
+                IjkVector::new(2, 5, 7),
+                &[
+                    IjkVector::new(2, 4, 7),
+                    IjkVector::new(2, 5, 6),
+                    IjkVector::new(2, 5, 8),
+                    IjkVector::new(3, 0, 14),
+                    IjkVector::new(3, 0, 15),
+                ],
+            );
+            assert_geometric_neighbors(
+                IjkVector::new(3, 0, 15),
+                &[
+                    IjkVector::new(2, 5, 7),
+                    IjkVector::new(3, 1, 15),
+                    IjkVector::new(3, 0, 14),
+                    IjkVector::new(3, 0, 16),
+                ],
+            );
+        }
+
+        /// Every cell's geometric neighbors live in its own chunk or one of its chunk's neighbors
+        #[test]
+        fn test_geometric_cell_neighbors_within_chunk_neighbors() {
+            let element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir();
+            for i in 0..6 {
+                for j in 0..coords.get_layer_num_concentric_circles(i) {
+                    for k in 0..coords.get_layer_num_radial_lines(i) {
+                        let cell = IjkVector::new(i, j, k);
+                        let chunk = coords.cell_idx_to_chunk_idx(cell).0;
+                        let chunk_neighbors = element_grid_dir.get_chunk_neighbors(chunk);
+                        for neighbor in coords.geometric_cell_neighbors(cell) {
+                            let neighbor_chunk = coords.cell_idx_to_chunk_idx(neighbor).0;
+                            assert!(
+                                neighbor_chunk == chunk
+                                    || chunk_neighbors.contains(&neighbor_chunk),
+                                "{:?} neighbors {:?} but chunk {:?} is not a neighbor of {:?}",
+                                cell,
+                                neighbor,
+                                neighbor_chunk,
+                                chunk
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn test_neighbor_symmetry() {
+            get_element_grid_dir().assert_neighbor_symmetry();
+        }
+
+        /// Merged passes never share a chunk between convolutions, and still process everything
+        #[test]
+        fn test_cross_layer_parallelism() {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.set_cross_layer_parallelism(true);
+            assert!(element_grid_dir.get_cross_layer_parallelism());
+            assert_eq!(
+                element_grid_dir.merged_parallel.len(),
+                FRAMES_PER_FULL_PROCESS
+            );
+            assert!(
+                element_grid_dir.merged_parallel.iter().any(Option::is_some),
+                "No pass was merged, so this tests nothing"
+            );
+            for (idx, merged) in element_grid_dir.merged_parallel.iter().enumerate() {
+                let Some(merged) = merged else {
+                    continue;
+                };
+                let targets = &element_grid_dir.process_targets;
+                let expected: HashSet<ChunkIjkVector> = targets.standard_convolution[idx]
+                    .0
+                    .union(&targets.has_multi_bottom_neighbor[idx].0)
+                    .copied()
+                    .collect();
+                assert_eq!(merged.0, expected, "Pass {}", idx);
+                let mut footprint = HashSet::new();
+                for &coord in &merged.0 {
+                    for chunk in element_grid_dir.convolution_footprint(coord) {
+                        assert!(footprint.insert(chunk), "{:?} is shared", chunk);
+                    }
+                }
+                for &coord in &targets.has_single_bottom_neighbor[idx].0 {
+                    for chunk in element_grid_dir.convolution_footprint(coord) {
+                        let in_multi =
+                            targets.has_multi_bottom_neighbor[idx]
+                                .0
+                                .iter()
+                                .any(|&multi| {
+                                    element_grid_dir
+                                        .convolution_footprint(multi)
+                                        .contains(&chunk)
+                                });
+                        assert!(!in_multi, "{:?} is sequential and merged", chunk);
+                    }
+                }
+            }
+            // Process checks that every chunk was processed after nine passes
+            element_grid_dir.process_full(Clock::default());
+            element_grid_dir.set_cross_layer_parallelism(false);
+            assert!(!element_grid_dir.get_cross_layer_parallelism());
+        }
+
+        /// Merging only changes which passes run together, not what they do
+        #[test]
+        fn test_cross_layer_parallelism_gives_the_same_result() {
+            let run = |cross_layer: bool| {
+                let mut element_grid_dir = get_element_grid_dir();
+                element_grid_dir.set_cross_layer_parallelism(cross_layer);
+                let radius = element_grid_dir.get_coordinate_dir().get_radius().0;
+                element_grid_dir.fill_settled(&Sand::default(), radius);
+                let mut clock = Clock::default();
+                for _ in 0..3 {
+                    clock.update(Duration::from_millis(100));
+                    element_grid_dir.process_full(clock);
+                }
+                element_grid_dir.state_hash()
+            };
+            assert_eq!(run(true), run(false));
+        }
+
+        /// Every chunk gets a pass, and no two neighbors are processed in parallel in the same pass
+        /// Neighbors may share a pass only if they are processed sequentially
+        #[test]
+        fn test_pass_of_chunk() {
+            let element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir();
+            let targets = &element_grid_dir.process_targets;
+            for i in 0..coords.get_num_layers() {
+                for j in 0..coords.get_layer_num_concentric_chunks(i) {
+                    for k in 0..coords.get_layer_num_tangential_chunkss(i) {
+                        let coord = ChunkIjkVector { i, j, k };
+                        let pass = element_grid_dir.pass_of_chunk(coord).unwrap();
+                        assert!(pass < 9);
+                        for neighbor in element_grid_dir.get_chunk_neighbors(coord).iter() {
+                            if element_grid_dir.pass_of_chunk(neighbor) != Some(pass) {
+                                continue;
+                            }
+                            for parallel_set in [
+                                &targets.standard_convolution[pass].0,
+                                &targets.has_multi_bottom_neighbor[pass].0,
+                            ] {
+                                assert!(
+                                    !(parallel_set.contains(&coord)
+                                        && parallel_set.contains(&neighbor)),
+                                    "{:?} and {:?} are neighbors processed in parallel in pass {}",
+                                    coord,
+                                    neighbor,
+                                    pass
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Not really necessary since this is done in sequence but good to have a test anyway
+        #[test]
+        fn test_has_single_bottom_neighbor_packaging() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let process_targets = element_grid_dir.get_process_targets();
+            for frame_nb in 0..9 {
+                for chunk_coord in process_targets.has_single_bottom_neighbor[frame_nb]
+                    .0
+                    .iter()
+                {
+                    let conv = element_grid_dir
+                        .package_coordinate_neighbors(*chunk_coord)
+                        .unwrap();
+                    let chunk = element_grid_dir.chunks[chunk_coord.i]
+                        .replace(chunk_coord.to_jk_vector(), None)
+                        .unwrap();
+                    element_grid_dir.unpackage_convolution(chunk, conv);
+                }
+            }
+        }
+
+        #[test]
+        #[warn(unused_variables)]
+        fn test_get_next_targets_manual_j_dim() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let all_targets_1 = get_next_targets(&mut element_grid_dir);
+
+            // For every j step by 3 we should
+            assert!(all_targets_1.contains(&ChunkIjkVector { i: 0, j: 0, k: 0 }));
+            assert!(all_targets_1.contains(&ChunkIjkVector { i: 3, j: 0, k: 0 }));
+            assert!(all_targets_1.contains(&ChunkIjkVector { i: 6, j: 3, k: 0 }));
+            // assert!(all_targets_1.contains(&ChunkIjkVector { i: 7, j: 0, k: 0 }));
+            assert!(all_targets_1.contains(&ChunkIjkVector { i: 7, j: 3, k: 0 }));
+            // assert!(all_targets_1.contains(&ChunkIjkVector { i: 8, j: 0, k: 0 }));
+            assert!(all_targets_1.contains(&ChunkIjkVector { i: 8, j: 3, k: 0 }));
             assert!(all_targets_1.contains(&ChunkIjkVector { i: 8, j: 6, k: 0 }));
             assert!(all_targets_1.contains(&ChunkIjkVector { i: 8, j: 9, k: 0 }));
 
@@ -1276,4 +4062,2649 @@ mod tests {
             assert!(all_targets_3.contains(&ChunkIjkVector { i: 6, j: 3, k: 5 }));
         }
     }
+
+    mod gravity {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{stone::Stone, vacuum::Vacuum};
+
+        #[test]
+        fn test_surface_gravity() {
+            let coordinate_dir = get_element_grid_dir().get_coordinate_dir().clone();
+            let stone = Stone::default();
+            let mut element_grid_dir =
+                ElementGridDir::new_checkerboard(coordinate_dir, &stone, &stone);
+            let mut clock = Clock::default();
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+
+            // Every cell is stone, with a density of 1 per square cell width
+            let coords = element_grid_dir.get_coordinate_dir();
+            let mut mass = 0.0;
+            for i in 0..coords.get_num_layers() {
+                for j in 0..coords.get_layer_num_concentric_chunks(i) {
+                    for k in 0..coords.get_layer_num_tangential_chunkss(i) {
+                        let chunk = coords.get_chunk_at_idx(ChunkIjkVector { i, j, k });
+                        mass += chunk.total_size() as f32 * chunk.get_cell_width().area().0;
+                    }
+                }
+            }
+            let radius = coords.get_radius().0;
+            let expected = G * mass / (radius * radius);
+            let actual = element_grid_dir.surface_gravity().0;
+            assert!(
+                (actual - expected).abs() <= expected * 1e-4,
+                "expected {} got {}",
+                expected,
+                actual
+            );
+        }
+
+        #[test]
+        fn test_shell_gravity() {
+            let coordinate_dir = get_element_grid_dir().get_coordinate_dir().clone();
+            let stone = Stone::default();
+            let mut element_grid_dir =
+                ElementGridDir::new_checkerboard(coordinate_dir, &stone, &stone);
+            let mut clock = Clock::default();
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            let radius = element_grid_dir.get_coordinate_dir().get_radius().0;
+
+            element_grid_dir.set_gravity_model(GravityModel::Shell);
+            let center = element_grid_dir.gravity_at_radius(0.0).0;
+            let near_center = element_grid_dir.gravity_at_radius(0.1).0;
+            let halfway = element_grid_dir.gravity_at_radius(radius / 2.0).0;
+            let surface = element_grid_dir.gravity_at_radius(radius).0;
+            let outside = element_grid_dir.gravity_at_radius(radius * 2.0).0;
+            assert_eq!(center, 0.0);
+            assert_eq!(near_center, 0.0);
+            assert!(halfway > near_center);
+            assert!(surface > near_center);
+            assert!(outside < surface);
+            // Outside the planet the two models agree
+            assert!((surface - element_grid_dir.surface_gravity().0).abs() <= surface * 1e-4);
+
+            // Whereas a point mass pulls hardest right by the center
+            element_grid_dir.set_gravity_model(GravityModel::PointMass);
+            assert!(element_grid_dir.gravity_at_radius(0.1).0 > surface);
+        }
+
+        #[test]
+        fn test_enclosed_mass() {
+            let coordinate_dir = get_element_grid_dir().get_coordinate_dir().clone();
+            let mut element_grid_dir = ElementGridDir::new_checkerboard(
+                coordinate_dir,
+                &Stone::default(),
+                &Vacuum::default(),
+            );
+            let mut clock = Clock::default();
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            let radius = element_grid_dir.get_coordinate_dir().get_radius().0;
+
+            let total = element_grid_dir.get_total_mass().0;
+            let enclosed = element_grid_dir.enclosed_mass(radius).0;
+            assert!(
+                (enclosed - total).abs() <= total * 1e-4,
+                "{} != {}",
+                enclosed,
+                total
+            );
+            assert_eq!(element_grid_dir.enclosed_mass(0.0).0, 0.0);
+
+            let mut last = 0.0;
+            for step in 0..=100 {
+                let mass = element_grid_dir
+                    .enclosed_mass(radius * step as f32 / 100.0)
+                    .0;
+                assert!(mass >= last, "Enclosed mass decreased at step {}", step);
+                last = mass;
+            }
+        }
+
+        /// Setting cells between recalculations shouldn't leave the profile behind
+        #[test]
+        fn test_enclosed_mass_follows_set_element() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let radius = coords.get_radius().0;
+            let cell = IjkVector::new(4, 2, 5);
+            let cell_width = coords.get_layer_cell_width(cell.i);
+            let stone = Stone::default().get_mass(cell_width).0;
+            let mid_radius = coords.cell_midpoint(cell).0.length();
+
+            element_grid_dir.set_element(cell, Box::<Stone>::default(), Clock::default());
+            assert_eq!(element_grid_dir.enclosed_mass(mid_radius - 0.01).0, 0.0);
+            assert_eq!(element_grid_dir.enclosed_mass(radius).0, stone);
+            // The same as working it out from scratch
+            let incremental = element_grid_dir.radial_profile.clone();
+            element_grid_dir.recalculate_everything();
+            assert_eq!(
+                element_grid_dir.enclosed_mass(radius).0,
+                incremental.last().unwrap().1 .0
+            );
+
+            element_grid_dir.set_element(cell, Box::<Vacuum>::default(), Clock::default());
+            assert_eq!(element_grid_dir.enclosed_mass(radius).0, 0.0);
+        }
+    }
+
+    mod layer_as_array {
+        use super::*;
+        use crate::physics::fallingsand::elements::{element::ElementType, sand::Sand};
+
+        #[test]
+        fn test_painted_band_is_a_row() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let layer = 5;
+            let band_j = 2;
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            for k in 0..coords.get_layer_num_radial_lines(layer) {
+                element_grid_dir.set_element(
+                    IjkVector::new(layer, band_j, k),
+                    Box::<Sand>::default(),
+                    Clock::default(),
+                );
+            }
+
+            let array = element_grid_dir.layer_as_array(layer);
+            assert_eq!(
+                array.dim(),
+                (
+                    coords.get_layer_num_concentric_circles(layer),
+                    coords.get_layer_num_radial_lines(layer)
+                )
+            );
+            for ((j, _), id) in array.indexed_iter() {
+                if j == band_j {
+                    assert_eq!(*id, ElementType::Sand.id());
+                } else {
+                    assert_eq!(*id, ElementType::Vacuum.id());
+                }
+            }
+        }
+    }
+
+    mod fill_settled {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{element::ElementType, sand::Sand};
+
+        #[test]
+        fn test_fill_settled() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let radius = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_end_radius(5)
+                + 2.0;
+            element_grid_dir.fill_settled(&Sand::default(), radius);
+
+            // Exactly the cells within the radius are filled
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let mut filled_mass = Mass(0.0);
+            for i in 0..coords.get_num_layers() {
+                let start_r = coords.get_layer_start_radius(i);
+                let separation = (coords.get_layer_end_radius(i) - start_r)
+                    / coords.get_layer_num_concentric_circles(i) as f32;
+                for j in 0..coords.get_layer_num_concentric_circles(i) {
+                    let mid_radius = start_r + (j as f32 + 0.5) * separation;
+                    for k in 0..coords.get_layer_num_radial_lines(i) {
+                        let element_type = element_grid_dir
+                            .get_element(IjkVector::new(i, j, k))
+                            .get_type();
+                        if mid_radius <= radius {
+                            assert_eq!(element_type, ElementType::Sand);
+                            filled_mass += Sand::default().get_mass(coords.get_layer_cell_width(i));
+                        } else {
+                            assert_eq!(element_type, ElementType::Vacuum);
+                        }
+                    }
+                }
+            }
+
+            // The totals don't wait for the end of a cycle to count it
+            let total_mass = element_grid_dir.get_total_mass().0;
+            assert!(
+                (total_mass - filled_mass.0).abs() <= filled_mass.0 * 1e-5,
+                "{} != {}",
+                total_mass,
+                filled_mass.0
+            );
+            assert_eq!(
+                element_grid_dir.enclosed_mass(coords.get_radius().0).0,
+                total_mass
+            );
+
+            // And nothing moves
+            let before: Vec<_> = (0..coords.get_num_layers())
+                .map(|i| element_grid_dir.layer_as_array(i))
+                .collect();
+            let mut clock = Clock::default();
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            for (i, layer) in before.iter().enumerate() {
+                assert_eq!(
+                    *layer,
+                    element_grid_dir.layer_as_array(i),
+                    "Layer {} moved",
+                    i
+                );
+            }
+        }
+    }
+
+    mod read_only_convolution {
+        use super::*;
+        use crate::physics::fallingsand::elements::{element::ElementType, sand::Sand};
+
+        /// Reading past the edge of a chunk through the view gives the same element
+        /// as reading the neighboring chunk directly
+        #[test]
+        fn test_edge_neighbor_matches_direct_access() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            let coord = ChunkIjkVector { i: 3, j: 0, k: 0 };
+            let width = element_grid_dir
+                .get_chunk_by_chunk_ijk(coord)
+                .get_chunk_coords()
+                .get_num_radial_lines();
+            let edge = IjkVector::new(3, 0, width - 1);
+            let across = IjkVector::new(3, 0, width);
+            element_grid_dir.set_element(across, Box::<Sand>::default(), clock);
+
+            let view = element_grid_dir.read_only_convolution(coord).unwrap();
+            let (edge_chunk, _) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(edge);
+            let (across_chunk, across_pos) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(across);
+            assert_eq!(edge_chunk, coord);
+            assert_ne!(across_chunk, coord);
+
+            let direct = element_grid_dir
+                .get_chunk_by_chunk_ijk(across_chunk)
+                .get(across_pos)
+                .get_type();
+            assert_eq!(direct, ElementType::Sand);
+            assert_eq!(view.get(across).unwrap().get_type(), direct);
+            assert_eq!(view.get(edge).unwrap().get_type(), ElementType::Vacuum);
+
+            // Cells outside the convolution aren't visible
+            assert!(view.get(IjkVector::new(8, 0, 0)).is_none());
+        }
+    }
+
+    mod memory {
+        use super::*;
+        use crate::physics::fallingsand::elements::vacuum::Vacuum;
+
+        #[test]
+        fn test_total_memory_bytes() {
+            let element_grid_dir = get_element_grid_dir();
+            let num_cells = element_grid_dir.get_total_num_cells();
+            let bytes = element_grid_dir.total_memory_bytes().unwrap();
+            // Every cell is a boxed vacuum, two temperatures for conduction to swap between, a pressure
+            // and a pixel, plus some overhead per chunk
+            let per_cell = std::mem::size_of::<Box<dyn Element>>()
+                + std::mem::size_of::<Vacuum>()
+                + 3 * std::mem::size_of::<f32>()
+                + 4;
+            let overhead = element_grid_dir.get_num_chunks() * std::mem::size_of::<ElementGrid>()
+                + std::mem::size_of::<ElementGridDir>();
+            assert!(bytes >= num_cells * per_cell);
+            assert!(bytes <= num_cells * per_cell + overhead);
+
+            // A bigger planet takes proportionally more memory
+            let bigger = ElementGridDir::new_empty(get_coordinate_dir(10));
+            let bigger_num_cells = bigger.get_total_num_cells();
+            let bigger_bytes = bigger.total_memory_bytes().unwrap();
+            assert!(bigger_num_cells > num_cells);
+            let ratio = bigger_bytes as f32 / bytes as f32;
+            let cell_ratio = bigger_num_cells as f32 / num_cells as f32;
+            assert!(
+                (ratio - cell_ratio).abs() / cell_ratio < 0.1,
+                "Memory grew by {} but cells grew by {}",
+                ratio,
+                cell_ratio
+            );
+        }
+
+        #[test]
+        fn test_total_memory_bytes_while_borrowed() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coord = ChunkIjkVector { i: 1, j: 0, k: 0 };
+            let conv = element_grid_dir
+                .package_coordinate_neighbors(coord)
+                .unwrap();
+            let err = element_grid_dir
+                .total_memory_bytes()
+                .expect_err("The neighbors are in the convolution");
+            assert!(element_grid_dir
+                .get_chunk_neighbors(coord)
+                .iter()
+                .any(|neighbor| neighbor == err.0));
+
+            let target = element_grid_dir.chunks[coord.i]
+                .replace(coord.to_jk_vector(), None)
+                .unwrap();
+            element_grid_dir.unpackage_convolution(target, conv);
+            assert!(element_grid_dir.total_memory_bytes().is_ok());
+        }
+    }
+
+    mod process_chunks {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{element::ElementType, sand::Sand};
+
+        fn count_sand(element_grid_dir: &ElementGridDir, layer: usize) -> usize {
+            element_grid_dir
+                .layer_as_array(layer)
+                .iter()
+                .filter(|&&id| id == ElementType::Sand.id())
+                .count()
+        }
+
+        #[test]
+        fn test_process_chunks_leaves_others_alone() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            // Two neighboring chunks, each with sand about to fall into the layer below
+            let first = IjkVector::new(3, 0, 5);
+            let second = IjkVector::new(3, 0, 20);
+            // And some sand in midair far away
+            let distant = IjkVector::new(6, 10, 0);
+            for cell in [first, second, distant] {
+                element_grid_dir.set_element(cell, Box::<Sand>::default(), clock);
+            }
+            let coords = element_grid_dir.get_coordinate_dir();
+            let chunks = HashSet::from([
+                coords.cell_idx_to_chunk_idx(first).0,
+                coords.cell_idx_to_chunk_idx(second).0,
+            ]);
+            assert_eq!(chunks.len(), 2);
+
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_chunks(clock, &chunks);
+
+            // Both fell into the neighboring chunks below them
+            assert_eq!(count_sand(&element_grid_dir, 3), 0);
+            assert_eq!(count_sand(&element_grid_dir, 2), 2);
+            // The distant one was never processed
+            assert_eq!(
+                element_grid_dir.get_element(distant).get_type(),
+                ElementType::Sand
+            );
+            // And a regular full process still works afterwards
+            element_grid_dir.process_full(clock);
+        }
+    }
+
+    mod coarsen {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, stone::Stone};
+
+        fn count(
+            element_grid_dir: &ElementGridDir,
+            layer: usize,
+            element_type: ElementType,
+        ) -> usize {
+            element_grid_dir
+                .layer_as_array(layer)
+                .iter()
+                .filter(|&&id| id == element_type.id())
+                .count()
+        }
+
+        #[test]
+        fn test_merge_then_split_preserves_distribution() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            let layer = 5;
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let num_concentric_circles = coords.get_layer_num_concentric_circles(layer);
+            let num_radial_lines = coords.get_layer_num_radial_lines(layer);
+            // A stone floor with sand on one side and a scattering of lone sand grains on the other
+            for j in 0..num_concentric_circles {
+                for k in 0..num_radial_lines {
+                    let element: Box<dyn Element> = if j < num_concentric_circles / 4 {
+                        Box::<Stone>::default()
+                    } else if k < num_radial_lines / 2 || (j * 7 + k * 3) % 11 == 0 {
+                        Box::<Sand>::default()
+                    } else {
+                        continue;
+                    };
+                    element_grid_dir.set_element(IjkVector::new(layer, j, k), element, clock);
+                }
+            }
+            let before = [ElementType::Sand, ElementType::Stone, ElementType::Vacuum]
+                .map(|t| count(&element_grid_dir, layer, t));
+
+            element_grid_dir.merge_chunks_in_layer(layer, 2).unwrap();
+            let coarse = element_grid_dir.get_coarse_layer(layer).unwrap();
+            assert_eq!(coarse.get_cells().get_width(), num_radial_lines / 2);
+            assert_eq!(coarse.get_cells().get_height(), num_concentric_circles / 2);
+            element_grid_dir.split_layer(layer, clock);
+            assert!(element_grid_dir.get_coarse_layer(layer).is_none());
+
+            let after = [ElementType::Sand, ElementType::Stone, ElementType::Vacuum]
+                .map(|t| count(&element_grid_dir, layer, t));
+            let total = (num_concentric_circles * num_radial_lines) as f32;
+            for (b, a) in before.iter().zip(after.iter()) {
+                assert!(
+                    (*b as f32 - *a as f32).abs() / total < 0.05,
+                    "Before {:?}, after {:?}",
+                    before,
+                    after
+                );
+            }
+            // The bulk regions come back exactly
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(layer, 0, 0))
+                    .get_type(),
+                ElementType::Stone
+            );
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(layer, num_concentric_circles - 1, 0))
+                    .get_type(),
+                ElementType::Sand
+            );
+        }
+
+        /// A merged layer is simulated coarsely, its chunks aren't touched until it is split again
+        #[test]
+        fn test_merged_layer_processes_coarsely() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            let layer = 5;
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let top = coords.get_layer_num_concentric_circles(layer) - 1;
+            // A 2x2 block of sand at the top of the layer, which is one coarse cell
+            for j in top - 1..=top {
+                for k in 0..2 {
+                    element_grid_dir.set_element(
+                        IjkVector::new(layer, j, k),
+                        Box::<Sand>::default(),
+                        clock,
+                    );
+                }
+            }
+            element_grid_dir.merge_chunks_in_layer(layer, 2).unwrap();
+            let coarse_top = top / 2;
+            for _ in 0..3 {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+            }
+
+            // The detail stayed where it was, the coarse sand fell a coarse cell each cycle
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(layer, top, 0))
+                    .get_type(),
+                ElementType::Sand
+            );
+            let cells = element_grid_dir
+                .get_coarse_layer(layer)
+                .unwrap()
+                .get_cells();
+            assert_eq!(
+                *cells.get(JkVector::new(coarse_top, 0)),
+                ElementType::Vacuum
+            );
+            assert_eq!(
+                *cells.get(JkVector::new(coarse_top - 3, 0)),
+                ElementType::Sand
+            );
+
+            // And it comes back where the coarse simulation left it
+            element_grid_dir.split_layer(layer, clock);
+            let landed = 2 * (coarse_top - 3);
+            for j in landed..landed + 2 {
+                for k in 0..2 {
+                    assert_eq!(
+                        element_grid_dir
+                            .get_element(IjkVector::new(layer, j, k))
+                            .get_type(),
+                        ElementType::Sand
+                    );
+                }
+            }
+            assert_eq!(count(&element_grid_dir, layer, ElementType::Sand), 4);
+        }
+    }
+
+    mod heatmap {
+        use super::*;
+        use crate::physics::fallingsand::elements::{lava::Lava, sand::Sand};
+
+        /// The rgba of a cell in a chunk texture
+        fn pixel(texture: &RawImage, chunk: &ElementGrid, pos: JkVector) -> [u8; 4] {
+            let idx = (pos.j * chunk.get_chunk_coords().get_num_radial_lines() + pos.k) * 4;
+            texture.pixels[idx..idx + 4].try_into().unwrap()
+        }
+
+        #[test]
+        fn test_hottest_and_coldest_cells_map_to_the_ends_of_the_gradient() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            let hot = IjkVector::new(3, 1, 5);
+            // Same chunk, but out of reach of the light of the lava
+            let cold = IjkVector::new(3, 2, 12);
+            element_grid_dir.set_element(hot, Box::<Lava>::default(), clock);
+            element_grid_dir.set_element(cold, Box::<Sand>::default(), clock);
+            let gradient = HeatGradient::black_body();
+            element_grid_dir.set_heatmap(Some(gradient.clone()));
+
+            let (chunk_idx, hot_pos) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(hot);
+            let (_, cold_pos) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(cold);
+            let textures = element_grid_dir.get_textures();
+            let texture = textures[&chunk_idx].texture.as_ref().unwrap();
+            let chunk = element_grid_dir.get_chunk_by_chunk_ijk(chunk_idx);
+            assert_eq!(pixel(texture, chunk, hot_pos), gradient.hot().as_rgba_u8());
+            assert_eq!(
+                pixel(texture, chunk, cold_pos),
+                gradient.cold().as_rgba_u8()
+            );
+            // Vacuum has no temperature so it stays see through
+            assert_eq!(pixel(texture, chunk, JkVector { j: 0, k: 0 }), [0, 0, 0, 0]);
+
+            // And turning it off goes back to the element colors
+            element_grid_dir.set_heatmap(None);
+            let textures = element_grid_dir.get_textures();
+            let texture = textures[&chunk_idx].texture.as_ref().unwrap();
+            let chunk = element_grid_dir.get_chunk_by_chunk_ijk(chunk_idx);
+            assert_eq!(
+                pixel(texture, chunk, cold_pos),
+                element_grid_dir.get_element(cold).get_color().as_rgba_u8()
+            );
+        }
+    }
+
+    mod chunk_average_temperatures {
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_only_the_hot_chunk_reads_hot() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            let hot = ChunkIjkVector { i: 5, j: 0, k: 1 };
+            let warm = ChunkIjkVector { i: 5, j: 0, k: 2 };
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            assert!(coords.get_layer_num_tangential_chunkss(5) > 2);
+            for (chunk_idx, temperature) in [(hot, 1000.0), (warm, 300.0)] {
+                let chunk = element_grid_dir.get_chunk_by_chunk_ijk_mut(chunk_idx);
+                let chunk_coords = *chunk.get_chunk_coords();
+                for j in 0..chunk_coords.get_num_concentric_circles() {
+                    for k in 0..chunk_coords.get_num_radial_lines() {
+                        let pos = JkVector { j, k };
+                        chunk.set(pos, Box::<Stone>::default(), clock);
+                        chunk.set_temperature(pos, ThermodynamicTemperature(temperature), clock);
+                    }
+                }
+            }
+
+            let averages = element_grid_dir.chunk_average_temperatures();
+            assert_eq!(averages.len(), coords.get_num_layers());
+            for (layer_num, layer) in averages.iter().enumerate() {
+                assert_eq!(
+                    layer.get_width(),
+                    coords.get_layer_num_tangential_chunkss(layer_num)
+                );
+                assert_eq!(
+                    layer.get_height(),
+                    coords.get_layer_num_concentric_chunks(layer_num)
+                );
+            }
+            assert_eq!(*averages[5].get(hot.to_jk_vector()), 1000.0);
+            assert_eq!(*averages[5].get(warm.to_jk_vector()), 300.0);
+            // The rest is all vacuum, which has no temperature
+            let others = averages
+                .iter()
+                .flat_map(|layer| layer.iter())
+                .filter(|&&average| average != 0.0)
+                .count();
+            assert_eq!(others, 2);
+        }
+    }
+
+    mod pressure {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, water::Water};
+
+        /// Two layers of sand, with nothing above them
+        fn sand_dir() -> ElementGridDir {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            for layer in 3..5 {
+                let coords = element_grid_dir.get_coordinate_dir();
+                let num_concentric_circles = coords.get_layer_num_concentric_circles(layer);
+                let num_radial_lines = coords.get_layer_num_radial_lines(layer);
+                for j in 0..num_concentric_circles {
+                    for k in 0..num_radial_lines {
+                        element_grid_dir.set_element(
+                            IjkVector::new(layer, j, k),
+                            Box::<Sand>::default(),
+                            clock,
+                        );
+                    }
+                }
+            }
+            element_grid_dir.recalculate_everything();
+            element_grid_dir
+        }
+
+        #[test]
+        fn test_pressure_grows_with_depth() {
+            let element_grid_dir = sand_dir();
+            let field = element_grid_dir.pressure_field();
+            let top = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_concentric_circles(4)
+                - 1;
+            assert_eq!(field[4][[top, 0]], 0.0);
+            assert!(field[4][[top - 1, 0]] > 0.0);
+            // Down through both layers, across the halving of the radial lines
+            let column: Vec<f32> = field[4]
+                .column(10)
+                .iter()
+                .rev()
+                .chain(field[3].column(5).iter().rev())
+                .copied()
+                .collect();
+            assert!(column.windows(2).all(|pair| pair[1] > pair[0]));
+            // The vacuum below the sand adds nothing more
+            assert!(field[2][[0, 0]] > field[3][[0, 0]]);
+            assert!(field[1].iter().all(|&p| p == field[2][[0, 0]]));
+            // And every column weighs the same
+            assert!(field[3].row(0).iter().all(|&p| p == field[3][[0, 0]]));
+        }
+
+        #[test]
+        fn test_chunks_take_the_pressure_after_a_cycle() {
+            let mut element_grid_dir = sand_dir();
+            let chunk_idx = ChunkIjkVector::new(3, 0, 1);
+            let coords = *element_grid_dir
+                .get_chunk_by_chunk_ijk(chunk_idx)
+                .get_chunk_coords();
+            let cell = JkVector { j: 2, k: 3 };
+            let global = IjkVector::new(
+                3,
+                coords.get_start_concentric_circle_layer_relative() + cell.j,
+                coords.get_start_radial_line() + cell.k,
+            );
+            // Only chunks a liquid could be in are given the pressure
+            element_grid_dir.set_element(global, Box::<Water>::default(), Clock::default());
+            assert_eq!(
+                element_grid_dir
+                    .get_chunk_by_chunk_ijk(chunk_idx)
+                    .get_pressure(cell),
+                0.0
+            );
+            // Taken at the end of the cycle, so it matches the cells until they move again
+            element_grid_dir.process_full(Clock::default());
+            let field = element_grid_dir.pressure_field();
+            let expected = field[3][[global.j, global.k]];
+            assert!(expected > 0.0);
+            assert_eq!(
+                element_grid_dir
+                    .get_chunk_by_chunk_ijk(chunk_idx)
+                    .get_pressure(cell),
+                expected
+            );
+        }
+
+        #[test]
+        fn test_dry_chunks_are_skipped() {
+            let mut element_grid_dir = sand_dir();
+            element_grid_dir.process_full(Clock::default());
+            // The sand has weight, but doesn't flow by it, so no chunk is handed the pressure
+            assert!(element_grid_dir.pressure_field()[3][[0, 0]] > 0.0);
+            for chunk in element_grid_dir
+                .chunks
+                .iter()
+                .flat_map(|layer| layer.iter().flatten())
+            {
+                assert!(!chunk.may_contain_liquid());
+                assert_eq!(chunk.get_pressure(JkVector::ZERO), 0.0);
+            }
+            assert!(element_grid_dir.pressure.is_none());
+        }
+
+        #[test]
+        fn test_overlay_draws_contours_and_the_gradient() {
+            let mut element_grid_dir = sand_dir();
+            let field = element_grid_dir.pressure_field();
+            let max = field
+                .iter()
+                .flat_map(|layer| layer.iter().copied())
+                .fold(0.0, f32::max);
+            let overlay = PressureOverlay {
+                contour_interval: max / 3.0,
+                ..Default::default()
+            };
+            element_grid_dir.set_pressure_overlay(Some(overlay.clone()));
+            let contours = overlay.contour_cells(&field[3]);
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let num_concentric_circles = coords.get_layer_num_concentric_circles(3);
+
+            let textures = element_grid_dir.get_textures();
+            let pixel = |cell: IjkVector| -> [u8; 4] {
+                let (chunk_idx, pos) = coords.cell_idx_to_chunk_idx(cell);
+                let width = coords.get_chunk_num_radial_lines(chunk_idx);
+                let texture = textures[&chunk_idx].texture.as_ref().unwrap();
+                let idx = (pos.j * width + pos.k) * 4;
+                texture.pixels[idx..idx + 4].try_into().unwrap()
+            };
+            let marked: Vec<usize> = (0..num_concentric_circles)
+                .filter(|&j| contours[[j, 0]])
+                .collect();
+            assert!(!marked.is_empty() && marked.len() < num_concentric_circles);
+            for j in 0..num_concentric_circles {
+                let cell = IjkVector::new(3, j, 0);
+                let band = overlay.contour_band(field[3][[j, 0]]);
+                let above = overlay.contour_band(if j + 1 < num_concentric_circles {
+                    field[3][[j + 1, 0]]
+                } else {
+                    field[4][[0, 0]]
+                });
+                // Crossing a threshold on the way up marks the cell, except at the top of the layer
+                if j + 1 < num_concentric_circles {
+                    assert_eq!(contours[[j, 0]], above < band, "j {}", j);
+                }
+                let expected = if contours[[j, 0]] {
+                    overlay.contour_color
+                } else {
+                    overlay.color_for(field[3][[j, 0]], max)
+                };
+                assert_eq!(pixel(cell), expected.as_rgba_u8(), "j {}", j);
+            }
+            // Empty cells stay see through
+            assert_eq!(pixel(IjkVector::new(5, 0, 0)), [0, 0, 0, 0]);
+
+            element_grid_dir.set_pressure_overlay(None);
+            let textures = element_grid_dir.get_textures();
+            let (chunk_idx, _) = coords.cell_idx_to_chunk_idx(IjkVector::new(3, 0, 0));
+            let texture = textures[&chunk_idx].texture.as_ref().unwrap();
+            assert_eq!(
+                texture.pixels[0..4],
+                Sand::default().get_color().as_rgba_u8()
+            );
+        }
+    }
+
+    /// Ties setting, picking and drawing cells together, so they can't disagree on which way k winds
+    mod winding {
+        use super::*;
+        use crate::physics::fallingsand::mesh::chunk_coords::VertexSettings;
+        use crate::physics::util::vectors::RelXyPoint;
+        use bevy::math::Vec2;
+
+        #[test]
+        fn test_set_pick_and_draw_agree() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coord_dir = element_grid_dir.get_coordinate_dir().clone();
+            let clock = Clock::default();
+            // A handful of cells spread around the circle, skipping the core which is drawn as a fan
+            let layer = 3;
+            let num_radial_lines = coord_dir.get_layer_num_radial_lines(layer);
+            let cells: Vec<IjkVector> = (0..8)
+                .map(|eighth| IjkVector::new(layer, 1, eighth * num_radial_lines / 8 + 1))
+                .collect();
+            for cell in &cells {
+                element_grid_dir.set_element(*cell, ElementType::Sand.get_element(), clock);
+            }
+            let textures = element_grid_dir.get_textures();
+
+            for cell in cells {
+                let (chunk_idx, local) = coord_dir.cell_idx_to_chunk_idx(cell);
+                let chunk_coords = *element_grid_dir
+                    .get_chunk_by_chunk_ijk(chunk_idx)
+                    .get_chunk_coords();
+                let width = chunk_coords.get_num_radial_lines();
+                let height = chunk_coords.get_num_concentric_circles();
+
+                // Render: which pixel the cell is drawn to, and where the mesh puts that pixel
+                let (x, y) = chunk_coords.cell_to_texel(local);
+                let texture = textures[&chunk_idx].texture.as_ref().unwrap();
+                let idx = (y as usize * width + x as usize) * 4;
+                assert_eq!(
+                    texture.pixels[idx..idx + 4],
+                    ElementType::Sand.get_element().get_color().as_rgba_u8(),
+                    "{:?} was not drawn to its texel",
+                    cell
+                );
+                let positions = chunk_coords.get_positions(VertexSettings::grid(1));
+                let uvs = chunk_coords.get_uvs(VertexSettings::grid(1));
+                let mut corners = Vec2::ZERO;
+                for (dy, dx) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                    let vertex = (y as usize + dy) * (width + 1) + x as usize + dx;
+                    assert_eq!(
+                        uvs[vertex],
+                        Vec2::new(
+                            (x as usize + dx) as f32 / width as f32,
+                            (y as usize + dy) as f32 / height as f32
+                        )
+                    );
+                    corners += positions[vertex];
+                }
+                let drawn_at = RelXyPoint(corners / 4.0);
+
+                // Pick: the cell under where it was drawn is the cell that was set
+                let picked = coord_dir.rel_pos_to_cell_idx(drawn_at).unwrap();
+                assert_eq!(picked, cell, "Drawn at {:?}", drawn_at);
+                assert_eq!(
+                    element_grid_dir.get_element(picked).get_type(),
+                    ElementType::Sand
+                );
+                assert!(
+                    drawn_at.0.distance(coord_dir.cell_midpoint(cell).0)
+                        < coord_dir.get_cell_width().0
+                );
+            }
+        }
+    }
+
+    mod cells_of_kind {
+        use super::*;
+        use bevy::math::Vec2;
+
+        #[test]
+        fn test_yields_exactly_the_painted_disk() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let center = Vec2::new(20.0, 6.0);
+            let radius = 5.0;
+            let mut painted = HashSet::new();
+            for i in 0..coords.get_num_layers() {
+                for j in 0..coords.get_layer_num_concentric_circles(i) {
+                    for k in 0..coords.get_layer_num_radial_lines(i) {
+                        let cell = IjkVector::new(i, j, k);
+                        if coords.cell_midpoint(cell).0.distance(center) < radius {
+                            element_grid_dir.set_element(
+                                cell,
+                                ElementType::Lava.get_element(),
+                                Clock::default(),
+                            );
+                            painted.insert(cell);
+                        }
+                    }
+                }
+            }
+            assert!(!painted.is_empty());
+
+            let found: Vec<IjkVector> = element_grid_dir.cells_of_kind(ElementType::Lava).collect();
+            assert_eq!(found.len(), painted.len(), "A cell was yielded twice");
+            assert_eq!(found.into_iter().collect::<HashSet<_>>(), painted);
+            assert_eq!(element_grid_dir.cells_of_kind(ElementType::Sand).count(), 0);
+        }
+    }
+
+    mod non_finite {
+        use super::*;
+        use crate::physics::fallingsand::{
+            convolution::behaviors::ElementGridConvolutionNeighbors,
+            elements::element::{Density, ElementTakeOptions, StateOfMatter},
+        };
+        use bevy::render::color::Color;
+
+        /// Sand whose temperature has blown up
+        #[derive(Default, Clone, Copy)]
+        struct Overheated {
+            last_processed: Clock,
+        }
+
+        impl Element for Overheated {
+            fn get_type(&self) -> ElementType {
+                ElementType::Sand
+            }
+            fn get_last_processed(&self) -> Clock {
+                self.last_processed
+            }
+            fn get_color(&self) -> Color {
+                Color::YELLOW
+            }
+            fn get_density(&self) -> Density {
+                Density(1.0)
+            }
+            fn get_state_of_matter(&self) -> StateOfMatter {
+                StateOfMatter::Solid
+            }
+            fn get_temperature(&self) -> ThermodynamicTemperature {
+                ThermodynamicTemperature(f32::INFINITY)
+            }
+            fn box_clone(&self) -> Box<dyn Element> {
+                Box::new(*self)
+            }
+            fn _process(
+                &mut self,
+                _pos: JkVector,
+                _coord_dir: &CoordinateDir,
+                _target_chunk: &mut ElementGrid,
+                _element_grid_conv: &mut ElementGridConvolutionNeighbors,
+                _current_time: Clock,
+            ) -> ElementTakeOptions {
+                ElementTakeOptions::PutBack
+            }
+            fn _set_last_processed(&mut self, current_time: Clock) {
+                self.last_processed = current_time;
+            }
+        }
+
+        #[test]
+        fn test_finds_exactly_the_injected_cell() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            assert!(element_grid_dir.find_non_finite_cells().is_empty());
+
+            let cell = IjkVector::new(5, 7, 100);
+            element_grid_dir.set_element(cell, Box::<Overheated>::default(), clock);
+            assert_eq!(
+                element_grid_dir.find_non_finite_cells(),
+                vec![(cell, FieldKind::Temperature)]
+            );
+        }
+    }
+
+    mod lazy {
+        use super::*;
+        use crate::physics::fallingsand::{
+            data::element_grid::chunk_seed, elements::sand::Sand, mesh::chunk_coords::ChunkCoords,
+        };
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        /// Scatters sand over the chunk, differently for every chunk and seed
+        fn scattered_sand(coords: &ChunkCoords, seed: u64) -> ElementGrid {
+            let mut rng = StdRng::seed_from_u64(chunk_seed(seed, coords.get_chunk_idx()));
+            let mut chunk = ElementGrid::new_empty(*coords);
+            for j in 0..coords.get_num_concentric_circles() {
+                for k in 0..coords.get_num_radial_lines() {
+                    if rng.gen_bool(0.5) {
+                        chunk.set(JkVector { j, k }, Box::<Sand>::default(), Clock::default());
+                    }
+                }
+            }
+            chunk
+        }
+
+        fn get_lazy_element_grid_dir(seed: u64) -> ElementGridDir {
+            let coords = get_element_grid_dir().get_coordinate_dir().clone();
+            ElementGridDir::new_lazy(coords, seed, Box::new(scattered_sand))
+        }
+
+        /// The element types of a chunk, in order
+        fn chunk_types(chunk: &ElementGrid) -> Vec<ElementType> {
+            chunk
+                .get_grid()
+                .get_data_slice()
+                .iter()
+                .map(|element| element.get_type())
+                .collect()
+        }
+
+        #[test]
+        fn test_generates_on_first_access_only() {
+            let mut element_grid_dir = get_lazy_element_grid_dir(7);
+            let coord = ChunkIjkVector { i: 4, j: 0, k: 1 };
+            let other = ChunkIjkVector { i: 4, j: 0, k: 2 };
+
+            // Nothing has been generated, so no chunk holds any elements
+            assert!(!element_grid_dir.is_chunk_generated(coord));
+            for layer in &element_grid_dir.chunks {
+                for chunk in layer.get_data_slice().iter().flatten() {
+                    assert_eq!(chunk.get_grid().total_size(), 0);
+                    assert_eq!(chunk.memory_bytes(), std::mem::size_of::<ElementGrid>());
+                }
+            }
+
+            let first = chunk_types(element_grid_dir.get_or_generate_chunk(coord));
+            assert!(element_grid_dir.is_chunk_generated(coord));
+            assert!(first.contains(&ElementType::Sand));
+            assert!(first.contains(&ElementType::Vacuum));
+            let second = chunk_types(element_grid_dir.get_or_generate_chunk(coord));
+            assert_eq!(first, second);
+
+            // Its neighbor is still ungenerated, and processing doesn't change that
+            assert!(!element_grid_dir.is_chunk_generated(other));
+            element_grid_dir.process_full(Clock::default());
+            assert!(!element_grid_dir.is_chunk_generated(other));
+
+            // The same seed grows the same chunk in a new world, a different seed doesn't
+            let mut same = get_lazy_element_grid_dir(7);
+            assert_eq!(chunk_types(same.get_or_generate_chunk(coord)), first);
+            let mut different = get_lazy_element_grid_dir(8);
+            assert_ne!(chunk_types(different.get_or_generate_chunk(coord)), first);
+        }
+
+        /// Reading an ungenerated cell doesn't panic, and the totals keep up as chunks generate
+        #[test]
+        fn test_reads_and_totals_before_generation() {
+            let mut element_grid_dir = get_lazy_element_grid_dir(7);
+            let coord = ChunkIjkVector { i: 4, j: 0, k: 1 };
+            let cell = element_grid_dir
+                .get_coordinate_dir()
+                .get_chunk_at_idx(coord)
+                .get_external_coord_from_internal_coord(JkVector::new(0, 0));
+            assert!(element_grid_dir.try_get_element(cell).is_none());
+            assert!(element_grid_dir.try_get_temperature(cell).is_none());
+            assert_eq!(element_grid_dir.surface_emission_color(0.0), Color::NONE);
+            element_grid_dir.propagate_power();
+            assert_eq!(element_grid_dir.get_total_mass().0, 0.0);
+
+            element_grid_dir.get_or_generate_chunk(coord);
+            element_grid_dir.get_or_generate_chunk(ChunkIjkVector { i: 5, j: 0, k: 3 });
+            assert!(element_grid_dir.try_get_element(cell).is_some());
+            assert!(element_grid_dir.try_get_temperature(cell).is_some());
+            let incremental = (
+                element_grid_dir.get_total_mass().0,
+                element_grid_dir.radial_profile.clone(),
+                element_grid_dir.get_center_of_mass().0,
+            );
+            assert!(incremental.0 > 0.0);
+            element_grid_dir.recalculate_everything();
+            assert!((element_grid_dir.get_total_mass().0 - incremental.0).abs() < 1e-3);
+            assert_eq!(element_grid_dir.radial_profile.len(), incremental.1.len());
+            for (a, b) in element_grid_dir
+                .radial_profile
+                .iter()
+                .zip(incremental.1.iter())
+            {
+                assert_eq!(a.0, b.0);
+                assert!((a.1 .0 - b.1 .0).abs() < 1e-3);
+            }
+            assert!(
+                (element_grid_dir.get_center_of_mass().0 - incremental.2).length() < 1e-3,
+                "{:?} != {:?}",
+                element_grid_dir.get_center_of_mass(),
+                incremental.2
+            );
+        }
+    }
+
+    mod settling {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, stone::Stone};
+
+        #[test]
+        fn test_taller_loose_columns_take_longer() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            assert_eq!(coords.get_layer_num_tangential_chunkss(3), 3);
+            let tall = ChunkIjkVector { i: 3, j: 0, k: 0 };
+            // Only the cells in the chunk count, not the rest of the layer above it
+            let num_concentric_circles = coords.get_chunk_at_idx(tall).get_num_concentric_circles();
+            let short = ChunkIjkVector { i: 3, j: 0, k: 1 };
+            let solid = ChunkIjkVector { i: 3, j: 0, k: 2 };
+            let clock = Clock::default();
+
+            for j in 2..num_concentric_circles {
+                element_grid_dir.set_element(
+                    IjkVector::new(3, j, 5),
+                    Box::<Sand>::default(),
+                    clock,
+                );
+            }
+            element_grid_dir.set_element(IjkVector::new(3, 2, 20), Box::<Sand>::default(), clock);
+            for j in 0..num_concentric_circles {
+                for k in 32..48 {
+                    element_grid_dir.set_element(
+                        IjkVector::new(3, j, k),
+                        Box::<Stone>::default(),
+                        clock,
+                    );
+                }
+            }
+
+            let tall_passes = element_grid_dir.estimated_passes_to_settle(tall);
+            let short_passes = element_grid_dir.estimated_passes_to_settle(short);
+            assert_eq!(tall_passes, num_concentric_circles - 1);
+            assert_eq!(short_passes, 2);
+            assert!(tall_passes > short_passes);
+            assert_eq!(element_grid_dir.estimated_passes_to_settle(solid), 0);
+
+            // Sand resting on stone has nothing left to do
+            element_grid_dir.set_element(IjkVector::new(3, 1, 20), Box::<Stone>::default(), clock);
+            element_grid_dir.set_element(IjkVector::new(3, 0, 20), Box::<Stone>::default(), clock);
+            assert_eq!(element_grid_dir.estimated_passes_to_settle(short), 0);
+        }
+    }
+
+    mod dirty_textures {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_only_changed_chunks_are_redrawn() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+
+            // Nothing has been drawn yet
+            assert_eq!(
+                element_grid_dir.get_dirty_textures().len(),
+                element_grid_dir.get_num_chunks()
+            );
+            assert!(element_grid_dir.get_dirty_textures().is_empty());
+
+            // Vacuum doesn't go anywhere
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert!(element_grid_dir.get_dirty_textures().is_empty());
+
+            let cell = IjkVector::new(4, 2, 7);
+            element_grid_dir.set_element(cell, Box::<Stone>::default(), clock);
+            let textures = element_grid_dir.get_dirty_textures();
+            assert_eq!(textures.len(), 1);
+            assert_eq!(
+                textures[0].0,
+                element_grid_dir
+                    .get_coordinate_dir()
+                    .cell_idx_to_chunk_idx(cell)
+                    .0
+            );
+
+            // Neither does stone
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert!(element_grid_dir.get_dirty_textures().is_empty());
+        }
+    }
+
+    mod sample_temperature {
+        use bevy::math::Vec2;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_bilinear_at_centers_and_between() {
+            let coordinate_dir = get_element_grid_dir().get_coordinate_dir().clone();
+            let mut element_grid_dir = ElementGridDir::new_checkerboard(
+                coordinate_dir,
+                &Stone::default(),
+                &Stone::default(),
+            );
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            // k 15 and 16 are on either side of a chunk boundary
+            assert_eq!(coords.get_layer_chunk_num_radial_lines(3), 16);
+            for j in 0..coords.get_layer_num_concentric_circles(3) {
+                for (k, kelvin) in [(15, 300.0), (16, 500.0)] {
+                    let (chunk, pos) = coords.cell_idx_to_chunk_idx(IjkVector::new(3, j, k));
+                    element_grid_dir
+                        .get_chunk_by_chunk_ijk_mut(chunk)
+                        .set_temperature(pos, ThermodynamicTemperature(kelvin), Clock::default());
+                }
+            }
+
+            let cell = IjkVector::new(3, 1, 15);
+            let at_center = element_grid_dir
+                .sample_temperature_bilinear(coords.cell_midpoint(cell))
+                .unwrap();
+            assert!((at_center - 300.0).abs() < 1e-2, "{}", at_center);
+
+            // Halfway around the ring from one center to the next
+            let radius = coords.cell_midpoint(cell).0.length();
+            let angle = -2.0 * PI / coords.get_layer_num_radial_lines(3) as f32 * 16.0;
+            let between = RelXyPoint(Vec2::new(radius * angle.cos(), radius * angle.sin()));
+            let blended = element_grid_dir
+                .sample_temperature_bilinear(between)
+                .unwrap();
+            assert!((blended - 400.0).abs() < 1e-2, "{}", blended);
+
+            // Nothing to sample out in space
+            let radius = coords.get_radius().0;
+            assert!(element_grid_dir
+                .sample_temperature_bilinear(RelXyPoint(Vec2::new(radius * 2.0, 0.0)))
+                .is_none());
+        }
+    }
+
+    mod day_night {
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_surface_warms_and_cools_as_it_turns() {
+            let coordinate_dir = get_coordinate_dir(5);
+            let mut element_grid_dir = ElementGridDir::new_checkerboard(
+                coordinate_dir,
+                &Stone::default(),
+                &Stone::default(),
+            );
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let layer = coords.get_num_layers() - 1;
+            let top = coords.get_layer_num_concentric_circles(layer) - 1;
+            // Open the surface up to space
+            for k in 0..coords.get_layer_num_radial_lines(layer) {
+                element_grid_dir.set_element(
+                    IjkVector::new(layer, top, k),
+                    ElementType::Vacuum.get_element(),
+                    Clock::default(),
+                );
+            }
+            element_grid_dir.set_heat_config(HeatConfig {
+                radiative_cooling_rate: 0.2,
+                solar_heating_rate: 50.0,
+                ..Default::default()
+            });
+            element_grid_dir.set_sunlight(Some(Vec2::X));
+            let period = 20.0;
+            element_grid_dir.set_spin(AngularVelocity(2.0 * PI / period));
+
+            let cell = IjkVector::new(layer, top - 1, 0);
+            let delta = Duration::from_millis(500);
+            let steps_per_period = (period / delta.as_secs_f32()) as usize;
+            let mut clock = Clock::default();
+            let mut history = Vec::new();
+            for _ in 0..steps_per_period * 2 {
+                clock.update(delta);
+                element_grid_dir.process_full(clock);
+                history.push(element_grid_dir.get_temperature(cell).0);
+            }
+            assert!((element_grid_dir.rotation() - 4.0 * PI).abs() < 1e-3);
+
+            let last_period = &history[steps_per_period..];
+            let hottest = last_period.iter().copied().fold(f32::MIN, f32::max);
+            let coldest = last_period.iter().copied().fold(f32::MAX, f32::min);
+            assert!(hottest - coldest > 10.0, "{:?}", history);
+            // It heats through the day and cools through the night, more than once
+            let turns = history
+                .windows(3)
+                .filter(|w| (w[1] - w[0]) * (w[2] - w[1]) < 0.0)
+                .count();
+            assert!(turns >= 2, "{:?}", history);
+
+            // No light, no day
+            element_grid_dir.set_sunlight(None);
+            let before = element_grid_dir.get_temperature(cell).0;
+            for _ in 0..steps_per_period {
+                clock.update(delta);
+                element_grid_dir.process_full(clock);
+            }
+            assert!(element_grid_dir.get_temperature(cell).0 < before);
+        }
+    }
+
+    mod motion_stats {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, stone::Stone};
+
+        #[test]
+        fn test_settling_pile_stops_moving() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let num_concentric_circles = coords.get_layer_num_concentric_circles(3);
+            let clock = Clock::default();
+            for k in 0..coords.get_layer_num_radial_lines(3) {
+                element_grid_dir.set_element(
+                    IjkVector::new(3, 0, k),
+                    Box::<Stone>::default(),
+                    clock,
+                );
+            }
+            let num_sand = (num_concentric_circles - 2) * 3;
+            for j in 2..num_concentric_circles {
+                for k in 20..23 {
+                    element_grid_dir.set_element(
+                        IjkVector::new(3, j, k),
+                        Box::<Sand>::default(),
+                        clock,
+                    );
+                }
+            }
+            assert_eq!(element_grid_dir.motion_stats(), MotionStats::default());
+
+            let mut clock = Clock::default();
+            let mut history = Vec::new();
+            for _ in 0..60 {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+                history.push(element_grid_dir.motion_stats());
+            }
+            assert!(history[0].moved > 0, "{:?}", history);
+            // A grain with only one side open picks a side at random and waits when it picks the
+            // blocked one, so the last few grains can trickle down a while after the rest stopped
+            let settled_at = history
+                .iter()
+                .rposition(|stats| stats.moved > 0)
+                .map_or(0, |last_move| last_move + 1);
+            assert!(settled_at < history.len() / 2, "{:?}", history);
+            // Once it stops it stays stopped, with every element accounted for
+            let stone = coords.get_layer_num_radial_lines(3);
+            for stats in &history[settled_at..] {
+                assert_eq!(stats.moved, 0, "{:?}", history);
+                assert_eq!(stats.settled, num_sand + stone, "{:?}", history);
+            }
+        }
+    }
+
+    mod center_of_mass {
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_shifts_towards_the_heavy_side() {
+            let coordinate_dir = get_coordinate_dir(6);
+            let mut element_grid_dir = ElementGridDir::new_checkerboard(
+                coordinate_dir,
+                &Stone::default(),
+                &Stone::default(),
+            );
+            element_grid_dir.recalculate_everything();
+            let balanced = element_grid_dir.get_center_of_mass().0;
+            assert!(balanced.length() < 1e-2, "{:?}", balanced);
+
+            // Hollow out the left half of the outer layer
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let layer = coords.get_num_layers() - 1;
+            for j in 0..coords.get_layer_num_concentric_circles(layer) {
+                for k in 0..coords.get_layer_num_radial_lines(layer) {
+                    let cell = IjkVector::new(layer, j, k);
+                    if coords.cell_midpoint(cell).0.x < 0.0 {
+                        element_grid_dir.set_element(
+                            cell,
+                            ElementType::Vacuum.get_element(),
+                            Clock::default(),
+                        );
+                    }
+                }
+            }
+            // Nothing moves until the next recalculation
+            assert_eq!(element_grid_dir.get_center_of_mass().0, balanced);
+            element_grid_dir.recalculate_everything();
+            let lopsided = element_grid_dir.get_center_of_mass().0;
+            assert!(lopsided.x > 1.0, "{:?}", lopsided);
+            assert!(lopsided.y.abs() < 1e-1, "{:?}", lopsided);
+        }
+    }
+
+    mod collision_outline {
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_smooth_planet_is_round() {
+            let coordinate_dir = get_coordinate_dir(5);
+            let mut element_grid_dir = ElementGridDir::new_checkerboard(
+                coordinate_dir,
+                &Stone::default(),
+                &Stone::default(),
+            );
+            let radius = element_grid_dir.get_coordinate_dir().get_radius().0;
+            let outline = element_grid_dir.collision_outline(64);
+            assert_eq!(outline.len(), 65);
+            assert_eq!(outline.first(), outline.last());
+            for point in &outline {
+                assert!((point.length() - radius).abs() < 1e-3, "{:?}", point);
+            }
+
+            // Dig a pit three cells deep straight down the x axis
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let layer = coords.get_num_layers() - 1;
+            let top = coords.get_layer_num_concentric_circles(layer) - 1;
+            let (pit, _) = coords.snap_to_cell_midpoint(RelXyPoint::new(radius, 0.1));
+            for j in top - 2..=top {
+                element_grid_dir.set_element(
+                    IjkVector { j, ..pit },
+                    ElementType::Vacuum.get_element(),
+                    Clock::default(),
+                );
+            }
+            let depth = radius - element_grid_dir.surface_radius_at_angle(0.01);
+            assert!((depth - 3.0).abs() < 1e-3, "{}", depth);
+            let outline = element_grid_dir.collision_outline(64);
+            assert!((outline[0].length() - (radius - 3.0)).abs() < 1e-3);
+            assert!((outline[32].length() - radius).abs() < 1e-3);
+
+            assert_eq!(
+                ElementGridDir::new_empty(coords).surface_radius_at_angle(1.0),
+                0.0
+            );
+        }
+    }
+
+    mod chunk_leaks {
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_convolution_that_is_never_returned_is_caught() {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.process_full(Clock::default());
+            assert!(element_grid_dir.check_chunks_returned().is_ok());
+
+            // Take a convolution out and drop it instead of unpackaging it
+            let coord = ChunkIjkVector::new(4, 0, 2);
+            let conv = element_grid_dir
+                .package_coordinate_neighbors(coord)
+                .unwrap();
+            let mut leaked: Vec<ChunkIjkVector> = conv.into_iter().map(|(idx, _)| idx).collect();
+            assert!(!leaked.is_empty());
+            leaked.sort_by_key(|coord| (coord.i, coord.j, coord.k));
+
+            let err = element_grid_dir.check_chunks_returned().unwrap_err();
+            assert_eq!(err.0, leaked);
+        }
+
+        /// A chunk going missing mid cycle is reported, but the rest of the grid still gets
+        /// unlocked and recounted at the end of the cycle
+        #[test]
+        fn test_process_finishes_the_cycle_after_a_leak() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            for _ in 0..FRAMES_PER_FULL_PROCESS - 1 {
+                element_grid_dir.process(clock);
+            }
+
+            // Lose a chunk the last pass doesn't need, filled with stone so it has some mass
+            let last = FRAMES_PER_FULL_PROCESS - 1;
+            let targets = &element_grid_dir.process_targets;
+            let busy: HashSet<ChunkIjkVector> = targets.standard_convolution[last]
+                .0
+                .iter()
+                .chain(targets.has_single_bottom_neighbor[last].0.iter())
+                .chain(targets.has_multi_bottom_neighbor[last].0.iter())
+                .flat_map(|coord| element_grid_dir.convolution_footprint(*coord))
+                .collect();
+            let coord = element_grid_dir
+                .get_coordinate_dir()
+                .get_chunk_bounding_boxes()
+                .into_iter()
+                .map(|(coord, _)| coord)
+                .find(|coord| !busy.contains(coord))
+                .unwrap();
+            let chunk_coords = *element_grid_dir
+                .get_chunk_by_chunk_ijk(coord)
+                .get_chunk_coords();
+            let start_j = chunk_coords.get_start_concentric_circle_layer_relative();
+            let start_k = chunk_coords.get_start_radial_line();
+            for j in 0..chunk_coords.get_num_concentric_circles() {
+                for k in 0..chunk_coords.get_num_radial_lines() {
+                    element_grid_dir.set_element(
+                        IjkVector::new(coord.i, start_j + j, start_k + k),
+                        Box::<Stone>::default(),
+                        clock,
+                    );
+                }
+            }
+            let before = element_grid_dir.get_total_mass();
+            let leaked = element_grid_dir.chunks[coord.i]
+                .replace(coord.to_jk_vector(), None)
+                .unwrap();
+
+            element_grid_dir.process(clock);
+
+            let expected = before.0 - leaked.get_total_mass().0;
+            assert!((element_grid_dir.get_total_mass().0 - expected).abs() <= 1e-3 * before.0);
+            for layer in &element_grid_dir.chunks {
+                for chunk in layer.iter().flatten() {
+                    assert!(!chunk.get_already_processed());
+                }
+            }
+        }
+    }
+
+    mod binding_energy {
+        use super::*;
+        use crate::physics::fallingsand::elements::{plant::Plant, stone::Stone};
+
+        #[test]
+        fn test_denser_planet_is_bound_tighter() {
+            let coordinate_dir = get_coordinate_dir(5);
+            let dense = ElementGridDir::new_checkerboard(
+                coordinate_dir.clone(),
+                &Stone::default(),
+                &Stone::default(),
+            );
+            let light = ElementGridDir::new_checkerboard(
+                coordinate_dir.clone(),
+                &Plant::default(),
+                &Plant::default(),
+            );
+            let dense_energy = dense.gravitational_binding_energy();
+            let light_energy = light.gravitational_binding_energy();
+            assert!(light_energy > 0.0);
+            assert!(
+                dense_energy > light_energy,
+                "{} {}",
+                dense_energy,
+                light_energy
+            );
+            // Every ring's mass goes up by the same factor, so the energy goes up by its square
+            let ratio = dense_energy / light_energy;
+            assert!((ratio - 4.0).abs() < 1e-3, "{}", ratio);
+
+            assert_eq!(
+                ElementGridDir::new_empty(coordinate_dir).gravitational_binding_energy(),
+                0.0
+            );
+        }
+
+        #[test]
+        fn test_escape_velocity_matches_the_formula() {
+            let coordinate_dir = get_coordinate_dir(5);
+            // Every cell is a unit square of density 1, out to a radius of 1 + 3 + 6 + 12 + 24
+            let cells = 6 + 12 * 3 + 24 * 6 + 48 * 12 + 96 * 24;
+            let element_grid_dir = ElementGridDir::new_checkerboard(
+                coordinate_dir.clone(),
+                &Stone::default(),
+                &Stone::default(),
+            );
+            assert_eq!(element_grid_dir.get_total_num_cells(), cells);
+            let expected = (2.0 * 1.0e3 * cells as f64 / 46.0).sqrt();
+            let escape = element_grid_dir.escape_velocity();
+            assert!((escape - expected).abs() < 1e-3 * expected, "{}", escape);
+
+            assert_eq!(
+                ElementGridDir::new_empty(coordinate_dir).escape_velocity(),
+                0.0
+            );
+        }
+    }
+
+    mod conduction {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_heat_crosses_chunk_seams() {
+            let coords = get_coordinate_dir(6);
+            let mut element_grid_dir =
+                ElementGridDir::new_checkerboard(coords, &Stone::default(), &Stone::default());
+            let cold = element_grid_dir.get_temperature(IjkVector::new(4, 0, 0));
+            element_grid_dir.set_heat_config(HeatConfig {
+                thermal_diffusivity: 1.0,
+                ..Default::default()
+            });
+            let hot_chunk = ChunkIjkVector::new(4, 0, 1);
+            element_grid_dir
+                .get_chunk_by_chunk_ijk_mut(hot_chunk)
+                .temperature_slice_mut(Clock::default())
+                .fill(1000.0);
+
+            let mut clock = Clock::default();
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+
+            // Warmed up past the seams on every side of it, but not much further in one cycle
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let warmed: HashSet<ChunkIjkVector> = (0..coords.get_num_layers())
+                .flat_map(|i| {
+                    let num_radial_lines = coords.get_layer_num_radial_lines(i);
+                    (0..coords.get_layer_num_concentric_circles(i)).flat_map(move |j| {
+                        (0..num_radial_lines).map(move |k| IjkVector::new(i, j, k))
+                    })
+                })
+                .filter(|&cell| element_grid_dir.get_temperature(cell).0 > cold.0 + 1e-3)
+                .map(|cell| coords.cell_idx_to_chunk_idx(cell).0)
+                .collect();
+            assert!(warmed.contains(&hot_chunk));
+            assert!(warmed.contains(&ChunkIjkVector::new(4, 0, 0)));
+            assert!(warmed.contains(&ChunkIjkVector::new(4, 0, 2)));
+            assert!(warmed.contains(&ChunkIjkVector::new(4, 1, 1)));
+            assert!(warmed.iter().any(|chunk| chunk.i == 3));
+            assert!(warmed.iter().all(|chunk| (3..=4).contains(&chunk.i)));
+        }
+    }
+
+    mod heat_field {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, stone::Stone};
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        /// Every cell in the directory
+        fn cells(coords: &CoordinateDir) -> Vec<IjkVector> {
+            let mut out = Vec::new();
+            for i in 0..coords.get_num_layers() {
+                for j in 0..coords.get_layer_num_concentric_circles(i) {
+                    for k in 0..coords.get_layer_num_radial_lines(i) {
+                        out.push(IjkVector::new(i, j, k));
+                    }
+                }
+            }
+            out
+        }
+
+        #[test]
+        fn test_heat_round_trips_exactly() {
+            let coords = get_coordinate_dir(6);
+            let mut element_grid_dir = ElementGridDir::new_checkerboard(
+                coords.clone(),
+                &Sand::default(),
+                &Stone::default(),
+            );
+            let mut rng = StdRng::seed_from_u64(3);
+            for cell in cells(&coords) {
+                let (chunk, pos) = coords.cell_idx_to_chunk_idx(cell);
+                element_grid_dir
+                    .get_chunk_by_chunk_ijk_mut(chunk)
+                    .set_temperature(
+                        pos,
+                        ThermodynamicTemperature(rng.gen_range(0.0..5000.0)),
+                        Clock::default(),
+                    );
+            }
+            let bytes = element_grid_dir.serialize_heat();
+
+            // Only the heat comes along, the elements stay whatever they were
+            let mut loaded = ElementGridDir::new_empty(coords.clone());
+            loaded.load_heat(&bytes).unwrap();
+            for cell in cells(&coords) {
+                assert_eq!(
+                    loaded.get_temperature(cell).0.to_bits(),
+                    element_grid_dir.get_temperature(cell).0.to_bits(),
+                    "{:?}",
+                    cell
+                );
+                assert_eq!(loaded.get_element(cell).get_type(), ElementType::Vacuum);
+            }
+            assert_eq!(loaded.serialize_heat(), bytes);
+        }
+
+        #[test]
+        fn test_mismatched_layout_is_rejected() {
+            let bytes = ElementGridDir::new_empty(get_coordinate_dir(6)).serialize_heat();
+            let mut smaller = ElementGridDir::new_empty(get_coordinate_dir(5));
+            assert!(smaller.load_heat(&bytes).is_err());
+
+            let mut same = ElementGridDir::new_empty(get_coordinate_dir(6));
+            assert!(same.load_heat(&bytes[..bytes.len() - 4]).is_err());
+            assert!(same.load_heat(&bytes[..6]).is_err());
+            assert!(same.load_heat(&bytes).is_ok());
+        }
+    }
+
+    mod layer_cadence {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, stone::Stone};
+
+        #[test]
+        fn test_core_runs_a_quarter_as_often() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let core = IjkVector::new(0, 0, 0);
+            let top = element_grid_dir.get_coordinate_dir().get_num_layers() - 1;
+            let surface = IjkVector::new(top, 0, 0);
+            for cell in [core, surface] {
+                element_grid_dir.set_element(cell, Box::<Stone>::default(), Clock::default());
+            }
+            element_grid_dir.set_layer_cadence(0, 4);
+            assert_eq!(element_grid_dir.get_layer_cadence(0), 4);
+            assert_eq!(element_grid_dir.get_layer_cadence(top), 1);
+
+            let delta = Duration::from_millis(100);
+            let mut clock = Clock::default();
+            let (mut core_runs, mut surface_runs) = (0, 0);
+            for _ in 0..8 {
+                clock.update(delta);
+                element_grid_dir.process_full(clock);
+                let ran = |cell| {
+                    let last = element_grid_dir.get_element(cell).get_last_processed();
+                    (last.get_current_frame() == clock.get_current_frame()).then_some(last)
+                };
+                if let Some(last) = ran(core) {
+                    core_runs += 1;
+                    // Its step covers the frames it sat out, but never more time than has passed
+                    assert_eq!(
+                        last.get_last_delta(),
+                        clock.get_current_time().min(delta * 4)
+                    );
+                    assert_eq!(last.get_current_time(), clock.get_current_time());
+                }
+                if let Some(last) = ran(surface) {
+                    surface_runs += 1;
+                    assert_eq!(last.get_last_delta(), delta);
+                }
+            }
+            assert_eq!(core_runs, 2);
+            assert_eq!(surface_runs, 8);
+
+            // Back to every cycle, and it no longer needs anything special
+            element_grid_dir.set_layer_cadence(0, 1);
+            assert!(element_grid_dir.layer_cadence.is_empty());
+        }
+
+        #[test]
+        fn test_resting_core_keeps_its_seam_between_runs() {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.set_layer_cadence(0, 4);
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let seam = coords.get_layer_num_concentric_circles(0) - 1;
+            let seam_ring = |element_grid_dir: &ElementGridDir| {
+                (0..coords.get_layer_num_radial_lines(0))
+                    .map(|k| {
+                        element_grid_dir
+                            .get_element(IjkVector::new(0, seam, k))
+                            .get_type()
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            let delta = Duration::from_millis(100);
+            let mut clock = Clock::default();
+            clock.update(delta);
+            element_grid_dir.process_full(clock);
+            // Sand lying right on top of the core, just after it ran
+            for k in 0..coords.get_layer_num_radial_lines(1) {
+                element_grid_dir.set_element(
+                    IjkVector::new(1, 0, k),
+                    Box::<Sand>::default(),
+                    clock,
+                );
+            }
+            let resting = seam_ring(&element_grid_dir);
+            for _ in 1..4 {
+                clock.update(delta);
+                element_grid_dir.process_full(clock);
+                assert_eq!(seam_ring(&element_grid_dir), resting);
+            }
+
+            // Once the core runs again the sand falls in
+            clock.update(delta);
+            element_grid_dir.process_full(clock);
+            assert_ne!(seam_ring(&element_grid_dir), resting);
+        }
+    }
+
+    mod merge_from {
+        use bevy::math::{Quat, Vec2};
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{plant::Plant, sand::Sand, stone::Stone};
+
+        fn type_at(element_grid_dir: &ElementGridDir, x: f32, y: f32) -> ElementType {
+            let coords = element_grid_dir.get_coordinate_dir();
+            let cell = coords.rel_pos_to_cell_idx(RelXyPoint::new(x, y)).unwrap();
+            element_grid_dir.get_element(cell).get_type()
+        }
+
+        /// Every cell whose middle is within `distance` of `pos`
+        fn cells_within(
+            element_grid_dir: &ElementGridDir,
+            pos: Vec2,
+            distance: f32,
+        ) -> Vec<IjkVector> {
+            let coords = element_grid_dir.get_coordinate_dir();
+            let mut out = Vec::new();
+            for i in 0..coords.get_num_layers() {
+                for j in 0..coords.get_layer_num_concentric_circles(i) {
+                    for k in 0..coords.get_layer_num_radial_lines(i) {
+                        let cell = IjkVector::new(i, j, k);
+                        if coords.cell_midpoint(cell).0.distance(pos) < distance {
+                            out.push(cell);
+                        }
+                    }
+                }
+            }
+            out
+        }
+
+        #[test]
+        fn test_small_planet_lands_at_its_offset() {
+            // A big planet of plants out to 70, with one stone in the way
+            let mut big = ElementGridDir::new_empty(get_coordinate_dir(7));
+            big.fill_settled(&Plant::default(), 70.0);
+            let in_the_way = big
+                .get_coordinate_dir()
+                .rel_pos_to_cell_idx(RelXyPoint::new(60.0, 0.0))
+                .unwrap();
+            big.set_element(in_the_way, Box::<Stone>::default(), Clock::default());
+
+            // A small planet of sand, with a lump of stone off to one side
+            let mut small = ElementGridDir::new_empty(get_coordinate_dir(4));
+            let small_radius = small.get_coordinate_dir().get_radius().0;
+            small.fill_settled(&Sand::default(), small_radius);
+            let lump_cells = cells_within(&small, Vec2::new(15.0, 0.0), 3.0);
+            assert!(!lump_cells.is_empty());
+            for cell in lump_cells {
+                small.set_element(cell, Box::<Stone>::default(), Clock::default());
+            }
+
+            // Turned a quarter around, so the lump ends up above its middle
+            let center = Vec2::new(75.0, 0.0);
+            let relative_transform = Transform::from_translation(center.extend(0.0))
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+            let filled_cells = |element_grid_dir: &ElementGridDir| {
+                element_grid_dir.get_total_num_cells()
+                    - element_grid_dir.cells_of_kind(ElementType::Vacuum).count()
+            };
+            let before = filled_cells(&big) + filled_cells(&small);
+            let leftovers = big.merge_from(&small, relative_transform);
+            // Nothing is made or destroyed, what didn't fit is handed back
+            assert_eq!(filled_cells(&big) + leftovers.len(), before);
+
+            assert_eq!(type_at(&big, 75.0, 0.0), ElementType::Sand);
+            assert_eq!(type_at(&big, 75.0, -15.0), ElementType::Sand);
+            // Sand pushed up out of the cells it shares may fill gaps in the lump, but the lump stays put
+            let stone_near = |pos: Vec2| {
+                cells_within(&big, pos, 3.0)
+                    .into_iter()
+                    .filter(|cell| big.get_element(*cell).get_type() == ElementType::Stone)
+                    .count()
+            };
+            assert!(stone_near(Vec2::new(75.0, 15.0)) > 0);
+            assert_eq!(stone_near(Vec2::new(75.0, -15.0)), 0);
+            // The denser sand buries the plants it lands on, but not the equally dense stone
+            assert_eq!(type_at(&big, 62.0, 5.0), ElementType::Sand);
+            assert_eq!(big.get_element(in_the_way).get_type(), ElementType::Stone);
+            // Everything else is left alone
+            assert_eq!(type_at(&big, -60.0, 0.0), ElementType::Plant);
+            assert_eq!(type_at(&big, 75.0, 40.0), ElementType::Vacuum);
+            // Sand that shared a cell is pushed straight up, so it stays over where the small one landed
+            let half_width = (small_radius / center.length()).asin() + 0.05;
+            for cell in big.cells_of_kind(ElementType::Sand).collect::<Vec<_>>() {
+                let midpoint = big.get_coordinate_dir().cell_midpoint(cell).0;
+                assert!(
+                    midpoint.angle_between(center).abs() < half_width,
+                    "{:?}",
+                    cell
+                );
+            }
+        }
+
+        #[test]
+        fn test_what_lands_outside_is_handed_back() {
+            let mut big = ElementGridDir::new_empty(get_coordinate_dir(5));
+            let mut small = ElementGridDir::new_empty(get_coordinate_dir(3));
+            let small_radius = small.get_coordinate_dir().get_radius().0;
+            small.fill_settled(&Sand::default(), small_radius);
+            let num_sand = small.cells_of_kind(ElementType::Sand).count();
+
+            // Only just touching the edge of the big one
+            let offset = big.get_coordinate_dir().get_radius().0 + small_radius - 2.0;
+            let leftovers = big.merge_from(&small, Transform::from_xyz(offset, 0.0, 0.0));
+
+            let landed = big.cells_of_kind(ElementType::Sand).count();
+            assert!(landed > 0);
+            assert!(leftovers.len() > landed);
+            assert_eq!(landed + leftovers.len(), num_sand);
+            assert!(leftovers
+                .iter()
+                .all(|element| element.get_type() == ElementType::Sand));
+        }
+    }
+
+    mod chunk_coords_consistent {
+        use super::*;
+
+        #[test]
+        fn test_fresh_directory_is_consistent() {
+            get_element_grid_dir().assert_chunk_coords_consistent();
+        }
+
+        #[test]
+        fn test_swapped_chunks_are_caught() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let layer = &mut element_grid_dir.chunks[3];
+            let first = layer.replace(JkVector::new(0, 0), None);
+            let second = layer.replace(JkVector::new(0, 1), first);
+            layer.replace(JkVector::new(0, 0), second);
+            let err = element_grid_dir
+                .check_chunk_coords_consistent()
+                .expect_err("The chunks were swapped");
+            assert_eq!(err.0, ChunkIjkVector::new(3, 0, 0));
+            assert_eq!(err.1, ChunkIjkVector::new(3, 0, 1));
+        }
+    }
+
+    mod stamp_image {
+        use super::*;
+        use crate::physics::fallingsand::elements::water::Water;
+        use bevy::math::Rect;
+
+        #[test]
+        fn test_two_color_image_lands_where_it_was_stamped() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let palette = ElementPalette::default().with_color(ElementType::Stone, Color::PURPLE);
+            // Under the pixel no element is drawn in
+            let untouched = IjkVector::new(3, 1, 0);
+            element_grid_dir.set_element(untouched, Box::<Water>::default(), Clock::default());
+            let stone = Color::PURPLE.as_rgba_u8();
+            let sand = ElementType::Sand.get_element().get_color().as_rgba_u8();
+            let unknown = [1, 2, 3, 255];
+            // Three pixels wide and two high, with the last column wrapping around the ring to k 0
+            let image = RawImage {
+                bounds: Rect::new(0.0, 0.0, 3.0, 2.0),
+                pixels: [stone, sand, unknown, sand, stone, stone].concat(),
+            };
+            let num_radial_lines = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_radial_lines(3);
+            element_grid_dir.stamp_image(3, 1, num_radial_lines - 2, image, &palette);
+
+            let type_at = |j, k| {
+                element_grid_dir
+                    .get_element(IjkVector::new(3, j, k))
+                    .get_type()
+            };
+            assert_eq!(type_at(1, num_radial_lines - 2), ElementType::Stone);
+            assert_eq!(type_at(1, num_radial_lines - 1), ElementType::Sand);
+            assert_eq!(type_at(2, num_radial_lines - 2), ElementType::Sand);
+            assert_eq!(type_at(2, num_radial_lines - 1), ElementType::Stone);
+            assert_eq!(type_at(2, 0), ElementType::Stone);
+            assert_eq!(
+                element_grid_dir.get_element(untouched).get_type(),
+                ElementType::Water
+            );
+            assert_eq!(
+                element_grid_dir.cells_of_kind(ElementType::Stone).count(),
+                3
+            );
+            assert_eq!(element_grid_dir.cells_of_kind(ElementType::Sand).count(), 2);
+        }
+
+        #[test]
+        fn test_transparent_pixels_leave_their_cell_alone() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let under_background = IjkVector::new(3, 0, 1);
+            element_grid_dir.set_element(
+                under_background,
+                Box::<Water>::default(),
+                Clock::default(),
+            );
+            let sand = ElementType::Sand.get_element().get_color().as_rgba_u8();
+            let image = RawImage {
+                bounds: Rect::new(0.0, 0.0, 2.0, 1.0),
+                pixels: [sand, [0, 0, 0, 0]].concat(),
+            };
+            element_grid_dir.stamp_image(3, 0, 0, image, &ElementPalette::default());
+
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(3, 0, 0))
+                    .get_type(),
+                ElementType::Sand
+            );
+            assert_eq!(
+                element_grid_dir.get_element(under_background).get_type(),
+                ElementType::Water
+            );
+        }
+    }
+
+    mod static_chunks {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, water::Water};
+
+        #[test]
+        fn test_static_core_holds_while_the_layer_above_falls() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            for i in 0..3 {
+                for j in 0..coords.get_layer_num_concentric_chunks(i) {
+                    for k in 0..coords.get_layer_num_tangential_chunkss(i) {
+                        element_grid_dir.set_chunk_static(ChunkIjkVector::new(i, j, k), true);
+                    }
+                }
+            }
+            let clock = Clock::default();
+            // Hanging from the top of the core, with nothing under it
+            let top = coords.get_layer_num_concentric_circles(2) - 1;
+            let hanging = IjkVector::new(2, top, 3);
+            element_grid_dir.set_element(hanging, Box::<Sand>::default(), clock);
+            // And a column above an empty part of it
+            for j in 6..10 {
+                element_grid_dir.set_element(
+                    IjkVector::new(3, j, 20),
+                    Box::<Sand>::default(),
+                    clock,
+                );
+            }
+
+            let mut clock = Clock::default();
+            for _ in 0..10 {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+            }
+
+            let sand: Vec<IjkVector> = element_grid_dir.cells_of_kind(ElementType::Sand).collect();
+            assert_eq!(sand.len(), 5, "{:?}", sand);
+            assert!(sand.contains(&hanging));
+            // The column landed on the core instead of falling into it
+            let landed: Vec<&IjkVector> = sand.iter().filter(|cell| cell.i == 3).collect();
+            assert_eq!(landed.len(), 4);
+            assert!(landed.iter().all(|cell| cell.j < 4), "{:?}", landed);
+            assert!(landed.iter().any(|cell| cell.j == 0), "{:?}", landed);
+
+            element_grid_dir.set_chunk_static(coords.cell_idx_to_chunk_idx(hanging).0, false);
+            assert!(!element_grid_dir.is_chunk_static(coords.cell_idx_to_chunk_idx(hanging).0));
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert_eq!(
+                element_grid_dir.get_element(hanging).get_type(),
+                ElementType::Vacuum
+            );
+        }
+
+        #[test]
+        fn test_sand_on_static_water_gets_wet_without_sinking() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let clock = Clock::default();
+            let top = coords.get_layer_num_concentric_circles(2) - 1;
+            let water = IjkVector::new(2, top, 3);
+            element_grid_dir.set_element(water, Box::<Water>::default(), clock);
+            element_grid_dir.set_chunk_static(coords.cell_idx_to_chunk_idx(water).0, true);
+            // Right on top of the water, across the seam into the next layer
+            let k = 3 * coords.get_layer_num_radial_lines(3) / coords.get_layer_num_radial_lines(2);
+            let sand = IjkVector::new(3, 0, k);
+            element_grid_dir.set_element(sand, Box::<Sand>::default(), clock);
+
+            let mut clock = Clock::default();
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+
+            assert_eq!(
+                element_grid_dir.get_element(water).get_type(),
+                ElementType::Water
+            );
+            let sand = element_grid_dir.get_element(sand);
+            assert_eq!(sand.get_type(), ElementType::Sand);
+            assert!(sand.get_wetness() > 0.0);
+        }
+    }
+
+    mod state_hash {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, water::Water};
+
+        /// Sand and water scattered through layer 3, processed for a few cycles
+        fn processed_dir() -> ElementGridDir {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.set_seed(42);
+            let clock = Clock::default();
+            for j in 2..10 {
+                for k in (0..48).step_by(3) {
+                    let element: Box<dyn Element> = if (j + k) % 2 == 0 {
+                        Box::<Sand>::default()
+                    } else {
+                        Box::<Water>::default()
+                    };
+                    element_grid_dir.set_element(IjkVector::new(3, j, k), element, clock);
+                }
+            }
+            let mut clock = Clock::default();
+            for _ in 0..5 {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+            }
+            element_grid_dir
+        }
+
+        #[test]
+        fn test_same_history_same_hash_and_any_cell_changes_it() {
+            let mut element_grid_dir = processed_dir();
+            let hash = element_grid_dir.state_hash();
+            assert_eq!(hash, processed_dir().state_hash());
+            assert_eq!(hash, element_grid_dir.state_hash());
+
+            // A different element in one cell
+            let cell = IjkVector::new(5, 7, 11);
+            element_grid_dir.set_element(cell, Box::<Sand>::default(), Clock::default());
+            let with_sand = element_grid_dir.state_hash();
+            assert_ne!(with_sand, hash);
+
+            // Or the same element a little warmer
+            let (chunk, pos) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(cell);
+            let temperature = element_grid_dir.get_temperature(cell);
+            element_grid_dir
+                .get_chunk_by_chunk_ijk_mut(chunk)
+                .set_temperature(
+                    pos,
+                    ThermodynamicTemperature(temperature.0 + 0.01),
+                    Clock::default(),
+                );
+            assert_ne!(element_grid_dir.state_hash(), with_sand);
+        }
+
+        #[test]
+        fn test_element_state_changes_it() {
+            let mut element_grid_dir = processed_dir();
+            let cell = IjkVector::new(5, 7, 11);
+            element_grid_dir.set_element(cell, Box::<Sand>::default(), Clock::default());
+            let dry = element_grid_dir.state_hash();
+            // The same sand, only wet, with nothing else about the cell changed
+            let (chunk, pos) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(cell);
+            element_grid_dir.get_chunk_by_chunk_ijk_mut(chunk).replace(
+                pos,
+                Box::new(Sand::new_wet(0.5)),
+                Clock::default(),
+            );
+            assert_ne!(element_grid_dir.state_hash(), dry);
+        }
+    }
+
+    mod surface_emission_color {
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+
+        #[test]
+        fn test_hot_surface_is_blue_and_cool_surface_is_red() {
+            let mut element_grid_dir = get_element_grid_dir();
+            assert_eq!(element_grid_dir.surface_emission_color(0.0), Color::NONE);
+
+            element_grid_dir.fill_settled(&Stone::default(), 22.0);
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let num_radial_lines = coords.get_layer_num_radial_lines(3);
+            let top = coords.get_layer_num_concentric_circles(3) - 1;
+            let mut heat = |angle: f32, kelvin: f32| {
+                let k = coords.get_winding().radial_line_at(angle, num_radial_lines);
+                let (chunk, pos) = coords.cell_idx_to_chunk_idx(IjkVector::new(3, top, k));
+                element_grid_dir
+                    .get_chunk_by_chunk_ijk_mut(chunk)
+                    .set_temperature(pos, ThermodynamicTemperature(kelvin), Clock::default());
+            };
+            heat(0.0, 20000.0);
+            heat(PI, 1500.0);
+
+            let [r, _, b, _] = element_grid_dir.surface_emission_color(0.0).as_rgba_f32();
+            assert_eq!(b, 1.0);
+            assert!(r < b);
+            let [r, g, b, _] = element_grid_dir.surface_emission_color(PI).as_rgba_f32();
+            assert_eq!(r, 1.0);
+            assert!(g < 0.5 && b == 0.0);
+            // The stone everywhere else is at room temperature, which doesn't glow
+            assert_eq!(
+                element_grid_dir.surface_emission_color(PI / 2.0),
+                Color::BLACK
+            );
+        }
+    }
+
+    mod sub_steps {
+        use super::*;
+        use crate::physics::fallingsand::elements::sand::Sand;
+
+        /// A tall column of sand standing in layer 3
+        fn pile() -> ElementGridDir {
+            let mut element_grid_dir = get_element_grid_dir();
+            let num_concentric_circles = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_concentric_circles(3);
+            for j in 0..num_concentric_circles {
+                for k in 20..23 {
+                    element_grid_dir.set_element(
+                        IjkVector::new(3, j, k),
+                        Box::<Sand>::default(),
+                        Clock::default(),
+                    );
+                }
+            }
+            element_grid_dir
+        }
+
+        /// The pile, stepped once
+        fn stepped_pile(sub_steps: usize) -> ElementGridDir {
+            let mut element_grid_dir = pile();
+            element_grid_dir.set_sub_steps(sub_steps);
+            element_grid_dir.step_full(Duration::from_millis(100));
+            element_grid_dir
+        }
+
+        /// How far up the sand is, lower is more settled
+        fn height(element_grid_dir: &ElementGridDir) -> f32 {
+            let coords = element_grid_dir.get_coordinate_dir();
+            element_grid_dir
+                .cells_of_kind(ElementType::Sand)
+                .map(|cell| coords.cell_midpoint(cell).0.length())
+                .sum()
+        }
+
+        #[test]
+        fn test_more_sub_steps_settle_further() {
+            let once = stepped_pile(1);
+            let nine = stepped_pile(9);
+            assert_eq!(once.clock().get_current_frame(), 1);
+            assert_eq!(nine.clock().get_current_frame(), 9);
+            assert_eq!(
+                once.clock().get_current_time(),
+                nine.clock().get_current_time()
+            );
+            assert_eq!(
+                once.cells_of_kind(ElementType::Sand).count(),
+                nine.cells_of_kind(ElementType::Sand).count()
+            );
+            assert!(height(&nine) < height(&once));
+        }
+
+        #[test]
+        fn test_step_full_runs_a_cycle_per_sub_step() {
+            let mut stepped = pile();
+            stepped.set_sub_steps(3);
+            stepped.step_full(Duration::from_millis(300));
+            let mut split = pile();
+            for _ in 0..3 {
+                split.step_full(Duration::from_millis(100));
+            }
+            assert_eq!(stepped.get_process_count(), 27);
+            assert_eq!(
+                stepped.clock().get_current_time(),
+                split.clock().get_current_time()
+            );
+            assert_eq!(
+                stepped.clock().get_current_frame(),
+                split.clock().get_current_frame()
+            );
+            assert_eq!(stepped.state_hash(), split.state_hash());
+        }
+
+        #[test]
+        fn test_step_does_a_pass_per_sub_step() {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.set_sub_steps(3);
+            element_grid_dir.step(Duration::from_millis(100));
+            assert_eq!(element_grid_dir.get_process_count(), 3);
+            assert_eq!(element_grid_dir.clock().get_current_frame(), 3);
+            assert_eq!(
+                element_grid_dir.clock().get_current_time(),
+                Duration::from_millis(100)
+            );
+        }
+    }
+
+    mod trapped_gas {
+        use super::*;
+        use crate::physics::fallingsand::elements::{vacuum::Vacuum, water::Water};
+
+        #[test]
+        fn test_bubble_under_water_is_trapped_but_open_hole_is_not() {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.fill_settled(&Water::default(), 22.0);
+            assert!(element_grid_dir.find_trapped_gas().is_empty());
+
+            // A bubble in the middle of the ocean
+            let clock = Clock::default();
+            let bubble = IjkVector::new(3, 5, 10);
+            element_grid_dir.set_element(bubble, Box::<Vacuum>::default(), clock);
+            // And a shaft open to the sky, two cells deep
+            let shaft = [IjkVector::new(3, 11, 30), IjkVector::new(3, 10, 30)];
+            for cell in shaft {
+                element_grid_dir.set_element(cell, Box::<Vacuum>::default(), clock);
+            }
+
+            assert_eq!(element_grid_dir.find_trapped_gas(), vec![bubble]);
+        }
+    }
+
+    mod cells_in_rect {
+        use super::*;
+
+        #[test]
+        fn test_cells_in_rect_are_inside_it() {
+            let element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir();
+            // Straddles the seam at angle 0 and reaches down through several layers
+            let rect = Rect::new(8.0, -6.0, 30.0, 6.0);
+            let cells = element_grid_dir.cells_in_rect(rect);
+            assert!(!cells.is_empty());
+            for cell in &cells {
+                assert!(rect.contains(coords.cell_midpoint(*cell).0), "{:?}", cell);
+            }
+            let layers: HashSet<usize> = cells.iter().map(|cell| cell.i).collect();
+            assert!(layers.len() > 1);
+            let num_radial_lines = coords.get_layer_num_radial_lines(3);
+            assert!(cells.iter().any(|cell| cell.i == 3 && cell.k == 0));
+            assert!(cells
+                .iter()
+                .any(|cell| cell.i == 3 && cell.k == num_radial_lines - 1));
+
+            // Nothing was missed either
+            let mut expected = Vec::new();
+            for i in 0..coords.get_num_layers() {
+                for j in 0..coords.get_layer_num_concentric_circles(i) {
+                    for k in 0..coords.get_layer_num_radial_lines(i) {
+                        let cell = IjkVector::new(i, j, k);
+                        if rect.contains(coords.cell_midpoint(cell).0) {
+                            expected.push(cell);
+                        }
+                    }
+                }
+            }
+            let found: HashSet<IjkVector> = cells.into_iter().collect();
+            assert_eq!(found, expected.into_iter().collect());
+        }
+    }
+
+    mod tuned_for_threads {
+        use super::*;
+
+        /// The chunks of the outermost layer a pass processes in parallel, for every pass
+        fn top_layer_pass_counts(coords: &CoordinateDir) -> Vec<usize> {
+            let top = coords.get_num_layers() - 1;
+            let process_targets = pregen_process_targets(coords);
+            (0..9)
+                .map(|frame_nb| {
+                    process_targets.standard_convolution[frame_nb]
+                        .0
+                        .iter()
+                        .filter(|chunk| chunk.i == top)
+                        .count()
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_every_pass_splits_evenly_between_threads() {
+            for thread_count in [1, 2, 4, 8] {
+                let coords = CoordinateDirBuilder::tuned_for_threads(9, thread_count).build();
+                let top = coords.get_num_layers() - 1;
+                let process_targets = pregen_process_targets(&coords);
+                for frame_nb in 0..9 {
+                    let parallel_sets = [
+                        &process_targets.standard_convolution[frame_nb].0,
+                        &process_targets.has_multi_bottom_neighbor[frame_nb].0,
+                    ];
+                    for (set_nb, chunks) in parallel_sets.into_iter().enumerate() {
+                        for layer_num in 0..=top {
+                            let count = chunks.iter().filter(|chunk| chunk.i == layer_num).count();
+                            // Layers too small to go around every thread just go as wide as they can
+                            assert!(
+                                count < thread_count || count % thread_count == 0,
+                                "{} threads, pass {}, set {}, layer {} has {} chunks",
+                                thread_count,
+                                frame_nb,
+                                set_nb,
+                                layer_num,
+                                count
+                            );
+                        }
+                    }
+                }
+                for (frame_nb, count) in top_layer_pass_counts(&coords).into_iter().enumerate() {
+                    assert!(
+                        count >= 2 * thread_count,
+                        "{} threads, pass {}",
+                        thread_count,
+                        frame_nb
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_other_thread_counts_get_the_nearest_layout() {
+            for thread_count in [3, 6, 12] {
+                let coords = CoordinateDirBuilder::tuned_for_threads(9, thread_count).build();
+                let counts = top_layer_pass_counts(&coords);
+                assert!(
+                    counts.iter().all(|&count| count >= 2 * thread_count),
+                    "{} threads, {:?}",
+                    thread_count,
+                    counts
+                );
+                // Only the chunks change, never the planet
+                let untuned = CoordinateDirBuilder::new()
+                    .num_layers(9)
+                    .second_num_concentric_circles(3)
+                    .build();
+                for layer_num in 0..9 {
+                    assert_eq!(
+                        coords.get_layer_num_radial_lines(layer_num),
+                        untuned.get_layer_num_radial_lines(layer_num)
+                    );
+                    assert_eq!(
+                        coords.get_layer_num_concentric_circles(layer_num),
+                        untuned.get_layer_num_concentric_circles(layer_num)
+                    );
+                }
+            }
+        }
+    }
+
+    mod double_buffered_textures {
+        use super::*;
+        use crate::physics::fallingsand::elements::{sand::Sand, water::Water};
+
+        #[test]
+        fn test_previous_holds_the_last_frame() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let cell = IjkVector::new(3, 4, 7);
+            let chunk = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(cell)
+                .0;
+            element_grid_dir.set_element(cell, Box::<Sand>::default(), Clock::default());
+
+            // Nothing to fade from yet
+            let first = element_grid_dir.get_textures_double_buffered()[&chunk];
+            assert_eq!(first.previous.pixels, first.current.pixels);
+            let first = first.current.clone();
+
+            element_grid_dir.set_element(cell, Box::<Water>::default(), Clock::default());
+            let second = element_grid_dir.get_textures_double_buffered()[&chunk];
+            assert_eq!(second.previous.pixels, first.pixels);
+            assert_ne!(second.current.pixels, first.pixels);
+
+            // The ends of the blend are the two frames
+            assert_eq!(second.interpolated(0.0).pixels, second.previous.pixels);
+            assert_eq!(second.interpolated(1.0).pixels, second.current.pixels);
+            let water = second.current.clone();
+
+            // Nothing changed, so both frames are the water, even though the sand is still in a buffer
+            let third = element_grid_dir.get_textures_double_buffered()[&chunk];
+            assert_eq!(third.previous.pixels, water.pixels);
+            assert_eq!(third.current.pixels, water.pixels);
+        }
+
+        #[test]
+        fn test_only_dirty_chunks_are_redrawn() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let num_chunks = element_grid_dir.get_textures_double_buffered().len();
+            assert_eq!(element_grid_dir.redrawn_textures.len(), num_chunks);
+
+            let cell = IjkVector::new(3, 4, 7);
+            let chunk = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(cell)
+                .0;
+            element_grid_dir.set_element(cell, Box::<Sand>::default(), Clock::default());
+            assert_eq!(
+                element_grid_dir.get_textures_double_buffered().len(),
+                num_chunks
+            );
+            assert_eq!(element_grid_dir.redrawn_textures, HashSet::from([chunk]));
+        }
+    }
+
+    mod cell_world_quad {
+        use bevy::math::Quat;
+
+        use super::*;
+        use crate::physics::fallingsand::mesh::chunk_coords::VertexSettings;
+
+        #[test]
+        fn test_quad_follows_the_transform() {
+            let element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir();
+            // The bottom of a layer on an odd radial line, where the inner corners are squeezed in
+            let cell = IjkVector::new(3, 0, 5);
+            let (chunk_idx, local) = coords.cell_idx_to_chunk_idx(cell);
+            let chunk_coords = coords.get_chunk_at_idx(chunk_idx);
+            let (_, raw) = chunk_coords
+                .iter_cell_quads()
+                .find(|(jk, _)| *jk == local)
+                .unwrap();
+
+            let quad = element_grid_dir.cell_world_quad(cell, &GlobalTransform::IDENTITY);
+            assert_eq!(quad, raw);
+            // The same corners as the drawn mesh
+            let positions: Vec<Vec2> = chunk_coords
+                .get_vertices(VertexSettings::grid(1))
+                .into_iter()
+                .map(|vertex| vertex.position)
+                .collect();
+            let width = chunk_coords.get_num_radial_lines() + 1;
+            let at = |j: usize, k: usize| positions[j * width + k];
+            let expected = [
+                at(local.j, local.k),
+                at(local.j, local.k + 1),
+                at(local.j + 1, local.k + 1),
+                at(local.j + 1, local.k),
+            ];
+            for (corner, expected) in quad.iter().zip(expected) {
+                assert!(
+                    corner.distance(expected) < 1e-4,
+                    "{} != {}",
+                    corner,
+                    expected
+                );
+            }
+
+            let transform = GlobalTransform::from(
+                Transform::from_xyz(100.0, -50.0, 0.0)
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)),
+            );
+            let moved = element_grid_dir.cell_world_quad(cell, &transform);
+            for (corner, raw) in moved.iter().zip(raw) {
+                let expected = Vec2::new(100.0 - raw.y, -50.0 + raw.x);
+                assert!(
+                    corner.distance(expected) < 1e-3,
+                    "{} != {}",
+                    corner,
+                    expected
+                );
+            }
+        }
+    }
+
+    mod power {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{
+            battery::Battery, lamp::Lamp, metal::Metal, vacuum::Vacuum,
+        };
+
+        #[test]
+        fn test_wire_powers_the_lamp_until_it_is_cut() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            let battery = IjkVector::new(3, 5, 10);
+            let wire: Vec<IjkVector> = (11..15).map(|k| IjkVector::new(3, 5, k)).collect();
+            let lamp = IjkVector::new(3, 5, 15);
+            element_grid_dir.set_element(battery, Box::<Battery>::default(), clock);
+            for cell in &wire {
+                element_grid_dir.set_element(*cell, Box::<Metal>::default(), clock);
+            }
+            element_grid_dir.set_element(lamp, Box::<Lamp>::default(), clock);
+            // A wire past the lamp, which the lamp doesn't pass power on to
+            let past_lamp = IjkVector::new(3, 5, 16);
+            element_grid_dir.set_element(past_lamp, Box::<Metal>::default(), clock);
+            assert!(!element_grid_dir.is_powered(lamp));
+
+            // Traced at the end of the cycle
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert!(element_grid_dir.is_powered(battery));
+            assert!(wire.iter().all(|cell| element_grid_dir.is_powered(*cell)));
+            assert!(element_grid_dir.is_powered(lamp));
+            assert!(!element_grid_dir.is_powered(past_lamp));
+            // The air around it doesn't conduct
+            assert!(!element_grid_dir.is_powered(IjkVector::new(3, 6, 12)));
+
+            assert!(element_grid_dir.get_element(lamp).emission().is_some());
+            let lit = lamp_pixel(&mut element_grid_dir, lamp);
+
+            // Cut the wire
+            element_grid_dir.set_element(wire[2], Box::<Vacuum>::default(), clock);
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert!(element_grid_dir.is_powered(wire[1]));
+            assert!(!element_grid_dir.is_powered(wire[2]));
+            assert!(!element_grid_dir.is_powered(wire[3]));
+            assert!(!element_grid_dir.is_powered(lamp));
+            assert!(element_grid_dir.get_element(lamp).emission().is_none());
+            assert_ne!(lamp_pixel(&mut element_grid_dir, lamp), lit);
+        }
+
+        #[test]
+        fn test_circuit_is_only_traced_when_it_changes() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            let battery = IjkVector::new(3, 5, 10);
+            let wire = IjkVector::new(3, 5, 11);
+            element_grid_dir.set_element(battery, Box::<Battery>::default(), clock);
+            element_grid_dir.set_element(wire, Box::<Metal>::default(), clock);
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert!(element_grid_dir.is_powered(wire));
+
+            // Nothing that carries power changed, so the stale set isn't traced over
+            element_grid_dir.powered.clear();
+            element_grid_dir.set_element(IjkVector::new(3, 8, 20), Box::<Vacuum>::default(), clock);
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert!(!element_grid_dir.is_powered(wire));
+
+            // Adding to the wire traces it again
+            element_grid_dir.set_element(IjkVector::new(3, 5, 12), Box::<Metal>::default(), clock);
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert!(element_grid_dir.is_powered(wire));
+        }
+
+        /// The lamp's pixel in its chunk's texture, which has to have been redrawn
+        fn lamp_pixel(element_grid_dir: &mut ElementGridDir, lamp: IjkVector) -> [u8; 4] {
+            let (chunk, pos) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(lamp);
+            let width = element_grid_dir
+                .get_chunk_by_chunk_ijk(chunk)
+                .get_chunk_coords()
+                .get_num_radial_lines();
+            let (_, texture) = element_grid_dir
+                .get_dirty_textures()
+                .into_iter()
+                .find(|(coord, _)| *coord == chunk)
+                .expect("The lamp's chunk changed, so it is drawn again");
+            let start = (pos.j * width + pos.k) * 4;
+            texture.pixels[start..start + 4].try_into().unwrap()
+        }
+    }
+
+    mod chunk_neighbor_cache {
+        use super::*;
+
+        #[test]
+        fn test_cached_neighbors_match_fresh_ones() {
+            let element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir();
+            assert_eq!(
+                element_grid_dir.chunk_neighbors.len(),
+                coords.get_num_chunks()
+            );
+            for i in 0..coords.get_num_layers() {
+                for j in 0..coords.get_layer_num_concentric_chunks(i) {
+                    for k in 0..coords.get_layer_num_tangential_chunkss(i) {
+                        let coord = ChunkIjkVector { i, j, k };
+                        assert_eq!(
+                            element_grid_dir.get_chunk_neighbors(coord),
+                            element_grid_dir.calc_chunk_neighbors(coord),
+                            "{:?}",
+                            coord
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -1,26 +1,56 @@
-use bevy::math::Rect;
+use bevy::math::{Rect, Vec2, Vec3};
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::sync::OnceLock;
+use std::time::Duration;
+use strum::IntoEnumIterator;
 
-use crate::physics::fallingsand::elements::element::{Element, ElementTakeOptions, ElementType};
+use crate::physics::fallingsand::elements::element::{
+    Conduction, CustomElementError, Element, ElementTakeOptions, ElementType, FieldKind,
+    StateOfMatter,
+};
 use crate::physics::fallingsand::mesh::chunk_coords::ChunkCoords;
-use crate::physics::fallingsand::util::vectors::JkVector;
-use crate::physics::orbits::components::Mass;
+use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector, JkVector};
+use crate::physics::orbits::components::{Mass, ThermodynamicTemperature};
 use crate::physics::util::clock::Clock;
 
 use super::super::convolution::behaviors::ElementGridConvolutionNeighbors;
+use super::super::convolution::neighbor_identifiers::{ConvolutionIdentifier, ConvolutionIdx};
 use super::super::elements::vacuum::Vacuum;
 use super::super::mesh::coordinate_directory::CoordinateDir;
 use super::super::util::grid::{Grid, GridOutOfBoundsError};
+use super::super::util::heatmap::HeatGradient;
 use super::super::util::image::RawImage;
+use super::super::util::palette::ElementPalette;
+use super::super::util::pressuremap::PressureOverlay;
 use itertools::iproduct;
+use ndarray::Array2;
+
+/// How many cells away from an emitting element its light still reaches
+const LIGHT_RADIUS: isize = 2;
+
+/// The bytes in [ElementGrid::get_rng_state]
+pub const RNG_STATE_LEN: usize = 56;
 
 /// An element grid is a 2D grid of elements tied to a chunk
 pub struct ElementGrid {
     grid: Grid<Box<dyn Element>>,
     coords: ChunkCoords,
 
+    /// The temperature of every cell, kept apart from the elements so that it can
+    /// survive an element changing kind, see [Self::transmute]
+    /// Kept as plain Kelvin so it can be handed out whole, see [Self::temperature_slice]
+    temperature: Grid<f32>,
+
+    /// Where each step of [Self::conduct_heat] is written before it is swapped with the temperature,
+    /// kept between frames so conducting never allocates a grid
+    temperature_back: Grid<f32>,
+
+    /// The pressure on every cell in pascals as of the end of the last cycle, see [Self::set_pressure]
+    pressure: Grid<f32>,
+
     /// Some low resolution data about the world
     total_mass: Mass, // Total mass in kilograms
     // total_mass_above: Mass, // Total mass above a certain point, in kilograms
@@ -35,6 +65,185 @@ pub struct ElementGrid {
     /// This deals with whether or not the element grid needs to be processed
     /// or if it hasn't seen any changes since the last frame maybe you can skip it
     last_set: Clock,
+
+    /// The chance an element is pushed a cell counter clockwise (positive) or clockwise (negative)
+    /// for each cell it falls towards the core
+    /// From -1 to 1, set by the directory from the spin of the celestial
+    coriolis_bias: f32,
+
+    /// All the randomness of processing this chunk comes from here, see [chunk_seed]
+    rng: ChaCha12Rng,
+
+    /// How the chunk gives off heat, set by the directory
+    heat_config: HeatConfig,
+
+    /// A bit per [ElementType] that might be somewhere in the chunk, see [Self::may_contain]
+    kinds_present: u32,
+
+    /// False for a placeholder that holds no elements yet, see [Self::new_ungenerated]
+    generated: bool,
+
+    /// Whether any cell changed element since the texture was last drawn, see [Self::take_texture_dirty]
+    texture_dirty: bool,
+
+    /// Whether a cell that can carry power changed since the circuit was last traced,
+    /// see [Self::take_circuit_dirty]
+    circuit_dirty: bool,
+
+    /// The unit direction light comes from, in the celestial's own frame, zero when there is none
+    /// Set by the directory as the celestial spins, see [HeatConfig::solar_heating_rate]
+    light_direction: Vec2,
+
+    /// What the elements did the last time this chunk was processed
+    motion: MotionStats,
+
+    /// Bedrock, never processed and never written to by its neighbors, see [Self::set_static]
+    is_static: bool,
+
+    /// Sitting out this cycle, and like bedrock until it runs again, see [Self::set_resting]
+    is_resting: bool,
+}
+
+/// How many elements left their cell while being processed, and how many stayed put
+/// Empty cells are neither, they have nowhere to go
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MotionStats {
+    /// Moved, swapped or disappeared
+    pub moved: usize,
+    /// Put back where they were
+    pub settled: usize,
+}
+
+impl std::ops::AddAssign for MotionStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.moved += rhs.moved;
+        self.settled += rhs.settled;
+    }
+}
+
+/// How heat gets around, traded with space, gained from a light, and carried by liquids
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeatConfig {
+    /// What space radiates back, cells facing space cool towards this rather than 0K
+    /// Defaults to the cosmic microwave background
+    pub ambient_temperature: ThermodynamicTemperature,
+    /// The fraction of the difference from ambient lost per second by a cell facing space
+    /// 0 turns radiative cooling off, which is the default
+    pub radiative_cooling_rate: f32,
+    /// Kelvin per second gained by a cell facing space with the light straight overhead
+    /// Cells facing the light at an angle gain less, and cells on the night side nothing,
+    /// see [ElementGrid::set_light_direction]. 0 turns sunlight off, which is the default
+    pub solar_heating_rate: f32,
+    /// How much density a liquid loses per Kelvin, so hot liquid floats up through cold liquid
+    /// and cold sinks, which turns a liquid heated from below into convection cells
+    /// 0 turns convection off, which is the default
+    pub thermal_expansion: f32,
+    /// How fast heat spreads between neighboring cells, in square meters per second
+    /// 0 turns conduction off, which is the default
+    pub thermal_diffusivity: f32,
+    /// What to do when a step is too long for conduction to stay stable, see [stable_conduction_step]
+    pub conduction_limit: ConductionLimit,
+}
+
+/// How conduction handles a step longer than [stable_conduction_step]
+/// Past that limit each cell would pass more heat to its neighbors than the difference between
+/// them, overshooting into oscillations that grow until they reach infinity
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConductionLimit {
+    /// Splits the step into as many stable steps as it takes, up to [MAX_CONDUCTION_SUBSTEPS]
+    /// Heat spreads as far as it should, but a long step costs more
+    #[default]
+    Substep,
+    /// Conducts for only one stable step, so heat spreads slower than it should when steps run long
+    ClampDelta,
+}
+
+/// The most stable steps one long step is split into, see [ConductionLimit::Substep]
+/// Any time past that is dropped, like [ConductionLimit::ClampDelta] would
+pub const MAX_CONDUCTION_SUBSTEPS: usize = 64;
+
+/// The longest step, in seconds, that conduction is stable for on square cells of `cell_width`
+/// Each cell takes heat from up to four neighbors. Keeping $\frac{\alpha \Delta t}{h^2} \le \frac{1}{4}$
+/// keeps every new temperature between its neighbors', but right at that limit a hot spot
+/// trades places with the ring around it every step. Half of it, $\frac{1}{8}$, keeps at least
+/// half of each cell's own heat, so temperatures only ever settle towards each other
+pub fn stable_conduction_step(thermal_diffusivity: f32, cell_width: f32) -> f32 {
+    if thermal_diffusivity <= 0.0 {
+        return f32::INFINITY;
+    }
+    cell_width * cell_width / (8.0 * thermal_diffusivity)
+}
+
+impl Default for HeatConfig {
+    fn default() -> Self {
+        Self {
+            ambient_temperature: ThermodynamicTemperature(2.7),
+            radiative_cooling_rate: 0.0,
+            solar_heating_rate: 0.0,
+            thermal_expansion: 0.0,
+            thermal_diffusivity: 0.0,
+            conduction_limit: ConductionLimit::default(),
+        }
+    }
+}
+
+/// Generates the elements of a chunk the first time it is needed
+/// Given the chunk coords and the world seed it must always generate the same chunk,
+/// see [chunk_seed] for a random stream that fits that
+pub type ChunkGenerator = Box<dyn Fn(&ChunkCoords, u64) -> ElementGrid + Send + Sync>;
+
+/// The bit of an element type in [ElementGrid::may_contain]
+/// Custom kinds share the bits left over after the built in ones, so two of them can share a bit
+fn kind_bit(kind: ElementType) -> u32 {
+    let id = match kind {
+        ElementType::Custom(custom) => {
+            ElementType::NUM_BUILT_IN + custom.0 % (u32::BITS as u16 - ElementType::NUM_BUILT_IN)
+        }
+        _ => kind.id(),
+    };
+    1 << id
+}
+
+/// The bits any custom kind could be in
+const CUSTOM_KINDS: u32 = u32::MAX << ElementType::NUM_BUILT_IN;
+
+/// The bits of every built in kind whose default element passes `test`
+/// Custom elements could be anything, so their bits are always in it
+fn kinds_where(test: impl Fn(&dyn Element) -> bool) -> u32 {
+    ElementType::iter()
+        .filter(|kind| test(kind.get_element().as_ref()))
+        .fold(CUSTOM_KINDS, |bits, kind| bits | kind_bit(kind))
+}
+
+/// The bits of every kind that is a liquid, see [ElementGrid::may_contain_liquid]
+fn liquid_kinds() -> u32 {
+    static LIQUID_KINDS: OnceLock<u32> = OnceLock::new();
+    *LIQUID_KINDS.get_or_init(|| {
+        kinds_where(|element| element.get_state_of_matter() == StateOfMatter::Liquid)
+    })
+}
+
+/// The bits of every kind that powers a circuit, see [ElementGrid::may_contain_source]
+fn source_kinds() -> u32 {
+    static SOURCE_KINDS: OnceLock<u32> = OnceLock::new();
+    *SOURCE_KINDS.get_or_init(|| kinds_where(|element| element.conduction() == Conduction::Source))
+}
+
+/// Mixes a global seed with a chunk index, so every chunk gets its own uncorrelated random stream
+/// but the same chunk always gets the same one
+pub fn chunk_seed(seed: u64, idx: ChunkIjkVector) -> u64 {
+    // splitmix64, applied once per component
+    fn mix(mut z: u64) -> u64 {
+        z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    let mut out = mix(seed);
+    for component in [idx.i, idx.j, idx.k] {
+        out = mix(out ^ component as u64);
+    }
+    out
 }
 
 /// Useful for borrowing the grid to have a default value of one
@@ -61,6 +270,7 @@ impl ElementGrid {
         {
             grid.push(fill.box_clone());
         }
+        let total_mass = Mass(fill.get_mass(chunk_coords.get_cell_width()).0 * grid.len() as f32);
         Self {
             grid: Grid::new_from_vec(
                 chunk_coords.get_num_radial_lines(),
@@ -68,9 +278,61 @@ impl ElementGrid {
                 grid,
             ),
             coords: chunk_coords,
+            temperature: Grid::new_fill(
+                chunk_coords.get_num_radial_lines(),
+                chunk_coords.get_num_concentric_circles(),
+                fill.get_temperature().0,
+            ),
+            temperature_back: Grid::new_fill(
+                chunk_coords.get_num_radial_lines(),
+                chunk_coords.get_num_concentric_circles(),
+                0.0,
+            ),
+            pressure: Grid::new_fill(
+                chunk_coords.get_num_radial_lines(),
+                chunk_coords.get_num_concentric_circles(),
+                0.0,
+            ),
+            already_processed: false,
+            last_set: Clock::default(),
+            total_mass,
+            coriolis_bias: 0.0,
+            rng: ChaCha12Rng::seed_from_u64(chunk_seed(0, chunk_coords.get_chunk_idx())),
+            heat_config: HeatConfig::default(),
+            kinds_present: kind_bit(fill.get_type()),
+            generated: true,
+            texture_dirty: true,
+            circuit_dirty: true,
+            light_direction: Vec2::ZERO,
+            motion: MotionStats::default(),
+            is_static: false,
+            is_resting: false,
+        }
+    }
+
+    /// Creates a placeholder for a chunk that hasn't been generated yet
+    /// It holds no elements at all, reads of any cell in it are out of bounds
+    pub fn new_ungenerated(chunk_coords: ChunkCoords) -> Self {
+        Self {
+            grid: Grid::new_from_vec(0, 0, Vec::new()),
+            coords: chunk_coords,
+            temperature: Grid::new_from_vec(0, 0, Vec::new()),
+            temperature_back: Grid::new_from_vec(0, 0, Vec::new()),
+            pressure: Grid::new_from_vec(0, 0, Vec::new()),
             already_processed: false,
             last_set: Clock::default(),
             total_mass: Mass(0.0),
+            coriolis_bias: 0.0,
+            rng: ChaCha12Rng::seed_from_u64(chunk_seed(0, chunk_coords.get_chunk_idx())),
+            heat_config: HeatConfig::default(),
+            kinds_present: 0,
+            generated: false,
+            texture_dirty: false,
+            circuit_dirty: false,
+            light_direction: Vec2::ZERO,
+            motion: MotionStats::default(),
+            is_static: false,
+            is_resting: false,
         }
     }
 }
@@ -94,6 +356,116 @@ impl ElementGrid {
         self.already_processed = already_processed;
         Ok(())
     }
+    /// Whether the elements of this chunk exist yet, see [Self::new_ungenerated]
+    pub fn is_generated(&self) -> bool {
+        self.generated
+    }
+    /// Whether the texture needs drawing again, because an element was put in or taken out of a cell
+    /// Elements that only change themselves in place, like sand drying, don't count
+    pub fn is_texture_dirty(&self) -> bool {
+        self.texture_dirty
+    }
+    /// Forces the texture to be drawn again, for when the way it is drawn changes
+    pub fn mark_texture_dirty(&mut self) {
+        self.texture_dirty = true;
+    }
+    /// Clears the texture dirty flag, returning what it was
+    /// Call this when drawing the texture, see [Self::is_texture_dirty]
+    pub fn take_texture_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.texture_dirty)
+    }
+    /// Clears the circuit dirty flag, returning what it was
+    /// Set whenever a source, conductor or sink is put in or taken out of a cell, so the directory
+    /// only traces the circuit again when it could have changed, see
+    /// [ElementGridDir::propagate_power](super::element_directory::ElementGridDir::propagate_power)
+    pub fn take_circuit_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.circuit_dirty)
+    }
+    /// See [Self::set_static]
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+    /// Pins every element of the chunk where it is
+    /// The directory stops processing a static chunk, and convolutions around it can look at its cells
+    /// but not move anything into or out of them, see [ElementGridConvolutionNeighbors::is_read_only]
+    pub fn set_static(&mut self, is_static: bool) {
+        self.is_static = is_static;
+    }
+    /// See [Self::set_resting]
+    pub fn is_resting(&self) -> bool {
+        self.is_resting
+    }
+    /// Marks the chunk as sitting out the cycle of a layer processed less often, see
+    /// [ElementGridDir::set_layer_cadence](super::element_directory::ElementGridDir::set_layer_cadence)
+    /// Until it runs again its neighbors treat it like a static chunk, so its seam stays put
+    pub fn set_resting(&mut self, is_resting: bool) {
+        self.is_resting = is_resting;
+    }
+    /// Whether neighboring convolutions can only look at the chunk, because it is static or resting
+    pub fn is_write_protected(&self) -> bool {
+        self.is_static || self.is_resting
+    }
+    pub fn get_coriolis_bias(&self) -> f32 {
+        self.coriolis_bias
+    }
+    pub fn set_coriolis_bias(&mut self, coriolis_bias: f32) {
+        debug_assert!((-1.0..=1.0).contains(&coriolis_bias));
+        self.coriolis_bias = coriolis_bias;
+    }
+    pub fn get_heat_config(&self) -> HeatConfig {
+        self.heat_config
+    }
+    pub fn set_heat_config(&mut self, heat_config: HeatConfig) {
+        self.heat_config = heat_config;
+    }
+    /// Where the light is coming from, relative to the center of the celestial and in its own frame
+    /// Either a unit vector, or zero for no light at all
+    pub fn set_light_direction(&mut self, light_direction: Vec2) {
+        debug_assert!(
+            light_direction == Vec2::ZERO || (light_direction.length() - 1.0).abs() < 1e-3,
+            "Light direction should be normalized, got {:?}",
+            light_direction
+        );
+        self.light_direction = light_direction;
+    }
+    /// What the elements did the last time the chunk was processed
+    pub fn get_motion_stats(&self) -> MotionStats {
+        self.motion
+    }
+    /// Restart this chunks random stream from the global seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(chunk_seed(seed, self.coords.get_chunk_idx()));
+    }
+    /// Pulls every element processed after `clock` back to it, for when the clock goes backwards
+    /// Otherwise they would all count as already processed until the clock caught up again
+    pub fn rewind(&mut self, clock: Clock) {
+        for element in self.grid.iter_mut() {
+            if element.get_last_processed().get_current_frame() > clock.get_current_frame() {
+                element._set_last_processed(clock);
+            }
+        }
+    }
+    /// Elements should draw their randomness from here rather than thread_rng,
+    /// so that a seeded run plays out the same way every time
+    pub fn get_rng(&mut self) -> &mut ChaCha12Rng {
+        &mut self.rng
+    }
+    /// Where this chunk's random stream is, its seed, stream and word position, little endian
+    /// Putting it back with [Self::set_rng_state] carries on from the same draw
+    pub fn get_rng_state(&self) -> [u8; RNG_STATE_LEN] {
+        let mut state = [0; RNG_STATE_LEN];
+        state[..32].copy_from_slice(&self.rng.get_seed());
+        state[32..40].copy_from_slice(&self.rng.get_stream().to_le_bytes());
+        state[40..].copy_from_slice(&self.rng.get_word_pos().to_le_bytes());
+        state
+    }
+    /// See [Self::get_rng_state]
+    pub fn set_rng_state(&mut self, state: &[u8; RNG_STATE_LEN]) {
+        let mut rng = ChaCha12Rng::from_seed(state[..32].try_into().unwrap());
+        rng.set_stream(u64::from_le_bytes(state[32..40].try_into().unwrap()));
+        rng.set_word_pos(u128::from_le_bytes(state[40..].try_into().unwrap()));
+        self.rng = rng;
+    }
     pub fn get_last_set(&self) -> Clock {
         self.last_set
     }
@@ -108,17 +480,42 @@ impl ElementGrid {
         self.total_mass
     }
 
-    // /// Recalculate the total mass
-    // pub fn recalculate_total_mass(&mut self) {
-    //     let mut mass = Mass(0.0);
-    //     for j in 0..self.coords.get_num_concentric_circles() {
-    //         for k in 0..self.coords.get_num_radial_lines() {
-    //             let pos = JkVector { j, k };
-    //             mass += self.grid.get(pos).get_mass(self.coords.get_cell_width());
-    //         }
-    //     }
-    //     self.total_mass = mass;
-    // }
+    /// Roughly how many bytes this chunk takes up, for budgeting
+    /// Counts the struct itself, every boxed element, the temperature and pressure fields,
+    /// the spare temperature grid conduction writes into, and the rgba texture drawn from it
+    pub fn memory_bytes(&self) -> usize {
+        let elements: usize = self
+            .grid
+            .get_data_slice()
+            .iter()
+            .map(|element| {
+                std::mem::size_of::<Box<dyn Element>>() + std::mem::size_of_val(&**element)
+            })
+            .sum();
+        // Ungenerated chunks aren't drawn either
+        let texture = if self.generated {
+            self.coords.total_size() * 4
+        } else {
+            0
+        };
+        let fields = (self.temperature.total_size()
+            + self.temperature_back.total_size()
+            + self.pressure.total_size())
+            * std::mem::size_of::<f32>();
+        std::mem::size_of::<Self>() + elements + fields + texture
+    }
+
+    /// Recalculate the total mass
+    fn recalculate_total_mass(&mut self) {
+        let mut mass = Mass(0.0);
+        for j in 0..self.coords.get_num_concentric_circles() {
+            for k in 0..self.coords.get_num_radial_lines() {
+                let pos = JkVector { j, k };
+                mass += self.grid.get(pos).get_mass(self.coords.get_cell_width());
+            }
+        }
+        self.total_mass = mass;
+    }
 
     /// Get the maximum temperature in the directory
     // pub fn get_max_min_temp(&self) -> (ThermodynamicTemperature, ThermodynamicTemperature) {
@@ -130,27 +527,109 @@ impl ElementGrid {
     //     (self.max_temp, self.min_temp) = self.calc_max_min_temp();
     // }
 
-    /// Calculate the maximum temperature in the directory
-    // pub fn calc_max_min_temp(&self) -> (ThermodynamicTemperature, ThermodynamicTemperature) {
-    //     let mut max = ThermodynamicTemperature(0.0);
-    //     let mut min = ThermodynamicTemperature(f32::INFINITY);
-    //     for k in 0..self.coords.get_num_radial_lines() {
-    //         for j in 0..self.coords.get_num_concentric_circles() {
-    //             let pos = JkVector { j, k };
-    //             let element = self.grid.get(pos);
-    //             let temp = element.get_temperature(self.coords.get_cell_width());
-    //             if temp > max {
-    //                 max = temp;
-    //             }
-    //             // If the temperature is 0, we don't want to include it in the minimum
-    //             // Because its usually a vaccuum
-    //             if temp < min && temp != ThermodynamicTemperature(0.0) {
-    //                 min = temp;
-    //             }
-    //         }
-    //     }
-    //     (max, min)
-    // }
+    /// Calculate the maximum and minimum temperature in the chunk
+    /// Cells at exactly 0K are left out of the minimum, because they are usually vacuum
+    /// If every cell is 0K the minimum is infinite
+    pub fn calc_max_min_temp(&self) -> (ThermodynamicTemperature, ThermodynamicTemperature) {
+        let mut max = ThermodynamicTemperature(0.0);
+        let mut min = ThermodynamicTemperature(f32::INFINITY);
+        for &temp in self.temperature.get_data_slice() {
+            let temp = ThermodynamicTemperature(temp);
+            if temp > max {
+                max = temp;
+            }
+            if temp < min && temp != ThermodynamicTemperature(0.0) {
+                min = temp;
+            }
+        }
+        (max, min)
+    }
+
+    /// The mean temperature of the cells in the chunk that have one, in Kelvin
+    /// Cheap enough to stand in for the whole chunk when it is drawn coarsely from far away
+    /// 0 if nothing in the chunk has a temperature
+    pub fn average_temperature(&self) -> f32 {
+        let (sum, count) = self
+            .temperature
+            .iter()
+            .filter(|temp| **temp != 0.0)
+            .fold((0.0, 0usize), |(sum, count), temp| (sum + temp, count + 1));
+        if count == 0 {
+            0.0
+        } else {
+            sum / count as f32
+        }
+    }
+
+    /// False means there is definitely no element of this kind in the chunk
+    /// True means there might be, the record is only trimmed back down when the chunk is processed
+    pub fn may_contain(&self, kind: ElementType) -> bool {
+        self.kinds_present & kind_bit(kind) != 0
+    }
+
+    /// Like [Self::may_contain] for every liquid at once, the only elements that flow by pressure
+    pub fn may_contain_liquid(&self) -> bool {
+        self.kinds_present & liquid_kinds() != 0
+    }
+
+    /// Like [Self::may_contain] for everything that powers a circuit, like batteries
+    pub fn may_contain_source(&self) -> bool {
+        self.kinds_present & source_kinds() != 0
+    }
+
+    /// Like [Self::may_contain] for every custom kind at once
+    pub fn may_contain_custom(&self) -> bool {
+        self.kinds_present & CUSTOM_KINDS != 0
+    }
+
+    /// Every cell in the chunk holding this kind of element, in global cell coordinates
+    /// Chunks that can't contain the kind aren't scanned at all
+    pub fn cells_of_kind(&self, kind: ElementType) -> impl Iterator<Item = IjkVector> + '_ {
+        let num_concentric_circles = if self.may_contain(kind) {
+            self.coords.get_num_concentric_circles()
+        } else {
+            0
+        };
+        let layer = self.coords.get_layer_num();
+        let start_j = self.coords.get_start_concentric_circle_layer_relative();
+        let start_k = self.coords.get_start_radial_line();
+        iproduct!(
+            0..num_concentric_circles,
+            0..self.coords.get_num_radial_lines()
+        )
+        .filter(move |&(j, k)| self.grid.get(JkVector { j, k }).get_type() == kind)
+        .map(move |(j, k)| IjkVector::new(layer, start_j + j, start_k + k))
+    }
+
+    /// Rebuild [Self::may_contain] from what is actually in the chunk
+    fn recalculate_kinds_present(&mut self) {
+        self.kinds_present = self
+            .grid
+            .get_data_slice()
+            .iter()
+            .fold(0, |bits, element| bits | kind_bit(element.get_type()));
+    }
+
+    /// Every cell in the chunk with a NaN or infinite field, in global cell coordinates
+    pub fn find_non_finite_cells(&self) -> Vec<(IjkVector, FieldKind)> {
+        let mut out = Vec::new();
+        if !self.generated {
+            return out;
+        }
+        let start_j = self.coords.get_start_concentric_circle_layer_relative();
+        let start_k = self.coords.get_start_radial_line();
+        for j in 0..self.coords.get_num_concentric_circles() {
+            for k in 0..self.coords.get_num_radial_lines() {
+                if !self.temperature.get(JkVector { j, k }).is_finite() {
+                    out.push((
+                        IjkVector::new(self.coords.get_layer_num(), start_j + j, start_k + k),
+                        FieldKind::Temperature,
+                    ));
+                }
+            }
+        }
+        out
+    }
 
     /// Does not calculate the total mass, just gets the set value of it
     // pub fn get_total_mass_above(&self) -> Mass {
@@ -172,13 +651,19 @@ impl ElementGrid {
     pub fn checked_get(&self, jk: JkVector) -> Result<&Box<dyn Element>, GridOutOfBoundsError> {
         self.grid.checked_get(jk)
     }
+    /// Only for changing the element in place, swapping in a different element
+    /// should go through [Self::replace] so the chunk keeps track of it
     #[allow(clippy::borrowed_box)]
     pub fn get_mut(&mut self, jk: JkVector) -> &mut Box<dyn Element> {
         self.grid.get_mut(jk)
     }
+    /// Puts a new element in the cell, which brings its own starting temperature with it
     pub fn set(&mut self, jk: JkVector, element: Box<dyn Element>, time: Clock) {
-        self.replace(jk, element, time);
+        let temperature = element.get_temperature();
+        self.replace_with_temperature(jk, element, temperature, time);
     }
+    /// Puts a new element in the cell, which takes on the cell's temperature
+    /// Returns the element that was there before
     pub fn replace(
         &mut self,
         jk: JkVector,
@@ -186,7 +671,96 @@ impl ElementGrid {
         time: Clock,
     ) -> Box<dyn Element> {
         self.last_set = time;
-        self.grid.replace(jk, element)
+        self.texture_dirty = true;
+        self.kinds_present |= kind_bit(element.get_type());
+        let cell_width = self.coords.get_cell_width();
+        self.total_mass += element.get_mass(cell_width);
+        let previous = self.grid.replace(jk, element);
+        self.total_mass -= previous.get_mass(cell_width);
+        self.circuit_dirty |= previous.conduction() != Conduction::Insulator
+            || self.grid.get(jk).conduction() != Conduction::Insulator;
+        previous
+    }
+    /// Moves an element into the cell along with its temperature
+    /// Returns the element that was there before along with its temperature, so it can be moved on in turn
+    pub fn replace_with_temperature(
+        &mut self,
+        jk: JkVector,
+        element: Box<dyn Element>,
+        temperature: ThermodynamicTemperature,
+        time: Clock,
+    ) -> (Box<dyn Element>, ThermodynamicTemperature) {
+        let previous_temperature = self.get_temperature(jk);
+        self.temperature.set(jk, temperature.0);
+        (self.replace(jk, element, time), previous_temperature)
+    }
+    /// Changes the kind of element in the cell, like stone melting into lava,
+    /// without touching the cell's temperature
+    /// Returns the element that was there before. Custom kinds can't be made from their type,
+    /// so those are an error and the cell is left alone
+    pub fn transmute(
+        &mut self,
+        jk: JkVector,
+        new_kind: ElementType,
+        time: Clock,
+    ) -> Result<Box<dyn Element>, CustomElementError> {
+        let element = new_kind.try_get_element()?;
+        Ok(self.replace(jk, element, time))
+    }
+    /// The pressure on a cell in pascals, as of the end of the last cycle
+    pub fn get_pressure(&self, jk: JkVector) -> f32 {
+        *self.pressure.get(jk)
+    }
+    /// Like [Self::get_pressure], but out of bounds is an error rather than a panic
+    pub fn checked_get_pressure(&self, jk: JkVector) -> Result<f32, GridOutOfBoundsError> {
+        self.pressure.checked_get(jk).copied()
+    }
+    /// Takes this chunk's part of the pressure on its whole layer, indexed `[j, k]` like
+    /// [ElementGridDir::pressure_field](super::element_directory::ElementGridDir::pressure_field)
+    /// The directory does this once a cycle, so pressure lags what is in the cells by up to a cycle
+    pub fn set_pressure(&mut self, layer_pressure: &Array2<f32>) {
+        let start_j = self.coords.get_start_concentric_circle_layer_relative();
+        let start_k = self.coords.get_start_radial_line();
+        for j in 0..self.pressure.get_height() {
+            for k in 0..self.pressure.get_width() {
+                self.pressure.set(
+                    JkVector { j, k },
+                    layer_pressure[[start_j + j, start_k + k]],
+                );
+            }
+        }
+    }
+    pub fn get_temperature(&self, jk: JkVector) -> ThermodynamicTemperature {
+        ThermodynamicTemperature(*self.temperature.get(jk))
+    }
+    pub fn checked_get_temperature(
+        &self,
+        jk: JkVector,
+    ) -> Result<ThermodynamicTemperature, GridOutOfBoundsError> {
+        self.temperature
+            .checked_get(jk)
+            .map(|&kelvin| ThermodynamicTemperature(kelvin))
+    }
+    pub fn set_temperature(
+        &mut self,
+        jk: JkVector,
+        temperature: ThermodynamicTemperature,
+        time: Clock,
+    ) {
+        self.last_set = time;
+        self.temperature.set(jk, temperature.0);
+    }
+    /// Every cell's temperature in Kelvin as one contiguous slice, for whole chunk passes
+    /// like diffusion that want to run over plain floats
+    /// The order is the chunk's [Grid] storage order, use [Grid::flat_index] to find a cell in it
+    pub fn temperature_slice(&self) -> &[f32] {
+        self.temperature.get_data_slice()
+    }
+    /// The mutable counterpart of [Self::temperature_slice]
+    /// Marks the chunk as changed, since anything could be written through it
+    pub fn temperature_slice_mut(&mut self, time: Clock) -> &mut [f32] {
+        self.last_set = time;
+        self.temperature.get_data_slice_mut()
     }
 }
 
@@ -197,9 +771,15 @@ impl ElementGrid {
         for j in 0..self.get_chunk_coords().get_num_concentric_circles() {
             for k in 0..self.get_chunk_coords().get_num_radial_lines() {
                 let pos = JkVector { j, k };
-                self.grid.replace(pos, element.get_element());
+                let element = element.get_element();
+                self.temperature.set(pos, element.get_temperature().0);
+                self.grid.replace(pos, element);
             }
         }
+        self.kinds_present = kind_bit(element);
+        self.texture_dirty = true;
+        self.circuit_dirty = true;
+        self.recalculate_total_mass();
     }
 }
 
@@ -213,9 +793,17 @@ impl ElementGrid {
         element_grid_conv_neigh: &mut ElementGridConvolutionNeighbors,
         current_time: Clock,
     ) {
+        // Nothing to do until the chunk exists
+        if !self.generated {
+            return;
+        }
         self.process_elements(coord_dir, element_grid_conv_neigh, current_time);
         // self.process_heat(element_grid_conv_neigh, current_time);
+        let edge = self.edge_temperatures(coord_dir, element_grid_conv_neigh);
+        self.conduct_heat(&edge, current_time.get_last_delta());
+        self.radiate_heat(current_time.get_last_delta());
         self.process_mass(element_grid_conv_neigh);
+        self.recalculate_kinds_present();
     }
 
     /// Run each elements process method
@@ -231,13 +819,13 @@ impl ElementGrid {
 
         // By randomly shuffling the order we process the elements
         // we can avoid creating a "favorite direction" for the elements to move
-        let mut rng = thread_rng();
         let mut iter: Vec<(usize, usize)> = iproduct!(
             0..self.coords.get_num_concentric_circles(),
             0..self.coords.get_num_radial_lines()
         )
         .collect();
-        iter.shuffle(&mut rng);
+        iter.shuffle(&mut self.rng);
+        self.motion = MotionStats::default();
         for (j, k) in iter.into_iter() {
             let pos = JkVector { j, k };
 
@@ -267,12 +855,26 @@ impl ElementGrid {
             //
             match res {
                 ElementTakeOptions::PutBack => {
+                    if element.get_state_of_matter() != StateOfMatter::Empty {
+                        self.motion.settled += 1;
+                    }
                     self.grid.replace(pos, element);
                 }
                 ElementTakeOptions::ReplaceWith(new_element) => {
+                    // Whoever swapped it in already moved its temperature here, see Element::try_swap_me
+                    self.motion.moved += 1;
+                    self.kinds_present |= kind_bit(new_element.get_type());
+                    self.texture_dirty = true;
+                    self.circuit_dirty |= element.conduction() != Conduction::Insulator
+                        || new_element.conduction() != Conduction::Insulator;
                     self.grid.replace(pos, new_element);
                 }
-                ElementTakeOptions::DoNothing => {}
+                ElementTakeOptions::DoNothing => {
+                    self.motion.moved += 1;
+                    self.kinds_present |= kind_bit(ElementType::Vacuum);
+                    self.texture_dirty = true;
+                    self.circuit_dirty |= element.conduction() != Conduction::Insulator;
+                }
             }
         }
     }
@@ -306,6 +908,167 @@ impl ElementGrid {
     //     // (self.max_temp, self.min_temp) = self.calc_max_min_temp();
     // }
 
+    /// Every cell on the edge of the chunk paired with the temperature of its neighbor across the edge,
+    /// read through the convolution. A cell on a corner shows up once for each side it has a neighbor on
+    fn edge_temperatures(
+        &self,
+        coord_dir: &CoordinateDir,
+        element_grid_conv: &ElementGridConvolutionNeighbors,
+    ) -> Vec<(JkVector, f32)> {
+        let num_radial_lines = self.coords.get_num_radial_lines();
+        let num_concentric_circles = self.coords.get_num_concentric_circles();
+        let mut out = Vec::with_capacity(2 * (num_radial_lines + num_concentric_circles));
+        let mut push = |pos: JkVector, idx: Option<ConvolutionIdx>| {
+            if let Some(temperature) =
+                idx.and_then(|idx| element_grid_conv.get_temperature(self, idx).ok())
+            {
+                out.push((pos, temperature.0));
+            }
+        };
+        for j in 0..num_concentric_circles {
+            for (k, rk) in [(0, -1), (num_radial_lines - 1, 1)] {
+                let pos = JkVector { j, k };
+                push(
+                    pos,
+                    element_grid_conv
+                        .get_left_right_idx_from_center(self, &pos, rk)
+                        .ok()
+                        .filter(|idx| !matches!(idx.1, ConvolutionIdentifier::Center)),
+                );
+            }
+        }
+        for k in 0..num_radial_lines {
+            let bottom = JkVector { j: 0, k };
+            push(
+                bottom,
+                element_grid_conv
+                    .get_below_idx_from_center(self, coord_dir, &bottom, 1)
+                    .ok(),
+            );
+            let top = JkVector {
+                j: num_concentric_circles - 1,
+                k,
+            };
+            let origin = self.coords.get_external_coord_from_internal_coord(top);
+            push(top, element_grid_conv.above_cell_for(self, origin));
+        }
+        out
+    }
+
+    /// Spreads heat between neighboring cells by the thermal diffusivity of the [HeatConfig]
+    /// Cells are treated as squares of the chunk's cell width, and cells with no temperature don't
+    /// conduct at all. Across the edge of the chunk heat flows to and from the `edge` temperatures
+    /// of [Self::edge_temperatures], which the neighbors conduct back through when they are processed
+    /// Steps too long to be stable are handled by the [ConductionLimit]
+    fn conduct_heat(&mut self, edge: &[(JkVector, f32)], delta: Duration) {
+        let HeatConfig {
+            thermal_diffusivity,
+            conduction_limit,
+            ..
+        } = self.heat_config;
+        let delta = delta.as_secs_f32();
+        if thermal_diffusivity <= 0.0 || delta <= 0.0 {
+            return;
+        }
+        let cell_width = self.coords.get_cell_width().0;
+        let stable = stable_conduction_step(thermal_diffusivity, cell_width);
+        let (substeps, substep) = match conduction_limit {
+            ConductionLimit::Substep => {
+                let substeps = ((delta / stable).ceil() as usize).clamp(1, MAX_CONDUCTION_SUBSTEPS);
+                (substeps, (delta / substeps as f32).min(stable))
+            }
+            ConductionLimit::ClampDelta => (1, delta.min(stable)),
+        };
+        let fraction = thermal_diffusivity * substep / (cell_width * cell_width);
+        let num_radial_lines = self.coords.get_num_radial_lines();
+        let num_concentric_circles = self.coords.get_num_concentric_circles();
+        let no_temperature = 0.0;
+        for _ in 0..substeps {
+            let (before, after) = (&self.temperature, &mut self.temperature_back);
+            for (j, k) in iproduct!(0..num_concentric_circles, 0..num_radial_lines) {
+                let here = *before.get(JkVector { j, k });
+                if here == no_temperature {
+                    after.set(JkVector { j, k }, here);
+                    continue;
+                }
+                let flow: f32 = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .into_iter()
+                    .filter_map(|(dj, dk): (isize, isize)| {
+                        let nj = j.checked_add_signed(dj)?;
+                        let nk = k.checked_add_signed(dk)?;
+                        before.checked_get(JkVector { j: nj, k: nk }).ok()
+                    })
+                    .filter(|&&neighbor| neighbor != no_temperature)
+                    .map(|neighbor| neighbor - here)
+                    .sum();
+                after.set(JkVector { j, k }, here + fraction * flow);
+            }
+            for &(pos, neighbor) in edge {
+                let here = *before.get(pos);
+                if here != no_temperature && neighbor != no_temperature {
+                    *after.get_mut(pos) += fraction * (neighbor - here);
+                }
+            }
+            std::mem::swap(&mut self.temperature, &mut self.temperature_back);
+        }
+    }
+
+    /// Cells facing space, meaning next to a cell with no temperature, cool towards the
+    /// ambient temperature of the [HeatConfig], and warm up on the day side if there is a light
+    /// The decay is exponential, so no step size can push a cell past ambient
+    /// Only neighbors inside this chunk are checked, like [Self::get_light_map]
+    fn radiate_heat(&mut self, delta: Duration) {
+        let HeatConfig {
+            ambient_temperature,
+            radiative_cooling_rate,
+            solar_heating_rate,
+            ..
+        } = self.heat_config;
+        let sunlit = solar_heating_rate > 0.0 && self.light_direction != Vec2::ZERO;
+        if radiative_cooling_rate <= 0.0 && !sunlit {
+            return;
+        }
+        let keep = (-radiative_cooling_rate * delta.as_secs_f32()).exp();
+        let num_radial_lines = self.coords.get_num_radial_lines();
+        let num_concentric_circles = self.coords.get_num_concentric_circles();
+        let no_temperature = 0.0;
+        let mut exposed = Vec::new();
+        for (j, k) in iproduct!(0..num_concentric_circles, 0..num_radial_lines) {
+            if *self.temperature.get(JkVector { j, k }) == no_temperature {
+                continue;
+            }
+            let faces_space = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .filter_map(|(dj, dk): (isize, isize)| {
+                    let nj = j.checked_add_signed(dj)?;
+                    let nk = k.checked_add_signed(dk)?;
+                    self.temperature.checked_get(JkVector { j: nj, k: nk }).ok()
+                })
+                .any(|neighbor| *neighbor == no_temperature);
+            if faces_space {
+                exposed.push(JkVector { j, k });
+            }
+        }
+        let layer_num_radial_lines = self.coords.get_layer_num_radial_lines();
+        let start_radial_line = self.coords.get_start_radial_line();
+        for pos in exposed {
+            // Lit cells face the light, the same terminator as CoordinateDir::shadowed_cells
+            let gained = if sunlit {
+                let angle = self.coords.get_winding().angle(
+                    (start_radial_line + pos.k) as f32 + 0.5,
+                    layer_num_radial_lines,
+                );
+                let facing = Vec2::new(angle.cos(), angle.sin()).dot(self.light_direction);
+                solar_heating_rate * facing.max(0.0) * delta.as_secs_f32()
+            } else {
+                0.0
+            };
+            let temperature = self.temperature.get_mut(pos);
+            *temperature =
+                ambient_temperature.0 + (*temperature - ambient_temperature.0) * keep + gained;
+        }
+    }
+
     /// Process the mass of the grid and the mass above the grid
     fn process_mass(&mut self, _element_grid_conv_neigh: &mut ElementGridConvolutionNeighbors) {
         // self.total_mass_above = {
@@ -343,17 +1106,105 @@ impl ElementGrid {
     // }
 }
 
+/* Level of detail */
+impl ElementGrid {
+    /// Shrinks the chunk by `factor` in both directions
+    /// Each factor by factor block of cells becomes its most common element type
+    /// The chunk must be divisible by the factor in both directions
+    pub fn downsample(&self, factor: usize) -> Grid<ElementType> {
+        let num_concentric_circles = self.coords.get_num_concentric_circles();
+        let num_radial_lines = self.coords.get_num_radial_lines();
+        assert!(
+            factor > 0 && num_concentric_circles % factor == 0 && num_radial_lines % factor == 0,
+            "Can not downsample a {}x{} chunk by {}",
+            num_concentric_circles,
+            num_radial_lines,
+            factor
+        );
+        let mut out = Grid::new_fill(
+            num_radial_lines / factor,
+            num_concentric_circles / factor,
+            ElementType::default(),
+        );
+        for (cj, ck) in iproduct!(
+            0..num_concentric_circles / factor,
+            0..num_radial_lines / factor
+        ) {
+            // A vec rather than a map so ties always go to whichever type was seen first
+            let mut counts: Vec<(ElementType, usize)> = Vec::new();
+            for (j, k) in iproduct!(
+                cj * factor..(cj + 1) * factor,
+                ck * factor..(ck + 1) * factor
+            ) {
+                let element_type = self.grid.get(JkVector { j, k }).get_type();
+                match counts.iter_mut().find(|(t, _)| *t == element_type) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((element_type, 1)),
+                }
+            }
+            let mut majority = counts[0];
+            for count in counts.into_iter().skip(1) {
+                if count.1 > majority.1 {
+                    majority = count;
+                }
+            }
+            out.set(JkVector { j: cj, k: ck }, majority.0);
+        }
+        out
+    }
+
+    /// The inverse of [Self::downsample]
+    /// Every block of cells is filled with a fresh element of its coarse cell's type,
+    /// so anything the elements were carrying besides their type is lost
+    pub fn upsample(&mut self, coarse: &Grid<ElementType>, factor: usize, current_time: Clock) {
+        debug_assert_eq!(
+            coarse.get_width() * factor,
+            self.coords.get_num_radial_lines()
+        );
+        debug_assert_eq!(
+            coarse.get_height() * factor,
+            self.coords.get_num_concentric_circles()
+        );
+        for (j, k) in iproduct!(
+            0..self.coords.get_num_concentric_circles(),
+            0..self.coords.get_num_radial_lines()
+        ) {
+            let element = coarse
+                .get(JkVector {
+                    j: j / factor,
+                    k: k / factor,
+                })
+                .get_element();
+            self.temperature
+                .set(JkVector { j, k }, element.get_temperature().0);
+            self.grid.replace(JkVector { j, k }, element);
+        }
+        self.recalculate_kinds_present();
+        self.last_set = current_time;
+        self.texture_dirty = true;
+        self.circuit_dirty = true;
+    }
+}
+
 /* Drawing */
 impl ElementGrid {
-    /// Draw the texture as the color of each element
-    pub fn get_texture(&self) -> RawImage {
+    /// Draw the texture as the color of each element, as the palette colors it
+    /// Light from emitting elements is added on top of the element colors
+    pub fn get_texture(&self, palette: &ElementPalette) -> RawImage {
         let mut out = Vec::with_capacity(
             self.coords.get_num_radial_lines() * self.coords.get_num_concentric_circles() * 4,
         );
+        let light_map = self.get_light_map();
         for j in 0..self.coords.get_num_concentric_circles() {
             for k in 0..self.coords.get_num_radial_lines() {
-                let element = self.grid.get(JkVector { j, k });
-                let color = element.get_color().as_rgba_u8();
+                let pos = JkVector { j, k };
+                let element = self.grid.get(pos);
+                let light = light_map.get(pos);
+                let mut color = palette.color_of(element.as_ref());
+                color.set_r((color.r() + light.x).min(1.0));
+                color.set_g((color.g() + light.y).min(1.0));
+                color.set_b((color.b() + light.z).min(1.0));
+                let color = color.as_rgba_u8();
                 out.push(color[0]);
                 out.push(color[1]);
                 out.push(color[2]);
@@ -362,17 +1213,159 @@ impl ElementGrid {
         }
         RawImage {
             pixels: out,
-            bounds: Rect::new(
-                self.coords.get_start_radial_line() as f32,
-                self.coords.get_start_concentric_circle_absolute() as f32,
-                self.coords.get_start_radial_line() as f32
-                    + self.coords.get_num_radial_lines() as f32,
-                self.coords.get_start_concentric_circle_absolute() as f32
-                    + self.coords.get_num_concentric_circles() as f32,
-            ),
+            bounds: self.get_texture_bounds(),
+        }
+    }
+
+    /// Draw the texture as the temperature of each cell, colored by the gradient
+    /// min and max are the range of temperatures the gradient spans, usually over the whole directory
+    /// so that neighboring chunks are colored consistently
+    /// Cells with no temperature, like vacuum, are left transparent
+    pub fn get_heat_texture(
+        &self,
+        gradient: &HeatGradient,
+        min: ThermodynamicTemperature,
+        max: ThermodynamicTemperature,
+    ) -> RawImage {
+        let mut out = Vec::with_capacity(
+            self.coords.get_num_radial_lines() * self.coords.get_num_concentric_circles() * 4,
+        );
+        for j in 0..self.coords.get_num_concentric_circles() {
+            for k in 0..self.coords.get_num_radial_lines() {
+                let temperature =
+                    ThermodynamicTemperature(*self.temperature.get(JkVector { j, k }));
+                let color = if temperature == ThermodynamicTemperature(0.0) {
+                    [0, 0, 0, 0]
+                } else {
+                    gradient.color_for(temperature, min, max).as_rgba_u8()
+                };
+                out.extend_from_slice(&color);
+            }
+        }
+        RawImage {
+            pixels: out,
+            bounds: self.get_texture_bounds(),
+        }
+    }
+
+    /// Like [Self::get_heat_texture] but every cell with a temperature gets the chunk's [Self::average_temperature]
+    /// For when the chunk is too far away for single cells to show anyway
+    pub fn get_coarse_heat_texture(
+        &self,
+        gradient: &HeatGradient,
+        min: ThermodynamicTemperature,
+        max: ThermodynamicTemperature,
+    ) -> RawImage {
+        let color = gradient
+            .color_for(
+                ThermodynamicTemperature(self.average_temperature()),
+                min,
+                max,
+            )
+            .as_rgba_u8();
+        let mut out = Vec::with_capacity(
+            self.coords.get_num_radial_lines() * self.coords.get_num_concentric_circles() * 4,
+        );
+        for j in 0..self.coords.get_num_concentric_circles() {
+            for k in 0..self.coords.get_num_radial_lines() {
+                if *self.temperature.get(JkVector { j, k }) == 0.0 {
+                    out.extend_from_slice(&[0, 0, 0, 0]);
+                } else {
+                    out.extend_from_slice(&color);
+                }
+            }
+        }
+        RawImage {
+            pixels: out,
+            bounds: self.get_texture_bounds(),
+        }
+    }
+
+    /// Draw the texture as the pressure on each cell, with the contour lines over it
+    /// pressure and contours are of this chunk's whole layer, indexed `[j, k]` like
+    /// [ElementGridDir::pressure_field](super::element_directory::ElementGridDir::pressure_field)
+    /// max is the pressure at the hot end of the gradient. Empty cells are left transparent
+    pub fn get_pressure_texture(
+        &self,
+        overlay: &PressureOverlay,
+        pressure: &Array2<f32>,
+        contours: &Array2<bool>,
+        max: f32,
+    ) -> RawImage {
+        let mut out = Vec::with_capacity(
+            self.coords.get_num_radial_lines() * self.coords.get_num_concentric_circles() * 4,
+        );
+        let start_j = self.coords.get_start_concentric_circle_layer_relative();
+        let start_k = self.coords.get_start_radial_line();
+        for j in 0..self.coords.get_num_concentric_circles() {
+            for k in 0..self.coords.get_num_radial_lines() {
+                let layer_pos = [start_j + j, start_k + k];
+                let color = if self.grid.get(JkVector { j, k }).get_state_of_matter()
+                    == StateOfMatter::Empty
+                {
+                    [0, 0, 0, 0]
+                } else if contours[layer_pos] {
+                    overlay.contour_color.as_rgba_u8()
+                } else {
+                    overlay.color_for(pressure[layer_pos], max).as_rgba_u8()
+                };
+                out.extend_from_slice(&color);
+            }
+        }
+        RawImage {
+            pixels: out,
+            bounds: self.get_texture_bounds(),
         }
     }
 
+    /// Where the texture of this chunk sits, in radial lines and concentric circles
+    fn get_texture_bounds(&self) -> Rect {
+        Rect::new(
+            self.coords.get_start_radial_line() as f32,
+            self.coords.get_start_concentric_circle_absolute() as f32,
+            self.coords.get_start_radial_line() as f32 + self.coords.get_num_radial_lines() as f32,
+            self.coords.get_start_concentric_circle_absolute() as f32
+                + self.coords.get_num_concentric_circles() as f32,
+        )
+    }
+
+    /// Accumulate the light given off by emitting elements into an rgb light map
+    /// This is a cheap box blur of the emitters, falling off with distance, not real ray tracing
+    /// Light does not cross into neighboring chunks
+    pub fn get_light_map(&self) -> Grid<Vec3> {
+        let num_radial_lines = self.coords.get_num_radial_lines();
+        let num_concentric_circles = self.coords.get_num_concentric_circles();
+        let mut light_map = Grid::new_fill(num_radial_lines, num_concentric_circles, Vec3::ZERO);
+        for j in 0..num_concentric_circles {
+            for k in 0..num_radial_lines {
+                let (color, intensity) = match self.grid.get(JkVector { j, k }).emission() {
+                    Some(emission) => emission,
+                    None => continue,
+                };
+                let rgb = Vec3::new(color.r(), color.g(), color.b()) * intensity;
+                for dj in -LIGHT_RADIUS..=LIGHT_RADIUS {
+                    for dk in -LIGHT_RADIUS..=LIGHT_RADIUS {
+                        let nj = j as isize + dj;
+                        let nk = k as isize + dk;
+                        if nj < 0
+                            || nk < 0
+                            || nj >= num_concentric_circles as isize
+                            || nk >= num_radial_lines as isize
+                        {
+                            continue;
+                        }
+                        let falloff = 1.0 / (1.0 + dj.abs().max(dk.abs()) as f32);
+                        *light_map.get_mut(JkVector {
+                            j: nj as usize,
+                            k: nk as usize,
+                        }) += rgb * falloff;
+                    }
+                }
+            }
+        }
+        light_map
+    }
+
     // Get the texture of the grid as to its heat
     // max_temp is the maximum temperature of the entire directory
     // min_temp is the minimum temperature of the entire directory
@@ -416,3 +1409,256 @@ impl ElementGrid {
     //     self.get_texture().save(ctx, chunk_path.as_str())
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::fallingsand::elements::element::CustomKind;
+    use crate::physics::fallingsand::mesh::chunk_coords::PartialLayerChunkCoordsBuilder;
+    use rand::Rng;
+
+    fn get_chunk_coords() -> ChunkCoords {
+        get_chunk_coords_at(ChunkIjkVector { i: 1, j: 0, k: 0 })
+    }
+
+    fn get_chunk_coords_at(idx: ChunkIjkVector) -> ChunkCoords {
+        PartialLayerChunkCoordsBuilder::new()
+            .chunk_idx(idx)
+            .start_concentric_circle_absolute(1)
+            .layer_num_radial_lines(12)
+            .num_concentric_circles(8)
+            .start_radial_line(0)
+            .end_radial_line(12)
+            .build()
+    }
+
+    #[test]
+    fn test_lava_lights_its_neighbors() {
+        let mut element_grid = ElementGrid::new_empty(get_chunk_coords());
+        let center = JkVector { j: 4, k: 6 };
+        element_grid.set(center, ElementType::Lava.get_element(), Clock::default());
+        let light_map = element_grid.get_light_map();
+        let neighbor = JkVector { j: 5, k: 6 };
+        assert!(light_map.get(neighbor).length() > 0.0);
+        assert!(light_map.get(center).length() > light_map.get(neighbor).length());
+        // Out of reach
+        assert_eq!(*light_map.get(JkVector { j: 0, k: 0 }), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_inert_elements_give_no_light() {
+        let mut element_grid = ElementGrid::new_empty(get_chunk_coords());
+        element_grid.fill(ElementType::Stone);
+        let light_map = element_grid.get_light_map();
+        assert!(light_map.iter().all(|light| *light == Vec3::ZERO));
+    }
+
+    #[test]
+    fn test_chunk_rng_is_seeded_per_chunk() {
+        let draw = |idx: ChunkIjkVector, seed: u64| {
+            let mut element_grid = ElementGrid::new_empty(get_chunk_coords_at(idx));
+            element_grid.reseed(seed);
+            (0..16)
+                .map(|_| element_grid.get_rng().gen::<u64>())
+                .collect::<Vec<_>>()
+        };
+        let here = ChunkIjkVector { i: 1, j: 0, k: 0 };
+        let there = ChunkIjkVector { i: 1, j: 0, k: 1 };
+        assert_eq!(draw(here, 42), draw(here, 42));
+        assert_ne!(draw(here, 42), draw(there, 42));
+        assert_ne!(draw(here, 42), draw(here, 43));
+    }
+
+    #[test]
+    fn test_radiative_cooling_settles_at_ambient() {
+        let mut element_grid = ElementGrid::new_empty(get_chunk_coords());
+        let heat_config = HeatConfig {
+            ambient_temperature: ThermodynamicTemperature(50.0),
+            radiative_cooling_rate: 0.5,
+            ..Default::default()
+        };
+        element_grid.set_heat_config(heat_config);
+        let pos = JkVector { j: 4, k: 6 };
+        element_grid.set(pos, ElementType::Stone.get_element(), Clock::default());
+        element_grid.set_temperature(pos, ThermodynamicTemperature(1000.0), Clock::default());
+        let mut last = element_grid.get_temperature(pos);
+        for _ in 0..100 {
+            element_grid.radiate_heat(Duration::from_secs(1));
+            let temperature = element_grid.get_temperature(pos);
+            assert!(temperature <= last);
+            assert!(temperature >= heat_config.ambient_temperature);
+            last = temperature;
+        }
+        assert!((last.0 - heat_config.ambient_temperature.0).abs() < 1e-3);
+        // Even a huge step doesn't overshoot
+        element_grid.set_temperature(pos, ThermodynamicTemperature(1000.0), Clock::default());
+        element_grid.radiate_heat(Duration::from_secs(1_000_000));
+        assert!(element_grid.get_temperature(pos) >= heat_config.ambient_temperature);
+        // Vacuum has no temperature to lose
+        let space = JkVector { j: 0, k: 0 };
+        assert_eq!(
+            element_grid.get_temperature(space),
+            ThermodynamicTemperature(0.0)
+        );
+    }
+
+    #[test]
+    fn test_average_temperature_of_half_hot_chunk() {
+        let mut element_grid = ElementGrid::new_empty(get_chunk_coords());
+        element_grid.fill(ElementType::Stone);
+        let coords = *element_grid.get_chunk_coords();
+        let half = coords.get_num_concentric_circles() / 2;
+        for (j, k) in iproduct!(
+            0..coords.get_num_concentric_circles(),
+            0..coords.get_num_radial_lines()
+        ) {
+            let temperature = if j < half { 1000.0 } else { 200.0 };
+            element_grid.set_temperature(
+                JkVector { j, k },
+                ThermodynamicTemperature(temperature),
+                Clock::default(),
+            );
+        }
+        assert!((element_grid.average_temperature() - 600.0).abs() < 1e-3);
+        // Vacuum doesn't drag the average down
+        element_grid.set(
+            JkVector { j: 0, k: 0 },
+            ElementType::Vacuum.get_element(),
+            Clock::default(),
+        );
+        let num_cells = coords.total_size() as f32;
+        let num_hot = (half * coords.get_num_radial_lines()) as f32;
+        let expected =
+            ((num_hot - 1.0) * 1000.0 + (num_cells - num_hot) * 200.0) / (num_cells - 1.0);
+        assert!((element_grid.average_temperature() - expected).abs() < 1e-3);
+        assert_eq!(ElementGrid::new_empty(coords).average_temperature(), 0.0);
+
+        // Drawn coarsely, every cell but the vacuum is the color of the average
+        let (min, max) = (
+            ThermodynamicTemperature(200.0),
+            ThermodynamicTemperature(1000.0),
+        );
+        let texture = element_grid.get_coarse_heat_texture(&HeatGradient::default(), min, max);
+        let average_color = HeatGradient::default()
+            .color_for(ThermodynamicTemperature(expected), min, max)
+            .as_rgba_u8();
+        let mut pixels = texture.pixels.chunks_exact(4);
+        assert_eq!(pixels.next().unwrap(), [0, 0, 0, 0]);
+        assert!(pixels.all(|pixel| pixel == average_color));
+    }
+
+    #[test]
+    fn test_temperature_slice_matches_cells() {
+        let coords = get_chunk_coords();
+        let mut element_grid = ElementGrid::new_empty(coords);
+        let cells = iproduct!(
+            0..coords.get_num_concentric_circles(),
+            0..coords.get_num_radial_lines()
+        )
+        .map(|(j, k)| JkVector { j, k });
+        for pos in cells.clone() {
+            let temperature = ThermodynamicTemperature((pos.j * 100 + pos.k) as f32);
+            element_grid.set_temperature(pos, temperature, Clock::default());
+        }
+        // Any grid of the chunk's shape shares its layout
+        let layout = Grid::new_fill(
+            coords.get_num_radial_lines(),
+            coords.get_num_concentric_circles(),
+            (),
+        );
+        assert_eq!(element_grid.temperature_slice().len(), coords.total_size());
+        for pos in cells {
+            assert_eq!(
+                element_grid.temperature_slice()[layout.flat_index(pos)],
+                element_grid.get_temperature(pos).0
+            );
+        }
+        let pos = JkVector { j: 2, k: 3 };
+        element_grid.temperature_slice_mut(Clock::default())[layout.flat_index(pos)] = 1.0;
+        assert_eq!(
+            element_grid.get_temperature(pos),
+            ThermodynamicTemperature(1.0)
+        );
+    }
+
+    #[test]
+    fn test_transmuting_hot_stone_to_lava_keeps_its_temperature() {
+        let mut element_grid = ElementGrid::new_empty(get_chunk_coords());
+        let pos = JkVector { j: 3, k: 5 };
+        element_grid.set(pos, ElementType::Stone.get_element(), Clock::default());
+        let hot = ThermodynamicTemperature(1234.5);
+        element_grid.set_temperature(pos, hot, Clock::default());
+        let prev = element_grid
+            .transmute(pos, ElementType::Lava, Clock::default())
+            .unwrap();
+        assert_eq!(prev.get_type(), ElementType::Stone);
+        assert!(element_grid
+            .transmute(pos, ElementType::Custom(CustomKind(0)), Clock::default())
+            .is_err());
+        assert_eq!(element_grid.get(pos).get_type(), ElementType::Lava);
+        assert_eq!(element_grid.get_temperature(pos), hot);
+        // Whereas set brings the new element's own temperature
+        element_grid.set(pos, ElementType::Stone.get_element(), Clock::default());
+        assert_eq!(
+            element_grid.get_temperature(pos),
+            ElementType::Stone.get_element().get_temperature()
+        );
+    }
+
+    #[test]
+    fn test_conduction_stays_stable_on_long_steps() {
+        let (hot, cold) = (1000.0, 300.0);
+        let center = JkVector { j: 4, k: 6 };
+        // A stone chunk at one temperature, but for a hot spot in the middle
+        let hot_spot = || {
+            let mut element_grid = ElementGrid::new_empty(get_chunk_coords());
+            for (j, k) in iproduct!(0..8, 0..12) {
+                let pos = JkVector { j, k };
+                element_grid.set(pos, ElementType::Stone.get_element(), Clock::default());
+                let temperature = if pos == center { hot } else { cold };
+                element_grid.set_temperature(
+                    pos,
+                    ThermodynamicTemperature(temperature),
+                    Clock::default(),
+                );
+            }
+            element_grid
+        };
+        let cell_width = get_chunk_coords().get_cell_width().0;
+        // A second is eighty times longer than a stable step
+        let thermal_diffusivity = 10.0 * cell_width * cell_width;
+        assert!(stable_conduction_step(thermal_diffusivity, cell_width) < 0.1);
+
+        for conduction_limit in [ConductionLimit::Substep, ConductionLimit::ClampDelta] {
+            let mut element_grid = hot_spot();
+            element_grid.set_heat_config(HeatConfig {
+                thermal_diffusivity,
+                conduction_limit,
+                ..Default::default()
+            });
+            let total = |element_grid: &ElementGrid| -> f32 {
+                iproduct!(0..8, 0..12)
+                    .map(|(j, k)| element_grid.get_temperature(JkVector { j, k }).0)
+                    .sum()
+            };
+            let start_total = total(&element_grid);
+            let mut last_center = hot;
+            for _ in 0..10 {
+                element_grid.conduct_heat(&[], Duration::from_secs(1));
+                // Nothing overshoots past where it started, and the center only ever cools
+                for (j, k) in iproduct!(0..8, 0..12) {
+                    let temperature = element_grid.get_temperature(JkVector { j, k }).0;
+                    assert!(temperature.is_finite());
+                    assert!((cold - 1e-3..=hot + 1e-3).contains(&temperature));
+                }
+                let center_temperature = element_grid.get_temperature(center).0;
+                assert!(center_temperature <= last_center, "{:?}", conduction_limit);
+                last_center = center_temperature;
+            }
+            assert!(last_center < hot);
+            assert!(element_grid.get_temperature(JkVector { j: 4, k: 7 }).0 > cold);
+            // Heat only moves around inside the chunk
+            assert!((total(&element_grid) - start_total).abs() < 1e-2 * start_total);
+        }
+    }
+}
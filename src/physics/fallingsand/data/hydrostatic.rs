@@ -0,0 +1,283 @@
+//! The layering a planet would settle into on its own, worked out ahead of time
+//!
+//! Filling a planet with a mix of elements and letting the simulation sort it out means every
+//! loose cell moves at once for the first few seconds. Starting from the settled profile instead
+//! leaves the simulation with almost nothing to do.
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use crate::physics::fallingsand::elements::element::{Density, ElementType, StateOfMatter};
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::IjkVector;
+use crate::physics::orbits::components::{Length, Mass};
+use crate::physics::orbits::nbody::G;
+use crate::physics::util::clock::Clock;
+
+use super::element_directory::ElementGridDir;
+
+/// One kind of element's shell in a [HydrostaticProfile]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HydrostaticBand {
+    /// What the shell is made of
+    pub kind: ElementType,
+    /// See [crate::physics::fallingsand::elements::element::Element::get_density]
+    pub density: Density,
+    /// How many cells of it there are
+    pub num_cells: usize,
+    /// The bottom of the ring its first cell is in
+    pub inner_radius: f32,
+    /// The top of the ring its last cell is in
+    pub outer_radius: f32,
+    /// The weight of everything above the shell pressing down on its inner radius, in pascals
+    /// Worked out the same way as [ElementGridDir::pressure_field]
+    pub base_pressure: f32,
+}
+
+/// How a mix of elements arranges itself once nothing is moving anymore
+/// Heavier elements sink below lighter ones, and where two are as dense as each other the solid
+/// sinks through the liquid, like sand through water. Each kind ends up in its own shell, filled
+/// ring by ring from the center out, and the pressure grows steadily down through them
+#[derive(Debug, Clone, PartialEq)]
+pub struct HydrostaticProfile {
+    /// From the center out
+    bands: Vec<HydrostaticBand>,
+}
+
+impl HydrostaticProfile {
+    /// Settles a composition, given as how many cells of each kind there are, into the directory's rings
+    /// Anything that doesn't fit is left out, and whatever is left over above the top band stays vacuum
+    /// Custom kinds are left out too, there is no default one to weigh
+    pub fn new(coords: &CoordinateDir, composition: &[(ElementType, usize)]) -> Self {
+        let mut sorted: Vec<(ElementType, Density, StateOfMatter, usize)> = composition
+            .iter()
+            .filter(|(_, num_cells)| *num_cells > 0)
+            .filter_map(|&(kind, num_cells)| {
+                let element = kind.try_get_element().ok()?;
+                Some((
+                    kind,
+                    element.get_density(),
+                    element.get_state_of_matter(),
+                    num_cells,
+                ))
+            })
+            .collect();
+        sorted.sort_by(|a, b| b.1 .0.total_cmp(&a.1 .0).then(b.2.cmp(&a.2)));
+
+        // Every ring from the center out, as its inner radius, cell height, cell width and number of cells
+        let mut rings: Vec<(f32, f32, Length, usize)> = Vec::new();
+        for layer in 0..coords.get_num_layers() {
+            let cell_height = coords.get_layer_cell_height(layer);
+            let start_radius = coords.get_layer_start_radius(layer);
+            for j in 0..coords.get_layer_num_concentric_circles(layer) {
+                rings.push((
+                    start_radius + j as f32 * cell_height,
+                    cell_height,
+                    coords.get_layer_cell_width(layer),
+                    coords.get_layer_num_radial_lines(layer),
+                ));
+            }
+        }
+        // The mass and column weight of each ring, built up as the bands fill them
+        let mut ring_mass = vec![Mass(0.0); rings.len()];
+        let mut ring_density = vec![0.0; rings.len()];
+        let mut bands = Vec::with_capacity(sorted.len());
+        let mut first_rings = Vec::with_capacity(sorted.len());
+        let (mut ring, mut filled) = (0, 0);
+        for (kind, density, _, num_cells) in sorted {
+            if ring >= rings.len() {
+                break;
+            }
+            let first_ring = ring;
+            let mut left = num_cells;
+            while left > 0 && ring < rings.len() {
+                let (_, _, cell_width, num_radial_lines) = rings[ring];
+                let here = left.min(num_radial_lines - filled);
+                ring_mass[ring] += Mass(density.mass(cell_width).0 * here as f32);
+                ring_density[ring] += density.0 * here as f32 / num_radial_lines as f32;
+                left -= here;
+                filled += here;
+                if filled == num_radial_lines {
+                    ring += 1;
+                    filled = 0;
+                }
+            }
+            let last_ring = if filled == 0 { ring - 1 } else { ring };
+            first_rings.push(first_ring);
+            bands.push(HydrostaticBand {
+                kind,
+                density,
+                num_cells: num_cells - left,
+                inner_radius: rings[first_ring].0,
+                outer_radius: rings[last_ring].0 + rings[last_ring].1,
+                base_pressure: 0.0,
+            });
+        }
+
+        // Then weigh it from the top down, the gravity at each ring coming from the mass below it
+        let mut enclosed = Vec::with_capacity(rings.len());
+        let mut total = Mass(0.0);
+        for mass in &ring_mass {
+            enclosed.push(total);
+            total += *mass;
+        }
+        let mut pressure_under = vec![0.0; rings.len()];
+        let mut pressure = 0.0;
+        for (idx, &(inner_radius, cell_height, _, _)) in rings.iter().enumerate().rev() {
+            let mid_radius = inner_radius + cell_height / 2.0;
+            let gravity =
+                G * (enclosed[idx].0 + ring_mass[idx].0 / 2.0) / (mid_radius * mid_radius);
+            pressure += ring_density[idx] * cell_height * gravity;
+            pressure_under[idx] = pressure;
+        }
+        for (band, first_ring) in bands.iter_mut().zip(first_rings) {
+            band.base_pressure = pressure_under[first_ring];
+        }
+        Self { bands }
+    }
+
+    /// The shells from the center out
+    pub fn bands(&self) -> &[HydrostaticBand] {
+        &self.bands
+    }
+
+    /// What the profile has at a radius, vacuum past the top band
+    /// Where two bands share a ring this is the outer one, the inner one only fills part of it
+    pub fn kind_at(&self, radius: f32) -> ElementType {
+        self.bands
+            .iter()
+            .rev()
+            .find(|band| band.inner_radius <= radius && radius < band.outer_radius)
+            .map_or(ElementType::Vacuum, |band| band.kind)
+    }
+
+    /// Fills the directory with the profile, ring by ring from the center out, over whatever was there
+    /// Cells past the last band are left alone
+    pub fn fill(&self, element_grid_dir: &mut ElementGridDir) {
+        let coords = element_grid_dir.get_coordinate_dir().clone();
+        let mut cells = (0..coords.get_num_layers()).flat_map(|layer| {
+            let num_radial_lines = coords.get_layer_num_radial_lines(layer);
+            (0..coords.get_layer_num_concentric_circles(layer))
+                .flat_map(move |j| (0..num_radial_lines).map(move |k| IjkVector::new(layer, j, k)))
+        });
+        for band in &self.bands {
+            for cell in cells.by_ref().take(band.num_cells) {
+                // Custom kinds never make it into a band, see Self::new
+                element_grid_dir.set_element(cell, band.kind.get_element(), Clock::default());
+            }
+        }
+        element_grid_dir.recalculate_everything();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::orbits::components::Length;
+
+    fn get_coordinate_dir() -> CoordinateDir {
+        CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(5)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build()
+    }
+
+    /// How many elements moved over a few full passes
+    fn moved_in_first_passes(element_grid_dir: &mut ElementGridDir) -> usize {
+        let mut clock = Clock::default();
+        (0..3)
+            .map(|_| {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+                element_grid_dir.motion_stats().moved
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_starting_at_the_profile_barely_moves() {
+        let coords = get_coordinate_dir();
+        let num_cells = |layer: usize| {
+            coords.get_layer_num_concentric_circles(layer)
+                * coords.get_layer_num_radial_lines(layer)
+        };
+        let num_stone = num_cells(0) + num_cells(1) + num_cells(2);
+        let ring = coords.get_layer_num_radial_lines(3);
+        // The water is listed first, but the solids sink under it
+        let composition = [
+            (ElementType::Water, 8 * ring),
+            (ElementType::Stone, num_stone),
+            (ElementType::Sand, 4 * ring),
+        ];
+        let profile = HydrostaticProfile::new(&coords, &composition);
+        let kinds: Vec<ElementType> = profile.bands().iter().map(|band| band.kind).collect();
+        assert_eq!(
+            kinds,
+            [ElementType::Stone, ElementType::Sand, ElementType::Water]
+        );
+        assert!(profile
+            .bands()
+            .windows(2)
+            .all(|pair| pair[0].base_pressure > pair[1].base_pressure
+                && pair[0].outer_radius == pair[1].inner_radius));
+        assert!(profile.bands()[2].base_pressure > 0.0);
+        assert_eq!(
+            profile.bands()[2].outer_radius,
+            coords.get_layer_end_radius(3)
+        );
+        assert_eq!(
+            profile.kind_at(coords.get_layer_end_radius(3) + 1.0),
+            ElementType::Vacuum
+        );
+
+        let mut settled = ElementGridDir::new_empty(coords.clone());
+        profile.fill(&mut settled);
+        for (kind, num_cells) in composition {
+            assert_eq!(settled.cells_of_kind(kind).count(), num_cells);
+        }
+
+        // The same cells, but mixed evenly through the whole volume
+        let mut mixed_kinds: Vec<ElementType> = composition
+            .iter()
+            .flat_map(|&(kind, num_cells)| std::iter::repeat_n(kind, num_cells))
+            .collect();
+        mixed_kinds.shuffle(&mut StdRng::seed_from_u64(0));
+        let mut mixed = ElementGridDir::new_empty(coords.clone());
+        let mut mixed_kinds = mixed_kinds.into_iter();
+        'fill: for layer in 0..coords.get_num_layers() {
+            for j in 0..coords.get_layer_num_concentric_circles(layer) {
+                for k in 0..coords.get_layer_num_radial_lines(layer) {
+                    let Some(kind) = mixed_kinds.next() else {
+                        break 'fill;
+                    };
+                    mixed.set_element(
+                        IjkVector::new(layer, j, k),
+                        kind.get_element(),
+                        Clock::default(),
+                    );
+                }
+            }
+        }
+        mixed.recalculate_everything();
+
+        let settled_moved = moved_in_first_passes(&mut settled);
+        let mixed_moved = moved_in_first_passes(&mut mixed);
+        assert!(mixed_moved > 0);
+        assert!(
+            settled_moved * 10 < mixed_moved,
+            "Settled {} moved, mixed {}",
+            settled_moved,
+            mixed_moved
+        );
+    }
+}
@@ -0,0 +1,876 @@
+//! Recording a session of edits to a directory so it can be played back exactly
+//!
+//! A [Recording] is a [save](super::save::save) of the directory, its temperatures, seed, pass and
+//! clock when recording started, then every input from outside the simulation in the order it
+//! happened, each stamped with the clock it was made at, along with every step in between.
+//! Processing only draws its randomness from the chunks' random streams, which are written down
+//! as they were, so playing the same inputs back onto the same start ends up in the same place,
+//! down to [ElementGridDir::state_hash].
+//! Attach one to a bug report and whatever went wrong happens again on any machine.
+//!
+//! Elements are written down by name and made fresh from an [ElementRegistry] when played back,
+//! so anything an element keeps to itself, like how far a fire has burned down, starts over in the
+//! replay. The sub steps and passes per step, static chunks, layer cadence, heat config, sunlight,
+//! spin, coriolis bias and gravity model are written down with the start, and the sunlight again
+//! whenever it moves. Anything else a save doesn't keep has to be set again with [Recording::replay_with].
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::core::FrameCount;
+use bevy::math::Vec2;
+use bevy::time::Time;
+
+use crate::physics::fallingsand::elements::registry::{ElementRegistry, UnknownElementError};
+use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector};
+use crate::physics::orbits::components::{AngularVelocity, ThermodynamicTemperature, Velocity};
+use crate::physics::util::clock::Clock;
+use crate::physics::util::vectors::RelXyPoint;
+
+use super::ejecta::{accrete, eject, BuriedCellError, Ejecta};
+use super::element_directory::{ElementGridDir, GravityModel};
+use super::element_grid::{ConductionLimit, HeatConfig};
+use super::save::{load, save};
+
+/// Marks the start of a recording, so loading something else fails fast
+const MAGIC: &[u8; 4] = b"OREC";
+
+/// Bumped whenever the layout of a recording changes
+const VERSION: u32 = 5;
+
+/// Something that happened to the directory while it was being recorded
+#[derive(Debug, Clone)]
+pub enum RecordedEvent {
+    /// A cell was painted over, like with the brush
+    SetElement {
+        /// The cell that was painted
+        cell: IjkVector,
+        /// The name of what it was painted with
+        name: String,
+    },
+    /// Something landed on the surface, see [accrete]
+    Impact {
+        /// The name of what landed
+        name: String,
+        /// Where it hit, relative to the center of the celestial
+        position: RelXyPoint,
+    },
+    /// A cell was thrown off, see [eject]
+    Eject {
+        /// The cell that was thrown
+        cell: IjkVector,
+        /// How fast, relative to the celestial
+        velocity: Velocity,
+    },
+    /// One pass, see [ElementGridDir::step]
+    Step(Duration),
+    /// Every sub step of full cycles, see [ElementGridDir::step_full]
+    StepFull(Duration),
+    /// The light moved, see [ElementGridDir::set_sunlight]
+    SetSunlight(Option<Vec2>),
+}
+
+impl RecordedEvent {
+    /// Does to the directory what was done when it was recorded, at the directory's clock
+    /// Elements are made by name with `registry`, one that isn't registered is an error
+    fn apply(
+        &self,
+        element_grid_dir: &mut ElementGridDir,
+        registry: &ElementRegistry,
+    ) -> Result<(), UnknownElementError> {
+        let current_time = element_grid_dir.clock();
+        match *self {
+            RecordedEvent::SetElement { cell, ref name } => {
+                element_grid_dir.set_element(cell, registry.create(name)?, current_time);
+            }
+            RecordedEvent::Impact { ref name, position } => {
+                accrete(
+                    element_grid_dir,
+                    registry.create(name)?,
+                    position,
+                    current_time,
+                );
+            }
+            RecordedEvent::Eject { cell, velocity } => {
+                // A buried cell failed the same way when it was recorded
+                eject(element_grid_dir, cell, velocity, current_time).ok();
+            }
+            RecordedEvent::Step(delta) => element_grid_dir.step(delta),
+            RecordedEvent::StepFull(delta) => element_grid_dir.step_full(delta),
+            RecordedEvent::SetSunlight(sunlight) => element_grid_dir.set_sunlight(sunlight),
+        }
+        Ok(())
+    }
+
+    /// A tag byte then the fields, little endian
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        match *self {
+            RecordedEvent::SetElement { cell, ref name } => {
+                out.write_all(&[0])?;
+                write_cell(out, cell)?;
+                write_name(out, name)
+            }
+            RecordedEvent::Impact { ref name, position } => {
+                out.write_all(&[1])?;
+                write_name(out, name)?;
+                write_vec2(out, position.0)
+            }
+            RecordedEvent::Eject { cell, velocity } => {
+                out.write_all(&[2])?;
+                write_cell(out, cell)?;
+                write_vec2(out, velocity.0)
+            }
+            RecordedEvent::Step(delta) => {
+                out.write_all(&[3])?;
+                write_duration(out, delta)
+            }
+            RecordedEvent::StepFull(delta) => {
+                out.write_all(&[4])?;
+                write_duration(out, delta)
+            }
+            RecordedEvent::SetSunlight(sunlight) => {
+                out.write_all(&[5])?;
+                write_sunlight(out, sunlight)
+            }
+        }
+    }
+
+    /// Reads an event written by [Self::write_to]
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let mut tag = [0; 1];
+        input.read_exact(&mut tag)?;
+        Ok(match tag[0] {
+            0 => RecordedEvent::SetElement {
+                cell: read_cell(input)?,
+                name: read_name(input)?,
+            },
+            1 => RecordedEvent::Impact {
+                name: read_name(input)?,
+                position: RelXyPoint(read_vec2(input)?),
+            },
+            2 => RecordedEvent::Eject {
+                cell: read_cell(input)?,
+                velocity: Velocity(read_vec2(input)?),
+            },
+            3 => RecordedEvent::Step(read_duration(input)?),
+            4 => RecordedEvent::StepFull(read_duration(input)?),
+            5 => RecordedEvent::SetSunlight(read_sunlight(input)?),
+            tag => return Err(invalid_data(format!("Unknown event {}", tag))),
+        })
+    }
+}
+
+/// The directory's settings that a save doesn't keep, as they were when recording started
+struct Settings {
+    /// See [ElementGridDir::set_sub_steps]
+    sub_steps: usize,
+    /// See [ElementGridDir::set_chunk_static], sorted so the same directory writes the same bytes
+    static_chunks: Vec<ChunkIjkVector>,
+    /// See [ElementGridDir::set_layer_cadence], one per layer
+    layer_cadence: Vec<usize>,
+    /// See [ElementGridDir::set_heat_config]
+    heat_config: HeatConfig,
+    /// See [ElementGridDir::set_sunlight]
+    sunlight: Option<Vec2>,
+    /// See [ElementGridDir::set_spin]
+    spin: AngularVelocity,
+    /// See [ElementGridDir::set_coriolis_bias]
+    coriolis_bias: f32,
+    /// See [ElementGridDir::set_gravity_model]
+    gravity_model: GravityModel,
+}
+
+impl Settings {
+    /// Reads the settings off the directory
+    fn of(element_grid_dir: &ElementGridDir) -> Self {
+        let mut static_chunks: Vec<_> = element_grid_dir.get_static_chunks().collect();
+        static_chunks.sort_by_key(|coord| (coord.i, coord.j, coord.k));
+        Self {
+            sub_steps: element_grid_dir.get_sub_steps(),
+            static_chunks,
+            layer_cadence: (0..element_grid_dir.get_coordinate_dir().get_num_layers())
+                .map(|layer| element_grid_dir.get_layer_cadence(layer))
+                .collect(),
+            heat_config: element_grid_dir.get_heat_config(),
+            sunlight: element_grid_dir.get_sunlight(),
+            spin: element_grid_dir.get_spin(),
+            coriolis_bias: element_grid_dir.get_coriolis_bias(),
+            gravity_model: element_grid_dir.get_gravity_model(),
+        }
+    }
+
+    /// Puts the settings back on a freshly loaded directory
+    fn apply(&self, element_grid_dir: &mut ElementGridDir) {
+        element_grid_dir.set_sub_steps(self.sub_steps);
+        for &coord in &self.static_chunks {
+            element_grid_dir.set_chunk_static(coord, true);
+        }
+        for (layer, &every) in self.layer_cadence.iter().enumerate() {
+            element_grid_dir.set_layer_cadence(layer, every);
+        }
+        element_grid_dir.set_heat_config(self.heat_config);
+        element_grid_dir.set_sunlight(self.sunlight);
+        element_grid_dir.set_spin(self.spin);
+        element_grid_dir.set_coriolis_bias(self.coriolis_bias);
+        element_grid_dir.set_gravity_model(self.gravity_model);
+    }
+
+    /// Counts as u64s, the heat config, spin and bias as f32s, and the conduction limit and
+    /// gravity model in a byte each
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&(self.sub_steps as u64).to_le_bytes())?;
+        out.write_all(&(self.static_chunks.len() as u64).to_le_bytes())?;
+        for coord in &self.static_chunks {
+            write_cell(out, IjkVector::new(coord.i, coord.j, coord.k))?;
+        }
+        out.write_all(&(self.layer_cadence.len() as u64).to_le_bytes())?;
+        for &every in &self.layer_cadence {
+            out.write_all(&(every as u64).to_le_bytes())?;
+        }
+        let heat_config = &self.heat_config;
+        for value in [
+            heat_config.ambient_temperature.0,
+            heat_config.radiative_cooling_rate,
+            heat_config.solar_heating_rate,
+            heat_config.thermal_expansion,
+            heat_config.thermal_diffusivity,
+        ] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&[heat_config.conduction_limit as u8])?;
+        write_sunlight(out, self.sunlight)?;
+        out.write_all(&self.spin.0.to_le_bytes())?;
+        out.write_all(&self.coriolis_bias.to_le_bytes())?;
+        out.write_all(&[self.gravity_model as u8])
+    }
+
+    /// Reads settings written by [Self::write_to]
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let sub_steps = read_u64(input)? as usize;
+        let static_chunks = (0..read_u64(input)?)
+            .map(|_| {
+                let cell = read_cell(input)?;
+                Ok(ChunkIjkVector::new(cell.i, cell.j, cell.k))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let layer_cadence = (0..read_u64(input)?)
+            .map(|_| Ok(read_u64(input)? as usize))
+            .collect::<io::Result<Vec<_>>>()?;
+        let heat_config = HeatConfig {
+            ambient_temperature: ThermodynamicTemperature(read_f32(input)?),
+            radiative_cooling_rate: read_f32(input)?,
+            solar_heating_rate: read_f32(input)?,
+            thermal_expansion: read_f32(input)?,
+            thermal_diffusivity: read_f32(input)?,
+            conduction_limit: match read_u8(input)? {
+                0 => ConductionLimit::Substep,
+                1 => ConductionLimit::ClampDelta,
+                limit => return Err(invalid_data(format!("Unknown conduction limit {}", limit))),
+            },
+        };
+        Ok(Self {
+            sub_steps,
+            static_chunks,
+            layer_cadence,
+            heat_config,
+            sunlight: read_sunlight(input)?,
+            spin: AngularVelocity(read_f32(input)?),
+            coriolis_bias: read_f32(input)?,
+            gravity_model: match read_u8(input)? {
+                0 => GravityModel::PointMass,
+                1 => GravityModel::Shell,
+                model => return Err(invalid_data(format!("Unknown gravity model {}", model))),
+            },
+        })
+    }
+}
+
+/// A directory as it was when recording started and everything done to it after, see [Recorder]
+pub struct Recording {
+    /// The directory from [save]
+    start: Vec<u8>,
+    /// From [ElementGridDir::serialize_heat]
+    start_heat: Vec<u8>,
+    /// From [ElementGridDir::serialize_rngs]
+    start_rngs: Vec<u8>,
+    /// Everything else the replay needs that a save doesn't keep
+    start_settings: Settings,
+    /// How many passes had run, always a whole number of cycles
+    start_process_count: usize,
+    /// The simulation time recording started at
+    start_clock: Clock,
+    /// Each event along with the clock it happened at
+    events: Vec<(Clock, RecordedEvent)>,
+    /// [ElementGridDir::state_hash] when recording finished
+    final_state_hash: u64,
+}
+
+impl Recording {
+    /// Everything done to the directory, in order, with the clock it was done at
+    pub fn events(&self) -> &[(Clock, RecordedEvent)] {
+        &self.events
+    }
+
+    /// What [ElementGridDir::state_hash] was when recording finished, a replay should match it
+    pub fn final_state_hash(&self) -> u64 {
+        self.final_state_hash
+    }
+
+    /// Plays the recording back from the start, making elements with `registry`
+    pub fn replay(&self, registry: &ElementRegistry) -> io::Result<ElementGridDir> {
+        self.replay_with(registry, |_| {})
+    }
+
+    /// Plays the recording back, letting `configure` put back the settings a save doesn't keep
+    /// before the first event
+    pub fn replay_with(
+        &self,
+        registry: &ElementRegistry,
+        configure: impl FnOnce(&mut ElementGridDir),
+    ) -> io::Result<ElementGridDir> {
+        let mut element_grid_dir = load(&mut self.start.as_slice(), registry)?;
+        element_grid_dir
+            .load_heat(&self.start_heat)
+            .map_err(|err| invalid_data(err.to_string()))?;
+        element_grid_dir
+            .load_rngs(&self.start_rngs)
+            .map_err(|err| invalid_data(err.to_string()))?;
+        element_grid_dir.set_process_count(self.start_process_count);
+        element_grid_dir.set_clock(self.start_clock);
+        self.start_settings.apply(&mut element_grid_dir);
+        configure(&mut element_grid_dir);
+        for (at, event) in &self.events {
+            element_grid_dir.set_clock(*at);
+            event
+                .apply(&mut element_grid_dir, registry)
+                .map_err(|err| invalid_data(err.to_string()))?;
+        }
+        Ok(element_grid_dir)
+    }
+
+    /// Writes the whole recording, little endian
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        for bytes in [&self.start, &self.start_heat, &self.start_rngs] {
+            out.write_all(&(bytes.len() as u64).to_le_bytes())?;
+            out.write_all(bytes)?;
+        }
+        self.start_settings.write_to(out)?;
+        out.write_all(&(self.start_process_count as u64).to_le_bytes())?;
+        write_clock(out, self.start_clock)?;
+        out.write_all(&(self.events.len() as u64).to_le_bytes())?;
+        for (at, event) in &self.events {
+            write_clock(out, *at)?;
+            event.write_to(out)?;
+        }
+        out.write_all(&self.final_state_hash.to_le_bytes())
+    }
+
+    /// Writes the recording to a file, replacing it if it exists
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()
+    }
+
+    /// Reads a recording written with [Self::write_to_file]
+    pub fn read_from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a recording written by [Self::write_to]
+    pub fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("Not a recording".to_string()));
+        }
+        let mut version = [0; 4];
+        input.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "Recording version {} is not supported, expected {}",
+                version, VERSION
+            )));
+        }
+        let mut read_bytes = || -> io::Result<Vec<u8>> {
+            let mut bytes = vec![0; read_u64(input)? as usize];
+            input.read_exact(&mut bytes)?;
+            Ok(bytes)
+        };
+        let start = read_bytes()?;
+        let start_heat = read_bytes()?;
+        let start_rngs = read_bytes()?;
+        let start_settings = Settings::read_from(input)?;
+        let start_process_count = read_u64(input)? as usize;
+        let start_clock = read_clock(input)?;
+        let events = (0..read_u64(input)?)
+            .map(|_| Ok((read_clock(input)?, RecordedEvent::read_from(input)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+        let final_state_hash = read_u64(input)?;
+        Ok(Self {
+            start,
+            start_heat,
+            start_rngs,
+            start_settings,
+            start_process_count,
+            start_clock,
+            events,
+            final_state_hash,
+        })
+    }
+}
+
+/// Recording can only start between full cycles of [ElementGridDir::process], as a save doesn't
+/// know which chunks have already been processed. Holds how many passes had run
+#[derive(Debug, Clone, Copy)]
+pub struct MidCycleError(pub usize);
+impl fmt::Display for MidCycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Recording has to start between full cycles, {} passes have run",
+            self.0
+        )
+    }
+}
+
+/// Makes changes to a directory and writes them down, so they can be played back with [Recording::replay]
+/// Everything from outside the simulation has to go through here while recording, anything that doesn't
+/// won't be in the replay
+pub struct Recorder {
+    /// Everything so far, the final hash is filled in by [Self::finish]
+    recording: Recording,
+}
+
+impl Recorder {
+    /// Starts recording from the directory as it is now, carrying on from wherever each chunk's
+    /// random stream is. Everything derived is recalculated, like loading the save will
+    /// Partway through a cycle of [ElementGridDir::process] nothing is changed and it is an error,
+    /// try again once [ElementGridDir::is_between_cycles]
+    pub fn start(element_grid_dir: &mut ElementGridDir) -> Result<Self, MidCycleError> {
+        if !element_grid_dir.is_between_cycles() {
+            return Err(MidCycleError(element_grid_dir.get_process_count()));
+        }
+        element_grid_dir.recalculate_everything();
+        let mut start = Vec::new();
+        save(element_grid_dir, &mut start).expect("Writing to a Vec can't fail");
+        Ok(Self {
+            recording: Recording {
+                start,
+                start_heat: element_grid_dir.serialize_heat(),
+                start_rngs: element_grid_dir.serialize_rngs(),
+                start_settings: Settings::of(element_grid_dir),
+                start_process_count: element_grid_dir.get_process_count(),
+                start_clock: element_grid_dir.clock(),
+                events: Vec::new(),
+                final_state_hash: 0,
+            },
+        })
+    }
+
+    /// Writes the event down and does it
+    /// An element that isn't in `registry` is an error, and then nothing is written down
+    fn record(
+        &mut self,
+        element_grid_dir: &mut ElementGridDir,
+        registry: &ElementRegistry,
+        event: RecordedEvent,
+    ) -> Result<(), UnknownElementError> {
+        let at = element_grid_dir.clock();
+        event.apply(element_grid_dir, registry)?;
+        self.recording.events.push((at, event));
+        Ok(())
+    }
+
+    /// [ElementGridDir::set_element] with a fresh element made by name, at the directory's clock
+    pub fn set_element(
+        &mut self,
+        element_grid_dir: &mut ElementGridDir,
+        registry: &ElementRegistry,
+        cell: IjkVector,
+        name: &str,
+    ) -> Result<(), UnknownElementError> {
+        self.record(
+            element_grid_dir,
+            registry,
+            RecordedEvent::SetElement {
+                cell,
+                name: name.to_string(),
+            },
+        )
+    }
+
+    /// [accrete] with a fresh element made by name, returning the cell it landed in
+    pub fn impact(
+        &mut self,
+        element_grid_dir: &mut ElementGridDir,
+        registry: &ElementRegistry,
+        name: &str,
+        position: RelXyPoint,
+    ) -> Result<Option<IjkVector>, UnknownElementError> {
+        let element = registry.create(name)?;
+        self.recording.events.push((
+            element_grid_dir.clock(),
+            RecordedEvent::Impact {
+                name: name.to_string(),
+                position,
+            },
+        ));
+        Ok(accrete(
+            element_grid_dir,
+            element,
+            position,
+            element_grid_dir.clock(),
+        ))
+    }
+
+    /// [eject], returning what left
+    pub fn eject(
+        &mut self,
+        element_grid_dir: &mut ElementGridDir,
+        cell: IjkVector,
+        velocity: Velocity,
+    ) -> Result<Option<Ejecta>, BuriedCellError> {
+        self.recording.events.push((
+            element_grid_dir.clock(),
+            RecordedEvent::Eject { cell, velocity },
+        ));
+        eject(element_grid_dir, cell, velocity, element_grid_dir.clock())
+    }
+
+    /// [ElementGridDir::step]
+    pub fn step(&mut self, element_grid_dir: &mut ElementGridDir, delta: Duration) {
+        self.recording
+            .events
+            .push((element_grid_dir.clock(), RecordedEvent::Step(delta)));
+        element_grid_dir.step(delta);
+    }
+
+    /// [ElementGridDir::step_full]
+    pub fn step_full(&mut self, element_grid_dir: &mut ElementGridDir, delta: Duration) {
+        self.recording
+            .events
+            .push((element_grid_dir.clock(), RecordedEvent::StepFull(delta)));
+        element_grid_dir.step_full(delta);
+    }
+
+    /// [ElementGridDir::set_sunlight]
+    pub fn set_sunlight(&mut self, element_grid_dir: &mut ElementGridDir, sunlight: Option<Vec2>) {
+        // The systems set it every frame, but only a change needs writing down
+        if sunlight.map(Vec2::normalize_or_zero) == element_grid_dir.get_sunlight() {
+            return;
+        }
+        self.recording.events.push((
+            element_grid_dir.clock(),
+            RecordedEvent::SetSunlight(sunlight),
+        ));
+        element_grid_dir.set_sunlight(sunlight);
+    }
+
+    /// Stops recording, noting where the directory ended up
+    pub fn finish(mut self, element_grid_dir: &ElementGridDir) -> Recording {
+        self.recording.final_state_hash = element_grid_dir.state_hash();
+        self.recording
+    }
+}
+
+/// An error for a recording that doesn't hold what it should
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a little endian u64
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reads a single byte
+fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    let mut byte = [0; 1];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Reads a little endian f32
+fn read_f32(input: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+/// A cell as three u64s
+fn write_cell(out: &mut impl Write, cell: IjkVector) -> io::Result<()> {
+    for value in [cell.i, cell.j, cell.k] {
+        out.write_all(&(value as u64).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads a cell written by [write_cell]
+fn read_cell(input: &mut impl Read) -> io::Result<IjkVector> {
+    Ok(IjkVector::new(
+        read_u64(input)? as usize,
+        read_u64(input)? as usize,
+        read_u64(input)? as usize,
+    ))
+}
+
+/// An element name, as its length in a u16 then its bytes
+fn write_name(out: &mut impl Write, name: &str) -> io::Result<()> {
+    out.write_all(&(name.len() as u16).to_le_bytes())?;
+    out.write_all(name.as_bytes())
+}
+
+/// Reads a name written by [write_name]
+fn read_name(input: &mut impl Read) -> io::Result<String> {
+    let mut len = [0; 2];
+    input.read_exact(&mut len)?;
+    let mut name = vec![0; u16::from_le_bytes(len) as usize];
+    input.read_exact(&mut name)?;
+    String::from_utf8(name).map_err(|err| invalid_data(err.to_string()))
+}
+
+/// x then y as f32s
+fn write_vec2(out: &mut impl Write, vec: Vec2) -> io::Result<()> {
+    out.write_all(&vec.x.to_le_bytes())?;
+    out.write_all(&vec.y.to_le_bytes())
+}
+
+/// Reads a vector written by [write_vec2]
+fn read_vec2(input: &mut impl Read) -> io::Result<Vec2> {
+    Ok(Vec2::new(read_f32(input)?, read_f32(input)?))
+}
+
+/// A byte for whether there is any light, then its direction
+fn write_sunlight(out: &mut impl Write, sunlight: Option<Vec2>) -> io::Result<()> {
+    out.write_all(&[sunlight.is_some() as u8])?;
+    write_vec2(out, sunlight.unwrap_or(Vec2::ZERO))
+}
+
+/// Reads a light written by [write_sunlight]
+fn read_sunlight(input: &mut impl Read) -> io::Result<Option<Vec2>> {
+    let is_lit = read_u8(input)? != 0;
+    let direction = read_vec2(input)?;
+    Ok(is_lit.then_some(direction))
+}
+
+/// Whole nanoseconds as a u64, which is over five hundred years
+fn write_duration(out: &mut impl Write, duration: Duration) -> io::Result<()> {
+    out.write_all(&(duration.as_nanos() as u64).to_le_bytes())
+}
+
+/// Reads a duration written by [write_duration]
+fn read_duration(input: &mut impl Read) -> io::Result<Duration> {
+    Ok(Duration::from_nanos(read_u64(input)?))
+}
+
+/// The time so far, the last delta, then the frame
+fn write_clock(out: &mut impl Write, clock: Clock) -> io::Result<()> {
+    write_duration(out, clock.get_current_time())?;
+    write_duration(out, clock.get_last_delta())?;
+    out.write_all(&clock.get_current_frame().to_le_bytes())
+}
+
+/// Reads a clock written by [write_clock]
+/// The time is advanced in two steps so the last delta comes out the same
+fn read_clock(input: &mut impl Read) -> io::Result<Clock> {
+    let elapsed = read_duration(input)?;
+    let delta = read_duration(input)?;
+    let mut frame = [0; 4];
+    input.read_exact(&mut frame)?;
+    let mut time = Time::default();
+    time.advance_by(elapsed.saturating_sub(delta));
+    time.advance_by(delta);
+    Ok(Clock::new(time, FrameCount(u32::from_le_bytes(frame))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::fallingsand::elements::element::ElementType;
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::orbits::components::Length;
+
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(7)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+        element_grid_dir.set_seed(11);
+        element_grid_dir.fill_settled(&*ElementType::Stone.get_element(), 10.0);
+        element_grid_dir.recalculate_everything();
+        element_grid_dir
+    }
+
+    #[test]
+    fn test_replay_ends_in_the_same_state() {
+        let mut element_grid_dir = get_element_grid_dir();
+        // Already some way into the simulation
+        element_grid_dir.step_full(Duration::from_millis(16));
+        let start_hash = element_grid_dir.state_hash();
+
+        let registry = ElementRegistry::default();
+        let mut recorder = Recorder::start(&mut element_grid_dir).unwrap();
+        for k in 0..20 {
+            recorder
+                .set_element(
+                    &mut element_grid_dir,
+                    &registry,
+                    IjkVector::new(3, 6, k),
+                    "Sand",
+                )
+                .unwrap();
+        }
+        // Nothing is done or written down for an element that isn't registered
+        assert!(recorder
+            .set_element(
+                &mut element_grid_dir,
+                &registry,
+                IjkVector::new(3, 6, 0),
+                "Unobtainium",
+            )
+            .is_err());
+        recorder.step(&mut element_grid_dir, Duration::from_millis(16));
+        recorder.step(&mut element_grid_dir, Duration::from_millis(16));
+        for k in 20..30 {
+            recorder
+                .set_element(
+                    &mut element_grid_dir,
+                    &registry,
+                    IjkVector::new(3, 8, k),
+                    "Water",
+                )
+                .unwrap();
+        }
+        let landed = recorder
+            .impact(
+                &mut element_grid_dir,
+                &registry,
+                "Sand",
+                RelXyPoint(Vec2::new(0.0, 20.0)),
+            )
+            .unwrap();
+        assert!(landed.is_some());
+        for _ in 0..3 {
+            recorder.step_full(&mut element_grid_dir, Duration::from_millis(16));
+        }
+        let recording = recorder.finish(&element_grid_dir);
+        assert_ne!(recording.final_state_hash(), start_hash);
+
+        assert_eq!(recording.events().len(), 36);
+        let replayed = recording.replay(&registry).unwrap();
+        assert_eq!(replayed.state_hash(), recording.final_state_hash());
+
+        // And the same after going through bytes, like attached to a bug report
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes).unwrap();
+        let read = Recording::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.events().len(), recording.events().len());
+        let mut reread = Vec::new();
+        read.write_to(&mut reread).unwrap();
+        assert_eq!(reread, bytes);
+        let replayed = read.replay(&registry).unwrap();
+        assert_eq!(replayed.state_hash(), recording.final_state_hash());
+    }
+
+    #[test]
+    fn test_replay_keeps_the_settings_and_random_streams() {
+        let mut element_grid_dir = get_element_grid_dir();
+        element_grid_dir.set_sub_steps(2);
+        element_grid_dir.set_chunk_static(ChunkIjkVector::new(0, 0, 0), true);
+        element_grid_dir.set_layer_cadence(1, 3);
+        element_grid_dir.set_heat_config(HeatConfig {
+            radiative_cooling_rate: 0.1,
+            solar_heating_rate: 50.0,
+            thermal_diffusivity: 0.5,
+            conduction_limit: ConductionLimit::ClampDelta,
+            ..Default::default()
+        });
+        element_grid_dir.set_sunlight(Some(Vec2::new(0.3, 1.0)));
+        element_grid_dir.set_spin(AngularVelocity(0.5));
+        element_grid_dir.set_coriolis(AngularVelocity(0.5), 0.4);
+        element_grid_dir.set_gravity_model(GravityModel::Shell);
+        // The random streams have moved on from the seed, and stay where they are
+        element_grid_dir.step_full(Duration::from_millis(16));
+        let rngs = element_grid_dir.serialize_rngs();
+
+        let registry = ElementRegistry::default();
+        let mut recorder = Recorder::start(&mut element_grid_dir).unwrap();
+        assert_eq!(element_grid_dir.serialize_rngs(), rngs);
+        for k in 0..10 {
+            recorder
+                .set_element(
+                    &mut element_grid_dir,
+                    &registry,
+                    IjkVector::new(3, 6, k),
+                    "Sand",
+                )
+                .unwrap();
+        }
+        for step in 0..9 {
+            // Set every step, like the systems do, but it only moves every other one
+            recorder.set_sunlight(
+                &mut element_grid_dir,
+                Some(Vec2::from_angle((step / 2) as f32 * 0.3)),
+            );
+            recorder.step(&mut element_grid_dir, Duration::from_millis(16));
+        }
+        recorder.set_sunlight(&mut element_grid_dir, None);
+        recorder.step_full(&mut element_grid_dir, Duration::from_millis(16));
+        let recording = recorder.finish(&element_grid_dir);
+        let moves = recording
+            .events()
+            .iter()
+            .filter(|(_, event)| matches!(event, RecordedEvent::SetSunlight(_)))
+            .count();
+        assert_eq!(moves, 6);
+
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes).unwrap();
+        let read = Recording::read_from(&mut bytes.as_slice()).unwrap();
+        let replayed = read.replay(&registry).unwrap();
+        assert_eq!(replayed.state_hash(), recording.final_state_hash());
+        assert_eq!(replayed.get_sub_steps(), 2);
+        assert!(replayed.is_chunk_static(ChunkIjkVector::new(0, 0, 0)));
+        assert_eq!(replayed.get_layer_cadence(1), 3);
+        assert_eq!(
+            replayed.get_heat_config(),
+            element_grid_dir.get_heat_config()
+        );
+        assert_eq!(replayed.get_sunlight(), None);
+        assert_eq!(replayed.get_spin().0, 0.5);
+        assert_eq!(replayed.get_coriolis_bias(), 0.2);
+        assert_eq!(replayed.get_gravity_model(), GravityModel::Shell);
+        assert_eq!(replayed.serialize_rngs(), element_grid_dir.serialize_rngs());
+    }
+
+    #[test]
+    fn test_cant_start_partway_through_a_cycle() {
+        let mut element_grid_dir = get_element_grid_dir();
+        element_grid_dir.step(Duration::from_millis(16));
+        let hash = element_grid_dir.state_hash();
+        assert!(Recorder::start(&mut element_grid_dir).is_err());
+        assert_eq!(element_grid_dir.state_hash(), hash);
+
+        // Once the cycle is done it can
+        for _ in 1..9 {
+            element_grid_dir.step(Duration::from_millis(16));
+        }
+        assert!(Recorder::start(&mut element_grid_dir).is_ok());
+    }
+}
@@ -0,0 +1,248 @@
+//! Saving an [ElementGridDir] to bytes and loading it back
+//!
+//! A save is the [CoordinateDirBuilder] the directory was laid out with, the seed,
+//! the names of every element in it, then every cell layer by layer as an index into those names,
+//! all little endian. Elements are made again by name from an [ElementRegistry], so custom elements
+//! load as long as they are registered. Only which element is in each cell is kept, so everything
+//! else about a cell, like its temperature, starts fresh from its element when loaded.
+//! The temperatures can be kept alongside with [ElementGridDir::serialize_heat].
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::physics::fallingsand::elements::element::ElementType;
+use crate::physics::fallingsand::elements::registry::ElementRegistry;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+use crate::physics::fallingsand::util::vectors::IjkVector;
+use crate::physics::util::clock::Clock;
+
+use super::element_directory::ElementGridDir;
+
+/// Marks the start of a save, so loading something else fails fast
+const MAGIC: &[u8; 4] = b"OSND";
+
+/// Bumped whenever the layout of a save changes
+const VERSION: u32 = 4;
+
+/// Writes the directory to `out`
+pub fn save(element_grid_dir: &ElementGridDir, out: &mut impl Write) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&VERSION.to_le_bytes())?;
+    let coord_dir = element_grid_dir.get_coordinate_dir();
+    coord_dir.get_builder().write_to(out)?;
+    out.write_all(&element_grid_dir.get_seed().to_le_bytes())?;
+    let mut names: Vec<String> = Vec::new();
+    let mut ids: Vec<u16> = Vec::new();
+    for i in 0..coord_dir.get_num_layers() {
+        for j in 0..coord_dir.get_layer_num_concentric_circles(i) {
+            for k in 0..coord_dir.get_layer_num_radial_lines(i) {
+                // Chunks that haven't been generated yet are saved as the vacuum they load as
+                let name = element_grid_dir
+                    .try_get_element(IjkVector::new(i, j, k))
+                    .map_or_else(|| format!("{:?}", ElementType::Vacuum), |e| e.get_name());
+                let id = match names.iter().position(|known| *known == name) {
+                    Some(id) => id,
+                    None => {
+                        names.push(name);
+                        names.len() - 1
+                    }
+                };
+                ids.push(id as u16);
+            }
+        }
+    }
+    out.write_all(&(names.len() as u16).to_le_bytes())?;
+    for name in &names {
+        out.write_all(&(name.len() as u16).to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+    }
+    for id in ids {
+        out.write_all(&id.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads a directory written by [save], making its elements with `registry`
+/// An element that isn't registered is an error, before anything is loaded
+pub fn load(input: &mut impl Read, registry: &ElementRegistry) -> io::Result<ElementGridDir> {
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("Not a save file".to_string()));
+    }
+    let mut version = [0; 4];
+    input.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(invalid_data(format!(
+            "Save version {} is not supported, expected {}",
+            version, VERSION
+        )));
+    }
+    let coord_dir = CoordinateDirBuilder::read_from(input)?.build();
+    let mut seed = [0; 8];
+    input.read_exact(&mut seed)?;
+    let prototypes = (0..read_u16(input)?)
+        .map(|_| {
+            let mut name = vec![0; read_u16(input)? as usize];
+            input.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|err| invalid_data(err.to_string()))?;
+            registry
+                .create(&name)
+                .map_err(|err| invalid_data(err.to_string()))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut element_grid_dir = ElementGridDir::new_empty(coord_dir.clone());
+    element_grid_dir.set_seed(u64::from_le_bytes(seed));
+    for i in 0..coord_dir.get_num_layers() {
+        for j in 0..coord_dir.get_layer_num_concentric_circles(i) {
+            for k in 0..coord_dir.get_layer_num_radial_lines(i) {
+                let id = read_u16(input)?;
+                let prototype = prototypes
+                    .get(id as usize)
+                    .ok_or_else(|| invalid_data(format!("Unknown element id {}", id)))?;
+                // The new directory is already all vacuum
+                if prototype.get_type() != ElementType::Vacuum {
+                    element_grid_dir.set_element(
+                        IjkVector::new(i, j, k),
+                        prototype.box_clone(),
+                        Clock::default(),
+                    );
+                }
+            }
+        }
+    }
+    element_grid_dir.recalculate_everything();
+    Ok(element_grid_dir)
+}
+
+/// Saves the directory to a file, replacing it if it exists
+pub fn save_to_file(element_grid_dir: &ElementGridDir, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    save(element_grid_dir, &mut out)?;
+    out.flush()
+}
+
+/// Loads a directory saved with [save_to_file]
+pub fn load_from_file(
+    path: impl AsRef<Path>,
+    registry: &ElementRegistry,
+) -> io::Result<ElementGridDir> {
+    load(&mut BufReader::new(File::open(path)?), registry)
+}
+
+/// Reads a little endian u16
+fn read_u16(input: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    input.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+/// An error for a save that doesn't hold what it should
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::orbits::components::Length;
+    use hashbrown::HashMap;
+
+    /// The layout of every layer, and how many of each element are in the directory
+    fn layout_and_histogram(
+        element_grid_dir: &ElementGridDir,
+    ) -> (Vec<(usize, usize)>, HashMap<u16, usize>) {
+        let coord_dir = element_grid_dir.get_coordinate_dir();
+        let mut layout = Vec::new();
+        let mut histogram = HashMap::new();
+        for layer in 0..coord_dir.get_num_layers() {
+            let array = element_grid_dir.layer_as_array(layer);
+            layout.push(array.dim());
+            for id in array.iter() {
+                *histogram.entry(*id).or_insert(0) += 1;
+            }
+        }
+        (layout, histogram)
+    }
+
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(7)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+        element_grid_dir.set_seed(7);
+        let coord_dir = element_grid_dir.get_coordinate_dir().clone();
+        for (n, element_type) in [ElementType::Sand, ElementType::Stone, ElementType::Water]
+            .into_iter()
+            .enumerate()
+        {
+            let layer = n + 3;
+            for k in 0..coord_dir.get_layer_num_radial_lines(layer) / (n + 2) {
+                element_grid_dir.set_element(
+                    IjkVector::new(layer, 1, k),
+                    element_type.get_element(),
+                    Clock::default(),
+                );
+            }
+        }
+        element_grid_dir.recalculate_everything();
+        element_grid_dir
+    }
+
+    #[test]
+    fn test_save_then_load_round_trips() {
+        let element_grid_dir = get_element_grid_dir();
+        let mut bytes = Vec::new();
+        save(&element_grid_dir, &mut bytes).unwrap();
+        let loaded = load(&mut bytes.as_slice(), &ElementRegistry::default()).unwrap();
+
+        assert_eq!(
+            loaded.get_coordinate_dir().get_builder(),
+            element_grid_dir.get_coordinate_dir().get_builder()
+        );
+        assert_eq!(loaded.get_seed(), element_grid_dir.get_seed());
+        assert_eq!(loaded.get_num_chunks(), element_grid_dir.get_num_chunks());
+        assert_eq!(
+            layout_and_histogram(&loaded),
+            layout_and_histogram(&element_grid_dir)
+        );
+        assert_eq!(
+            loaded.get_total_mass().0,
+            element_grid_dir.get_total_mass().0
+        );
+    }
+
+    #[test]
+    fn test_save_then_load_from_file() {
+        let element_grid_dir = get_element_grid_dir();
+        let path = std::env::temp_dir().join(format!("orbiting_sand_{}.osnd", std::process::id()));
+        save_to_file(&element_grid_dir, &path).unwrap();
+        let loaded = load_from_file(&path, &ElementRegistry::default());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            layout_and_histogram(&loaded.unwrap()),
+            layout_and_histogram(&element_grid_dir)
+        );
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        let err = load(
+            &mut b"not a save at all".as_slice(),
+            &ElementRegistry::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,9 +1,18 @@
 //! This module contains all the elements that are used in the game.
 
+pub mod ash;
+pub mod battery;
+pub mod conveyor;
 pub mod element;
+pub mod fire;
 pub mod fliers;
+pub mod ice;
+pub mod lamp;
 pub mod lava;
+pub mod metal;
 pub mod movement;
+pub mod plant;
+pub mod registry;
 pub mod sand;
 pub mod solarplasma;
 pub mod stone;
@@ -0,0 +1,59 @@
+use super::element::{Density, Element, ElementTakeOptions, ElementType, StateOfMatter};
+use super::movement::solid::solid_process;
+use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::data::element_grid::ElementGrid;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::util::clock::Clock;
+use bevy::render::color::Color;
+
+/// What is left once a [Fire](super::fire::Fire) burns out, a light powder that piles up like sand
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Ash {
+    last_processed: Clock,
+}
+
+impl Element for Ash {
+    fn get_type(&self) -> ElementType {
+        ElementType::Ash
+    }
+    fn get_density(&self) -> Density {
+        Density(0.3)
+    }
+    fn get_last_processed(&self) -> Clock {
+        self.last_processed
+    }
+    fn _set_last_processed(&mut self, current_time: Clock) {
+        self.last_processed = current_time;
+    }
+    fn get_state_of_matter(&self) -> StateOfMatter {
+        StateOfMatter::Solid
+    }
+    // Dark gray
+    fn get_color(&self) -> Color {
+        Color::rgb_u8(80, 78, 76)
+    }
+    fn is_loose(&self) -> bool {
+        true
+    }
+    fn _process(
+        &mut self,
+        pos: JkVector,
+        coord_dir: &CoordinateDir,
+        target_chunk: &mut ElementGrid,
+        element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        current_time: Clock,
+    ) -> ElementTakeOptions {
+        solid_process(
+            self,
+            pos,
+            coord_dir,
+            target_chunk,
+            element_grid_conv,
+            current_time,
+        )
+    }
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(*self)
+    }
+}
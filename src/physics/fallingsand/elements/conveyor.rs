@@ -0,0 +1,284 @@
+use super::element::{Density, Element, ElementTakeOptions, ElementType, StateOfMatter};
+use super::vacuum::Vacuum;
+use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::convolution::neighbor_identifiers::{
+    ConvolutionIdentifier, ConvolutionIdx,
+};
+use crate::physics::fallingsand::data::element_directory::StateHasher;
+use crate::physics::fallingsand::data::element_grid::ElementGrid;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::orbits::components::ThermodynamicTemperature;
+use crate::physics::util::clock::Clock;
+use bevy::render::color::Color;
+
+/// Which way a conveyor pushes the element sitting on top of it
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConveyorDirection {
+    /// Positive k, which is clockwise unless the layer's [Winding](crate::physics::fallingsand::util::vectors::Winding) is reversed
+    #[default]
+    Left,
+    /// Negative k, which is counter clockwise unless the winding is reversed
+    Right,
+    /// Away from the center of the celestial
+    Up,
+}
+
+/// A solid that doesn't fall, and instead moves whatever loose element or gas is on top of it
+/// one cell in its direction every pass, if there is room
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Conveyor {
+    last_processed: Clock,
+    direction: ConveyorDirection,
+}
+
+impl Conveyor {
+    pub fn new(direction: ConveyorDirection) -> Self {
+        Self {
+            last_processed: Clock::default(),
+            direction,
+        }
+    }
+
+    pub fn get_direction(&self) -> ConveyorDirection {
+        self.direction
+    }
+
+    /// Where the element above us should be pushed to
+    /// Lateral moves are only made from inside the target chunk, as the convolution
+    /// can only look left and right from the center
+    fn get_destination(
+        &self,
+        target_chunk: &ElementGrid,
+        element_grid_conv: &ElementGridConvolutionNeighbors,
+        above: ConvolutionIdx,
+    ) -> Option<ConvolutionIdx> {
+        if !matches!(above.1, ConvolutionIdentifier::Center) {
+            return None;
+        }
+        match self.direction {
+            ConveyorDirection::Left => element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &above.0, 1)
+                .ok(),
+            ConveyorDirection::Right => element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &above.0, -1)
+                .ok(),
+            ConveyorDirection::Up => {
+                let origin = target_chunk
+                    .get_chunk_coords()
+                    .get_external_coord_from_internal_coord(above.0);
+                element_grid_conv.above_cell_for(target_chunk, origin)
+            }
+        }
+    }
+}
+
+impl Element for Conveyor {
+    fn get_type(&self) -> ElementType {
+        ElementType::Conveyor
+    }
+    fn get_density(&self) -> Density {
+        Density(1.0)
+    }
+    fn get_last_processed(&self) -> Clock {
+        self.last_processed
+    }
+    fn _set_last_processed(&mut self, current_time: Clock) {
+        self.last_processed = current_time;
+    }
+    fn get_state_of_matter(&self) -> StateOfMatter {
+        StateOfMatter::Solid
+    }
+    // Dark blue gray
+    fn get_color(&self) -> Color {
+        Color::rgb_u8(64, 64, 96)
+    }
+    fn _process(
+        &mut self,
+        pos: JkVector,
+        _: &CoordinateDir,
+        target_chunk: &mut ElementGrid,
+        element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        current_time: Clock,
+    ) -> ElementTakeOptions {
+        let origin = target_chunk
+            .get_chunk_coords()
+            .get_external_coord_from_internal_coord(pos);
+        let Some(above) = element_grid_conv.above_cell_for(target_chunk, origin) else {
+            return ElementTakeOptions::PutBack;
+        };
+        let Some(destination) = self.get_destination(target_chunk, element_grid_conv, above) else {
+            return ElementTakeOptions::PutBack;
+        };
+
+        // Only push loose things and gases into nothing, other solids and empty cells stay put
+        let pushable = element_grid_conv.get(target_chunk, above).map(|element| {
+            element.is_loose() || element.get_state_of_matter() == StateOfMatter::Gas
+        });
+        let free = element_grid_conv
+            .get(target_chunk, destination)
+            .map(|element| element.get_state_of_matter() == StateOfMatter::Empty);
+        let writable =
+            !element_grid_conv.is_read_only(above) && !element_grid_conv.is_read_only(destination);
+        if let (Ok(true), Ok(true), true) = (pushable, free, writable) {
+            // Swap the pushed element with the empty cell, each taking its temperature along
+            // The placeholder temperature is overwritten once we know the empty cell's
+            let (mut pushed, temperature) = element_grid_conv
+                .replace_with_temperature(
+                    target_chunk,
+                    above,
+                    Box::<Vacuum>::default(),
+                    ThermodynamicTemperature(0.0),
+                    current_time,
+                )
+                .expect("Already checked this index");
+            // The pushed element shouldn't also get to move itself this frame
+            pushed._set_last_processed(current_time);
+            let (empty, empty_temperature) = element_grid_conv
+                .replace_with_temperature(
+                    target_chunk,
+                    destination,
+                    pushed,
+                    temperature,
+                    current_time,
+                )
+                .expect("Already checked this index");
+            element_grid_conv
+                .replace_with_temperature(
+                    target_chunk,
+                    above,
+                    empty,
+                    empty_temperature,
+                    current_time,
+                )
+                .expect("Already checked this index");
+        }
+        ElementTakeOptions::PutBack
+    }
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write(&[self.direction as u8]);
+    }
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::physics::{
+        fallingsand::{
+            data::element_directory::ElementGridDir,
+            mesh::coordinate_directory::CoordinateDirBuilder,
+        },
+        orbits::components::Length,
+    };
+
+    use super::*;
+
+    /// The default element grid directory for testing
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(7)
+            .first_num_radial_lines(12)
+            .second_num_concentric_circles(3)
+            .first_num_tangential_chunkss(3)
+            .max_radial_lines_per_chunk(128)
+            .max_concentric_circles_per_chunk(128)
+            .build();
+        ElementGridDir::new_empty(coordinate_dir)
+    }
+
+    /// Sand sitting on a single conveyor, walled in by stone so it can't slide off by itself
+    mod pushes {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::{
+            elements::{element::ElementType, sand::Sand, stone::Stone},
+            util::vectors::IjkVector,
+        };
+
+        fn assert_pushed(direction: ConveyorDirection, to: IjkVector) {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            let conveyor = IjkVector::new(2, 0, 10);
+            let sand = IjkVector::new(2, 1, 10);
+            element_grid_dir.set_element(conveyor, Box::new(Conveyor::new(direction)), clock);
+            element_grid_dir.set_element(IjkVector::new(2, 0, 9), Box::<Stone>::default(), clock);
+            element_grid_dir.set_element(IjkVector::new(2, 0, 11), Box::<Stone>::default(), clock);
+            element_grid_dir.set_element(sand, Box::<Sand>::default(), clock);
+
+            // Process one pass
+            clock.update(Duration::from_millis(100));
+            let chunk_idx = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(conveyor)
+                .0;
+            element_grid_dir.process_single_chunk(clock, chunk_idx);
+
+            // The sand moved exactly one cell
+            assert_eq!(
+                element_grid_dir.get_element(sand).get_type(),
+                ElementType::Vacuum
+            );
+            assert_eq!(
+                element_grid_dir.get_element(to).get_type(),
+                ElementType::Sand
+            );
+            // And the conveyor stayed put
+            assert_eq!(
+                element_grid_dir.get_element(conveyor).get_type(),
+                ElementType::Conveyor
+            );
+        }
+
+        #[test]
+        fn test_pushes_left() {
+            assert_pushed(ConveyorDirection::Left, IjkVector::new(2, 1, 11));
+        }
+
+        #[test]
+        fn test_pushes_right() {
+            assert_pushed(ConveyorDirection::Right, IjkVector::new(2, 1, 9));
+        }
+
+        #[test]
+        fn test_pushes_up() {
+            assert_pushed(ConveyorDirection::Up, IjkVector::new(2, 2, 10));
+        }
+
+        /// Solids that don't fall, like stone, are too heavy to move
+        #[test]
+        fn test_leaves_stone_alone() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            let conveyor = IjkVector::new(2, 0, 10);
+            let stone = IjkVector::new(2, 1, 10);
+            element_grid_dir.set_element(
+                conveyor,
+                Box::new(Conveyor::new(ConveyorDirection::Left)),
+                clock,
+            );
+            element_grid_dir.set_element(stone, Box::<Stone>::default(), clock);
+
+            clock.update(Duration::from_millis(100));
+            let chunk_idx = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(conveyor)
+                .0;
+            element_grid_dir.process_single_chunk(clock, chunk_idx);
+
+            assert_eq!(
+                element_grid_dir.get_element(stone).get_type(),
+                ElementType::Stone
+            );
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(2, 1, 11))
+                    .get_type(),
+                ElementType::Vacuum
+            );
+        }
+    }
+}
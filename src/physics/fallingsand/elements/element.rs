@@ -4,19 +4,30 @@
 
 use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
 use crate::physics::fallingsand::convolution::neighbor_identifiers::ConvolutionIdx;
+use crate::physics::fallingsand::data::element_directory::StateHasher;
 use crate::physics::fallingsand::data::element_grid::ElementGrid;
 use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
 use crate::physics::fallingsand::util::vectors::JkVector;
-use crate::physics::orbits::components::{Length, Mass};
+use crate::physics::orbits::components::{Length, Mass, ThermodynamicTemperature};
 use crate::physics::util::clock::Clock;
 use bevy::render::color::Color;
 use ndarray::Array2;
+use std::time::Duration;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+use super::ash::Ash;
+use super::battery::Battery;
+use super::conveyor::Conveyor;
+use super::fire::Fire;
 use super::fliers::down::DownFlier;
 use super::fliers::left::LeftFlier;
 use super::fliers::right::RightFlier;
+use super::ice::Ice;
+use super::lamp::Lamp;
 use super::lava::Lava;
+use super::metal::Metal;
+use super::plant::Plant;
 use super::sand::Sand;
 use super::solarplasma::SolarPlasma;
 use super::stone::Stone;
@@ -63,6 +74,28 @@ pub enum StateOfMatter {
     Solid,
 }
 
+/// The per cell fields that are floats, and so can blow up to NaN or infinity
+/// Only temperature for now, pressure will join it once elements carry one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum FieldKind {
+    Temperature,
+}
+
+/// How an element takes part in a circuit
+/// See [ElementGridDir::propagate_power](crate::physics::fallingsand::data::element_directory::ElementGridDir::propagate_power)
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Conduction {
+    /// Blocks power
+    #[default]
+    Insulator,
+    /// Always powered, and powers what it touches
+    Source,
+    /// Powered when it touches something powered, and passes it on
+    Conductor,
+    /// Powered when it touches something powered, but doesn't pass it on
+    Sink,
+}
+
 /// Allows you to match on the type of element
 /// each element impl has a unique item in this enum
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
@@ -77,12 +110,42 @@ pub enum ElementType {
     DownFlier,
     LeftFlier,
     RightFlier,
+    Conveyor,
+    Ice,
+    Plant,
+    Fire,
+    Ash,
+    Metal,
+    Battery,
+    Lamp,
+    /// Any element defined outside of this crate, made through an
+    /// [ElementRegistry](super::registry::ElementRegistry) rather than [ElementType::get_element]
+    /// Each one registered gets its own kind, so they can be told apart like the built in ones.
+    /// Left out of iteration, since which there are depends on the registry
+    #[strum(disabled)]
+    Custom(CustomKind),
 }
 
+/// Which custom element an [ElementType::Custom] is
+/// Handed out in order by an [ElementRegistry](super::registry::ElementRegistry) as they are registered
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CustomKind(pub u16);
+
 impl ElementType {
     /// This gets the default element of the type
+    ///
+    /// # Panics
+    /// There is no default custom element, those come from an
+    /// [ElementRegistry](super::registry::ElementRegistry). Anywhere the type isn't known
+    /// up front should use [Self::try_get_element]
     pub fn get_element(&self) -> Box<dyn Element> {
-        match self {
+        self.try_get_element()
+            .expect("Custom elements have no default, make them with an ElementRegistry")
+    }
+
+    /// Like [Self::get_element], but a custom type is an error rather than a panic
+    pub fn try_get_element(&self) -> Result<Box<dyn Element>, CustomElementError> {
+        Ok(match self {
             ElementType::Vacuum => Box::<Vacuum>::default(),
             ElementType::DownFlier => Box::<DownFlier>::default(),
             ElementType::LeftFlier => Box::<LeftFlier>::default(),
@@ -92,19 +155,78 @@ impl ElementType {
             ElementType::Water => Box::<Water>::default(),
             ElementType::SolarPlasma => Box::<SolarPlasma>::default(),
             ElementType::Lava => Box::<Lava>::default(),
+            ElementType::Conveyor => Box::<Conveyor>::default(),
+            ElementType::Ice => Box::<Ice>::default(),
+            ElementType::Plant => Box::<Plant>::default(),
+            ElementType::Fire => Box::<Fire>::default(),
+            ElementType::Ash => Box::<Ash>::default(),
+            ElementType::Metal => Box::<Metal>::default(),
+            ElementType::Battery => Box::<Battery>::default(),
+            ElementType::Lamp => Box::<Lamp>::default(),
+            ElementType::Custom(_) => return Err(CustomElementError),
+        })
+    }
+
+    /// How many types are built in, every one but [ElementType::Custom]
+    pub const NUM_BUILT_IN: u16 = 17;
+
+    /// A number for the type, unique among the built in types and every custom kind
+    /// The built in types count up in order, and custom kinds come after them
+    pub fn id(&self) -> u16 {
+        match self {
+            ElementType::Vacuum => 0,
+            ElementType::Sand => 1,
+            ElementType::Stone => 2,
+            ElementType::Lava => 3,
+            ElementType::Water => 4,
+            ElementType::SolarPlasma => 5,
+            ElementType::DownFlier => 6,
+            ElementType::LeftFlier => 7,
+            ElementType::RightFlier => 8,
+            ElementType::Conveyor => 9,
+            ElementType::Ice => 10,
+            ElementType::Plant => 11,
+            ElementType::Fire => 12,
+            ElementType::Ash => 13,
+            ElementType::Metal => 14,
+            ElementType::Battery => 15,
+            ElementType::Lamp => 16,
+            ElementType::Custom(kind) => Self::NUM_BUILT_IN + kind.0,
         }
     }
+
+    /// The inverse of [Self::id] for the built in types, None for any other id
+    /// Which custom kind is which depends on the registry, so those aren't known here
+    pub fn from_id(id: u16) -> Option<Self> {
+        Self::iter().nth(id as usize)
+    }
 }
 
+/// The temperature of anything that doesn't say otherwise, roughly room temperature
+pub const AMBIENT_TEMPERATURE: ThermodynamicTemperature = ThermodynamicTemperature(290.0);
+
 /// If something has 0 heat capacity or specific heat, you should not set its heat
 #[derive(Default, Debug)]
 pub struct SetHeatOnZeroSpecificHeatError;
 
+/// Only the [ElementRegistry](super::registry::ElementRegistry) a custom element was registered
+/// with knows how to make it, so [ElementType::Custom] can't be made from the type alone
+#[derive(Default, Debug, Clone, Copy)]
+pub struct CustomElementError;
+
 /// This is the trait that all elements must implement
 pub trait Element: Send + Sync {
     /// This gets the type of the element
     /// Converts between the trait and the enum
+    /// Custom elements report the type their factory was handed when they were registered
     fn get_type(&self) -> ElementType;
+    /// The name this element is made by in an [ElementRegistry](super::registry::ElementRegistry),
+    /// which is how saves and replays tell elements apart
+    /// Built in elements are named after their [ElementType]. Custom elements have to override
+    /// this with the name they were registered under
+    fn get_name(&self) -> String {
+        format!("{:?}", self.get_type())
+    }
     /// This gets the last time the element was processed
     /// Useful for physics calculations by getting the dt between now and then
     fn get_last_processed(&self) -> Clock;
@@ -113,6 +235,12 @@ pub trait Element: Send + Sync {
     /// in fragment shaders knowing their type just by their color
     /// You can map them to other colors and add effects using the fragment shader
     fn get_color(&self) -> Color;
+    /// The light this element gives off, as a color and an intensity
+    /// Most elements don't glow, so this defaults to None
+    /// The light is added on top of the texture colors by [ElementGrid::get_light_map]
+    fn emission(&self) -> Option<(Color, f32)> {
+        None
+    }
     /// This gets the density of the element relative to the cell_width
     /// This is so bigger cells have more mass, so we don't have to have as many cells
     /// for simpler bodies, like gas giants or the sun
@@ -123,6 +251,46 @@ pub trait Element: Send + Sync {
     }
     /// This gets the state of matter of the element
     fn get_state_of_matter(&self) -> StateOfMatter;
+    /// The furthest this element may fall in one step, given how long the step took
+    /// Falling behaviors check every cell along the way, so a large delta can't tunnel through a floor
+    fn max_fall_cells_per_step(&self, _delta: Duration) -> usize {
+        1
+    }
+    /// Whether this element falls when nothing holds it up, like sand or water
+    /// Liquids always do, everything else has to say so
+    fn is_loose(&self) -> bool {
+        self.get_state_of_matter() == StateOfMatter::Liquid
+    }
+    /// How wet this element is, from 0 (dry) to 1 (soaked)
+    /// Wet solids don't slide off of each other, so they pile up steeper
+    fn get_wetness(&self) -> f32 {
+        0.0
+    }
+    /// The chance each pass, from 0 to 1, that this element catches from a neighboring
+    /// [Fire](super::fire::Fire) burning at full intensity
+    /// Most things don't burn, so this defaults to 0
+    fn flammability(&self) -> f32 {
+        0.0
+    }
+    /// Whether this element carries power, see [Conduction]
+    /// Almost nothing does, so this defaults to [Conduction::Insulator]
+    fn conduction(&self) -> Conduction {
+        Conduction::Insulator
+    }
+    /// Called by the directory whenever the cell this element is in gains or loses power
+    /// Only elements that show it, like lamps, need to remember it
+    fn set_powered(&mut self, _powered: bool) {}
+    /// The temperature a freshly placed element of this kind starts at
+    /// After that the cell's temperature lives in [ElementGrid], see [ElementGrid::transmute]
+    /// 0K means the element has no temperature at all, like vacuum
+    fn get_temperature(&self) -> ThermodynamicTemperature {
+        AMBIENT_TEMPERATURE
+    }
+    /// Adds whatever this element remembers beyond its type to a
+    /// [state hash](crate::physics::fallingsand::data::element_directory::ElementGridDir::state_hash)
+    /// Two elements of the same type that would act differently have to hash differently,
+    /// so anything with fields of its own besides when it was last processed should write them here
+    fn hash_state(&self, _hasher: &mut StateHasher) {}
     /// This is the "public" process method, that calls the private _process method
     /// makes sure that _set_last_processed is called
     fn process(
@@ -146,23 +314,39 @@ pub trait Element: Send + Sync {
     /// This is the way we implement clone for a trait object
     fn box_clone(&self) -> Box<dyn Element>;
 
-    /// Instructs the loop to swap the element with the element at pos1
+    /// Instructs the loop to swap the element at pos, which is us, with the element at pos1
+    /// Both take their temperatures with them
     /// you should have already checked to see if pos1 is valid, most likely it comes from another function
     /// as such this function will panic if pos1 is invalid
+    /// A pos1 in a static neighbor is valid but read only, so nothing moves and we are put back
     fn try_swap_me(
         &self,
+        pos: JkVector,
         pos1: ConvolutionIdx,
         target_chunk: &mut ElementGrid,
         element_grid_conv: &mut ElementGridConvolutionNeighbors,
         current_time: Clock,
     ) -> ElementTakeOptions {
+        if element_grid_conv.is_read_only(pos1) {
+            return ElementTakeOptions::PutBack;
+        }
         let mut clone = self.box_clone();
         // Its important we set the last processed time to the current time
         // here because self wont yet have been updated by the process function
         clone._set_last_processed(current_time);
-        let prev = element_grid_conv.replace(target_chunk, pos1, clone, current_time);
+        let temperature = target_chunk.get_temperature(pos);
+        let prev = element_grid_conv.replace_with_temperature(
+            target_chunk,
+            pos1,
+            clone,
+            temperature,
+            current_time,
+        );
         match prev {
-            Ok(prev) => ElementTakeOptions::ReplaceWith(prev),
+            Ok((prev, prev_temperature)) => {
+                target_chunk.set_temperature(pos, prev_temperature, current_time);
+                ElementTakeOptions::ReplaceWith(prev)
+            }
             Err(_) => panic!("Tried to swap with an invalid position"),
         }
     }
@@ -197,7 +381,7 @@ mod tests {
     use bevy::render::color::Color;
     use strum::IntoEnumIterator;
 
-    use super::ElementType;
+    use super::{CustomKind, ElementType};
 
     /// This tests that all elements have different colors
     /// This is important because we use the color to identify the element in shaders
@@ -243,4 +427,21 @@ mod tests {
             );
         }
     }
+
+    /// Saves store each element as its id, so reading one back has to give the same type
+    #[test]
+    fn test_from_id_inverts_id() {
+        for element_type in ElementType::iter() {
+            assert_eq!(ElementType::from_id(element_type.id()), Some(element_type));
+        }
+        assert_eq!(
+            ElementType::iter().count(),
+            ElementType::NUM_BUILT_IN as usize
+        );
+        assert_eq!(
+            ElementType::Custom(CustomKind(2)).id(),
+            ElementType::NUM_BUILT_IN + 2
+        );
+        assert_eq!(ElementType::from_id(u16::MAX), None);
+    }
 }
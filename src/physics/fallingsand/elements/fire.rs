@@ -0,0 +1,252 @@
+use super::element::{Density, Element, ElementTakeOptions, ElementType, StateOfMatter};
+use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::convolution::neighbor_identifiers::ConvolutionIdx;
+use crate::physics::fallingsand::data::element_directory::StateHasher;
+use crate::physics::fallingsand::data::element_grid::ElementGrid;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::orbits::components::ThermodynamicTemperature;
+use crate::physics::util::clock::Clock;
+use bevy::render::color::Color;
+use rand::Rng;
+use std::time::Duration;
+
+/// How much intensity a fire loses per second, so a fresh one burns for five seconds
+const BURN_RATE: f32 = 0.2;
+
+/// How many kelvin per second a fire at full intensity heats its own cell by
+const HEATING_RATE: f32 = 200.0;
+
+/// How long a neighbor's flammability is the chance of it catching for, in seconds
+/// Longer or shorter steps compound the chance, so fire spreads as fast at any frame rate
+const SPREAD_INTERVAL: f32 = 0.1;
+
+/// The chance of catching over a step of `delta`, given the chance over one [SPREAD_INTERVAL]
+/// Each interval is its own roll, so it is the chance that not all of them miss
+fn spread_chance(per_interval: f32, delta: Duration) -> f64 {
+    let intervals = delta.as_secs_f32() / SPREAD_INTERVAL;
+    (1.0 - (1.0 - per_interval.clamp(0.0, 1.0)).powf(intervals)).clamp(0.0, 1.0) as f64
+}
+
+/// A flame burning in place, which doesn't move on its own
+/// Its intensity starts at 1 and burns down over time. While it lasts it heats its cell in
+/// proportion and may catch any flammable neighbor, see [Element::flammability].
+/// Once it is gone the cell is left as [Ash](super::ash::Ash), keeping the heat
+#[derive(Copy, Clone, Debug)]
+pub struct Fire {
+    last_processed: Clock,
+    /// From 0 to 1, how strongly it is still burning
+    intensity: f32,
+}
+
+impl Default for Fire {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl Fire {
+    pub fn new(intensity: f32) -> Self {
+        debug_assert!((0.0..=1.0).contains(&intensity));
+        Self {
+            last_processed: Clock::default(),
+            intensity,
+        }
+    }
+
+    pub fn get_intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Lights up each flammable neighbor above, below, left or right of us
+    /// with a chance of its flammability every [SPREAD_INTERVAL], scaled down as we burn out
+    fn spread(
+        &self,
+        pos: JkVector,
+        coord_dir: &CoordinateDir,
+        target_chunk: &mut ElementGrid,
+        element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        current_time: Clock,
+    ) {
+        let origin = target_chunk
+            .get_chunk_coords()
+            .get_external_coord_from_internal_coord(pos);
+        let neighbors: [Option<ConvolutionIdx>; 4] = [
+            element_grid_conv.above_cell_for(target_chunk, origin),
+            element_grid_conv
+                .get_below_idx_from_center(target_chunk, coord_dir, &pos, 1)
+                .ok(),
+            element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &pos, 1)
+                .ok(),
+            element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &pos, -1)
+                .ok(),
+        ];
+        for idx in neighbors.into_iter().flatten() {
+            if element_grid_conv.is_read_only(idx) {
+                continue;
+            }
+            let flammability = element_grid_conv
+                .get(target_chunk, idx)
+                .map_or(0.0, |element| element.flammability());
+            if flammability <= 0.0 {
+                continue;
+            }
+            let chance =
+                spread_chance(flammability * self.intensity, current_time.get_last_delta());
+            if target_chunk.get_rng().gen_bool(chance) {
+                // The new flame waits until next pass to burn
+                let mut flame = Fire::default();
+                flame._set_last_processed(current_time);
+                element_grid_conv
+                    .replace(target_chunk, idx, Box::new(flame), current_time)
+                    .expect("Already got this index");
+            }
+        }
+    }
+}
+
+impl Element for Fire {
+    fn get_type(&self) -> ElementType {
+        ElementType::Fire
+    }
+    fn get_density(&self) -> Density {
+        Density(0.01)
+    }
+    fn get_temperature(&self) -> ThermodynamicTemperature {
+        ThermodynamicTemperature(1000.0)
+    }
+    fn get_last_processed(&self) -> Clock {
+        self.last_processed
+    }
+    fn _set_last_processed(&mut self, current_time: Clock) {
+        self.last_processed = current_time;
+    }
+    fn get_state_of_matter(&self) -> StateOfMatter {
+        StateOfMatter::Gas
+    }
+    // Flame orange
+    fn get_color(&self) -> Color {
+        Color::rgb_u8(255, 120, 20)
+    }
+    // Brighter the harder it burns
+    fn emission(&self) -> Option<(Color, f32)> {
+        Some((Color::ORANGE, self.intensity))
+    }
+    fn _process(
+        &mut self,
+        pos: JkVector,
+        coord_dir: &CoordinateDir,
+        target_chunk: &mut ElementGrid,
+        element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        current_time: Clock,
+    ) -> ElementTakeOptions {
+        let delta = current_time.get_last_delta().as_secs_f32();
+        let temperature = target_chunk.get_temperature(pos).0;
+        target_chunk.set_temperature(
+            pos,
+            ThermodynamicTemperature(temperature + HEATING_RATE * self.intensity * delta),
+            current_time,
+        );
+        self.spread(
+            pos,
+            coord_dir,
+            target_chunk,
+            element_grid_conv,
+            current_time,
+        );
+        self.intensity -= BURN_RATE * delta;
+        if self.intensity <= 0.0 {
+            // Transmuting keeps the heat of the fire in the ash
+            target_chunk
+                .transmute(pos, ElementType::Ash, current_time)
+                .expect("Ash is a built in element");
+            return ElementTakeOptions::DoNothing;
+        }
+        ElementTakeOptions::PutBack
+    }
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write(&self.intensity.to_bits().to_le_bytes());
+    }
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::fallingsand::data::element_directory::ElementGridDir;
+    use crate::physics::fallingsand::elements::{plant::Plant, stone::Stone};
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::fallingsand::util::vectors::IjkVector;
+    use crate::physics::orbits::components::Length;
+
+    #[test]
+    fn test_fire_spreads_heats_and_burns_out() {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(7)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+        let clock = Clock::default();
+        let num_radial_lines = element_grid_dir
+            .get_coordinate_dir()
+            .get_layer_num_radial_lines(3);
+        for k in 0..num_radial_lines {
+            element_grid_dir.set_element(IjkVector::new(3, 0, k), Box::<Stone>::default(), clock);
+        }
+        let fire = IjkVector::new(3, 1, 9);
+        let plant = IjkVector::new(3, 1, 10);
+        // Stone doesn't burn
+        let stone = IjkVector::new(3, 1, 8);
+        element_grid_dir.set_element(fire, Box::<Fire>::default(), clock);
+        element_grid_dir.set_element(plant, Box::<Plant>::default(), clock);
+        element_grid_dir.set_element(stone, Box::<Stone>::default(), clock);
+        let start_temperature = element_grid_dir.get_temperature(fire);
+
+        let mut clock = Clock::default();
+        let mut ignited = false;
+        for frame in 0..100 {
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            if frame == 0 {
+                assert!(element_grid_dir.get_temperature(fire) > start_temperature);
+            }
+            ignited |= element_grid_dir.get_element(plant).get_type() == ElementType::Fire;
+        }
+
+        assert!(ignited);
+        assert_eq!(
+            element_grid_dir.get_element(fire).get_type(),
+            ElementType::Ash
+        );
+        assert_eq!(
+            element_grid_dir.get_element(plant).get_type(),
+            ElementType::Ash
+        );
+        assert_eq!(
+            element_grid_dir.get_element(stone).get_type(),
+            ElementType::Stone
+        );
+        // The ash keeps the heat the fire left behind
+        assert!(element_grid_dir.get_temperature(fire) > start_temperature);
+        assert_eq!(element_grid_dir.cells_of_kind(ElementType::Fire).count(), 0);
+    }
+
+    #[test]
+    fn test_spread_chance_compounds_over_the_step() {
+        let interval = Duration::from_secs_f32(SPREAD_INTERVAL);
+        assert!((spread_chance(0.3, interval) - 0.3).abs() < 1e-6);
+        // Two half steps in a row catch as often as one whole one
+        let half = spread_chance(0.3, interval / 2);
+        assert!((1.0 - (1.0 - half).powi(2) - 0.3).abs() < 1e-6);
+        assert_eq!(spread_chance(0.3, Duration::ZERO), 0.0);
+        assert_eq!(spread_chance(1.0, interval * 3), 1.0);
+    }
+}
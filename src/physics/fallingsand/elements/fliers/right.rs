@@ -48,9 +48,13 @@ impl Element for RightFlier {
                 Ok(idx) => {
                     if let Ok(element) = element_grid_conv.get(target_chunk, idx) {
                         match element.get_state_of_matter() {
-                            StateOfMatter::Empty => {
-                                self.try_swap_me(idx, target_chunk, element_grid_conv, current_time)
-                            }
+                            StateOfMatter::Empty => self.try_swap_me(
+                                pos,
+                                idx,
+                                target_chunk,
+                                element_grid_conv,
+                                current_time,
+                            ),
                             _ => ElementTakeOptions::PutBack,
                         }
                     } else {
@@ -0,0 +1,189 @@
+use super::element::{Density, Element, ElementTakeOptions, ElementType, StateOfMatter};
+use super::movement::solid::solid_process;
+use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::data::element_grid::ElementGrid;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::orbits::components::ThermodynamicTemperature;
+use crate::physics::util::clock::Clock;
+use bevy::render::color::Color;
+
+/// Above this the ice melts into water
+pub const MELTING_POINT: ThermodynamicTemperature = ThermodynamicTemperature(273.15);
+
+/// A solid lighter than water, so it floats up to the surface
+/// Melts into water once its cell warms past [MELTING_POINT]
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Ice {
+    last_processed: Clock,
+}
+
+impl Element for Ice {
+    fn get_type(&self) -> ElementType {
+        ElementType::Ice
+    }
+    fn get_density(&self) -> Density {
+        Density(0.9)
+    }
+    fn get_temperature(&self) -> ThermodynamicTemperature {
+        ThermodynamicTemperature(260.0)
+    }
+    fn get_last_processed(&self) -> Clock {
+        self.last_processed
+    }
+    fn _set_last_processed(&mut self, current_time: Clock) {
+        self.last_processed = current_time;
+    }
+    fn get_state_of_matter(&self) -> StateOfMatter {
+        StateOfMatter::Solid
+    }
+    // Pale blue
+    fn get_color(&self) -> Color {
+        Color::rgb_u8(200, 230, 255)
+    }
+    fn _process(
+        &mut self,
+        pos: JkVector,
+        coord_dir: &CoordinateDir,
+        target_chunk: &mut ElementGrid,
+        element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        current_time: Clock,
+    ) -> ElementTakeOptions {
+        if target_chunk.get_temperature(pos) > MELTING_POINT {
+            // Transmuting keeps the heat that melted us in the cell, the water is already in
+            // our place so there is nothing to put back
+            target_chunk
+                .transmute(pos, ElementType::Water, current_time)
+                .expect("Water is a built in element");
+            return ElementTakeOptions::DoNothing;
+        }
+        solid_process(
+            self,
+            pos,
+            coord_dir,
+            target_chunk,
+            element_grid_conv,
+            current_time,
+        )
+    }
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::physics::{
+        fallingsand::{
+            data::element_directory::ElementGridDir,
+            mesh::coordinate_directory::CoordinateDirBuilder,
+        },
+        orbits::components::Length,
+    };
+
+    use super::*;
+
+    /// The default element grid directory for testing
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(7)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        ElementGridDir::new_empty(coordinate_dir)
+    }
+
+    /// Ice let go at the bottom of a pool comes up to the top of it, and no further
+    mod floats {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::{
+            elements::{sand::Sand, stone::Stone, water::Water},
+            util::vectors::IjkVector,
+        };
+
+        /// A pool of water five cells deep in layer 3, with the given solid at the bottom of it
+        fn pool_with(solid: Box<dyn Element>) -> ElementGridDir {
+            let mut element_grid_dir = get_element_grid_dir();
+            let clock = Clock::default();
+            let num_radial_lines = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_radial_lines(3);
+            for k in 0..num_radial_lines {
+                element_grid_dir.set_element(
+                    IjkVector::new(3, 0, k),
+                    Box::<Stone>::default(),
+                    clock,
+                );
+            }
+            for j in 1..9 {
+                for k in [7, 13] {
+                    element_grid_dir.set_element(
+                        IjkVector::new(3, j, k),
+                        Box::<Stone>::default(),
+                        clock,
+                    );
+                }
+            }
+            for j in 1..6 {
+                for k in 8..13 {
+                    element_grid_dir.set_element(
+                        IjkVector::new(3, j, k),
+                        Box::<Water>::default(),
+                        clock,
+                    );
+                }
+            }
+            element_grid_dir.set_element(IjkVector::new(3, 1, 10), solid, clock);
+            element_grid_dir
+        }
+
+        fn run(element_grid_dir: &mut ElementGridDir) {
+            let mut clock = Clock::default();
+            for _ in 0..10 {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+            }
+        }
+
+        #[test]
+        fn test_ice_rises_to_the_surface() {
+            let mut element_grid_dir = pool_with(Box::<Ice>::default());
+            run(&mut element_grid_dir);
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(3, 5, 10))
+                    .get_type(),
+                ElementType::Ice
+            );
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(3, 6, 10))
+                    .get_type(),
+                ElementType::Vacuum
+            );
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(3, 1, 10))
+                    .get_type(),
+                ElementType::Water
+            );
+        }
+
+        #[test]
+        fn test_sand_stays_at_the_bottom() {
+            let mut element_grid_dir = pool_with(Box::<Sand>::default());
+            run(&mut element_grid_dir);
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(3, 1, 10))
+                    .get_type(),
+                ElementType::Sand
+            );
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use super::element::{
+    Conduction, Density, Element, ElementTakeOptions, ElementType, StateOfMatter,
+};
+use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::data::element_directory::StateHasher;
+use crate::physics::fallingsand::data::element_grid::ElementGrid;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::util::clock::Clock;
+use bevy::render::color::Color;
+
+/// Lit when it touches a powered wire or a battery, see
+/// [ElementGridDir::is_powered](crate::physics::fallingsand::data::element_directory::ElementGridDir::is_powered)
+/// It uses the power up, so it doesn't pass it on to anything past it
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Lamp {
+    last_processed: Clock,
+    /// Whether it is lit, kept up to date by the directory through [Element::set_powered]
+    powered: bool,
+}
+
+impl Element for Lamp {
+    fn get_type(&self) -> ElementType {
+        ElementType::Lamp
+    }
+    fn get_density(&self) -> Density {
+        Density(1.0)
+    }
+    fn get_last_processed(&self) -> Clock {
+        self.last_processed
+    }
+    fn _set_last_processed(&mut self, current_time: Clock) {
+        self.last_processed = current_time;
+    }
+    fn get_state_of_matter(&self) -> StateOfMatter {
+        StateOfMatter::Solid
+    }
+    // Pale yellow
+    fn get_color(&self) -> Color {
+        Color::rgb_u8(250, 240, 170)
+    }
+    // Lights up its surroundings while it has power
+    fn emission(&self) -> Option<(Color, f32)> {
+        self.powered.then_some((Color::rgb_u8(255, 245, 200), 0.8))
+    }
+    fn conduction(&self) -> Conduction {
+        Conduction::Sink
+    }
+    fn set_powered(&mut self, powered: bool) {
+        self.powered = powered;
+    }
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write(&[self.powered as u8]);
+    }
+    // Power is carried by the directory, see ElementGridDir::propagate_power
+    fn _process(
+        &mut self,
+        _pos: JkVector,
+        _coord_dir: &CoordinateDir,
+        _target_chunk: &mut ElementGrid,
+        _element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        _current_time: Clock,
+    ) -> ElementTakeOptions {
+        ElementTakeOptions::PutBack
+    }
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(*self)
+    }
+}
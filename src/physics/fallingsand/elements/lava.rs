@@ -4,6 +4,7 @@ use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionN
 use crate::physics::fallingsand::data::element_grid::ElementGrid;
 use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
 use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::orbits::components::ThermodynamicTemperature;
 use crate::physics::util::clock::Clock;
 use bevy::render::color::Color;
 
@@ -18,6 +19,10 @@ impl Element for Lava {
     fn get_density(&self) -> Density {
         Density(1.0)
     }
+    // Molten rock
+    fn get_temperature(&self) -> ThermodynamicTemperature {
+        ThermodynamicTemperature(1500.0)
+    }
     fn get_last_processed(&self) -> Clock {
         self.last_processed
     }
@@ -31,6 +36,10 @@ impl Element for Lava {
     fn get_color(&self) -> Color {
         Color::RED
     }
+    // Lava glows a little onto its surroundings
+    fn emission(&self) -> Option<(Color, f32)> {
+        Some((Color::ORANGE_RED, 0.5))
+    }
     // Stone does nothing
     fn _process(
         &mut self,
@@ -0,0 +1,55 @@
+use super::element::{
+    Conduction, Density, Element, ElementTakeOptions, ElementType, StateOfMatter,
+};
+use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::data::element_grid::ElementGrid;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::util::clock::Clock;
+use bevy::render::color::Color;
+
+/// A heavy solid wire that carries power between batteries and whatever they run
+/// It stays where it is put, so circuits can be built hanging in the air
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Metal {
+    last_processed: Clock,
+}
+
+impl Element for Metal {
+    fn get_type(&self) -> ElementType {
+        ElementType::Metal
+    }
+    fn get_density(&self) -> Density {
+        Density(3.0)
+    }
+    fn get_last_processed(&self) -> Clock {
+        self.last_processed
+    }
+    fn _set_last_processed(&mut self, current_time: Clock) {
+        self.last_processed = current_time;
+    }
+    fn get_state_of_matter(&self) -> StateOfMatter {
+        StateOfMatter::Solid
+    }
+    // Steel gray
+    fn get_color(&self) -> Color {
+        Color::rgb_u8(160, 165, 175)
+    }
+    fn conduction(&self) -> Conduction {
+        Conduction::Conductor
+    }
+    // Power is carried by the directory, see ElementGridDir::propagate_power
+    fn _process(
+        &mut self,
+        _pos: JkVector,
+        _coord_dir: &CoordinateDir,
+        _target_chunk: &mut ElementGrid,
+        _element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        _current_time: Clock,
+    ) -> ElementTakeOptions {
+        ElementTakeOptions::PutBack
+    }
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(*self)
+    }
+}
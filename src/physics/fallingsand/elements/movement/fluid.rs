@@ -2,7 +2,9 @@ use rand::Rng;
 
 use crate::physics::{
     fallingsand::{
-        convolution::behaviors::ElementGridConvolutionNeighbors,
+        convolution::{
+            behaviors::ElementGridConvolutionNeighbors, neighbor_identifiers::ConvolutionIdx,
+        },
         data::element_grid::ElementGrid,
         elements::element::{Element, ElementTakeOptions, StateOfMatter},
         mesh::coordinate_directory::CoordinateDir,
@@ -11,7 +13,13 @@ use crate::physics::{
     util::clock::Clock,
 };
 
+/// A liquid only pushes sideways into a cell under at most this fraction of its own pressure
+/// Keeps liquids from wandering off over tiny differences, like the two sides of a level surface
+const PRESSURE_RELIEF_RATIO: f32 = 0.5;
+
 /// Default fluid behavior
+/// Falls or spreads into a neighboring empty cell, and if it can't, rises through the liquid above
+/// when the heat has made it lighter, and failing that lets pressure push it sideways
 pub fn fluid_process(
     self_element: &mut dyn Element,
     pos: JkVector,
@@ -19,6 +27,129 @@ pub fn fluid_process(
     target_chunk: &mut ElementGrid,
     element_grid_conv: &mut ElementGridConvolutionNeighbors,
     current_time: Clock,
+) -> ElementTakeOptions {
+    match fluid_fall(
+        self_element,
+        pos,
+        coord_dir,
+        target_chunk,
+        element_grid_conv,
+        current_time,
+    ) {
+        ElementTakeOptions::PutBack => {
+            match convect(
+                self_element,
+                pos,
+                target_chunk,
+                element_grid_conv,
+                current_time,
+            ) {
+                ElementTakeOptions::PutBack => pressure_relief(
+                    self_element,
+                    pos,
+                    target_chunk,
+                    element_grid_conv,
+                    current_time,
+                ),
+                moved => moved,
+            }
+        }
+        moved => moved,
+    }
+}
+
+/// Swaps places with the liquid right above when this one is lighter, so hot liquid rises and
+/// cold liquid sinks under it, which turns a liquid heated from below into convection cells
+/// A liquid's density here is its base density minus
+/// [HeatConfig::thermal_expansion](crate::physics::fallingsand::data::element_grid::HeatConfig::thermal_expansion)
+/// times its temperature
+/// The cell above can be in the next chunk or layer up, so a plume carries on across the seams
+fn convect(
+    self_element: &mut dyn Element,
+    pos: JkVector,
+    target_chunk: &mut ElementGrid,
+    element_grid_conv: &mut ElementGridConvolutionNeighbors,
+    current_time: Clock,
+) -> ElementTakeOptions {
+    let thermal_expansion = target_chunk.get_heat_config().thermal_expansion;
+    if thermal_expansion <= 0.0 {
+        return ElementTakeOptions::PutBack;
+    }
+    let origin = target_chunk
+        .get_chunk_coords()
+        .get_external_coord_from_internal_coord(pos);
+    let Some(above) = element_grid_conv.above_cell_for(target_chunk, origin) else {
+        return ElementTakeOptions::PutBack;
+    };
+    let (Ok(element), Ok(above_temperature)) = (
+        element_grid_conv.get(target_chunk, above),
+        element_grid_conv.get_temperature(target_chunk, above),
+    ) else {
+        return ElementTakeOptions::PutBack;
+    };
+    if element.get_state_of_matter() != StateOfMatter::Liquid {
+        return ElementTakeOptions::PutBack;
+    }
+    let density =
+        self_element.get_density().0 - thermal_expansion * target_chunk.get_temperature(pos).0;
+    let above_density = element.get_density().0 - thermal_expansion * above_temperature.0;
+    if density < above_density {
+        self_element.try_swap_me(pos, above, target_chunk, element_grid_conv, current_time)
+    } else {
+        ElementTakeOptions::PutBack
+    }
+}
+
+/// A liquid that can't fall or spread is pushed one cell along its ring, into whichever empty
+/// neighbor has the least pressure on it, see [ElementGrid::get_pressure]
+/// Only happens where the difference is big enough, see [PRESSURE_RELIEF_RATIO]
+/// The liquid under a tall column is pushed out from under it, which levels a lopsided pool
+/// instead of waiting for its surface to random walk flat
+fn pressure_relief(
+    self_element: &mut dyn Element,
+    pos: JkVector,
+    target_chunk: &mut ElementGrid,
+    element_grid_conv: &mut ElementGridConvolutionNeighbors,
+    current_time: Clock,
+) -> ElementTakeOptions {
+    let pressure = target_chunk.get_pressure(pos);
+    let mut lowest: Option<(ConvolutionIdx, f32)> = None;
+    for rk in [1, -1] {
+        let Ok(idx) = element_grid_conv.get_left_right_idx_from_center(target_chunk, &pos, rk)
+        else {
+            continue;
+        };
+        let Ok(element) = element_grid_conv.get(target_chunk, idx) else {
+            continue;
+        };
+        if element.get_state_of_matter() > StateOfMatter::Gas {
+            continue;
+        }
+        let Ok(side_pressure) = element_grid_conv.get_pressure(target_chunk, idx) else {
+            continue;
+        };
+        if side_pressure <= pressure * PRESSURE_RELIEF_RATIO
+            && lowest.is_none_or(|(_, lowest)| side_pressure < lowest)
+        {
+            lowest = Some((idx, side_pressure));
+        }
+    }
+    match lowest {
+        Some((idx, _)) if pressure > 0.0 => {
+            self_element.try_swap_me(pos, idx, target_chunk, element_grid_conv, current_time)
+        }
+        _ => ElementTakeOptions::PutBack,
+    }
+}
+
+/// Falls into the empty cell below, or failing that spreads into an empty cell to either side
+fn fluid_fall(
+    self_element: &mut dyn Element,
+    pos: JkVector,
+    coord_dir: &CoordinateDir,
+    target_chunk: &mut ElementGrid,
+    element_grid_conv: &mut ElementGridConvolutionNeighbors,
+    current_time: Clock,
 ) -> ElementTakeOptions {
     // Go down one cell
     let below = element_grid_conv.get_below_idx_from_center(target_chunk, coord_dir, &pos, 1);
@@ -38,6 +169,7 @@ pub fn fluid_process(
         Ok(element) => {
             if element.get_state_of_matter() <= StateOfMatter::Gas {
                 self_element.try_swap_me(
+                    pos,
                     below.unwrap(),
                     target_chunk,
                     element_grid_conv,
@@ -62,12 +194,12 @@ pub fn fluid_process(
                 };
 
                 // Now decide if we go left or right
-                let mut rng = rand::thread_rng();
-                let rand_bool = rng.gen_bool(0.5);
+                let rand_bool = target_chunk.get_rng().gen_bool(0.5);
                 match (element_l, element_r, rand_bool) {
                     (Ok(element_l), Ok(_), false) => {
                         if element_l.get_state_of_matter() <= StateOfMatter::Gas {
                             self_element.try_swap_me(
+                                pos,
                                 new_idx_l.unwrap(),
                                 target_chunk,
                                 element_grid_conv,
@@ -80,6 +212,7 @@ pub fn fluid_process(
                     (Ok(_), Ok(element_r), true) => {
                         if element_r.get_state_of_matter() <= StateOfMatter::Gas {
                             self_element.try_swap_me(
+                                pos,
                                 new_idx_r.unwrap(),
                                 target_chunk,
                                 element_grid_conv,
@@ -92,6 +225,7 @@ pub fn fluid_process(
                     (Ok(element_l), Err(_), _) => {
                         if element_l.get_state_of_matter() <= StateOfMatter::Gas {
                             self_element.try_swap_me(
+                                pos,
                                 new_idx_l.unwrap(),
                                 target_chunk,
                                 element_grid_conv,
@@ -104,6 +238,7 @@ pub fn fluid_process(
                     (Err(_), Ok(element_r), _) => {
                         if element_r.get_state_of_matter() <= StateOfMatter::Gas {
                             self_element.try_swap_me(
+                                pos,
                                 new_idx_r.unwrap(),
                                 target_chunk,
                                 element_grid_conv,
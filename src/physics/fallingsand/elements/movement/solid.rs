@@ -3,16 +3,161 @@ use rand::Rng;
 use crate::physics::{
     fallingsand::{
         convolution::{
-            behaviors::ElementGridConvolutionNeighbors, neighbor_identifiers::ConvolutionIdentifier,
+            behaviors::ElementGridConvolutionNeighbors,
+            neighbor_identifiers::{ConvolutionIdentifier, ConvolutionIdx},
         },
         data::element_grid::ElementGrid,
         elements::element::{Element, ElementTakeOptions, StateOfMatter},
         mesh::coordinate_directory::CoordinateDir,
-        util::vectors::JkVector,
+        util::vectors::{JkVector, Winding},
     },
     util::clock::Clock,
 };
 
+/// Whether a solid can fall into the cell holding `other`
+/// Anything emptier than a liquid gives way, and so do liquids no denser than the solid
+/// Equal densities sink, so only solids lighter than the liquid float
+fn can_sink_into(self_element: &dyn Element, other: &dyn Element) -> bool {
+    match other.get_state_of_matter() {
+        StateOfMatter::Liquid => self_element.get_density() >= other.get_density(),
+        state => state < StateOfMatter::Liquid,
+    }
+}
+
+/// If the solid is lighter than the liquid right above it, that is where it floats up to
+fn buoyant_rise(
+    self_element: &dyn Element,
+    pos: JkVector,
+    target_chunk: &ElementGrid,
+    element_grid_conv: &ElementGridConvolutionNeighbors,
+) -> Option<ConvolutionIdx> {
+    let origin = target_chunk
+        .get_chunk_coords()
+        .get_external_coord_from_internal_coord(pos);
+    let above = element_grid_conv.above_cell_for(target_chunk, origin)?;
+    let element = element_grid_conv.get(target_chunk, above).ok()?;
+    let floats = element.get_state_of_matter() == StateOfMatter::Liquid
+        && self_element.get_density() < element.get_density();
+    floats.then_some(above)
+}
+
+/// How far along k one cell counter clockwise is, which depends on the winding of the layer
+fn counter_clockwise_step(target_chunk: &ElementGrid) -> isize {
+    match target_chunk.get_chunk_coords().get_winding() {
+        Winding::Clockwise => -1,
+        Winding::CounterClockwise => 1,
+    }
+}
+
+/// Pushes a move within the chunk one cell sideways, like the coriolis effect on a spinning celestial
+/// The push is proportional to the radial velocity, so the chance grows with how many cells the move
+/// drops towards the core this step. Falling goes with the spin, rising goes against it.
+/// Only ends up somewhere the element could have moved to anyway, otherwise the move is left as it was
+fn coriolis_deflect(
+    self_element: &dyn Element,
+    to: ConvolutionIdx,
+    pos: JkVector,
+    target_chunk: &mut ElementGrid,
+    element_grid_conv: &ElementGridConvolutionNeighbors,
+) -> ConvolutionIdx {
+    if !matches!(to.1, ConvolutionIdentifier::Center) {
+        return to;
+    }
+    let fallen = pos.j as f32 - to.0.j as f32;
+    let push = target_chunk.get_coriolis_bias() * fallen;
+    if push == 0.0 || !target_chunk.get_rng().gen_bool(push.abs().min(1.0) as f64) {
+        return to;
+    }
+    let rk = counter_clockwise_step(target_chunk) * push.signum() as isize;
+    match element_grid_conv.get_left_right_idx_from_center(target_chunk, &to.0, rk) {
+        Ok(side)
+            if element_grid_conv
+                .get(target_chunk, side)
+                .is_ok_and(|e| can_sink_into(self_element, &*e)) =>
+        {
+            side
+        }
+        _ => to,
+    }
+}
+
+/// Whether a solid that can't fall straight down can slide off into the cell `side` holding `other`
+/// Dry solids slide whenever there is room, wet ones stick together and only slide where the cell
+/// under `side` is open too, so they hold a slope twice as steep
+fn can_slide_to(
+    self_element: &dyn Element,
+    other: &dyn Element,
+    side: ConvolutionIdx,
+    coord_dir: &CoordinateDir,
+    target_chunk: &ElementGrid,
+    element_grid_conv: &ElementGridConvolutionNeighbors,
+) -> bool {
+    if !can_sink_into(self_element, other) {
+        return false;
+    }
+    if self_element.get_wetness() == 0.0 {
+        return true;
+    }
+    if !matches!(side.1, ConvolutionIdentifier::Center) {
+        return false;
+    }
+    element_grid_conv
+        .get_below_idx_from_center(target_chunk, coord_dir, &side.0, 1)
+        .and_then(|below| element_grid_conv.get(target_chunk, below))
+        .is_ok_and(|below| can_sink_into(self_element, &*below))
+}
+
+/// Given that we can fall into `below`, keep looking further down as far as the element
+/// is allowed to fall this step, stopping at the first cell we can't fall into
+///
+/// The allowed fall is measured in cells of the layer we start in. Crossing into a layer
+/// with a different resolution ends the step on the first ring of that layer, the rest of
+/// the fall is worked out next step in the new layer's own cells, so nothing skips past the seam
+fn furthest_fall(
+    self_element: &dyn Element,
+    below: ConvolutionIdx,
+    pos: JkVector,
+    coord_dir: &CoordinateDir,
+    target_chunk: &ElementGrid,
+    element_grid_conv: &ElementGridConvolutionNeighbors,
+    current_time: Clock,
+) -> ConvolutionIdx {
+    let start_layer = target_chunk.get_chunk_coords().get_layer_num();
+    let start_height = coord_dir.get_layer_cell_height(start_layer);
+    let cell_cost = |idx: ConvolutionIdx| {
+        let layer = element_grid_conv.get_layer_num(target_chunk, idx);
+        (layer, coord_dir.get_layer_cell_height(layer) / start_height)
+    };
+    let max_fall = self_element.max_fall_cells_per_step(current_time.get_last_delta());
+    let (mut layer, cost) = cell_cost(below);
+    let mut budget = max_fall as f32 - cost;
+    let mut out = below;
+    for n in 2..=max_fall {
+        if coord_dir.radial_resolution_ratio(start_layer, layer) != 1.0 {
+            break;
+        }
+        let next = element_grid_conv.get_below_idx_from_center(target_chunk, coord_dir, &pos, n);
+        match next {
+            Ok(next)
+                if !element_grid_conv.is_read_only(next)
+                    && element_grid_conv
+                        .get(target_chunk, next)
+                        .is_ok_and(|e| can_sink_into(self_element, &*e)) =>
+            {
+                let (next_layer, cost) = cell_cost(next);
+                if cost > budget {
+                    break;
+                }
+                budget -= cost;
+                layer = next_layer;
+                out = next
+            }
+            _ => break,
+        }
+    }
+    out
+}
+
 /// Default solid element behavior
 pub fn solid_process(
     self_element: &mut dyn Element,
@@ -22,6 +167,12 @@ pub fn solid_process(
     element_grid_conv: &mut ElementGridConvolutionNeighbors,
     current_time: Clock,
 ) -> ElementTakeOptions {
+    // Float up through anything denser
+    if let Some(above) = buoyant_rise(self_element, pos, target_chunk, element_grid_conv) {
+        let above = coriolis_deflect(self_element, above, pos, target_chunk, element_grid_conv);
+        return self_element.try_swap_me(pos, above, target_chunk, element_grid_conv, current_time);
+    }
+
     // Go down one cell
     let below = element_grid_conv.get_below_idx_from_center(target_chunk, coord_dir, &pos, 1);
     match below {
@@ -35,8 +186,25 @@ pub fn solid_process(
                     let element = element_grid_conv.get(target_chunk, idx);
                     match element {
                         Ok(element) => {
-                            if element.get_state_of_matter() <= StateOfMatter::Liquid {
+                            if can_sink_into(self_element, &*element) {
+                                let idx = furthest_fall(
+                                    self_element,
+                                    idx,
+                                    pos,
+                                    coord_dir,
+                                    target_chunk,
+                                    element_grid_conv,
+                                    current_time,
+                                );
+                                let idx = coriolis_deflect(
+                                    self_element,
+                                    idx,
+                                    pos,
+                                    target_chunk,
+                                    element_grid_conv,
+                                );
                                 self_element.try_swap_me(
+                                    pos,
                                     idx,
                                     target_chunk,
                                     element_grid_conv,
@@ -53,80 +221,63 @@ pub fn solid_process(
                                     &idx.0,
                                     -1,
                                 );
-                                let element_l = {
-                                    match new_idx_l {
-                                        Ok(new_idx_l) => {
-                                            element_grid_conv.get(target_chunk, new_idx_l)
-                                        }
-                                        Err(err) => Err(err),
-                                    }
-                                };
-                                let element_r = {
-                                    match new_idx_r {
-                                        Ok(new_idx_r) => {
-                                            element_grid_conv.get(target_chunk, new_idx_r)
-                                        }
-                                        Err(err) => Err(err),
-                                    }
-                                };
-
-                                // Now decide if we go left or right
-                                let mut rng = rand::thread_rng();
-                                let rand_bool = rng.gen_bool(0.5);
-                                match (element_l, element_r, rand_bool) {
-                                    (Ok(element_l), Ok(_), false) => {
-                                        if element_l.get_state_of_matter() <= StateOfMatter::Liquid
-                                        {
-                                            self_element.try_swap_me(
-                                                new_idx_l.unwrap(),
-                                                target_chunk,
-                                                element_grid_conv,
-                                                current_time,
-                                            )
-                                        } else {
-                                            ElementTakeOptions::PutBack
-                                        }
-                                    }
-                                    (Ok(_), Ok(element_r), true) => {
-                                        if element_r.get_state_of_matter() <= StateOfMatter::Liquid
-                                        {
-                                            self_element.try_swap_me(
-                                                new_idx_r.unwrap(),
+                                let slide_l = match new_idx_l {
+                                    Ok(new_idx_l) => element_grid_conv
+                                        .get(target_chunk, new_idx_l)
+                                        .map(|element_l| {
+                                            can_slide_to(
+                                                self_element,
+                                                &*element_l,
+                                                new_idx_l,
+                                                coord_dir,
                                                 target_chunk,
                                                 element_grid_conv,
-                                                current_time,
                                             )
-                                        } else {
-                                            ElementTakeOptions::PutBack
-                                        }
-                                    }
-                                    (Ok(element_l), Err(_), _) => {
-                                        if element_l.get_state_of_matter() <= StateOfMatter::Liquid
-                                        {
-                                            self_element.try_swap_me(
-                                                new_idx_l.unwrap(),
-                                                target_chunk,
-                                                element_grid_conv,
-                                                current_time,
-                                            )
-                                        } else {
-                                            ElementTakeOptions::PutBack
-                                        }
-                                    }
-                                    (Err(_), Ok(element_r), _) => {
-                                        if element_r.get_state_of_matter() <= StateOfMatter::Liquid
-                                        {
-                                            self_element.try_swap_me(
-                                                new_idx_r.unwrap(),
+                                        }),
+                                    Err(err) => Err(err),
+                                };
+                                let slide_r = match new_idx_r {
+                                    Ok(new_idx_r) => element_grid_conv
+                                        .get(target_chunk, new_idx_r)
+                                        .map(|element_r| {
+                                            can_slide_to(
+                                                self_element,
+                                                &*element_r,
+                                                new_idx_r,
+                                                coord_dir,
                                                 target_chunk,
                                                 element_grid_conv,
-                                                current_time,
                                             )
-                                        } else {
-                                            ElementTakeOptions::PutBack
-                                        }
+                                        }),
+                                    Err(err) => Err(err),
+                                };
+
+                                // Now decide if we go left or right
+                                // Sliding off drops one cell towards the core, so a spinning celestial
+                                // pushes it towards its spin, whichever way k goes around the layer
+                                let with_spin = target_chunk.get_coriolis_bias()
+                                    * counter_clockwise_step(target_chunk) as f32;
+                                let go_right = (1.0 - with_spin) / 2.0;
+                                let rand_bool = target_chunk.get_rng().gen_bool(go_right as f64);
+                                match (slide_l, slide_r, rand_bool) {
+                                    (Ok(true), Ok(_), false) | (Ok(true), Err(_), _) => {
+                                        self_element.try_swap_me(
+                                            pos,
+                                            new_idx_l.unwrap(),
+                                            target_chunk,
+                                            element_grid_conv,
+                                            current_time,
+                                        )
                                     }
-                                    (Err(_), Err(_), _) => ElementTakeOptions::PutBack,
+                                    (Ok(_), Ok(true), true) | (Err(_), Ok(true), _) => self_element
+                                        .try_swap_me(
+                                            pos,
+                                            new_idx_r.unwrap(),
+                                            target_chunk,
+                                            element_grid_conv,
+                                            current_time,
+                                        ),
+                                    _ => ElementTakeOptions::PutBack,
                                 }
                             }
                         }
@@ -138,8 +289,18 @@ pub fn solid_process(
                     let element = element_grid_conv.get(target_chunk, idx);
                     match element {
                         Ok(element) => {
-                            if element.get_state_of_matter() <= StateOfMatter::Liquid {
+                            if can_sink_into(self_element, &*element) {
+                                let idx = furthest_fall(
+                                    self_element,
+                                    idx,
+                                    pos,
+                                    coord_dir,
+                                    target_chunk,
+                                    element_grid_conv,
+                                    current_time,
+                                );
                                 self_element.try_swap_me(
+                                    pos,
                                     idx,
                                     target_chunk,
                                     element_grid_conv,
@@ -0,0 +1,235 @@
+use super::element::{Density, Element, ElementTakeOptions, ElementType, StateOfMatter};
+use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::convolution::neighbor_identifiers::ConvolutionIdx;
+use crate::physics::fallingsand::data::element_directory::StateHasher;
+use crate::physics::fallingsand::data::element_grid::ElementGrid;
+use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
+use crate::physics::fallingsand::util::vectors::{IjkVector, JkVector};
+use crate::physics::util::clock::Clock;
+use bevy::render::color::Color;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// What an empty cell needs before a plant will grow into it
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GrowthCondition {
+    /// Any empty cell will do
+    Anywhere,
+    /// The cell has to be above, below, left or right of water
+    #[default]
+    NextToWater,
+}
+
+/// A solid that stays put and, every pass, might copy itself into a neighboring empty cell
+/// It only ever grows through the convolution, so it can't reach past the chunks it is allowed
+/// to touch, and there is nothing above the top of the grid for it to grow into
+#[derive(Copy, Clone, Debug)]
+pub struct Plant {
+    last_processed: Clock,
+    /// The chance of growing each pass, from 0 to 1
+    growth_chance: f32,
+    condition: GrowthCondition,
+}
+
+impl Default for Plant {
+    fn default() -> Self {
+        Self::new(0.05, GrowthCondition::default())
+    }
+}
+
+impl Plant {
+    pub fn new(growth_chance: f32, condition: GrowthCondition) -> Self {
+        debug_assert!((0.0..=1.0).contains(&growth_chance));
+        Self {
+            last_processed: Clock::default(),
+            growth_chance,
+            condition,
+        }
+    }
+
+    /// Whether the cell satisfies our [GrowthCondition]
+    /// The cell's own neighbors are only looked for in the chunk it is in
+    fn can_grow_into(
+        &self,
+        target_chunk: &ElementGrid,
+        element_grid_conv: &ElementGridConvolutionNeighbors,
+        idx: ConvolutionIdx,
+    ) -> bool {
+        let empty = element_grid_conv
+            .get(target_chunk, idx)
+            .is_ok_and(|element| element.get_state_of_matter() == StateOfMatter::Empty);
+        if !empty || element_grid_conv.is_read_only(idx) {
+            return false;
+        }
+        match self.condition {
+            GrowthCondition::Anywhere => true,
+            GrowthCondition::NextToWater => {
+                let JkVector { j, k } = idx.0;
+                [
+                    Some(JkVector::new(j + 1, k)),
+                    j.checked_sub(1).map(|j| JkVector::new(j, k)),
+                    Some(JkVector::new(j, k + 1)),
+                    k.checked_sub(1).map(|k| JkVector::new(j, k)),
+                ]
+                .into_iter()
+                .flatten()
+                .any(|neighbor| {
+                    element_grid_conv
+                        .get(target_chunk, ConvolutionIdx(neighbor, idx.1))
+                        .is_ok_and(|element| element.get_type() == ElementType::Water)
+                })
+            }
+        }
+    }
+}
+
+impl Element for Plant {
+    fn get_type(&self) -> ElementType {
+        ElementType::Plant
+    }
+    fn get_density(&self) -> Density {
+        Density(0.5)
+    }
+    fn get_last_processed(&self) -> Clock {
+        self.last_processed
+    }
+    fn _set_last_processed(&mut self, current_time: Clock) {
+        self.last_processed = current_time;
+    }
+    fn get_state_of_matter(&self) -> StateOfMatter {
+        StateOfMatter::Solid
+    }
+    fn flammability(&self) -> f32 {
+        0.5
+    }
+    // Leaf green
+    fn get_color(&self) -> Color {
+        Color::rgb_u8(40, 160, 60)
+    }
+    fn _process(
+        &mut self,
+        pos: JkVector,
+        coord_dir: &CoordinateDir,
+        target_chunk: &mut ElementGrid,
+        element_grid_conv: &mut ElementGridConvolutionNeighbors,
+        current_time: Clock,
+    ) -> ElementTakeOptions {
+        if !target_chunk.get_rng().gen_bool(self.growth_chance as f64) {
+            return ElementTakeOptions::PutBack;
+        }
+        let coords = target_chunk.get_chunk_coords();
+        let origin = IjkVector::new(
+            coords.get_layer_num(),
+            coords.get_start_concentric_circle_layer_relative() + pos.j,
+            coords.get_start_radial_line() + pos.k,
+        );
+        let neighbors: [Option<ConvolutionIdx>; 4] = [
+            element_grid_conv.above_cell_for(target_chunk, origin),
+            element_grid_conv
+                .get_below_idx_from_center(target_chunk, coord_dir, &pos, 1)
+                .ok(),
+            element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &pos, 1)
+                .ok(),
+            element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &pos, -1)
+                .ok(),
+        ];
+        let candidates: Vec<ConvolutionIdx> = neighbors
+            .into_iter()
+            .flatten()
+            .filter(|idx| self.can_grow_into(target_chunk, element_grid_conv, *idx))
+            .collect();
+        if let Some(&idx) = candidates.choose(target_chunk.get_rng()) {
+            // The sprout waits until next pass to grow itself
+            let mut sprout = self.box_clone();
+            sprout._set_last_processed(current_time);
+            element_grid_conv
+                .replace(target_chunk, idx, sprout, current_time)
+                .expect("Already checked this index");
+        }
+        ElementTakeOptions::PutBack
+    }
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write(&self.growth_chance.to_bits().to_le_bytes());
+        hasher.write(&[self.condition as u8]);
+    }
+    fn box_clone(&self) -> Box<dyn Element> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::physics::fallingsand::data::element_directory::ElementGridDir;
+    use crate::physics::fallingsand::elements::{stone::Stone, water::Water};
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::orbits::components::Length;
+
+    /// The default element grid directory for testing
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(7)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        ElementGridDir::new_empty(coordinate_dir)
+    }
+
+    /// Fills a channel one cell deep with water, walled in so it can't go anywhere
+    fn water_channel(element_grid_dir: &mut ElementGridDir, ks: std::ops::Range<usize>) {
+        let clock = Clock::default();
+        element_grid_dir.set_element(
+            IjkVector::new(3, 1, ks.start - 1),
+            Box::<Stone>::default(),
+            clock,
+        );
+        element_grid_dir.set_element(IjkVector::new(3, 1, ks.end), Box::<Stone>::default(), clock);
+        for k in ks {
+            element_grid_dir.set_element(IjkVector::new(3, 1, k), Box::<Water>::default(), clock);
+        }
+    }
+
+    #[test]
+    fn test_grows_along_wet_cells_only() {
+        let mut element_grid_dir = get_element_grid_dir();
+        let clock = Clock::default();
+        let num_radial_lines = element_grid_dir
+            .get_coordinate_dir()
+            .get_layer_num_radial_lines(3);
+        for k in 0..num_radial_lines {
+            element_grid_dir.set_element(IjkVector::new(3, 0, k), Box::<Stone>::default(), clock);
+        }
+        water_channel(&mut element_grid_dir, 10..20);
+        // Wet too, but only reachable through dry cells
+        water_channel(&mut element_grid_dir, 30..35);
+        element_grid_dir.set_element(
+            IjkVector::new(3, 2, 10),
+            Box::new(Plant::new(1.0, GrowthCondition::NextToWater)),
+            clock,
+        );
+
+        let mut clock = Clock::default();
+        for _ in 0..60 {
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+        }
+
+        let plants: Vec<IjkVector> = element_grid_dir.cells_of_kind(ElementType::Plant).collect();
+        for k in 10..20 {
+            assert!(plants.contains(&IjkVector::new(3, 2, k)), "k {}", k);
+        }
+        assert_eq!(plants.len(), 10, "{:?}", plants);
+        // The water stayed put and wasn't grown over
+        assert_eq!(
+            element_grid_dir.cells_of_kind(ElementType::Water).count(),
+            15
+        );
+    }
+}
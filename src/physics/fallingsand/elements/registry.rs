@@ -0,0 +1,288 @@
+//! Making elements by name, so new ones can be added without touching [ElementType]
+//!
+//! The simulation never needs to know which elements exist, every element processes itself
+//! through the [Element] trait. What used to be hardcoded is making them, in [ElementType::get_element].
+//! The registry does that by name instead, for the elements that ship with the game and for any
+//! registered at startup from outside the crate. Each of those is handed its own [ElementType::Custom]
+//! kind to report as its type, so the simulation can still tell them apart.
+//! Saves and replays write elements down by [Element::get_name] and make them again through here,
+//! so custom elements survive them.
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::fmt;
+
+use bevy::ecs::system::Resource;
+use hashbrown::HashMap;
+use strum::IntoEnumIterator;
+
+use super::element::{CustomKind, Element, ElementType};
+
+/// Makes a fresh element of the kind it is given, see [ElementRegistry::register]
+pub type ElementFactory = Box<dyn Fn(ElementType) -> Box<dyn Element> + Send + Sync>;
+
+/// Something was already registered under that name
+#[derive(Debug, Clone)]
+pub struct DuplicateElementError(pub String);
+impl fmt::Display for DuplicateElementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "An element named {} is already registered", self.0)
+    }
+}
+
+/// Nothing was registered under that name, or as that kind
+#[derive(Debug, Clone)]
+pub struct UnknownElementError(pub String);
+impl fmt::Display for UnknownElementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No element named {} is registered", self.0)
+    }
+}
+
+/// Every element that can be made, by name
+/// Starts out with the built in elements, named like their [ElementType]
+#[derive(Resource)]
+pub struct ElementRegistry {
+    /// How to make each element, and the kind it was given
+    factories: HashMap<String, (ElementType, ElementFactory)>,
+    /// The name of each custom kind, indexed by it
+    custom_names: Vec<String>,
+}
+
+impl Default for ElementRegistry {
+    fn default() -> Self {
+        let mut out = Self {
+            factories: HashMap::new(),
+            custom_names: Vec::new(),
+        };
+        for element_type in ElementType::iter() {
+            out.factories.insert(
+                format!("{:?}", element_type),
+                (element_type, Box::new(|kind| kind.get_element())),
+            );
+        }
+        out
+    }
+}
+
+impl ElementRegistry {
+    /// Adds a new element, which can then be made with [Self::create]
+    /// Names are never replaced, so a mod can't quietly swap out a built in element
+    /// Returns the [ElementType::Custom] kind it was given, which is also handed to the factory.
+    /// What the factory makes should report that kind from [Element::get_type] and give `name` back
+    /// from [Element::get_name], or it won't be told apart from other elements or load from a save
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: ElementFactory,
+    ) -> Result<ElementType, DuplicateElementError> {
+        let name = name.into();
+        if self.factories.contains_key(&name) {
+            return Err(DuplicateElementError(name));
+        }
+        let kind = ElementType::Custom(CustomKind(self.custom_names.len() as u16));
+        self.custom_names.push(name.clone());
+        self.factories.insert(name, (kind, factory));
+        Ok(kind)
+    }
+
+    /// Makes a new element by name
+    pub fn create(&self, name: &str) -> Result<Box<dyn Element>, UnknownElementError> {
+        self.factories
+            .get(name)
+            .map(|(kind, factory)| factory(*kind))
+            .ok_or_else(|| UnknownElementError(name.to_string()))
+    }
+
+    /// Makes a new element by kind, which unlike [ElementType::get_element] covers custom kinds too
+    pub fn create_kind(&self, kind: ElementType) -> Result<Box<dyn Element>, UnknownElementError> {
+        match kind {
+            ElementType::Custom(CustomKind(idx)) => self
+                .custom_names
+                .get(idx as usize)
+                .ok_or_else(|| UnknownElementError(format!("{:?}", kind)))
+                .and_then(|name| self.create(name)),
+            _ => Ok(kind.get_element()),
+        }
+    }
+
+    /// The names of every registered element, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::render::color::Color;
+
+    use super::*;
+    use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+    use crate::physics::fallingsand::data::element_directory::ElementGridDir;
+    use crate::physics::fallingsand::data::element_grid::ElementGrid;
+    use crate::physics::fallingsand::data::save::{load, save};
+    use crate::physics::fallingsand::elements::element::{
+        Density, ElementTakeOptions, StateOfMatter,
+    };
+    use crate::physics::fallingsand::mesh::coordinate_directory::{
+        CoordinateDir, CoordinateDirBuilder,
+    };
+    use crate::physics::fallingsand::util::vectors::{IjkVector, JkVector};
+    use crate::physics::orbits::components::Length;
+    use crate::physics::util::clock::Clock;
+
+    /// An element the core knows nothing about, which does nothing
+    #[derive(Clone, Copy)]
+    struct Inert {
+        kind: ElementType,
+        name: &'static str,
+        last_processed: Clock,
+    }
+
+    impl Inert {
+        /// A factory for inert elements going by `name`
+        fn named(name: &'static str) -> ElementFactory {
+            Box::new(move |kind| {
+                Box::new(Inert {
+                    kind,
+                    name,
+                    last_processed: Clock::default(),
+                })
+            })
+        }
+    }
+
+    impl Element for Inert {
+        fn get_type(&self) -> ElementType {
+            self.kind
+        }
+        fn get_name(&self) -> String {
+            self.name.to_string()
+        }
+        fn get_last_processed(&self) -> Clock {
+            self.last_processed
+        }
+        fn _set_last_processed(&mut self, current_time: Clock) {
+            self.last_processed = current_time;
+        }
+        fn get_color(&self) -> Color {
+            Color::rgb_u8(1, 2, 3)
+        }
+        fn get_density(&self) -> Density {
+            Density(1.0)
+        }
+        fn get_state_of_matter(&self) -> StateOfMatter {
+            StateOfMatter::Solid
+        }
+        fn _process(
+            &mut self,
+            _pos: JkVector,
+            _coord_dir: &CoordinateDir,
+            _target_chunk: &mut ElementGrid,
+            _element_grid_conv: &mut ElementGridConvolutionNeighbors,
+            _current_time: Clock,
+        ) -> ElementTakeOptions {
+            ElementTakeOptions::PutBack
+        }
+        fn box_clone(&self) -> Box<dyn Element> {
+            Box::new(*self)
+        }
+    }
+
+    /// An empty directory to put elements in
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(5)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        ElementGridDir::new_empty(coordinate_dir)
+    }
+
+    #[test]
+    fn test_builtins_are_registered() {
+        let registry = ElementRegistry::default();
+        for element_type in ElementType::iter() {
+            let name = format!("{:?}", element_type);
+            let element = registry.create(&name).unwrap();
+            assert_eq!(element.get_type(), element_type);
+            assert_eq!(element.get_name(), name);
+        }
+        assert!(registry.create("Unobtainium").is_err());
+        assert!(registry
+            .create_kind(ElementType::Custom(CustomKind(0)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_custom_element_is_processed() {
+        let mut registry = ElementRegistry::default();
+        registry.register("Inert", Inert::named("Inert")).unwrap();
+        assert!(registry.register("Inert", Inert::named("Inert")).is_err());
+        assert!(registry.register("Sand", Inert::named("Sand")).is_err());
+
+        let mut element_grid_dir = get_element_grid_dir();
+        let cell = IjkVector::new(3, 4, 5);
+        element_grid_dir.set_element(cell, registry.create("Inert").unwrap(), Clock::default());
+
+        let mut clock = Clock::default();
+        for _ in 0..3 {
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+        }
+        let element = element_grid_dir.get_element(cell);
+        assert_eq!(element.get_name(), "Inert");
+        assert_eq!(
+            element.get_last_processed().get_current_frame(),
+            clock.get_current_frame()
+        );
+        // A coarse layer only knows types, which would lose what it is
+        assert!(element_grid_dir.merge_chunks_in_layer(cell.i, 2).is_err());
+    }
+
+    #[test]
+    fn test_custom_elements_are_told_apart() {
+        let mut registry = ElementRegistry::default();
+        let inert = registry.register("Inert", Inert::named("Inert")).unwrap();
+        let dull = registry.register("Dull", Inert::named("Dull")).unwrap();
+        assert_ne!(inert, dull);
+        assert_eq!(registry.create_kind(dull).unwrap().get_name(), "Dull");
+        assert_eq!(registry.create("Inert").unwrap().get_type(), inert);
+
+        let mut element_grid_dir = get_element_grid_dir();
+        let cell = IjkVector::new(3, 4, 5);
+        element_grid_dir.set_element(cell, registry.create_kind(inert).unwrap(), Clock::default());
+        let (chunk, _) = element_grid_dir
+            .get_coordinate_dir()
+            .cell_idx_to_chunk_idx(cell);
+        let chunk = element_grid_dir.get_chunk_by_chunk_ijk(chunk);
+        assert!(chunk.may_contain(inert));
+        assert!(!chunk.may_contain(dull));
+        assert_eq!(
+            element_grid_dir.cells_of_kind(inert).collect::<Vec<_>>(),
+            [cell]
+        );
+        assert_eq!(element_grid_dir.cells_of_kind(dull).count(), 0);
+    }
+
+    #[test]
+    fn test_custom_element_survives_a_save() {
+        let mut registry = ElementRegistry::default();
+        registry.register("Inert", Inert::named("Inert")).unwrap();
+        let mut element_grid_dir = get_element_grid_dir();
+        let cell = IjkVector::new(3, 4, 5);
+        element_grid_dir.set_element(cell, registry.create("Inert").unwrap(), Clock::default());
+        let mut bytes = Vec::new();
+        save(&element_grid_dir, &mut bytes).unwrap();
+
+        let loaded = load(&mut bytes.as_slice(), &registry).unwrap();
+        assert_eq!(loaded.get_element(cell).get_name(), "Inert");
+        // Without it registered there is nothing to make it from
+        assert!(load(&mut bytes.as_slice(), &ElementRegistry::default()).is_err());
+    }
+}
@@ -1,18 +1,80 @@
-use super::element::{Density, Element, ElementTakeOptions, ElementType, StateOfMatter};
+use super::element::{
+    Density, Element, ElementTakeOptions, ElementType, StateOfMatter, AMBIENT_TEMPERATURE,
+};
+use super::ice::MELTING_POINT;
 use super::movement::solid::solid_process;
 use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
+use crate::physics::fallingsand::convolution::neighbor_identifiers::ConvolutionIdx;
 
+use crate::physics::fallingsand::data::element_directory::StateHasher;
 use crate::physics::fallingsand::data::element_grid::ElementGrid;
 use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
 use crate::physics::fallingsand::util::vectors::JkVector;
 
+use crate::physics::orbits::components::ThermodynamicTemperature;
 use crate::physics::util::clock::Clock;
 use bevy::render::color::Color;
+use std::time::Duration;
+
+/// How many cells per second sand falls
+const FALL_SPEED: f32 = 10.0;
+
+/// How much wetness sand loses per second at [AMBIENT_TEMPERATURE] once it isn't touching water
+const DRYING_RATE: f32 = 0.1;
 
 /// Literally nothing
 #[derive(Default, Copy, Clone, Debug)]
 pub struct Sand {
     last_processed: Clock,
+    /// From 0 to 1, soaked up from neighboring water
+    wetness: f32,
+}
+
+impl Sand {
+    /// Sand that starts out with some wetness, from 0 to 1
+    pub fn new_wet(wetness: f32) -> Self {
+        debug_assert!((0.0..=1.0).contains(&wetness));
+        Self {
+            wetness,
+            ..Default::default()
+        }
+    }
+
+    /// How much wetness evaporates over `delta` from sand at this temperature
+    /// Scales with how far above freezing it is, so frozen sand stays wet and hot sand dries fast
+    fn evaporated(temperature: ThermodynamicTemperature, delta: Duration) -> f32 {
+        let warmth = (temperature.0 - MELTING_POINT.0) / (AMBIENT_TEMPERATURE.0 - MELTING_POINT.0);
+        warmth.max(0.0) * DRYING_RATE * delta.as_secs_f32()
+    }
+
+    /// Whether any of the cells above, below, left or right of us are water
+    fn touching_water(
+        pos: JkVector,
+        coord_dir: &CoordinateDir,
+        target_chunk: &ElementGrid,
+        element_grid_conv: &ElementGridConvolutionNeighbors,
+    ) -> bool {
+        let origin = target_chunk
+            .get_chunk_coords()
+            .get_external_coord_from_internal_coord(pos);
+        let neighbors: [Option<ConvolutionIdx>; 4] = [
+            element_grid_conv.above_cell_for(target_chunk, origin),
+            element_grid_conv
+                .get_below_idx_from_center(target_chunk, coord_dir, &pos, 1)
+                .ok(),
+            element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &pos, 1)
+                .ok(),
+            element_grid_conv
+                .get_left_right_idx_from_center(target_chunk, &pos, -1)
+                .ok(),
+        ];
+        neighbors.into_iter().flatten().any(|idx| {
+            element_grid_conv
+                .get(target_chunk, idx)
+                .is_ok_and(|element| element.get_type() == ElementType::Water)
+        })
+    }
 }
 
 impl Element for Sand {
@@ -34,6 +96,15 @@ impl Element for Sand {
     fn get_color(&self) -> Color {
         Color::YELLOW
     }
+    fn is_loose(&self) -> bool {
+        true
+    }
+    fn max_fall_cells_per_step(&self, delta: Duration) -> usize {
+        ((delta.as_secs_f32() * FALL_SPEED).floor() as usize).max(1)
+    }
+    fn get_wetness(&self) -> f32 {
+        self.wetness
+    }
     fn _process(
         &mut self,
         pos: JkVector,
@@ -42,6 +113,16 @@ impl Element for Sand {
         element_grid_conv: &mut ElementGridConvolutionNeighbors,
         current_time: Clock,
     ) -> ElementTakeOptions {
+        // Soak up water before moving, so the move carries the new wetness with it
+        if Self::touching_water(pos, coord_dir, target_chunk, element_grid_conv) {
+            self.wetness = 1.0;
+        } else {
+            let dried = Self::evaporated(
+                target_chunk.get_temperature(pos),
+                current_time.get_last_delta(),
+            );
+            self.wetness = (self.wetness - dried).max(0.0);
+        }
         solid_process(
             self,
             pos,
@@ -51,6 +132,9 @@ impl Element for Sand {
             current_time,
         )
     }
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write(&self.wetness.to_bits().to_le_bytes());
+    }
     fn box_clone(&self) -> Box<dyn Element> {
         Box::new(*self)
     }
@@ -142,5 +226,442 @@ mod tests {
         }
 
         test_movement!(test_movement_i2_j2_k1, (2, 2, 1), (2, 1, 1));
+
+        /// A hot grain stays hot as it falls, and the vacuum it swapped with stays as cold as it was
+        #[test]
+        fn test_carries_its_temperature() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            let from = IjkVector::new(2, 2, 1);
+            let to = IjkVector::new(2, 1, 1);
+            let cold = element_grid_dir.get_temperature(to);
+            element_grid_dir.set_element(from, Box::<Sand>::default(), clock);
+            let (chunk_idx, jk) = element_grid_dir
+                .get_coordinate_dir()
+                .cell_idx_to_chunk_idx(from);
+            element_grid_dir
+                .get_chunk_by_chunk_ijk_mut(chunk_idx)
+                .set_temperature(jk, ThermodynamicTemperature(500.0), clock);
+
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+
+            assert_eq!(
+                element_grid_dir.get_element(to).get_type(),
+                ElementType::Sand
+            );
+            assert_eq!(
+                element_grid_dir.get_temperature(to),
+                ThermodynamicTemperature(500.0)
+            );
+            assert_eq!(element_grid_dir.get_temperature(from), cold);
+        }
+    }
+
+    /// A column of sand dropped on a spinning planet should pile up in the direction of spin
+    mod coriolis {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::stone::Stone;
+        use crate::physics::fallingsand::util::vectors::{IjkVector, Winding};
+        use crate::physics::orbits::components::AngularVelocity;
+
+        const LAYER: usize = 3;
+        const COLUMN_K: usize = 20;
+
+        /// Drops a column of sand onto a stone floor and returns the average
+        /// tangential distance it ended up from the column, positive is counter clockwise
+        fn drop_column(angular_velocity: AngularVelocity) -> f32 {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.set_coriolis(angular_velocity, 1.0);
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let num_radial_lines = coords.get_layer_num_radial_lines(LAYER);
+            let num_concentric_circles = coords.get_layer_num_concentric_circles(LAYER);
+            let mut clock = Clock::default();
+
+            for k in 0..num_radial_lines {
+                element_grid_dir.set_element(
+                    IjkVector::new(LAYER, 0, k),
+                    Box::<Stone>::default(),
+                    clock,
+                );
+            }
+            for j in 1..num_concentric_circles {
+                element_grid_dir.set_element(
+                    IjkVector::new(LAYER, j, COLUMN_K),
+                    Box::<Sand>::default(),
+                    clock,
+                );
+            }
+
+            for _ in 0..(2 * num_concentric_circles) {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+            }
+
+            let mut total = 0.0;
+            let mut count = 0;
+            for j in 1..num_concentric_circles {
+                for k in 0..num_radial_lines {
+                    let element = element_grid_dir.get_element(IjkVector::new(LAYER, j, k));
+                    if element.get_type() != ElementType::Sand {
+                        continue;
+                    }
+                    let mut dk = k as isize - COLUMN_K as isize;
+                    if dk > num_radial_lines as isize / 2 {
+                        dk -= num_radial_lines as isize;
+                    }
+                    if coords.get_winding() == Winding::Clockwise {
+                        dk = -dk;
+                    }
+                    total += dk as f32;
+                    count += 1;
+                }
+            }
+            assert_eq!(count, num_concentric_circles - 1, "Sand went missing");
+            total / count as f32
+        }
+
+        /// Drops one grain from the top of the layer through open space onto a stone floor
+        /// and returns how far counter clockwise it landed
+        fn drop_grain(angular_velocity: AngularVelocity) -> isize {
+            let mut element_grid_dir = get_element_grid_dir();
+            element_grid_dir.set_coriolis(angular_velocity, 1.0);
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let num_radial_lines = coords.get_layer_num_radial_lines(LAYER);
+            let num_concentric_circles = coords.get_layer_num_concentric_circles(LAYER);
+            let mut clock = Clock::default();
+            for k in 0..num_radial_lines {
+                element_grid_dir.set_element(
+                    IjkVector::new(LAYER, 0, k),
+                    Box::<Stone>::default(),
+                    clock,
+                );
+            }
+            element_grid_dir.set_element(
+                IjkVector::new(LAYER, num_concentric_circles - 1, COLUMN_K),
+                Box::<Sand>::default(),
+                clock,
+            );
+            for _ in 0..(2 * num_concentric_circles) {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+            }
+            let k = (0..num_radial_lines)
+                .find(|&k| {
+                    element_grid_dir
+                        .get_element(IjkVector::new(LAYER, 1, k))
+                        .get_type()
+                        == ElementType::Sand
+                })
+                .expect("The grain should have landed on the floor");
+            let dk = k as isize - COLUMN_K as isize;
+            match coords.get_winding() {
+                Winding::Clockwise => -dk,
+                Winding::CounterClockwise => dk,
+            }
+        }
+
+        /// Nothing holds the grain up, so it never slides, any drift comes from its fall
+        #[test]
+        fn test_falling_grain_drifts_with_spin() {
+            assert_eq!(drop_grain(AngularVelocity(0.0)), 0);
+            assert!(drop_grain(AngularVelocity(0.3)) > 0);
+            assert!(drop_grain(AngularVelocity(-0.3)) < 0);
+        }
+
+        #[test]
+        fn test_sand_drifts_with_spin() {
+            let spinning = drop_column(AngularVelocity(1.0));
+            let control = drop_column(AngularVelocity(0.0));
+            assert!(spinning > 0.0, "Sand drifted {} against the spin", spinning);
+            assert!(
+                spinning > control,
+                "Spinning drift {} is not more than the control {}",
+                spinning,
+                control
+            );
+        }
+    }
+
+    /// A long step lets sand fall further, but never through a floor
+    mod large_delta {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{element::ElementType, stone::Stone};
+        use crate::physics::fallingsand::util::vectors::IjkVector;
+
+        #[test]
+        fn test_does_not_tunnel_through_floor() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let mut clock = Clock::default();
+            let num_radial_lines = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_radial_lines(3);
+            for k in 0..num_radial_lines {
+                element_grid_dir.set_element(
+                    IjkVector::new(3, 4, k),
+                    Box::<Stone>::default(),
+                    clock,
+                );
+            }
+            element_grid_dir.set_element(IjkVector::new(3, 8, 10), Box::<Sand>::default(), clock);
+
+            // Long enough to fall far past the floor in one step
+            clock.update(Duration::from_secs(10));
+            element_grid_dir.process_full(clock);
+
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(3, 5, 10))
+                    .get_type(),
+                ElementType::Sand
+            );
+            assert_eq!(
+                element_grid_dir
+                    .get_element(IjkVector::new(3, 4, 10))
+                    .get_type(),
+                ElementType::Stone
+            );
+            for j in 0..4 {
+                assert_eq!(
+                    element_grid_dir
+                        .get_element(IjkVector::new(3, j, 10))
+                        .get_type(),
+                    ElementType::Vacuum
+                );
+            }
+        }
+    }
+
+    /// Fast sand falling through the seam between two layers lands on the first ring past the seam
+    mod layer_transition {
+        use std::time::Duration;
+
+        use hashbrown::HashSet;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::element::ElementType;
+        use crate::physics::fallingsand::util::vectors::IjkVector;
+
+        /// Where the one grain of sand in the directory is
+        fn find_sand(element_grid_dir: &ElementGridDir, layers: usize) -> IjkVector {
+            let coords = element_grid_dir.get_coordinate_dir();
+            for i in 0..layers {
+                for j in 0..coords.get_layer_num_concentric_circles(i) {
+                    for k in 0..coords.get_layer_num_radial_lines(i) {
+                        let cell = IjkVector::new(i, j, k);
+                        if element_grid_dir.get_element(cell).get_type() == ElementType::Sand {
+                            return cell;
+                        }
+                    }
+                }
+            }
+            panic!("Sand went missing");
+        }
+
+        #[test]
+        fn test_fast_sand_lands_on_every_transition_ring() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let coords = element_grid_dir.get_coordinate_dir().clone();
+            let mut clock = Clock::default();
+            let start = IjkVector::new(4, 3, 20);
+            element_grid_dir.set_element(start, Box::<Sand>::default(), clock);
+
+            // Each step is long enough to fall several rings at once
+            let mut path = vec![start];
+            let mut sand = start;
+            while sand.i >= 2 {
+                clock.update(Duration::from_secs(1));
+                let chunk_idx = coords.cell_idx_to_chunk_idx(sand).0;
+                element_grid_dir.process_chunks(clock, &HashSet::from([chunk_idx]));
+                let next = find_sand(&element_grid_dir, 5);
+                assert!(
+                    next.i < sand.i || next.j < sand.j,
+                    "Sand stopped at {:?}",
+                    next
+                );
+                sand = next;
+                path.push(sand);
+            }
+
+            // It went through both seams, and every time it changed layers it came
+            // to rest on the outermost ring of the new layer
+            let mut transitions = 0;
+            for step in path.windows(2) {
+                if step[1].i != step[0].i {
+                    transitions += 1;
+                    assert_eq!(step[1].i, step[0].i - 1, "Skipped a layer in {:?}", path);
+                    assert_eq!(
+                        step[1].j,
+                        coords.get_layer_num_concentric_circles(step[1].i) - 1,
+                        "Skipped the transition ring in {:?}",
+                        path
+                    );
+                }
+            }
+            assert_eq!(transitions, 3, "{:?}", path);
+            // The first step was cut short at the seam rather than by the fall speed
+            assert_eq!(path[1].i, 3);
+        }
+    }
+
+    /// Sand soaks up neighboring water, which stops it sliding until it dries out
+    mod wetness {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::physics::fallingsand::elements::{
+            element::ElementType, stone::Stone, vacuum::Vacuum, water::Water,
+        };
+        use crate::physics::fallingsand::util::vectors::IjkVector;
+
+        /// A directory with a stone floor all the way around layer 3
+        fn get_floored_element_grid_dir(clock: Clock) -> ElementGridDir {
+            let mut element_grid_dir = get_element_grid_dir();
+            let num_radial_lines = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_radial_lines(3);
+            for k in 0..num_radial_lines {
+                element_grid_dir.set_element(
+                    IjkVector::new(3, 0, k),
+                    Box::<Stone>::default(),
+                    clock,
+                );
+            }
+            element_grid_dir
+        }
+
+        /// How many sand cells are stacked on the floor at k
+        fn column_height(element_grid_dir: &ElementGridDir, k: usize) -> usize {
+            (1..element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_concentric_circles(3))
+                .take_while(|j| {
+                    element_grid_dir
+                        .get_element(IjkVector::new(3, *j, k))
+                        .get_type()
+                        == ElementType::Sand
+                })
+                .count()
+        }
+
+        #[test]
+        fn test_wets_and_dries() {
+            let mut clock = Clock::default();
+            let mut element_grid_dir = get_floored_element_grid_dir(clock);
+            let sand = IjkVector::new(3, 1, 10);
+            let water = IjkVector::new(3, 1, 11);
+            // Water is boxed in by the sand, the floor and this stone so it can't flow away
+            element_grid_dir.set_element(sand, Box::<Sand>::default(), clock);
+            element_grid_dir.set_element(water, Box::<Water>::default(), clock);
+            element_grid_dir.set_element(IjkVector::new(3, 1, 12), Box::<Stone>::default(), clock);
+            assert_eq!(element_grid_dir.get_element(sand).get_wetness(), 0.0);
+
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+            assert_eq!(
+                element_grid_dir.get_element(sand).get_type(),
+                ElementType::Sand
+            );
+            assert_eq!(element_grid_dir.get_element(sand).get_wetness(), 1.0);
+
+            // Take the water away and let it dry
+            element_grid_dir.set_element(water, Box::<Vacuum>::default(), clock);
+            for _ in 0..2 {
+                clock.update(Duration::from_secs_f32(1.0 / DRYING_RATE));
+                element_grid_dir.process_full(clock);
+            }
+            assert_eq!(
+                element_grid_dir.get_element(sand).get_type(),
+                ElementType::Sand
+            );
+            assert_eq!(element_grid_dir.get_element(sand).get_wetness(), 0.0);
+        }
+
+        #[test]
+        fn test_dries_by_the_directory_clock() {
+            let mut element_grid_dir = get_floored_element_grid_dir(Clock::default());
+            let sand = IjkVector::new(3, 1, 10);
+            element_grid_dir.set_element(sand, Box::new(Sand::new_wet(1.0)), Clock::default());
+
+            for _ in 0..4 {
+                element_grid_dir.advance_clock(Duration::from_secs(1));
+                element_grid_dir.process_full(element_grid_dir.clock());
+            }
+            assert_eq!(
+                element_grid_dir.clock().get_current_time(),
+                Duration::from_secs(4)
+            );
+            assert_eq!(element_grid_dir.clock().get_current_frame(), 4);
+            let wetness = element_grid_dir.get_element(sand).get_wetness();
+            assert!(
+                (wetness - (1.0 - 4.0 * DRYING_RATE)).abs() < 1e-5,
+                "{}",
+                wetness
+            );
+
+            element_grid_dir.reset_clock();
+            assert_eq!(element_grid_dir.clock().get_current_time(), Duration::ZERO);
+            assert_eq!(element_grid_dir.clock().get_current_frame(), 0);
+
+            // It keeps drying from the start again, rather than waiting for the old frame count
+            element_grid_dir.advance_clock(Duration::from_secs(1));
+            element_grid_dir.process_full(element_grid_dir.clock());
+            let rewound = element_grid_dir.get_element(sand).get_wetness();
+            assert!(
+                (wetness - rewound - DRYING_RATE).abs() < 1e-5,
+                "{}",
+                rewound
+            );
+        }
+
+        #[test]
+        fn test_wet_sand_piles_steeper() {
+            let pile = |sand: Sand| {
+                let mut clock = Clock::default();
+                let mut element_grid_dir = get_floored_element_grid_dir(clock);
+                for j in 1..6 {
+                    element_grid_dir.set_element(IjkVector::new(3, j, 10), Box::new(sand), clock);
+                }
+                for _ in 0..20 {
+                    clock.update(Duration::from_millis(100));
+                    element_grid_dir.process_full(clock);
+                }
+                column_height(&element_grid_dir, 10)
+            };
+
+            // Dry sand slumps into a low pile, wet sand still slides but holds a steeper one
+            let dry = pile(Sand::default());
+            let wet = pile(Sand::new_wet(1.0));
+            assert!(wet < 5, "Wet sand stood as a column");
+            assert!(dry < wet, "Dry {} is not lower than wet {}", dry, wet);
+        }
+
+        /// Wetness evaporates with heat, faster the hotter it is and not at all below freezing
+        #[test]
+        fn test_dries_with_heat() {
+            let dry_at = |temperature: ThermodynamicTemperature| {
+                let mut clock = Clock::default();
+                let mut element_grid_dir = get_floored_element_grid_dir(clock);
+                let sand = IjkVector::new(3, 1, 10);
+                element_grid_dir.set_element(sand, Box::new(Sand::new_wet(1.0)), clock);
+                let (chunk_idx, jk) = element_grid_dir
+                    .get_coordinate_dir()
+                    .cell_idx_to_chunk_idx(sand);
+                element_grid_dir
+                    .get_chunk_by_chunk_ijk_mut(chunk_idx)
+                    .set_temperature(jk, temperature, clock);
+                clock.update(Duration::from_secs(1));
+                element_grid_dir.process_full(clock);
+                element_grid_dir.get_element(sand).get_wetness()
+            };
+            assert_eq!(dry_at(ThermodynamicTemperature(250.0)), 1.0);
+            let ambient = dry_at(AMBIENT_TEMPERATURE);
+            assert!((ambient - (1.0 - DRYING_RATE)).abs() < 1e-5, "{}", ambient);
+            assert!(dry_at(ThermodynamicTemperature(350.0)) < ambient);
+        }
     }
 }
@@ -4,6 +4,7 @@ use crate::physics::fallingsand::data::element_grid::ElementGrid;
 use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
 use crate::physics::fallingsand::util::vectors::JkVector;
 
+use crate::physics::orbits::components::ThermodynamicTemperature;
 use crate::physics::util::clock::Clock;
 use bevy::render::color::Color;
 use rand::Rng;
@@ -21,6 +22,10 @@ impl Element for SolarPlasma {
     fn get_density(&self) -> Density {
         Density(100.0)
     }
+    // About the surface of the sun
+    fn get_temperature(&self) -> ThermodynamicTemperature {
+        ThermodynamicTemperature(5800.0)
+    }
     fn get_last_processed(&self) -> Clock {
         self.last_processed
     }
@@ -59,6 +64,7 @@ impl Element for SolarPlasma {
             Ok(element) => {
                 if element.get_state_of_matter() <= StateOfMatter::Gas {
                     self.try_swap_me(
+                        pos,
                         below.unwrap(),
                         target_chunk,
                         element_grid_conv,
@@ -83,12 +89,12 @@ impl Element for SolarPlasma {
                     };
 
                     // Now decide if we go left or right
-                    let mut rng = rand::thread_rng();
-                    let rand_bool = rng.gen_bool(0.5);
+                    let rand_bool = target_chunk.get_rng().gen_bool(0.5);
                     match (element_l, element_r, rand_bool) {
                         (Ok(element_l), Ok(_), false) => {
                             if element_l.get_state_of_matter() <= StateOfMatter::Gas {
                                 self.try_swap_me(
+                                    pos,
                                     new_idx_l.unwrap(),
                                     target_chunk,
                                     element_grid_conv,
@@ -101,6 +107,7 @@ impl Element for SolarPlasma {
                         (Ok(_), Ok(element_r), true) => {
                             if element_r.get_state_of_matter() <= StateOfMatter::Gas {
                                 self.try_swap_me(
+                                    pos,
                                     new_idx_r.unwrap(),
                                     target_chunk,
                                     element_grid_conv,
@@ -113,6 +120,7 @@ impl Element for SolarPlasma {
                         (Ok(element_l), Err(_), _) => {
                             if element_l.get_state_of_matter() <= StateOfMatter::Gas {
                                 self.try_swap_me(
+                                    pos,
                                     new_idx_l.unwrap(),
                                     target_chunk,
                                     element_grid_conv,
@@ -125,6 +133,7 @@ impl Element for SolarPlasma {
                         (Err(_), Ok(element_r), _) => {
                             if element_r.get_state_of_matter() <= StateOfMatter::Gas {
                                 self.try_swap_me(
+                                    pos,
                                     new_idx_r.unwrap(),
                                     target_chunk,
                                     element_grid_conv,
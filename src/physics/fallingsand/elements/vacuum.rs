@@ -5,9 +5,13 @@ use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionN
 use crate::physics::fallingsand::data::element_grid::ElementGrid;
 use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir;
 use crate::physics::fallingsand::util::vectors::JkVector;
+use crate::physics::orbits::components::ThermodynamicTemperature;
 use crate::physics::util::clock::Clock;
 
 /// Literally nothing
+/// Every cell always holds an element, and an empty cell holds this one: weightless, invisible,
+/// and never going anywhere. Moving an element is a swap, so whatever moves into vacuum leaves
+/// vacuum behind, and nothing has to check for a missing element
 #[derive(Default, Copy, Clone, Debug)]
 pub struct Vacuum {
     last_processed: Clock,
@@ -23,6 +27,9 @@ impl Element for Vacuum {
     fn get_density(&self) -> Density {
         Density(0.0)
     }
+    fn get_temperature(&self) -> ThermodynamicTemperature {
+        ThermodynamicTemperature(0.0)
+    }
     fn _set_last_processed(&mut self, current_time: Clock) {
         self.last_processed = current_time;
     }
@@ -46,3 +53,48 @@ impl Element for Vacuum {
         Box::new(*self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::physics::fallingsand::data::element_directory::ElementGridDir;
+    use crate::physics::fallingsand::elements::sand::Sand;
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector};
+    use crate::physics::orbits::components::Length;
+
+    #[test]
+    fn test_fresh_chunks_are_vacuum_and_moving_leaves_vacuum() {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(5)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+        let chunk = element_grid_dir.get_chunk_by_chunk_ijk(ChunkIjkVector::new(3, 0, 1));
+        let coords = chunk.get_chunk_coords();
+        for j in 0..coords.get_num_concentric_circles() {
+            for k in 0..coords.get_num_radial_lines() {
+                let element = chunk.get(JkVector { j, k });
+                assert_eq!(element.get_type(), ElementType::Vacuum);
+                assert_eq!(element.get_density().0, 0.0);
+            }
+        }
+
+        let start = IjkVector::new(3, 5, 20);
+        element_grid_dir.set_element(start, Box::<Sand>::default(), Clock::default());
+        let mut clock = Clock::default();
+        clock.update(Duration::from_millis(100));
+        element_grid_dir.process_full(clock);
+        assert_eq!(
+            element_grid_dir.get_element(start).get_type(),
+            ElementType::Vacuum
+        );
+        assert_eq!(element_grid_dir.cells_of_kind(ElementType::Sand).count(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 use super::element::{Density, Element, ElementTakeOptions, ElementType, StateOfMatter};
+use super::ice::MELTING_POINT;
 use super::movement::fluid::fluid_process;
 use crate::physics::fallingsand::convolution::behaviors::ElementGridConvolutionNeighbors;
 use crate::physics::fallingsand::data::element_grid::ElementGrid;
@@ -9,6 +10,7 @@ use crate::physics::util::clock::Clock;
 use bevy::render::color::Color;
 
 /// Literally nothing
+/// Freezes into ice once its cell cools below the [MELTING_POINT] of ice
 #[derive(Default, Copy, Clone, Debug)]
 pub struct Water {
     last_processed: Clock,
@@ -41,6 +43,13 @@ impl Element for Water {
         element_grid_conv: &mut ElementGridConvolutionNeighbors,
         current_time: Clock,
     ) -> ElementTakeOptions {
+        if target_chunk.get_temperature(pos) < MELTING_POINT {
+            // The cold stays in the cell, so the ice doesn't melt straight back
+            target_chunk
+                .transmute(pos, ElementType::Ice, current_time)
+                .expect("Ice is a built in element");
+            return ElementTakeOptions::DoNothing;
+        }
         fluid_process(
             self,
             pos,
@@ -54,3 +63,203 @@ impl Element for Water {
         Box::new(*self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::physics::{
+        fallingsand::{
+            data::{element_directory::ElementGridDir, element_grid::HeatConfig},
+            elements::stone::Stone,
+            mesh::coordinate_directory::CoordinateDirBuilder,
+            util::vectors::IjkVector,
+        },
+        orbits::components::{Length, ThermodynamicTemperature},
+    };
+
+    /// A small directory, so it can be run for many frames
+    fn get_element_grid_dir() -> ElementGridDir {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(5)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        ElementGridDir::new_empty(coordinate_dir)
+    }
+
+    mod pressure_relief {
+        use super::*;
+
+        /// How deep the water is in each column of the basin
+        fn water_depths(
+            element_grid_dir: &ElementGridDir,
+            layer: usize,
+            columns: std::ops::Range<usize>,
+        ) -> Vec<usize> {
+            let num_concentric_circles = element_grid_dir
+                .get_coordinate_dir()
+                .get_layer_num_concentric_circles(layer);
+            columns
+                .map(|k| {
+                    (0..num_concentric_circles)
+                        .filter(|j| {
+                            element_grid_dir
+                                .get_element(IjkVector::new(layer, *j, k))
+                                .get_type()
+                                == ElementType::Water
+                        })
+                        .count()
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_lopsided_pool_levels_out() {
+            let mut element_grid_dir = get_element_grid_dir();
+            let layer = 3;
+            let coord_dir = element_grid_dir.get_coordinate_dir().clone();
+            let mut clock = Clock::default();
+
+            // A floor on the first ring of the layer, and walls either side of a basin
+            // The basin spans a chunk boundary, so the water has to flow between chunks
+            let floor =
+                coord_dir.get_layer_start_radius(layer) + coord_dir.get_layer_cell_height(layer);
+            element_grid_dir.fill_settled(&Stone::default(), floor);
+            let (left_wall, right_wall) = (10, 26);
+            for j in 1..coord_dir.get_layer_num_concentric_circles(layer) {
+                for k in [left_wall, right_wall] {
+                    element_grid_dir.set_element(
+                        IjkVector::new(layer, j, k),
+                        Box::<Stone>::default(),
+                        clock,
+                    );
+                }
+            }
+
+            // All the water piled up against the left wall
+            let basin = left_wall + 1..right_wall;
+            for j in 1..9 {
+                for k in left_wall + 1..left_wall + 5 {
+                    element_grid_dir.set_element(
+                        IjkVector::new(layer, j, k),
+                        Box::<Water>::default(),
+                        clock,
+                    );
+                }
+            }
+            let before = water_depths(&element_grid_dir, layer, basin.clone());
+            assert_eq!(before.iter().max(), Some(&8));
+            assert_eq!(before.iter().min(), Some(&0));
+
+            for _ in 0..300 {
+                clock.update(Duration::from_millis(100));
+                element_grid_dir.process_full(clock);
+            }
+
+            let after = water_depths(&element_grid_dir, layer, basin);
+            assert_eq!(
+                after.iter().sum::<usize>(),
+                before.iter().sum::<usize>(),
+                "Water was lost or made"
+            );
+            let highest = after.iter().max().unwrap();
+            let lowest = after.iter().min().unwrap();
+            assert!(
+                highest - lowest <= 1,
+                "The surface is not level: {:?}",
+                after
+            );
+        }
+    }
+
+    /// Water cooled below freezing turns to ice, and the ice keeps the cold
+    #[test]
+    fn test_freezes_below_the_melting_point() {
+        let mut element_grid_dir = get_element_grid_dir();
+        let mut clock = Clock::default();
+        let (warm, cold) = (IjkVector::new(2, 0, 1), IjkVector::new(2, 0, 4));
+        let chilled = ThermodynamicTemperature(250.0);
+        for cell in [warm, cold] {
+            element_grid_dir.set_element(cell, Box::<Water>::default(), clock);
+        }
+        let (chunk_idx, jk) = element_grid_dir
+            .get_coordinate_dir()
+            .cell_idx_to_chunk_idx(cold);
+        element_grid_dir
+            .get_chunk_by_chunk_ijk_mut(chunk_idx)
+            .set_temperature(jk, chilled, clock);
+
+        clock.update(Duration::from_millis(100));
+        element_grid_dir.process_full(clock);
+
+        // Both may have fallen since, so go by what there is rather than where
+        let ice: Vec<_> = element_grid_dir.cells_of_kind(ElementType::Ice).collect();
+        assert_eq!(ice.len(), 1);
+        assert!(element_grid_dir.get_temperature(ice[0]) < MELTING_POINT);
+        assert_eq!(
+            element_grid_dir.cells_of_kind(ElementType::Water).count(),
+            1
+        );
+    }
+
+    /// A pool heated from below turns over, the hot water rising through the cold across the
+    /// seams between the chunks the pool is split into
+    #[test]
+    fn test_hot_water_rises_across_chunks() {
+        let mut element_grid_dir = get_element_grid_dir();
+        element_grid_dir.set_heat_config(HeatConfig {
+            thermal_expansion: 1e-3,
+            ..Default::default()
+        });
+        let layer = 3;
+        let coord_dir = element_grid_dir.get_coordinate_dir().clone();
+        assert!(coord_dir.get_layer_num_concentric_chunks(layer) > 1);
+        let num_concentric_circles = coord_dir.get_layer_num_concentric_circles(layer);
+        let num_radial_lines = coord_dir.get_layer_num_radial_lines(layer);
+        let mut clock = Clock::default();
+        element_grid_dir.fill_settled(&Stone::default(), coord_dir.get_layer_start_radius(layer));
+
+        let (hot, cold) = (
+            ThermodynamicTemperature(400.0),
+            ThermodynamicTemperature(280.0),
+        );
+        for j in 0..num_concentric_circles {
+            for k in 0..num_radial_lines {
+                let cell = IjkVector::new(layer, j, k);
+                element_grid_dir.set_element(cell, Box::<Water>::default(), clock);
+                let (chunk_idx, jk) = coord_dir.cell_idx_to_chunk_idx(cell);
+                let temperature = if j < 2 { hot } else { cold };
+                element_grid_dir
+                    .get_chunk_by_chunk_ijk_mut(chunk_idx)
+                    .set_temperature(jk, temperature, clock);
+            }
+        }
+        let hot_in_ring = |element_grid_dir: &ElementGridDir, j: usize| {
+            (0..num_radial_lines)
+                .filter(|&k| element_grid_dir.get_temperature(IjkVector::new(layer, j, k)) == hot)
+                .count()
+        };
+        let top = num_concentric_circles - 1;
+        assert_eq!(hot_in_ring(&element_grid_dir, top), 0);
+
+        for _ in 0..100 {
+            clock.update(Duration::from_millis(100));
+            element_grid_dir.process_full(clock);
+        }
+
+        // The hot water floated up out of the bottom chunk to the top of the pool
+        assert_eq!(hot_in_ring(&element_grid_dir, top), num_radial_lines);
+        assert_eq!(hot_in_ring(&element_grid_dir, top - 1), num_radial_lines);
+        assert_eq!(hot_in_ring(&element_grid_dir, 0), 0);
+        // Nothing but the order changed
+        assert_eq!(
+            element_grid_dir.cells_of_kind(ElementType::Water).count(),
+            num_concentric_circles * num_radial_lines
+        );
+    }
+}
@@ -1,13 +1,12 @@
 use crate::physics::fallingsand::util::functions::interpolate_points;
 
 use crate::physics::fallingsand::util::mesh::OwnedMeshData;
-use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector, JkVector};
+use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector, JkVector, Winding};
 use crate::physics::orbits::components::Length;
 use crate::physics::util::vectors::{RelXyPoint, Vertex};
 use bevy::math::{Rect, Vec2};
 use bevy::render::color::Color;
-
-use std::f32::consts::PI;
+use bevy::transform::components::Transform;
 
 /// The settings for generating the vertexes
 #[derive(Debug, Clone, Copy)]
@@ -55,6 +54,10 @@ pub enum VertexMode {
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ChunkCoords {
     width: Length,
+    /// The radius of the innermost concentric circle, the layers below can have other cell widths
+    start_radius: f32,
+    /// Which way k goes around the layer
+    winding: Winding,
     chunk_idx: ChunkIjkVector,
     start_concentric_circle_layer_relative: usize,
     start_concentric_circle_absolute: usize,
@@ -66,6 +69,8 @@ pub struct ChunkCoords {
 
 pub struct PartialLayerChunkCoordsBuilder {
     cell_width: Length,
+    start_radius: Option<f32>,
+    winding: Winding,
     chunk_idx: ChunkIjkVector,
     start_concentric_circle_layer_relative: usize,
     start_concentric_circle_absolute: usize,
@@ -86,6 +91,8 @@ impl PartialLayerChunkCoordsBuilder {
     pub fn new() -> PartialLayerChunkCoordsBuilder {
         PartialLayerChunkCoordsBuilder {
             cell_width: Length(1.0),
+            start_radius: None,
+            winding: Winding::default(),
             chunk_idx: ChunkIjkVector::ZERO,
             start_concentric_circle_layer_relative: 0,
             start_concentric_circle_absolute: 0,
@@ -103,6 +110,21 @@ impl PartialLayerChunkCoordsBuilder {
         self
     }
 
+    /// Set the radius the chunk starts at
+    /// Only needed when the cells below are a different size, by default every cell from the
+    /// center out is assumed to be as wide as these
+    pub fn start_radius(mut self, start_radius: f32) -> PartialLayerChunkCoordsBuilder {
+        debug_assert!(start_radius >= 0.0);
+        self.start_radius = Some(start_radius);
+        self
+    }
+
+    /// Set which way k goes around the layer
+    pub fn winding(mut self, winding: Winding) -> PartialLayerChunkCoordsBuilder {
+        self.winding = winding;
+        self
+    }
+
     /// Set the index of the first concentric circle starting from the beginning of the layer
     pub fn start_concentric_circle_layer_relative(
         mut self,
@@ -164,6 +186,10 @@ impl PartialLayerChunkCoordsBuilder {
         debug_assert_ne!(self.end_radial_line, 0);
         ChunkCoords {
             width: self.cell_width,
+            start_radius: self
+                .start_radius
+                .unwrap_or(self.start_concentric_circle_absolute as f32 * self.cell_width.0),
+            winding: self.winding,
             start_concentric_circle_layer_relative: self.start_concentric_circle_layer_relative,
             start_concentric_circle_absolute: self.start_concentric_circle_absolute,
             start_radial_line: self.start_radial_line,
@@ -186,7 +212,6 @@ impl ChunkCoords {
         let ending_r = self.get_end_radius();
         let circle_separation_distance =
             (ending_r - starting_r) / self.get_num_concentric_circles() as f32;
-        let theta = (-2.0 * PI) / self.layer_num_radial_lines as f32;
 
         // Create the concentric range with the appropriate level of detail and test it has the right bounds
         let start_concentric = self.start_concentric_circle_layer_relative;
@@ -228,13 +253,15 @@ impl ChunkCoords {
 
             for k in &radial_range {
                 if j == 0 && k % 2 == 1 {
-                    let angle_next = (k + 1) as f32 * theta;
+                    let angle_next = self
+                        .winding
+                        .angle((k + 1) as f32, self.layer_num_radial_lines);
                     let radius = starting_r + diff;
                     let v_last = vertexes.last().unwrap();
                     let v_next = Vec2::new(angle_next.cos() * radius, angle_next.sin() * radius);
                     vertexes.push(interpolate_points(v_last, &v_next));
                 } else {
-                    let angle_point = (*k as f32) * theta;
+                    let angle_point = self.winding.angle(*k as f32, self.layer_num_radial_lines);
                     let radius = starting_r + diff;
                     let new_coord =
                         Vec2::new(angle_point.cos() * radius, angle_point.sin() * radius);
@@ -257,7 +284,6 @@ impl ChunkCoords {
         let ending_r = self.get_end_radius();
         let circle_separation_distance =
             (ending_r - starting_r) / self.get_num_concentric_circles() as f32;
-        let theta = (-2.0 * PI) / self.layer_num_radial_lines as f32;
 
         for j in [
             start_concentric_circle,
@@ -275,13 +301,15 @@ impl ChunkCoords {
 
             for k in iter {
                 if j == 0 && k % 2 == 1 {
-                    let angle_next = (k + 1) as f32 * theta;
+                    let angle_next = self
+                        .winding
+                        .angle((k + 1) as f32, self.layer_num_radial_lines);
                     let radius = starting_r + diff;
                     let v_last = vertexes.last().unwrap();
                     let v_next = Vec2::new(angle_next.cos() * radius, angle_next.sin() * radius);
                     vertexes.push(interpolate_points(v_last, &v_next));
                 } else {
-                    let angle_point = k as f32 * theta;
+                    let angle_point = self.winding.angle(k as f32, self.layer_num_radial_lines);
                     let radius = starting_r + diff;
                     let new_coord =
                         Vec2::new(angle_point.cos() * radius, angle_point.sin() * radius);
@@ -293,6 +321,18 @@ impl ChunkCoords {
         vertexes
     }
 
+    /// The outer edge of the chunk, one vertex per radial line from the start to the end
+    /// Goes in increasing k, unlike the outer half of [Self::get_outline] which runs back
+    pub fn get_outer_arc(&self) -> Vec<Vec2> {
+        let radius = self.get_end_radius();
+        (self.start_radial_line..self.end_radial_line + 1)
+            .map(|k| {
+                let angle = self.winding.angle(k as f32, self.layer_num_radial_lines);
+                Vec2::new(angle.cos() * radius, angle.sin() * radius)
+            })
+            .collect()
+    }
+
     /// Gets the min and max positions in raw x, y of the chunk
     pub fn get_bounding_box(&self) -> Rect {
         let outline = self.get_outline();
@@ -305,6 +345,72 @@ impl ChunkCoords {
         Rect::new(min_x, min_y, max_x, max_y)
     }
 
+    /// Gets a circle, as a center and radius in raw x, y, that encloses the whole chunk
+    /// Thin outer wedges fit best around their bounding box center, while wide chunks
+    /// that wrap around the planet fit best around the planet center, so this tries both
+    pub fn get_bounding_circle(&self) -> (Vec2, f32) {
+        let outline = self.get_outline();
+        let enclosing_radius = |center: Vec2| {
+            outline
+                .iter()
+                .map(|v| v.distance(center))
+                .fold(0.0, f32::max)
+        };
+        [self.get_bounding_box().center(), Vec2::ZERO]
+            .into_iter()
+            .map(|center| (center, enclosing_radius(center)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap()
+    }
+
+    /// A tighter visibility test than the bounding box for culling.
+    /// Outer chunks are thin wedges, so their bounding box covers a lot of empty space,
+    /// especially towards the center of the circle. This tests whether the actual wedge,
+    /// bounded by its two arcs and two radial edges, intersects the camera rect.
+    /// The camera rect is in world coordinates, and the transform is the chunk's global transform.
+    pub fn angular_visible(&self, camera_rect: Rect, transform: &Transform) -> bool {
+        let outline: Vec<Vec2> = self
+            .get_outline()
+            .into_iter()
+            .map(|v| {
+                transform
+                    .transform_point((v * self.get_cell_width().0).extend(0.0))
+                    .truncate()
+            })
+            .collect();
+
+        // Any point of the wedge inside the camera
+        if outline.iter().any(|v| camera_rect.contains(*v)) {
+            return true;
+        }
+
+        // The camera is entirely inside the wedge
+        if point_in_polygon(camera_rect.center(), &outline) {
+            return true;
+        }
+
+        // Any edge of the wedge crossing the camera
+        let corners = [
+            camera_rect.min,
+            Vec2::new(camera_rect.max.x, camera_rect.min.y),
+            camera_rect.max,
+            Vec2::new(camera_rect.min.x, camera_rect.max.y),
+        ];
+        for idx in 0..outline.len() {
+            let a = outline[idx];
+            let b = outline[(idx + 1) % outline.len()];
+            for c_idx in 0..corners.len() {
+                let c = corners[c_idx];
+                let d = corners[(c_idx + 1) % corners.len()];
+                if segments_intersect(a, b, c, d) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
     /// Gets the UV coordinates of the vertexes of the chunk
     /// This is a more traditional square grid
     /// If you set skip to 1, you will get the full resolution
@@ -380,13 +486,17 @@ impl ChunkCoords {
     pub fn total_size(&self) -> usize {
         self.get_num_radial_lines() * self.get_num_concentric_circles()
     }
-    /// Get the width of a cell (which is a square in this case)
+    /// Get the width of a cell, which is also how far apart the concentric circles are
     pub fn get_cell_width(&self) -> Length {
         self.width
     }
+    /// Which way k goes around the layer
+    pub fn get_winding(&self) -> Winding {
+        self.winding
+    }
     /// Get the radius of the smallest concentric circle
     pub fn get_start_radius(&self) -> f32 {
-        self.start_concentric_circle_absolute as f32 * self.width.0
+        self.start_radius
     }
     /// Get the radius of the largest concentric circle
     pub fn get_end_radius(&self) -> f32 {
@@ -402,13 +512,16 @@ impl ChunkCoords {
     pub fn get_num_concentric_circles(&self) -> usize {
         self.num_concentric_circles
     }
+    /// The angle of the chunk's last edge, counter clockwise from 3 o'clock like `atan2`
+    /// With the default clockwise [Winding] this is below the start theta, see [Winding::angle]
     pub fn get_end_theta(&self) -> f32 {
-        let diff = (2.0 * PI) / self.layer_num_radial_lines as f32;
-        self.end_radial_line as f32 * diff
+        self.winding
+            .angle(self.end_radial_line as f32, self.layer_num_radial_lines)
     }
+    /// The angle of the chunk's first edge, counter clockwise from 3 o'clock like `atan2`
     pub fn get_start_theta(&self) -> f32 {
-        let diff = (2.0 * PI) / self.layer_num_radial_lines as f32;
-        self.start_radial_line as f32 * diff
+        self.winding
+            .angle(self.start_radial_line as f32, self.layer_num_radial_lines)
     }
     /// Get the index of the first concentric circle starting from the beginning of the layer
     pub fn get_start_concentric_circle_layer_relative(&self) -> usize {
@@ -437,6 +550,10 @@ impl ChunkCoords {
     pub fn get_start_radial_line(&self) -> usize {
         self.start_radial_line
     }
+    /// Get the number of radial lines in the whole layer this chunk is a part of
+    pub fn get_layer_num_radial_lines(&self) -> usize {
+        self.layer_num_radial_lines
+    }
     /// Get the layer number this chunk is a part of
     pub fn get_layer_num(&self) -> usize {
         self.chunk_idx.i
@@ -450,31 +567,84 @@ impl ChunkCoords {
     /// Checks to see if an absolute position around the circle is in the chunk
     pub fn contains(&self, idx: IjkVector) -> bool {
         idx.i == self.get_layer_num()
-            && idx.j >= self.get_start_radial_line()
-            && idx.j < self.get_end_radial_line()
-            && idx.k >= self.get_start_concentric_circle_absolute()
-            && idx.k < self.get_end_concentric_circle_absolute()
+            && idx.j >= self.get_start_concentric_circle_layer_relative()
+            && idx.j < self.get_end_concentric_circle_layer_relative()
+            && idx.k >= self.get_start_radial_line()
+            && idx.k < self.get_end_radial_line()
     }
     /// Converts a coordinate from anywhere on the circle, assuming it is in the chunk
     /// to a coordinate inside the grid of this chunk
     pub fn get_internal_coord_from_external_coord(&self, external_coord: IjkVector) -> JkVector {
         debug_assert!(self.contains(external_coord));
         JkVector {
-            j: external_coord.j - self.get_start_radial_line(),
-            k: external_coord.k - self.get_start_concentric_circle_absolute(),
+            j: external_coord.j - self.get_start_concentric_circle_layer_relative(),
+            k: external_coord.k - self.get_start_radial_line(),
         }
     }
     /// Converts a coordinate from inside this chunk to a coordinate on the circle
     pub fn get_external_coord_from_internal_coord(&self, internal_coord: JkVector) -> IjkVector {
-        debug_assert!(internal_coord.j < self.get_num_radial_lines());
-        debug_assert!(internal_coord.k < self.get_num_concentric_circles());
+        debug_assert!(internal_coord.j < self.get_num_concentric_circles());
+        debug_assert!(internal_coord.k < self.get_num_radial_lines());
         IjkVector {
             i: self.get_layer_num(),
-            j: internal_coord.j + self.get_start_radial_line(),
-            k: internal_coord.k + self.get_start_concentric_circle_absolute(),
+            j: internal_coord.j + self.get_start_concentric_circle_layer_relative(),
+            k: internal_coord.k + self.get_start_radial_line(),
         }
     }
 
+    /// The pixel a cell is drawn to in this chunk's texture, as (x, y)
+    /// Textures are laid out a row per concentric circle, so k runs across and j runs down
+    pub fn cell_to_texel(&self, local: JkVector) -> (u32, u32) {
+        debug_assert!(local.k < self.get_num_radial_lines());
+        debug_assert!(local.j < self.get_num_concentric_circles());
+        (local.k as u32, local.j as u32)
+    }
+
+    /// One vertex of [Self::get_positions] in [VertexMode::Grid], by its layer relative circle and radial line
+    /// Odd radial lines on the bottom of a layer sit halfway between their neighbors, so the edge
+    /// lines up with the layer below, which has half as many
+    fn grid_position(&self, j: usize, k: usize) -> Vec2 {
+        let circle_separation_distance = (self.get_end_radius() - self.get_start_radius())
+            / self.get_num_concentric_circles() as f32;
+        let radius = self.get_start_radius()
+            + (j - self.start_concentric_circle_layer_relative) as f32 * circle_separation_distance;
+        let point = |k: usize| {
+            let angle = self.winding.angle(k as f32, self.layer_num_radial_lines);
+            Vec2::new(angle.cos() * radius, angle.sin() * radius)
+        };
+        if j == 0 && k % 2 == 1 {
+            interpolate_points(&point(k - 1), &point(k + 1))
+        } else {
+            point(k)
+        }
+    }
+
+    /// The four corners of a cell in the chunk's mesh, the same space as [Self::get_vertices]
+    /// The inner edge in increasing k, then back along the outer edge, like [Self::get_outline]
+    pub fn cell_quad(&self, local: JkVector) -> [Vec2; 4] {
+        debug_assert!(local.j < self.get_num_concentric_circles());
+        debug_assert!(local.k < self.get_num_radial_lines());
+        let j = self.start_concentric_circle_layer_relative + local.j;
+        let k = self.start_radial_line + local.k;
+        [
+            self.grid_position(j, k),
+            self.grid_position(j, k + 1),
+            self.grid_position(j + 1, k + 1),
+            self.grid_position(j + 1, k),
+        ]
+        .map(|corner| corner * self.get_cell_width().0)
+    }
+
+    /// Every cell in the chunk with its [Self::cell_quad], a row at a time from the inside out
+    pub fn iter_cell_quads(&self) -> impl Iterator<Item = (JkVector, [Vec2; 4])> + '_ {
+        (0..self.get_num_concentric_circles()).flat_map(move |j| {
+            (0..self.get_num_radial_lines()).map(move |k| {
+                let local = JkVector { j, k };
+                (local, self.cell_quad(local))
+            })
+        })
+    }
+
     /* Convienience Functions */
     /// Get all the vertexes for the chunk
     pub fn get_vertices(&self, settings: VertexSettings) -> Vec<Vertex> {
@@ -509,16 +679,73 @@ impl ChunkCoords {
         OwnedMeshData::new(vertices, indices)
     }
     /// Get the mesh data for the chunk as you would normally draw it
+    /// The core is fanned out from its center, see [Self::calc_core_fan_meshdata]
     pub fn calc_chunk_meshdata(&self, settings: VertexSettings) -> OwnedMeshData {
+        if self.is_core() {
+            return self.calc_core_fan_meshdata(settings);
+        }
         let indices = self.get_indices(settings);
         let vertices: Vec<Vertex> = self.get_vertices(settings);
         OwnedMeshData::new(vertices, indices)
     }
 
+    /// Whether this chunk is the core, the only chunk whose innermost circle is the center point
+    pub fn is_core(&self) -> bool {
+        self.start_concentric_circle_absolute == 0
+    }
+
+    /// Get the mesh data for the core
+    /// The grid puts a whole row of vertices on the center point, which makes half the triangles
+    /// of the innermost ring zero area. Instead this keeps one center vertex and fans the
+    /// innermost ring out from it, one triangle per cell, and the rest of the rings are quads as usual
+    /// Each fan triangle takes its cell's color from the texture along its outer edge
+    pub fn calc_core_fan_meshdata(&self, settings: VertexSettings) -> OwnedMeshData {
+        debug_assert!(self.is_core());
+        let grid_vertices = self.get_vertices(settings);
+        let k_count = (0..(self.get_num_radial_lines() + 1))
+            .step_by(settings.lod)
+            .len();
+        let j_count = grid_vertices.len() / k_count;
+
+        // Swap the first row for a single center vertex
+        let center = Vertex {
+            position: Vec2::ZERO,
+            uv: Vec2::new(0.5, grid_vertices[0].uv.y),
+            color: grid_vertices[0].color,
+        };
+        let mut vertices = Vec::with_capacity(grid_vertices.len() - k_count + 1);
+        vertices.push(center);
+        vertices.extend(grid_vertices.into_iter().skip(k_count));
+
+        let mut indices = Vec::with_capacity(j_count * k_count * 6);
+        for k in 0..k_count - 1 {
+            // Same winding as the grid (top-right, bottom-left, bottom-right), with the center on top
+            indices.push(0);
+            indices.push((1 + k) as u32);
+            indices.push((2 + k) as u32);
+        }
+        for j in 0..j_count - 2 {
+            for k in 0..k_count - 1 {
+                let v0 = 1 + j * k_count + k; // Top-left
+                let v1 = v0 + 1; // Top-right
+                let v2 = v0 + k_count + 1; // Bottom-right
+                let v3 = v0 + k_count; // Bottom-left
+
+                indices.push(v0 as u32);
+                indices.push(v3 as u32);
+                indices.push(v1 as u32);
+
+                indices.push(v1 as u32);
+                indices.push(v3 as u32);
+                indices.push(v2 as u32);
+            }
+        }
+        OwnedMeshData::new(vertices, indices)
+    }
+
     /// Get the wireframe mesh data for the chunk
     pub fn calc_chunk_triangle_wireframe(&self, settings: VertexSettings) -> OwnedMeshData {
-        let indices = self.get_indices(settings);
-        let vertices: Vec<Vertex> = self.get_vertices(settings);
+        let OwnedMeshData { vertices, indices } = self.calc_chunk_meshdata(settings);
         let mut new_indices = Vec::new();
         for i in (0..indices.len()).step_by(3) {
             let i1 = indices[i];
@@ -541,11 +768,8 @@ impl ChunkCoords {
         let starting_r = self.get_start_radius();
         let ending_r = self.get_end_radius();
         let num_concentric_circles = self.get_num_concentric_circles();
-        let num_radial_lines = self.get_num_radial_lines();
         let start_radial_line = self.get_start_radial_line();
         let end_radial_line = self.get_end_radial_line();
-        let start_theta = self.get_start_theta();
-        let end_theta = self.get_end_theta();
 
         // Get the concentric circle we are on
         let circle_separation_distance = (ending_r - starting_r) / num_concentric_circles as f32;
@@ -555,13 +779,12 @@ impl ChunkCoords {
             ((norm_vertex_coord - starting_r) / circle_separation_distance).floor() as usize;
         let j = j_rel.min(end_concentric_circle - 1) + start_concentric_circle;
 
-        // Get the radial line to the left of the vertex
-        let angle = (xy_coord.0.y.atan2(xy_coord.0.x) + -2.0 * PI) % (2.0 * PI);
-        let theta = -(end_theta - start_theta) / num_radial_lines as f32;
-
-        // Calculate 'k' directly without the while loop
-        let k_rel = (angle / theta).floor() as usize;
-        let k = k_rel.min(end_radial_line - 1);
+        // Get the radial line the vertex is in
+        let angle = xy_coord.0.y.atan2(xy_coord.0.x);
+        let k = self
+            .winding
+            .radial_line_at(angle, self.layer_num_radial_lines)
+            .min(end_radial_line - 1);
 
         // Check to see if the vertex is in the chunk
         if j < start_concentric_circle && j >= end_concentric_circle {
@@ -631,9 +854,37 @@ impl ChunkCoords {
     }
 }
 
+/// Ray casting test for a point inside a (possibly concave) polygon
+fn point_in_polygon(point: Vec2, polygon: &[Vec2]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (pi, pj) = (polygon[i], polygon[j]);
+        if (pi.y > point.y) != (pj.y > point.y)
+            && point.x < (pj.x - pi.x) * (point.y - pi.y) / (pj.y - pi.y) + pi.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Tests if the line segment ab crosses the line segment cd
+fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let cross = |o: Vec2, p: Vec2, q: Vec2| (p - o).perp_dot(q - o);
+    let d1 = cross(c, d, a);
+    let d2 = cross(c, d, b);
+    let d3 = cross(a, b, c);
+    let d4 = cross(a, b, d);
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::f32::consts::PI;
 
     use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
     use crate::physics::fallingsand::util::vectors::{IjkVector, JkVector};
@@ -798,6 +1049,8 @@ mod tests {
 
         pub const FIRST_LAYER: ChunkCoords = ChunkCoords {
             width: Length(1.0),
+            start_radius: 1.0,
+            winding: Winding::Clockwise,
             num_concentric_circles: 2,
             chunk_idx: ChunkIjkVector { i: 1, j: 0, k: 0 },
             start_concentric_circle_layer_relative: 0,
@@ -807,6 +1060,25 @@ mod tests {
             start_concentric_circle_absolute: 1,
         };
 
+        #[test]
+        fn test_cells_cover_every_texel_once() {
+            let width = FIRST_LAYER.get_num_radial_lines();
+            let height = FIRST_LAYER.get_num_concentric_circles();
+            let mut seen = std::collections::HashSet::new();
+            let mut pixel_idx = 0;
+            // Same order the texture pushes its pixels in
+            for j in 0..height {
+                for k in 0..width {
+                    let (x, y) = FIRST_LAYER.cell_to_texel(JkVector { j, k });
+                    assert!((x as usize) < width && (y as usize) < height);
+                    assert_eq!(y as usize * width + x as usize, pixel_idx);
+                    assert!(seen.insert((x, y)), "({}, {}) was drawn twice", x, y);
+                    pixel_idx += 1;
+                }
+            }
+            assert_eq!(seen.len(), FIRST_LAYER.total_size());
+        }
+
         #[test]
         fn test_first_layer_circle() {
             let vertices = FIRST_LAYER.get_positions(VertexSettings::default());
@@ -1067,6 +1339,8 @@ mod tests {
 
         pub const FIRST_LAYER_PARTIAL: ChunkCoords = ChunkCoords {
             width: Length(1.0),
+            start_radius: 3.0,
+            winding: Winding::Clockwise,
             num_concentric_circles: 1,
             chunk_idx: ChunkIjkVector { i: 1, j: 0, k: 0 },
             start_concentric_circle_layer_relative: 1,
@@ -1187,6 +1461,54 @@ mod tests {
             )
         }
 
+        #[test]
+        fn test_angular_visible_culls_inside_bounding_box() {
+            // The chunk is the upper half of the ring between radius 3 and 4
+            let bb = FIRST_LAYER_PARTIAL.get_bounding_box();
+            let camera_rect = Rect::new(-0.5, 0.5, 0.5, 1.5);
+            assert!(!bb.intersect(camera_rect).is_empty());
+            assert!(!FIRST_LAYER_PARTIAL.angular_visible(camera_rect, &Transform::IDENTITY));
+        }
+
+        #[test]
+        fn test_angular_visible_on_wedge() {
+            let transform = Transform::IDENTITY;
+            // Straddling the outer arc
+            let camera_rect = Rect::new(-0.5, 3.5, 0.5, 4.5);
+            assert!(FIRST_LAYER_PARTIAL.angular_visible(camera_rect, &transform));
+            // Entirely inside the wedge
+            let camera_rect = Rect::new(-0.1, 3.4, 0.1, 3.6);
+            assert!(FIRST_LAYER_PARTIAL.angular_visible(camera_rect, &transform));
+            // Camera contains the whole chunk
+            let camera_rect = Rect::new(-10.0, -10.0, 10.0, 10.0);
+            assert!(FIRST_LAYER_PARTIAL.angular_visible(camera_rect, &transform));
+            // Below the chunk, on the other half of the ring
+            let camera_rect = Rect::new(-0.5, -3.6, 0.5, -3.4);
+            assert!(!FIRST_LAYER_PARTIAL.angular_visible(camera_rect, &transform));
+            // Moving the chunk moves its visible area
+            let transform = Transform::from_xyz(0.0, -7.0, 0.0);
+            assert!(FIRST_LAYER_PARTIAL.angular_visible(camera_rect, &transform));
+        }
+
+        #[test]
+        fn test_bounding_circle_encloses_outline() {
+            for coords in [FIRST_LAYER_PARTIAL, super::full_layer::FIRST_LAYER] {
+                let (center, radius) = coords.get_bounding_circle();
+                for v in coords.get_outline() {
+                    assert!(
+                        v.distance(center) <= radius + 1e-4,
+                        "{:?} is outside the circle at {:?} with radius {}",
+                        v,
+                        center,
+                        radius
+                    );
+                }
+            }
+            // Never looser than the circle around the whole ring
+            let (_, radius) = FIRST_LAYER_PARTIAL.get_bounding_circle();
+            assert!(radius <= FIRST_LAYER_PARTIAL.get_end_radius() + 1e-4);
+        }
+
         #[test]
         fn test_first_layer_uv_partial() {
             let uvs = FIRST_LAYER_PARTIAL.get_uvs(VertexSettings::default());
@@ -1266,11 +1588,13 @@ mod tests {
             use crate::physics::fallingsand::mesh::chunk_coords::{
                 ChunkCoords, VertexMode, VertexSettings,
             };
-            use crate::physics::fallingsand::util::vectors::ChunkIjkVector;
+            use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, Winding};
             use crate::physics::orbits::components::Length;
 
             pub const CORE: ChunkCoords = ChunkCoords {
                 width: Length(1.0),
+                start_radius: 0.0,
+                winding: Winding::Clockwise,
                 num_concentric_circles: 1,
                 chunk_idx: ChunkIjkVector { i: 0, j: 0, k: 0 },
                 start_concentric_circle_layer_relative: 0,
@@ -1447,6 +1771,49 @@ mod tests {
                     )
                 );
             }
+
+            /// The fanned core mesh has exactly one vertex on the center, shared by one fan triangle
+            /// per innermost cell, and no flat triangles
+            #[test]
+            fn test_core_fan_has_no_degenerate_triangles() {
+                let thick_core = ChunkCoords {
+                    num_concentric_circles: 3,
+                    ..CORE
+                };
+                for core in [CORE, thick_core] {
+                    for settings in [
+                        VertexSettings::default(),
+                        VertexSettings::grid(1),
+                        VertexSettings::grid(2),
+                    ] {
+                        let mesh = core.calc_chunk_meshdata(settings);
+                        let centers: Vec<_> = (0..mesh.vertices.len())
+                            .filter(|&n| {
+                                vec2_approx_eq(mesh.vertices[n].position, Vec2::ZERO, 1e-6)
+                            })
+                            .collect();
+                        assert_eq!(centers.len(), 1, "{:?}", settings);
+                        let fans = mesh
+                            .indices
+                            .chunks(3)
+                            .filter(|triangle| triangle.contains(&(centers[0] as u32)))
+                            .count();
+                        let cells = (0..=core.get_num_radial_lines())
+                            .step_by(settings.lod)
+                            .count()
+                            - 1;
+                        assert_eq!(fans, cells, "{:?}", settings);
+                        assert_eq!(mesh.indices.len() % 3, 0);
+                        for triangle in mesh.indices.chunks(3) {
+                            let a = mesh.vertices[triangle[0] as usize].position;
+                            let b = mesh.vertices[triangle[1] as usize].position;
+                            let c = mesh.vertices[triangle[2] as usize].position;
+                            let area = (b - a).perp_dot(c - a).abs() / 2.0;
+                            assert!(area > 1e-6, "Triangle {:?} has no area", triangle);
+                        }
+                    }
+                }
+            }
         }
     }
 }
@@ -2,17 +2,23 @@
 //!
 
 use std::f32::consts::PI;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
 
-use bevy::math::Rect;
+use bevy::math::{Rect, Vec2};
+use hashbrown::HashSet;
 
 use crate::entities::utils::Radius;
 use crate::physics::fallingsand::util::grid::Grid;
-use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector, JkVector};
+use crate::physics::fallingsand::util::mesh::OwnedMeshData;
+use crate::physics::fallingsand::util::vectors::{ChunkIjkVector, IjkVector, JkVector, Winding};
 use crate::physics::orbits::components::Length;
 use crate::physics::util::vectors::RelXyPoint;
 
 use super::chunk_coords::ChunkCoords;
 use super::chunk_coords::PartialLayerChunkCoordsBuilder;
+use super::chunk_coords::VertexSettings;
 
 /// The different ways to draw a mesh
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -25,6 +31,21 @@ pub enum MeshDrawMode {
     TriangleWireframe,
 }
 
+/// A direction relative to where you stand on a celestial, rather than on the screen
+/// Up is away from the center, against gravity. Spinward is the way the surface is moving
+/// when the celestial spins counterclockwise, which is every positive [AngularVelocity](crate::physics::orbits::components::AngularVelocity)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum RelativeDir {
+    /// One ring outward
+    Up,
+    /// One ring inward, the way things fall
+    Down,
+    /// One cell along the ring in the direction of spin
+    Spinward,
+    /// One cell along the ring against the direction of spin
+    Antispinward,
+}
+
 /// A structure that contains all the chunk coordinates for a celestial body
 /// Useful for drawing the total mesh
 #[derive(Clone)]
@@ -33,22 +54,53 @@ pub struct CoordinateDir {
     /// Every index in the vec represents a layer
     /// The Grid then represents the chunks in that layer
     partial_chunks: Vec<Grid<ChunkCoords>>,
+    /// Cached [Self::get_layer_num_radial_lines] for every layer
+    /// The chunks never change after building, and this is hit for every cell lookup
+    layer_num_radial_lines: Vec<usize>,
+    /// Cached [Self::get_layer_num_concentric_circles] for every layer
+    layer_num_concentric_circles: Vec<usize>,
+    /// The settings this was built from, see [Self::get_builder]
+    builder: CoordinateDirBuilder,
 }
 
 /// A builder for CoordinateDir
 /// Needs more parameters than CoordinateDir because
 /// it assembles the chunks whereas CoordinateDir can re-derive
 /// these parameters from the chunks themselves
+#[derive(Debug, Clone, PartialEq)]
 pub struct CoordinateDirBuilder {
     cell_radius: Length,
+    /// See [Self::layer_cell_radius_fn], filled in by [Self::build], layers past the end use [Self::cell_radius]
+    layer_cell_radii: Vec<Length>,
+    /// See [Self::layer_cell_radius_fn], taken and evaluated by [Self::build]
+    layer_cell_radius_fn: Option<LayerCellRadiusFn>,
+    winding: Winding,
     num_layers: usize,
     first_num_radial_lines: usize,
+    core_num_concentric_circles: usize,
     second_num_concentric_circles: usize,
     first_num_tangential_chunkss: usize,
     max_radial_lines_per_chunk: usize,
     max_concentric_circles_per_chunk: usize,
 }
 
+/// The closure given to [CoordinateDirBuilder::layer_cell_radius_fn]
+/// Shared so the builder can still be cloned, and compared by pointer since closures can't be
+#[derive(Clone)]
+struct LayerCellRadiusFn(Arc<dyn Fn(usize) -> f32 + Send + Sync>);
+
+impl fmt::Debug for LayerCellRadiusFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LayerCellRadiusFn")
+    }
+}
+
+impl PartialEq for LayerCellRadiusFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 impl Default for CoordinateDirBuilder {
     /// Start here
     fn default() -> Self {
@@ -63,11 +115,15 @@ impl CoordinateDirBuilder {
     pub fn new() -> Self {
         Self {
             cell_radius: Length(1.0),
+            layer_cell_radii: Vec::new(),
+            layer_cell_radius_fn: None,
+            winding: Winding::default(),
             num_layers: 1,
             first_num_radial_lines: 6,
             first_num_tangential_chunkss: 3,
             max_radial_lines_per_chunk: 128,
             max_concentric_circles_per_chunk: 128,
+            core_num_concentric_circles: 1,
             second_num_concentric_circles: 2,
         }
     }
@@ -76,6 +132,31 @@ impl CoordinateDirBuilder {
         self.cell_radius = cell_radius;
         self
     }
+    /// A different cell radius for every layer, by layer number, instead of one for all of them
+    /// Like a fine crust on a coarse core. Cells are only square where the radius
+    /// happens to match the spacing of the radial lines
+    /// Evaluated for every layer when the directory is built, so it can be set before or after [Self::num_layers]
+    pub fn layer_cell_radius_fn(
+        mut self,
+        layer_cell_radius: impl Fn(usize) -> f32 + Send + Sync + 'static,
+    ) -> Self {
+        self.layer_cell_radius_fn = Some(LayerCellRadiusFn(Arc::new(layer_cell_radius)));
+        self
+    }
+    /// The cell radius of one layer, see [Self::layer_cell_radius_fn]
+    fn get_layer_cell_radius(&self, layer_num: usize) -> Length {
+        self.layer_cell_radii
+            .get(layer_num)
+            .copied()
+            .unwrap_or(self.cell_radius)
+    }
+    /// Which way k goes around every layer, clockwise by default
+    /// Everything that turns a k into an angle or back asks the chunks, so the mesh, the textures
+    /// and picking always agree whichever way it goes
+    pub fn winding(mut self, winding: Winding) -> Self {
+        self.winding = winding;
+        self
+    }
     /// The number of layers in the circle
     pub fn num_layers(mut self, num_layers: usize) -> Self {
         self.num_layers = num_layers;
@@ -97,9 +178,18 @@ impl CoordinateDirBuilder {
         self
     }
 
+    /// The number of concentric circles in the core, 1 by default.
+    /// The core is always a single chunk concentrically, so this doesn't affect the
+    /// multiple of 3 concentric chunk count, but it can't exceed max_concentric_circles_per_chunk.
+    pub fn core_num_concentric_circles(mut self, core_num_concentric_circles: usize) -> Self {
+        self.core_num_concentric_circles = core_num_concentric_circles;
+        self
+    }
+
     /// The number of concentric circles in the second layer.
     /// Each future layer has 2x the number of concentric circles as the previous layer.
-    /// The reason we define the second layer separately is because the core always has 1
+    /// The reason we define the second layer separately is because the core is set
+    /// on its own by [Self::core_num_concentric_circles]
     pub fn second_num_concentric_circles(mut self, second_num_concentric_circles: usize) -> Self {
         // debug_assert!(
         //     second_num_concentric_circles % 3 == 0,
@@ -125,33 +215,129 @@ impl CoordinateDirBuilder {
         self
     }
 
+    /// A builder whose chunk sizes split each pass of the outer layer evenly between `thread_count` threads
+    /// Passes only come in powers of two chunks, so other thread counts get the nearest layout with two or more each
+    pub fn tuned_for_threads(num_layers: usize, thread_count: usize) -> Self {
+        assert!(
+            num_layers > 3,
+            "The outer layer is only split up concentrically from layer 3, got {} layers",
+            num_layers
+        );
+        assert_ne!(thread_count, 0);
+        // Keeps every layer from 3 up a multiple of 3 concentric circles
+        let base = Self::new()
+            .num_layers(num_layers)
+            .second_num_concentric_circles(3);
+        let target = 2 * thread_count;
+        let top = num_layers - 1;
+        let top_num_radial_lines = base.first_num_radial_lines << top;
+        let top_num_concentric_circles = base.second_num_concentric_circles << (top - 1);
+
+        // Splitting only happens where a layer goes over the max, so only these maxes give different layouts
+        let candidates = (1..=top).flat_map(|r| {
+            let base = &base;
+            (0..top).map(move |c| {
+                base.clone()
+                    .max_radial_lines_per_chunk(base.first_num_radial_lines << r)
+                    .max_concentric_circles_per_chunk(base.second_num_concentric_circles << c)
+            })
+        });
+        let score = |builder: &Self| {
+            let (num_concentric_chunks, num_tangential_chunkss) = builder.top_layer_num_chunks();
+            // A ninth of the chunks are a third of the rows by a third of the columns. When the layer splits
+            // tangentially its bottom row waits for a pass of its own, so the passes with it hold a row less
+            let columns = num_tangential_chunkss / 3;
+            let rows = num_concentric_chunks / 3;
+            let splits = top_num_radial_lines > builder.max_radial_lines_per_chunk;
+            let fewest = if splits { rows - 1 } else { rows } * columns;
+            let most = rows * columns;
+            let width = top_num_radial_lines / num_tangential_chunkss;
+            let height = top_num_concentric_circles / num_concentric_chunks;
+            (
+                fewest % thread_count != 0 || most % thread_count != 0,
+                fewest < target,
+                fewest.abs_diff(target),
+                width.max(height) / width.min(height).max(1),
+            )
+        };
+        candidates
+            .min_by_key(score)
+            .expect("There is always at least one candidate")
+    }
+
+    /// The number of concentric and tangential chunks the outermost layer will have, without building it
+    /// Follows the same rules as [Self::build]
+    fn top_layer_num_chunks(&self) -> (usize, usize) {
+        let top = self.num_layers - 1;
+        let mut num_tangential_chunkss = self.first_num_tangential_chunkss;
+        let mut num_concentric_chunks = 1;
+        for layer_num in 2..=top {
+            if self.first_num_radial_lines << layer_num > self.max_radial_lines_per_chunk {
+                num_tangential_chunkss *= 2;
+            }
+            if layer_num == 3 {
+                num_concentric_chunks *= 3;
+            } else if layer_num > 3
+                && self.second_num_concentric_circles << (layer_num - 1)
+                    > self.max_concentric_circles_per_chunk
+            {
+                num_concentric_chunks *= 2;
+            }
+        }
+        (num_concentric_chunks, num_tangential_chunkss)
+    }
+
     /// builds a CoordinateDir by iterating over the number of layers
     /// and dynamically allocating chunks to each layer based on max_cells
     /// and the other parameters of the builder.
-    pub fn build(self) -> CoordinateDir {
+    pub fn build(mut self) -> CoordinateDir {
         assert_ne!(self.num_layers, 0);
+        if let Some(LayerCellRadiusFn(layer_cell_radius)) = self.layer_cell_radius_fn.take() {
+            self.layer_cell_radii = (0..self.num_layers)
+                .map(|layer| {
+                    let cell_radius = layer_cell_radius(layer);
+                    assert!(
+                        cell_radius > 0.0,
+                        "Cell radius must be positive, got {} for layer {}",
+                        cell_radius,
+                        layer
+                    );
+                    Length(cell_radius)
+                })
+                .collect();
+        }
         assert!(
             self.max_radial_lines_per_chunk > self.first_num_radial_lines,
             "max_radial_lines_per_chunk must be greater than first_num_radial_lines, got {} and {}",
             self.max_radial_lines_per_chunk,
             self.first_num_radial_lines
         );
+        assert_ne!(self.core_num_concentric_circles, 0);
+        assert!(
+            self.core_num_concentric_circles <= self.max_concentric_circles_per_chunk,
+            "core_num_concentric_circles must be at most max_concentric_circles_per_chunk, got {} and {}",
+            self.core_num_concentric_circles,
+            self.max_concentric_circles_per_chunk
+        );
 
         // These will be all the chunks
         let mut partial_chunks: Vec<Grid<ChunkCoords>> = Vec::new();
 
         // Create the core
         let mut layer_num_radial_lines = self.first_num_radial_lines;
-        let mut num_concentric_circles = 1;
+        let mut num_concentric_circles = self.core_num_concentric_circles;
         let mut start_concentric_circle_absolute = 0;
         let mut layer_num = 0;
         let mut total_concentric_circle_chunks = 0;
         let mut num_tangential_chunkss = self.first_num_tangential_chunkss;
         let mut num_concentric_chunks = 1;
+        let mut start_radius = 0.0;
         let mut core_chunks = Grid::new_empty(num_tangential_chunkss, num_concentric_chunks);
         for k in 0..num_tangential_chunkss {
             let next_layer = PartialLayerChunkCoordsBuilder::new()
-                .cell_radius(self.cell_radius)
+                .cell_radius(self.get_layer_cell_radius(layer_num))
+                .start_radius(start_radius)
+                .winding(self.winding)
                 .layer_num_radial_lines(layer_num_radial_lines)
                 .chunk_idx(ChunkIjkVector {
                     i: layer_num,
@@ -171,9 +357,11 @@ impl CoordinateDirBuilder {
         partial_chunks.push(core_chunks);
 
         // These variables will help us keep track of the current layer
+        start_radius +=
+            self.get_layer_cell_radius(layer_num).0 * self.core_num_concentric_circles as f32;
         layer_num_radial_lines *= 2;
         num_concentric_circles = self.second_num_concentric_circles;
-        start_concentric_circle_absolute += 1;
+        start_concentric_circle_absolute += self.core_num_concentric_circles;
         layer_num += 1;
         total_concentric_circle_chunks += 1;
         loop {
@@ -182,12 +370,15 @@ impl CoordinateDirBuilder {
             }
 
             // TODO: Check this
+            let cell_radius = self.get_layer_cell_radius(layer_num);
             let mut layer_partial_chunks =
                 Grid::new_empty(num_tangential_chunkss, num_concentric_chunks);
             for j in 0..num_concentric_chunks {
                 for k in 0..num_tangential_chunkss {
                     let next_layer = PartialLayerChunkCoordsBuilder::new()
-                        .cell_radius(self.cell_radius)
+                        .cell_radius(cell_radius)
+                        .start_radius(start_radius)
+                        .winding(self.winding)
                         .layer_num_radial_lines(layer_num_radial_lines)
                         .chunk_idx(ChunkIjkVector { i: layer_num, j, k })
                         .num_concentric_circles(num_concentric_circles / num_concentric_chunks)
@@ -205,6 +396,8 @@ impl CoordinateDirBuilder {
                     layer_partial_chunks.replace(JkVector { j, k }, next_layer);
                 }
                 start_concentric_circle_absolute += num_concentric_circles / num_concentric_chunks;
+                start_radius +=
+                    cell_radius.0 * (num_concentric_circles / num_concentric_chunks) as f32;
                 debug_assert!(num_concentric_circles % num_concentric_chunks == 0);
             }
             partial_chunks.push(layer_partial_chunks);
@@ -236,12 +429,105 @@ impl CoordinateDirBuilder {
 
         debug_assert!(total_concentric_circle_chunks % 3 == 0, "For multithreading purposes, the total number of concentric circle chunks must be a multiple of 3, got {}", total_concentric_circle_chunks);
 
-        let out = CoordinateDir { partial_chunks };
+        let layer_num_radial_lines = partial_chunks
+            .iter()
+            .map(CoordinateDir::calc_layer_num_radial_lines)
+            .collect();
+        let layer_num_concentric_circles = partial_chunks
+            .iter()
+            .map(CoordinateDir::calc_layer_num_concentric_circles)
+            .collect();
+        let out = CoordinateDir {
+            partial_chunks,
+            layer_num_radial_lines,
+            layer_num_concentric_circles,
+            builder: self,
+        };
         debug_assert!(out.get_total_number_concentric_chunks() % 3 == 0);
         out
     }
 }
 
+/* ==========
+ *   Saving
+ * ========== */
+impl CoordinateDirBuilder {
+    /// Writes every setting of the builder, little endian
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&self.cell_radius.0.to_le_bytes())?;
+        out.write_all(&(self.layer_cell_radii.len() as u64).to_le_bytes())?;
+        for cell_radius in &self.layer_cell_radii {
+            out.write_all(&cell_radius.0.to_le_bytes())?;
+        }
+        for value in [
+            self.winding as usize,
+            self.num_layers,
+            self.first_num_radial_lines,
+            self.core_num_concentric_circles,
+            self.second_num_concentric_circles,
+            self.first_num_tangential_chunkss,
+            self.max_radial_lines_per_chunk,
+            self.max_concentric_circles_per_chunk,
+        ] {
+            out.write_all(&(value as u64).to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a builder written by [Self::write_to]
+    pub fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let read_f32 = |input: &mut dyn Read| -> io::Result<f32> {
+            let mut bytes = [0; 4];
+            input.read_exact(&mut bytes)?;
+            Ok(f32::from_le_bytes(bytes))
+        };
+        let cell_radius = read_f32(input)?;
+        let mut num_layer_cell_radii = [0; 8];
+        input.read_exact(&mut num_layer_cell_radii)?;
+        let layer_cell_radii = (0..u64::from_le_bytes(num_layer_cell_radii))
+            .map(|_| read_f32(input).map(Length))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut values = [0; 8];
+        for value in &mut values {
+            let mut bytes = [0; 8];
+            input.read_exact(&mut bytes)?;
+            *value = u64::from_le_bytes(bytes) as usize;
+        }
+        let [winding, num_layers, first_num_radial_lines, core_num_concentric_circles, second_num_concentric_circles, first_num_tangential_chunkss, max_radial_lines_per_chunk, max_concentric_circles_per_chunk] =
+            values;
+        Ok(Self {
+            cell_radius: Length(cell_radius),
+            layer_cell_radii,
+            layer_cell_radius_fn: None,
+            winding: match winding {
+                0 => Winding::Clockwise,
+                1 => Winding::CounterClockwise,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown winding {}", winding),
+                    ))
+                }
+            },
+            num_layers,
+            first_num_radial_lines,
+            core_num_concentric_circles,
+            second_num_concentric_circles,
+            first_num_tangential_chunkss,
+            max_radial_lines_per_chunk,
+            max_concentric_circles_per_chunk,
+        })
+    }
+}
+
+impl CoordinateDir {
+    /// The builder this directory was built from
+    /// Building it again gives back exactly this layout, which is all a save needs to store
+    pub fn get_builder(&self) -> CoordinateDirBuilder {
+        self.builder.clone()
+    }
+}
+
 /* =========================================
  *         Individual Chunk Getters
  * These functions run a getter on a specific
@@ -400,10 +686,21 @@ impl CoordinateDir {
         }
         total_size
     }
-    /// Cell radius is constant for all chunks
+    /// The cell radius of the core, which is every layer's unless they were given their own
+    /// with [CoordinateDirBuilder::layer_cell_radius_fn], see [Self::get_layer_cell_width]
     pub fn get_cell_width(&self) -> Length {
         self.get_core_chunks().get(JkVector::ZERO).get_cell_width()
     }
+    /// Which way k goes around the layers, see [CoordinateDirBuilder::winding]
+    pub fn get_winding(&self) -> Winding {
+        self.builder.winding
+    }
+    /// The cell radius of one layer
+    pub fn get_layer_cell_width(&self, layer_num: usize) -> Length {
+        self.partial_chunks[layer_num]
+            .get(JkVector::ZERO)
+            .get_cell_width()
+    }
     /// The number of layers in the circle
     pub fn get_num_layers(&self) -> usize {
         self.partial_chunks.len()
@@ -419,25 +716,35 @@ impl CoordinateDir {
     /// The number of concentric circles in a given layer
     /// Always 2x the previous layer except for the first and second layers
     pub fn get_layer_num_concentric_circles(&self, layer_num: usize) -> usize {
-        let mut total_height = 0;
-        for j in 0..self.partial_chunks[layer_num].get_height() {
-            total_height += self.partial_chunks[layer_num]
-                .get(JkVector { j, k: 0 })
-                .get_num_concentric_circles();
-        }
-        total_height
+        self.layer_num_concentric_circles[layer_num]
     }
     /// The number of radial lines in a given layer
     /// Always 2x the previous layer except for the first layer
     pub fn get_layer_num_radial_lines(&self, layer_num: usize) -> usize {
+        self.layer_num_radial_lines[layer_num]
+    }
+    /// Sums up the concentric circles of a layers chunks, see [Self::get_layer_num_concentric_circles]
+    fn calc_layer_num_concentric_circles(layer: &Grid<ChunkCoords>) -> usize {
+        let mut total_height = 0;
+        for j in 0..layer.get_height() {
+            total_height += layer.get(JkVector { j, k: 0 }).get_num_concentric_circles();
+        }
+        total_height
+    }
+    /// Sums up the radial lines of a layers chunks, see [Self::get_layer_num_radial_lines]
+    fn calc_layer_num_radial_lines(layer: &Grid<ChunkCoords>) -> usize {
         let mut total_width = 0;
-        for k in 0..self.partial_chunks[layer_num].get_width() {
-            total_width += self.partial_chunks[layer_num]
-                .get(JkVector { j: 0, k })
-                .get_num_radial_lines();
+        for k in 0..layer.get_width() {
+            total_width += layer.get(JkVector { j: 0, k }).get_num_radial_lines();
         }
         total_width
     }
+    /// How many radial lines `to_layer` has for every radial line in `from_layer`
+    /// 2.0 going up across a doubling, 0.5 going down across one, and 1.0 within a layer
+    pub fn radial_resolution_ratio(&self, from_layer: usize, to_layer: usize) -> f32 {
+        self.get_layer_num_radial_lines(to_layer) as f32
+            / self.get_layer_num_radial_lines(from_layer) as f32
+    }
     /// The total number of chunks in the directory
     pub fn get_num_chunks(&self) -> usize {
         let mut out = 0;
@@ -446,6 +753,22 @@ impl CoordinateDir {
         }
         out
     }
+    /// The bounding box of every chunk, paired with its index, in `[i, j, k]` order
+    /// See [ChunkCoords::get_bounding_box]
+    pub fn get_chunk_bounding_boxes(&self) -> Vec<(ChunkIjkVector, Rect)> {
+        let mut out = Vec::with_capacity(self.get_num_chunks());
+        for (i, layer) in self.partial_chunks.iter().enumerate() {
+            for j in 0..layer.get_height() {
+                for k in 0..layer.get_width() {
+                    out.push((
+                        ChunkIjkVector { i, j, k },
+                        layer.get(JkVector { j, k }).get_bounding_box(),
+                    ));
+                }
+            }
+        }
+        out
+    }
 
     /// Gets the starting radius of an entire layer
     pub fn get_layer_start_radius(&self, layer_num: usize) -> f32 {
@@ -464,6 +787,63 @@ impl CoordinateDir {
             .get_end_radius()
     }
 
+    /// The radial height of a cell in a given layer
+    /// Layers double their concentric circles as their radius doubles, so this is usually
+    /// the same everywhere, but movement shouldn't have to assume it
+    pub fn get_layer_cell_height(&self, layer_num: usize) -> f32 {
+        (self.get_layer_end_radius(layer_num) - self.get_layer_start_radius(layer_num))
+            / self.get_layer_num_concentric_circles(layer_num) as f32
+    }
+
+    /// How square the cells of a layer are, their radial height over their arc length
+    /// at the middle of the layer. 1.0 is square, above it they are tall and thin, below it short and wide.
+    /// The arc length grows through a layer while the height doesn't, so even a good layer is only close to 1
+    pub fn cell_aspect_ratio(&self, layer_num: usize) -> f32 {
+        let mid_radius =
+            (self.get_layer_start_radius(layer_num) + self.get_layer_end_radius(layer_num)) / 2.0;
+        let arc_length =
+            std::f32::consts::TAU * mid_radius / self.get_layer_num_radial_lines(layer_num) as f32;
+        self.get_layer_cell_height(layer_num) / arc_length
+    }
+
+    /// The silhouette of a layer, as one closed polygon around its outer edge
+    /// Stitches the [ChunkCoords::get_outer_arc] of the outermost chunks together in k order,
+    /// dropping the vertex each arc shares with the next, and repeats the first vertex at the end
+    pub fn layer_outline(&self, layer_num: usize) -> Vec<Vec2> {
+        let chunks = &self.partial_chunks[layer_num];
+        let j = chunks.get_height() - 1;
+        let mut outline: Vec<Vec2> = (0..chunks.get_width())
+            .flat_map(|k| {
+                let mut arc = chunks.get(JkVector { j, k }).get_outer_arc();
+                arc.pop();
+                arc
+            })
+            .collect();
+        outline.push(outline[0]);
+        outline
+    }
+
+    /// One mesh of the outermost `thickness_layers` layers, with nothing underneath
+    /// For big bodies where only the surface is ever seen, this skips most of the vertices
+    /// Each chunk keeps its own uvs, so it can't be drawn with the chunk textures directly
+    pub fn surface_shell_meshdata(
+        &self,
+        thickness_layers: usize,
+        settings: VertexSettings,
+    ) -> OwnedMeshData {
+        let num_layers = self.get_num_layers();
+        let mut out = OwnedMeshData::default();
+        for i in num_layers - thickness_layers.min(num_layers)..num_layers {
+            let chunks = &self.partial_chunks[i];
+            for j in 0..chunks.get_height() {
+                for k in 0..chunks.get_width() {
+                    out.extend(chunks.get(JkVector { j, k }).calc_chunk_meshdata(settings));
+                }
+            }
+        }
+        out
+    }
+
     /// Gets the radius of the entire coordinate directory
     pub fn get_radius(&self) -> Radius {
         Radius(self.get_layer_end_radius(self.get_num_layers() - 1))
@@ -509,16 +889,11 @@ impl CoordinateDir {
             ((norm_vertex_coord - starting_r) / circle_separation_distance).floor() as usize;
         let j = j_rel.min(ith_num_concentric_circles - 1);
 
-        // Get the radial line to the left of the vertex
-        let angle = (xy_coord.0.y.atan2(xy_coord.0.x) + 2.0 * PI) % (2.0 * PI);
-        let theta = 2.0 * PI / ith_num_radial_lines as f32;
-
-        // Calculate 'k' directly without the while loop
-        let k_rel = (angle / theta).floor() as usize;
-        let k = k_rel.min(ith_num_radial_lines - 1);
-
-        // Later discovered that k is inverted
-        let k = ith_num_radial_lines - k - 1;
+        // The angle is counter clockwise but k might not be, see the vectors module docs
+        let angle = xy_coord.0.y.atan2(xy_coord.0.x);
+        let k = self
+            .get_winding()
+            .radial_line_at(angle, ith_num_radial_lines);
 
         if outside_mesh {
             Err(IjkVector { i, j, k })
@@ -527,6 +902,148 @@ impl CoordinateDir {
         }
     }
 
+    /// The position of the middle of a cell, halfway between its concentric circles and radial lines
+    pub fn cell_midpoint(&self, cell: IjkVector) -> RelXyPoint {
+        let num_radial_lines = self.get_layer_num_radial_lines(cell.i);
+        let start_r = self.get_layer_start_radius(cell.i);
+        let separation = (self.get_layer_end_radius(cell.i) - start_r)
+            / self.get_layer_num_concentric_circles(cell.i) as f32;
+        let radius = start_r + (cell.j as f32 + 0.5) * separation;
+        let angle = self
+            .get_winding()
+            .angle(cell.k as f32 + 0.5, num_radial_lines);
+        RelXyPoint(Vec2::new(radius * angle.cos(), radius * angle.sin()))
+    }
+
+    /// Finds the cell containing a position and returns it along with that cell's midpoint
+    /// Positions outside the mesh snap to the outermost cell in their direction
+    pub fn snap_to_cell_midpoint(&self, pos: RelXyPoint) -> (IjkVector, RelXyPoint) {
+        let cell = match self.rel_pos_to_cell_idx(pos) {
+            Ok(cell) | Err(cell) => cell,
+        };
+        (cell, self.cell_midpoint(cell))
+    }
+
+    /// Every cell on the night side, facing away from parallel light shining from `light_dir`
+    /// The celestial is a solid disk, so any cell whose middle is behind the terminator,
+    /// the line through the center square to the light, has the rest of the planet between it and the light
+    pub fn shadowed_cells(&self, light_dir: Vec2) -> HashSet<IjkVector> {
+        debug_assert_ne!(light_dir, Vec2::ZERO, "Light has to come from somewhere");
+        let light_dir = light_dir.normalize_or_zero();
+        let mut out = HashSet::new();
+        for i in 0..self.get_num_layers() {
+            for j in 0..self.get_layer_num_concentric_circles(i) {
+                for k in 0..self.get_layer_num_radial_lines(i) {
+                    let cell = IjkVector::new(i, j, k);
+                    if self.cell_midpoint(cell).0.dot(light_dir) < 0.0 {
+                        out.insert(cell);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Finds the neighbors of a cell purely from the geometry of the mesh, by sampling points
+    /// just outside each edge of the cell and looking up which cell they land in.
+    /// Slow, but it shares no logic with the convolution neighbor code, so it makes a good oracle
+    /// for testing it. Where the layer above has double the radial lines the top edge has two neighbors.
+    pub fn geometric_cell_neighbors(&self, cell: IjkVector) -> Vec<IjkVector> {
+        let num_radial_lines = self.get_layer_num_radial_lines(cell.i);
+        let start_r = self.get_layer_start_radius(cell.i);
+        let separation = (self.get_layer_end_radius(cell.i) - start_r)
+            / self.get_layer_num_concentric_circles(cell.i) as f32;
+        let theta = 2.0 * PI / num_radial_lines as f32;
+
+        // The clockwise most edge, whichever way k goes, the samples then sweep counter clockwise
+        let start_angle = self
+            .get_winding()
+            .angle(cell.k as f32 + 0.5, num_radial_lines)
+            - theta / 2.0;
+        let inner_r = start_r + cell.j as f32 * separation;
+        let outer_r = inner_r + separation;
+        let mid_r = (inner_r + outer_r) / 2.0;
+        let eps_r = separation * 0.05;
+        let eps_angle = theta * 0.05;
+
+        let mut samples = vec![
+            (mid_r, start_angle - eps_angle),
+            (mid_r, start_angle + theta + eps_angle),
+            (outer_r + eps_r, start_angle + theta * 0.25),
+            (outer_r + eps_r, start_angle + theta * 0.75),
+        ];
+        // Nothing is below the center of the core
+        if inner_r > 0.0 {
+            samples.push((inner_r - eps_r, start_angle + theta * 0.25));
+            samples.push((inner_r - eps_r, start_angle + theta * 0.75));
+        }
+
+        let mut out = Vec::new();
+        for (r, angle) in samples {
+            let point = RelXyPoint(Vec2::new(r * angle.cos(), r * angle.sin()));
+            if let Ok(neighbor) = self.rel_pos_to_cell_idx(point) {
+                if neighbor != cell && !out.contains(&neighbor) {
+                    out.push(neighbor);
+                }
+            }
+        }
+        out
+    }
+
+    /// The cell next to `cell` in a direction relative to the surface, see [RelativeDir]
+    /// Crossing into a layer with twice the radial lines lands on the first of the two cells above,
+    /// crossing into one with half lands on the one cell both share
+    /// None going up out of the top of the mesh, or down out of the bottom of the core
+    pub fn relative_direction(&self, cell: IjkVector, dir: RelativeDir) -> Option<IjkVector> {
+        let num_radial_lines = self.get_layer_num_radial_lines(cell.i);
+        // A counterclockwise spin carries the surface counterclockwise, whichever way k runs
+        let winding = self.get_winding();
+        match dir {
+            RelativeDir::Up => {
+                if cell.j + 1 < self.get_layer_num_concentric_circles(cell.i) {
+                    Some(IjkVector {
+                        j: cell.j + 1,
+                        ..cell
+                    })
+                } else if cell.i + 1 < self.get_num_layers() {
+                    let above = self.get_layer_num_radial_lines(cell.i + 1);
+                    Some(IjkVector::new(
+                        cell.i + 1,
+                        0,
+                        cell.k * above / num_radial_lines,
+                    ))
+                } else {
+                    None
+                }
+            }
+            RelativeDir::Down => {
+                if cell.j > 0 {
+                    Some(IjkVector {
+                        j: cell.j - 1,
+                        ..cell
+                    })
+                } else if cell.i > 0 {
+                    let below = self.get_layer_num_radial_lines(cell.i - 1);
+                    Some(IjkVector::new(
+                        cell.i - 1,
+                        self.get_layer_num_concentric_circles(cell.i - 1) - 1,
+                        cell.k * below / num_radial_lines,
+                    ))
+                } else {
+                    None
+                }
+            }
+            RelativeDir::Spinward => Some(IjkVector {
+                k: winding.counter_clockwise_of(cell.k, num_radial_lines),
+                ..cell
+            }),
+            RelativeDir::Antispinward => Some(IjkVector {
+                k: winding.clockwise_of(cell.k, num_radial_lines),
+                ..cell
+            }),
+        }
+    }
+
     pub fn cell_idx_to_chunk_idx(&self, cell_idx: IjkVector) -> (ChunkIjkVector, JkVector) {
         let chunk_layer_num_concentric_circles =
             self.get_layer_chunk_num_concentric_circles(cell_idx.i);
@@ -578,6 +1095,17 @@ mod tests {
         };
     }
 
+    /// The builder most tests here start from, with `num_layers` layers
+    fn test_builder(num_layers: usize) -> CoordinateDirBuilder {
+        CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(num_layers)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+    }
+
     /// Needed these when I noticed get_layer_num_from_absolute_chunk_concentric_circle was wrong
     mod test_concentric_circles_conversions {
         use super::*;
@@ -634,6 +1162,18 @@ mod tests {
             assert_eq!(coord_dir.get_layer_num_tangential_chunkss(8), 96);
         }
 
+        #[test]
+        fn test_radial_resolution_ratio() {
+            let coord_dir = default_coordinate_dir();
+            // Every layer doubles its radial lines
+            assert_eq!(coord_dir.radial_resolution_ratio(1, 2), 2.0);
+            assert_eq!(coord_dir.radial_resolution_ratio(3, 4), 2.0);
+            assert_eq!(coord_dir.radial_resolution_ratio(4, 3), 0.5);
+            assert_eq!(coord_dir.radial_resolution_ratio(1, 3), 4.0);
+            // Chunks within a layer always match
+            assert_eq!(coord_dir.radial_resolution_ratio(3, 3), 1.0);
+        }
+
         #[test]
         fn test_get_total_number_chunks_in_concentric_circle_dimension() {
             let coord_dir = default_coordinate_dir();
@@ -943,7 +1483,7 @@ mod tests {
         );
         assert_eq!(
             coordinate_dir.get_chunk_end_theta(ChunkIjkVector::ZERO),
-            2.0 * PI / 3.0
+            -2.0 * PI / 3.0
         );
 
         // Layer 1
@@ -957,7 +1497,7 @@ mod tests {
         assert_eq!(coordinate_dir.get_chunk_start_radius(layer1), 1.0);
         assert_eq!(coordinate_dir.get_chunk_end_radius(layer1), 4.0);
         assert_eq!(coordinate_dir.get_chunk_start_theta(layer1), 0.0);
-        assert_eq!(coordinate_dir.get_chunk_end_theta(layer1), 2.0 * PI / 3.0);
+        assert_eq!(coordinate_dir.get_chunk_end_theta(layer1), -2.0 * PI / 3.0);
 
         // Layer 2
         let layer2 = ChunkIjkVector { i: 2, j: 0, k: 0 };
@@ -968,7 +1508,7 @@ mod tests {
         assert_eq!(coordinate_dir.get_chunk_start_radius(layer2), 4.0);
         assert_eq!(coordinate_dir.get_chunk_end_radius(layer2), 10.0);
         assert_eq!(coordinate_dir.get_chunk_start_theta(layer2), 0.0);
-        assert_eq!(coordinate_dir.get_chunk_end_theta(layer2), 2.0 * PI / 3.0);
+        assert_eq!(coordinate_dir.get_chunk_end_theta(layer2), -2.0 * PI / 3.0);
 
         // Layer 3
         let layer3 = ChunkIjkVector { i: 3, j: 0, k: 0 };
@@ -979,7 +1519,7 @@ mod tests {
         assert_eq!(coordinate_dir.get_chunk_start_radius(layer3), 10.0);
         assert_eq!(coordinate_dir.get_chunk_end_radius(layer3), 14.0);
         assert_eq!(coordinate_dir.get_chunk_start_theta(layer3), 0.0);
-        assert_eq!(coordinate_dir.get_chunk_end_theta(layer3), 2.0 * PI / 3.0);
+        assert_eq!(coordinate_dir.get_chunk_end_theta(layer3), -2.0 * PI / 3.0);
 
         // Layer 4
         let layer4 = ChunkIjkVector { i: 4, j: 0, k: 0 };
@@ -990,7 +1530,7 @@ mod tests {
         assert_eq!(coordinate_dir.get_chunk_start_radius(layer4), 22.0);
         assert_eq!(coordinate_dir.get_chunk_end_radius(layer4), 30.0);
         assert_eq!(coordinate_dir.get_chunk_start_theta(layer4), 0.0);
-        assert_eq!(coordinate_dir.get_chunk_end_theta(layer4), 2.0 * PI / 6.0);
+        assert_eq!(coordinate_dir.get_chunk_end_theta(layer4), -2.0 * PI / 6.0);
 
         // Layer 5
         let layer5 = ChunkIjkVector { i: 5, j: 0, k: 0 };
@@ -1002,7 +1542,7 @@ mod tests {
         assert_eq!(coordinate_dir.get_chunk_start_radius(layer5), 46.0);
         assert_eq!(coordinate_dir.get_chunk_end_radius(layer5), 62.0);
         assert_eq!(coordinate_dir.get_chunk_start_theta(layer5), 0.0);
-        assert_approx_eq!(coordinate_dir.get_chunk_end_theta(layer5), 2.0 * PI / 12.0);
+        assert_approx_eq!(coordinate_dir.get_chunk_end_theta(layer5), -2.0 * PI / 12.0);
 
         // // Layer 6
         // I had to change this several times, it works
@@ -1053,4 +1593,382 @@ mod tests {
         //     2.0 * PI / 12.0
         // );
     }
+
+    #[test]
+    fn test_core_num_concentric_circles() {
+        let coordinate_dir = test_builder(9).core_num_concentric_circles(3).build();
+        let core = coordinate_dir.get_core_chunks().get(JkVector::ZERO);
+        assert_eq!(core.get_num_concentric_circles(), 3);
+        assert_eq!(coordinate_dir.get_layer_num_concentric_circles(0), 3);
+        assert_eq!(coordinate_dir.get_layer_end_radius(0), 3.0);
+        // The next layer starts where the thicker core ends
+        assert_eq!(coordinate_dir.get_layer_start_radius(1), 3.0);
+        assert_eq!(coordinate_dir.get_total_number_concentric_chunks() % 3, 0);
+    }
+
+    #[test]
+    fn test_snap_to_cell_midpoint() {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(8)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .build();
+        let cell = IjkVector { i: 4, j: 2, k: 17 };
+        let midpoint = coordinate_dir.cell_midpoint(cell);
+        assert_eq!(coordinate_dir.rel_pos_to_cell_idx(midpoint).unwrap(), cell);
+
+        // Just inside the cell's outer edge and its last radial line
+        let num_radial_lines = coordinate_dir.get_layer_num_radial_lines(cell.i);
+        let separation = (coordinate_dir.get_layer_end_radius(cell.i)
+            - coordinate_dir.get_layer_start_radius(cell.i))
+            / coordinate_dir.get_layer_num_concentric_circles(cell.i) as f32;
+        let radius =
+            coordinate_dir.get_layer_start_radius(cell.i) + (cell.j as f32 + 0.95) * separation;
+        let angle = -2.0 * PI / num_radial_lines as f32 * (cell.k as f32 + 0.95);
+        let near_edge = RelXyPoint(Vec2::new(radius * angle.cos(), radius * angle.sin()));
+        let (snapped_cell, snapped) = coordinate_dir.snap_to_cell_midpoint(near_edge);
+        assert_eq!(snapped_cell, cell);
+        assert_approx_eq!(snapped.0.x, midpoint.0.x, 1e-4);
+        assert_approx_eq!(snapped.0.y, midpoint.0.y, 1e-4);
+
+        // Far outside the mesh lands on the outermost cell
+        let far_away = RelXyPoint(Vec2::new(1.0e6, 0.0));
+        let (snapped_cell, _) = coordinate_dir.snap_to_cell_midpoint(far_away);
+        let last_layer = coordinate_dir.get_num_layers() - 1;
+        assert_eq!(snapped_cell.i, last_layer);
+        assert_eq!(
+            snapped_cell.j,
+            coordinate_dir.get_layer_num_concentric_circles(last_layer) - 1
+        );
+    }
+
+    #[test]
+    fn test_both_windings_round_trip() {
+        for winding in [Winding::Clockwise, Winding::CounterClockwise] {
+            let coordinate_dir = test_builder(5).winding(winding).build();
+            assert_eq!(coordinate_dir.get_winding(), winding);
+            for cell in [
+                IjkVector::new(1, 0, 0),
+                IjkVector::new(2, 3, 7),
+                IjkVector::new(3, 1, 5),
+                IjkVector::new(4, 5, 40),
+            ] {
+                let midpoint = coordinate_dir.cell_midpoint(cell);
+                assert_eq!(
+                    coordinate_dir.rel_pos_to_cell_idx(midpoint),
+                    Ok(cell),
+                    "{:?}",
+                    winding
+                );
+            }
+
+            // The outer ring of a chunk's vertexes goes around the way the winding says
+            let chunk = coordinate_dir.get_chunk_at_idx(ChunkIjkVector::new(3, 0, 1));
+            let num_radial_lines = coordinate_dir.get_layer_num_radial_lines(3);
+            let radial_lines = chunk.get_start_radial_line()..chunk.get_end_radial_line() + 1;
+            let positions = chunk.get_positions(VertexSettings::default());
+            let outer_ring = &positions[positions.len() - radial_lines.len()..];
+            for (k, position) in radial_lines.zip(outer_ring) {
+                let expected = Vec2::from_angle(winding.angle(k as f32, num_radial_lines));
+                assert_approx_eq!(position.normalize().dot(expected), 1.0, 1e-5);
+            }
+        }
+
+        // The same cell on either winding is mirrored across the x axis
+        let build = |winding| {
+            CoordinateDirBuilder::new()
+                .cell_radius(Length(1.0))
+                .num_layers(5)
+                .first_num_radial_lines(6)
+                .second_num_concentric_circles(3)
+                .winding(winding)
+                .build()
+        };
+        let cell = IjkVector::new(3, 2, 11);
+        let clockwise = build(Winding::Clockwise).cell_midpoint(cell);
+        let counter_clockwise = build(Winding::CounterClockwise).cell_midpoint(cell);
+        assert_approx_eq!(clockwise.0.x, counter_clockwise.0.x, 1e-4);
+        assert_approx_eq!(clockwise.0.y, -counter_clockwise.0.y, 1e-4);
+    }
+
+    /// A chunk's thetas are real angles, so its cells lie between them whichever way k winds
+    #[test]
+    fn test_chunk_thetas_follow_the_winding() {
+        for winding in [Winding::Clockwise, Winding::CounterClockwise] {
+            let coordinate_dir = test_builder(5).winding(winding).build();
+            let chunk_idx = ChunkIjkVector::new(3, 0, 1);
+            let chunk = coordinate_dir.get_chunk_at_idx(chunk_idx);
+            let start = coordinate_dir.get_chunk_start_theta(chunk_idx);
+            let end = coordinate_dir.get_chunk_end_theta(chunk_idx);
+            match winding {
+                Winding::Clockwise => assert!(end < start),
+                Winding::CounterClockwise => assert!(end > start),
+            }
+            for k in chunk.get_start_radial_line()..chunk.get_end_radial_line() {
+                let midpoint = coordinate_dir.cell_midpoint(IjkVector::new(3, 0, k)).0;
+                let angle = midpoint.y.atan2(midpoint.x);
+                // atan2 stays within half a turn, the thetas can go further
+                assert!(
+                    [angle - 2.0 * PI, angle, angle + 2.0 * PI]
+                        .iter()
+                        .any(|angle| (start.min(end)..start.max(end)).contains(angle)),
+                    "{:?} cell {} at {} is outside {}..{}",
+                    winding,
+                    k,
+                    angle,
+                    start,
+                    end
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_layer_outline_is_closed() {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(8)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(16)
+            .max_radial_lines_per_chunk(16)
+            .build();
+        for i in 0..coordinate_dir.get_num_layers() {
+            let outline = coordinate_dir.layer_outline(i);
+            assert_eq!(outline.first(), outline.last(), "Layer {}", i);
+
+            // Every arc shares its last vertex with the next arc, the last one with the first
+            let chunks = &coordinate_dir.partial_chunks[i];
+            let arc_vertices: usize = (0..chunks.get_width())
+                .map(|k| {
+                    chunks
+                        .get(JkVector {
+                            j: chunks.get_height() - 1,
+                            k,
+                        })
+                        .get_outer_arc()
+                        .len()
+                })
+                .sum();
+            assert_eq!(
+                outline.len(),
+                arc_vertices - chunks.get_width() + 1,
+                "Layer {}",
+                i
+            );
+            assert_eq!(
+                outline.len(),
+                coordinate_dir.get_layer_num_radial_lines(i) + 1
+            );
+
+            let radius = coordinate_dir.get_layer_end_radius(i);
+            for vertex in outline {
+                assert_approx_eq!(vertex.length(), radius, 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_surface_shell_is_only_the_outer_layers() {
+        let coordinate_dir = test_builder(8).build();
+        let settings = VertexSettings::default();
+        let shell = coordinate_dir.surface_shell_meshdata(2, settings);
+        let inner_radius = coordinate_dir.get_layer_start_radius(6);
+        assert!(!shell.vertices.is_empty());
+        // The vertices are placed with f32 trig, so allow for rounding that grows with the radius
+        for vertex in &shell.vertices {
+            assert!(vertex.position.length() >= inner_radius * (1.0 - 1e-3));
+        }
+        for idx in &shell.indices {
+            assert!((*idx as usize) < shell.vertices.len());
+        }
+
+        let everything = coordinate_dir.surface_shell_meshdata(100, settings);
+        assert!(everything
+            .vertices
+            .iter()
+            .any(|vertex| vertex.position.length() < 1e-3));
+        assert!(shell.vertices.len() < everything.vertices.len());
+    }
+
+    #[test]
+    fn test_cached_layer_sizes_match_chunks() {
+        let coordinate_dir = test_builder(12).build();
+        for i in 0..coordinate_dir.get_num_layers() {
+            let layer = &coordinate_dir.partial_chunks[i];
+            assert_eq!(
+                coordinate_dir.get_layer_num_radial_lines(i),
+                CoordinateDir::calc_layer_num_radial_lines(layer),
+                "Layer {}",
+                i
+            );
+            assert_eq!(
+                coordinate_dir.get_layer_num_concentric_circles(i),
+                CoordinateDir::calc_layer_num_concentric_circles(layer),
+                "Layer {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_relative_direction() {
+        let coordinate_dir = test_builder(6).build();
+        let top_layer = coordinate_dir.get_num_layers() - 1;
+        let top = coordinate_dir.get_layer_num_concentric_circles(top_layer) - 1;
+        let surface = IjkVector::new(top_layer, top, 7);
+
+        // Nothing above the surface but space
+        assert_eq!(
+            coordinate_dir.relative_direction(surface, RelativeDir::Up),
+            None
+        );
+        let below = coordinate_dir
+            .relative_direction(surface, RelativeDir::Down)
+            .unwrap();
+        assert_eq!(below, IjkVector::new(top_layer, top - 1, 7));
+        assert_eq!(
+            coordinate_dir.relative_direction(below, RelativeDir::Up),
+            Some(surface)
+        );
+
+        // Around the ring, wrapping at the seam
+        let num_radial_lines = coordinate_dir.get_layer_num_radial_lines(top_layer);
+        let seam = IjkVector::new(top_layer, top, 0);
+        assert_eq!(
+            coordinate_dir.relative_direction(seam, RelativeDir::Spinward),
+            Some(IjkVector::new(top_layer, top, num_radial_lines - 1))
+        );
+        assert_eq!(
+            coordinate_dir.relative_direction(seam, RelativeDir::Antispinward),
+            Some(IjkVector::new(top_layer, top, 1))
+        );
+
+        // Across every layer boundary, up and down land on a cell that really touches the edge
+        for i in 1..coordinate_dir.get_num_layers() {
+            let bottom = IjkVector::new(i, 0, 5);
+            let down = coordinate_dir
+                .relative_direction(bottom, RelativeDir::Down)
+                .unwrap();
+            assert_eq!(down.i, i - 1);
+            assert!(coordinate_dir
+                .geometric_cell_neighbors(bottom)
+                .contains(&down));
+            let up = coordinate_dir
+                .relative_direction(down, RelativeDir::Up)
+                .unwrap();
+            assert_eq!(up.i, i);
+            assert!(coordinate_dir.geometric_cell_neighbors(down).contains(&up));
+        }
+        assert_eq!(
+            coordinate_dir.relative_direction(IjkVector::new(0, 0, 0), RelativeDir::Down),
+            None
+        );
+    }
+
+    #[test]
+    fn test_night_side_is_shadowed() {
+        let coordinate_dir = test_builder(6).build();
+        let shadowed = coordinate_dir.shadowed_cells(Vec2::X);
+        let radius = coordinate_dir.get_radius().0;
+        let (day_surface, _) = coordinate_dir.snap_to_cell_midpoint(RelXyPoint::new(radius, 0.1));
+        let (night_surface, _) =
+            coordinate_dir.snap_to_cell_midpoint(RelXyPoint::new(-radius, 0.1));
+        let (night_deep, _) =
+            coordinate_dir.snap_to_cell_midpoint(RelXyPoint::new(-radius / 2.0, 0.1));
+        assert_eq!(night_surface.i, coordinate_dir.get_num_layers() - 1);
+        assert!(shadowed.contains(&night_surface));
+        assert!(shadowed.contains(&night_deep));
+        assert!(!shadowed.contains(&day_surface));
+
+        // Roughly half the planet is in the dark, and none of it is on the lit side
+        let total: usize = (0..coordinate_dir.get_num_layers())
+            .map(|i| {
+                coordinate_dir.get_layer_num_concentric_circles(i)
+                    * coordinate_dir.get_layer_num_radial_lines(i)
+            })
+            .sum();
+        assert!(shadowed.len() * 3 > total && shadowed.len() * 3 < total * 2);
+        for cell in &shadowed {
+            assert!(coordinate_dir.cell_midpoint(*cell).0.x < 0.0);
+        }
+    }
+
+    #[test]
+    fn test_layer_cell_radius_accumulates() {
+        let fine = 0.25;
+        let coordinate_dir = test_builder(6)
+            .layer_cell_radius_fn(move |layer| if layer == 5 { fine } else { 1.0 })
+            .build();
+        assert_eq!(coordinate_dir.get_layer_cell_width(4), Length(1.0));
+        assert_eq!(coordinate_dir.get_layer_cell_width(5), Length(fine));
+
+        // Giving the radii before the number of layers makes no difference
+        let reordered = CoordinateDirBuilder::new()
+            .layer_cell_radius_fn(move |layer| if layer == 5 { fine } else { 1.0 })
+            .cell_radius(Length(1.0))
+            .num_layers(6)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        assert_eq!(reordered.get_builder(), coordinate_dir.get_builder());
+
+        // Each layer starts where the last one ended, and is as thick as its own cells make it
+        let mut expected_start = 0.0;
+        for i in 0..coordinate_dir.get_num_layers() {
+            let start = coordinate_dir.get_layer_start_radius(i);
+            let end = coordinate_dir.get_layer_end_radius(i);
+            assert!((start - expected_start).abs() < 1e-4, "layer {}", i);
+            let thickness = coordinate_dir.get_layer_cell_width(i).0
+                * coordinate_dir.get_layer_num_concentric_circles(i) as f32;
+            assert!((end - start - thickness).abs() < 1e-4, "layer {}", i);
+            expected_start = end;
+        }
+        assert!((coordinate_dir.get_radius().0 - expected_start).abs() < 1e-4);
+        let uniform = test_builder(6).build();
+        assert!(coordinate_dir.get_radius().0 < uniform.get_radius().0);
+
+        // Picking still finds the cells of the finer layer
+        let cell = IjkVector::new(5, 7, 3);
+        assert_eq!(
+            coordinate_dir.rel_pos_to_cell_idx(coordinate_dir.cell_midpoint(cell)),
+            Ok(cell)
+        );
+
+        // And building again from the saved settings gives the same layout
+        let mut bytes = Vec::new();
+        coordinate_dir.get_builder().write_to(&mut bytes).unwrap();
+        let rebuilt = CoordinateDirBuilder::read_from(&mut bytes.as_slice())
+            .unwrap()
+            .build();
+        assert_eq!(rebuilt.get_radius().0, coordinate_dir.get_radius().0);
+    }
+
+    #[test]
+    fn test_cell_aspect_ratio() {
+        let builder = || {
+            test_builder(7)
+                .first_num_radial_lines(12)
+                .core_num_concentric_circles(2)
+        };
+        let coordinate_dir = builder().build();
+        // The core is a disc, so it has no good shape to compare against
+        for i in 1..coordinate_dir.get_num_layers() {
+            let ratio = coordinate_dir.cell_aspect_ratio(i);
+            assert!((0.8..1.25).contains(&ratio), "layer {} is {}", i, ratio);
+        }
+
+        // Cells a quarter as tall in the outer layer are squashed flat
+        let squashed = builder()
+            .layer_cell_radius_fn(|layer| if layer == 6 { 0.25 } else { 1.0 })
+            .build();
+        assert_eq!(
+            squashed.cell_aspect_ratio(5),
+            coordinate_dir.cell_aspect_ratio(5)
+        );
+        assert!(squashed.cell_aspect_ratio(6) < 0.5);
+    }
 }
@@ -1,5 +1,8 @@
 pub mod functions;
 pub mod grid;
+pub mod heatmap;
 pub mod image;
 pub mod mesh;
+pub mod palette;
+pub mod pressuremap;
 pub mod vectors;
@@ -3,6 +3,14 @@
 //! use the ndarray crate as the backend for this type. This is because the ndarray crate
 //! has a convolution function that is helpful for the physics simulation.
 //! So some of this code is now redundant, but is maintained for legacy reasons
+//!
+//! # Layout
+//! The data is row-major with one row per concentric circle, so the ndarray is indexed `[j, k]`
+//! and the cell at (j, k) is at `j * width + k` in [Grid::get_data_slice].
+//! The row stride is the width, so walking k is walking memory, and the cells just above
+//! or below are exactly one stride away, which is what the movement and conduction stencils read.
+//! This is also the order textures are drawn in, see
+//! [ChunkCoords::cell_to_texel](crate::physics::fallingsand::mesh::chunk_coords::ChunkCoords::cell_to_texel).
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 
@@ -23,11 +31,12 @@ impl<T> Grid<T> {
     where
         T: Clone,
     {
-        Self(ndarray::Array2::from_elem((width, height), value))
+        Self(ndarray::Array2::from_elem((height, width), value))
     }
     /// Create a new grid with the given width and height, and fill it with the given data
+    /// The data is read row by row, see the [module docs](self)
     pub fn new_from_vec(width: usize, height: usize, data: Vec<T>) -> Self {
-        Self(ndarray::Array2::from_shape_vec((width, height), data).unwrap())
+        Self(ndarray::Array2::from_shape_vec((height, width), data).unwrap())
     }
     /// Create a new grid with the given width and height, and fill it with default values
     pub fn new_empty(width: usize, height: usize) -> Self
@@ -38,7 +47,7 @@ impl<T> Grid<T> {
         for _ in 0..width * height {
             data.push(Default::default());
         }
-        Self(ndarray::Array2::from_shape_vec((width, height), data).unwrap())
+        Self(ndarray::Array2::from_shape_vec((height, width), data).unwrap())
     }
 }
 
@@ -49,21 +58,29 @@ impl<T> Grid<T> {
 impl<T> Grid<T> {
     /// Get the width of the grid
     pub fn get_width(&self) -> usize {
-        self.0.shape()[0]
+        self.0.shape()[1]
     }
     /// Get the height of the grid
     pub fn get_height(&self) -> usize {
-        self.0.shape()[1]
+        self.0.shape()[0]
+    }
+    /// How far apart in [Self::get_data_slice] two cells one concentric circle apart are
+    pub fn row_stride(&self) -> usize {
+        self.get_width()
     }
     /// Get the total size of the grid
     pub fn total_size(&self) -> usize {
         self.0.len()
     }
-    /// Get the data as a slice
+    /// Get the data as a slice, row by row
     pub fn get_data_slice(&self) -> &[T] {
         self.0.as_slice().unwrap()
     }
-    /// Get the data as an ndarray
+    /// Get the data as a mutable slice, in the same order as [Self::get_data_slice]
+    pub fn get_data_slice_mut(&mut self) -> &mut [T] {
+        self.0.as_slice_mut().unwrap()
+    }
+    /// Get the data as an ndarray, indexed `[j, k]`
     pub fn get_data(&self) -> &ndarray::Array2<T> {
         &self.0
     }
@@ -110,9 +127,13 @@ impl<T> Grid<T> {
         let coord = self.transform_jk_coord_to_ndarray(idx);
         std::mem::replace(&mut self.0[coord], replacement)
     }
-    /// Transforms the coordinate to the ndarray coordinate system using this grid's width and height
+    /// Where a coordinate lives in [Self::get_data_slice], `j * width + k`
+    pub fn flat_index(&self, idx: JkVector) -> usize {
+        idx.j * self.row_stride() + idx.k
+    }
+    /// Transforms the coordinate to the ndarray coordinate system, which is just `[j, k]`
     fn transform_jk_coord_to_ndarray(&self, idx: JkVector) -> [usize; 2] {
-        [self.get_width() - 1 - idx.k, self.get_height() - 1 - idx.j]
+        [idx.j, idx.k]
     }
 }
 
@@ -129,6 +150,30 @@ impl<T> Grid<T> {
     }
 }
 
+/// Reorientation
+/// Handy for checking whether a bug is just the grid being read the wrong way around
+impl<T: Clone> Grid<T> {
+    /// A new grid where the value at (j, k) is this grid's value at (k, j)
+    /// The width and height swap
+    pub fn transpose(&self) -> Self {
+        Self(self.0.t().as_standard_layout().into_owned())
+    }
+    /// A new grid mirrored along k, so the first radial line becomes the last
+    pub fn flip_horizontal(&self) -> Self {
+        self.inverted(ndarray::Axis(1))
+    }
+    /// A new grid mirrored along j, so the first concentric circle becomes the last
+    pub fn flip_vertical(&self) -> Self {
+        self.inverted(ndarray::Axis(0))
+    }
+    /// Copy the grid with one of the ndarray axes reversed
+    fn inverted(&self, axis: ndarray::Axis) -> Self {
+        let mut data = self.0.clone();
+        data.invert_axis(axis);
+        Self(data.as_standard_layout().into_owned())
+    }
+}
+
 impl<'a, T> IntoIterator for &'a Grid<T> {
     type Item = &'a T;
     type IntoIter = std::slice::Iter<'a, T>;
@@ -187,6 +232,93 @@ mod tests {
         assert!(iter.next().is_none());
     }
 
+    /// A grid where every value is unique, so any reordering shows up
+    fn get_numbered_grid() -> Grid<usize> {
+        Grid::new_from_vec(3, 4, (0..12).collect())
+    }
+
+    #[test]
+    fn test_transpose() {
+        let grid = get_numbered_grid();
+        let transposed = grid.transpose();
+        assert_eq!(transposed.get_width(), grid.get_height());
+        assert_eq!(transposed.get_height(), grid.get_width());
+        for j in 0..grid.get_height() {
+            for k in 0..grid.get_width() {
+                assert_eq!(
+                    transposed.get(JkVector { j: k, k: j }),
+                    grid.get(JkVector { j, k })
+                );
+            }
+        }
+        assert_eq!(
+            grid.transpose().transpose().get_data_slice(),
+            grid.get_data_slice()
+        );
+    }
+
+    #[test]
+    fn test_flips() {
+        let grid = get_numbered_grid();
+        let horizontal = grid.flip_horizontal();
+        let vertical = grid.flip_vertical();
+        assert_eq!(
+            horizontal.get(JkVector { j: 1, k: 0 }),
+            grid.get(JkVector { j: 1, k: 2 })
+        );
+        assert_eq!(
+            vertical.get(JkVector { j: 0, k: 1 }),
+            grid.get(JkVector { j: 3, k: 1 })
+        );
+        assert_eq!(
+            horizontal.flip_horizontal().get_data_slice(),
+            grid.get_data_slice()
+        );
+        assert_eq!(
+            vertical.flip_vertical().get_data_slice(),
+            grid.get_data_slice()
+        );
+    }
+
+    #[test]
+    fn test_row_major() {
+        let grid = get_numbered_grid();
+        assert_eq!(grid.get_width(), 3);
+        assert_eq!(grid.get_height(), 4);
+        assert_eq!(grid.row_stride(), grid.get_width());
+        for j in 0..grid.get_height() {
+            for k in 0..grid.get_width() {
+                let idx = JkVector { j, k };
+                assert_eq!(*grid.get(idx), j * grid.get_width() + k);
+                assert_eq!(
+                    grid.get_data_slice()[j * grid.get_width() + k],
+                    *grid.get(idx)
+                );
+                assert_eq!(grid.get_data()[[j, k]], *grid.get(idx));
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_mut_and_replace_are_row_major() {
+        let mut grid = get_numbered_grid();
+        *grid.get_mut(JkVector { j: 1, k: 2 }) = 100;
+        assert_eq!(grid.replace(JkVector { j: 3, k: 0 }, 200), 9);
+        assert_eq!(grid.get_data_slice()[5], 100);
+        assert_eq!(grid.get_data_slice()[9], 200);
+    }
+
+    #[test]
+    fn test_flat_index_matches_get() {
+        let grid = get_numbered_grid();
+        for j in 0..grid.get_height() {
+            for k in 0..grid.get_width() {
+                let idx = JkVector { j, k };
+                assert_eq!(grid.get_data_slice()[grid.flat_index(idx)], *grid.get(idx));
+            }
+        }
+    }
+
     #[test]
     fn test_iter_mut() {
         let mut grid = Grid::new_from_vec(2, 3, vec![1, 2, 3, 4, 5, 6]);
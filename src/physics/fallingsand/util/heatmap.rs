@@ -0,0 +1,152 @@
+//! Color gradients for drawing scalar fields, like temperature, over the falling sand
+
+use bevy::{ecs::system::Resource, render::color::Color};
+
+use crate::physics::orbits::components::ThermodynamicTemperature;
+
+/// Below this nothing glows visibly, see [black_body_color]
+pub const DRAPER_POINT: ThermodynamicTemperature = ThermodynamicTemperature(798.0);
+
+/// The color a black body glows at a temperature, going from a dull red through orange and white
+/// to blue. Follows Tanner Helland's fit of the black body curve, which is good from 1000K to 40000K
+/// and clamped outside of that. Anything colder than the [DRAPER_POINT] doesn't glow, and is black
+pub fn black_body_color(temperature: ThermodynamicTemperature) -> Color {
+    if temperature.0 < DRAPER_POINT.0 {
+        return Color::BLACK;
+    }
+    let t = temperature.0.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (t - 60.0).powf(-0.13320476)
+    };
+    let green = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12216 * (t - 60.0).powf(-0.07551485)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    Color::rgb(
+        red.clamp(0.0, 255.0) / 255.0,
+        green.clamp(0.0, 255.0) / 255.0,
+        blue.clamp(0.0, 255.0) / 255.0,
+    )
+}
+
+/// A gradient of evenly spaced colors, from cold to hot
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct HeatGradient {
+    stops: Vec<Color>,
+}
+
+impl Default for HeatGradient {
+    fn default() -> Self {
+        Self::black_body()
+    }
+}
+
+impl HeatGradient {
+    /// Create a gradient from its color stops, the first is the coldest and the last the hottest
+    pub fn new(stops: Vec<Color>) -> Self {
+        assert!(stops.len() >= 2, "A gradient needs at least two colors");
+        Self { stops }
+    }
+
+    /// Roughly the colors a black body glows as it heats up
+    pub fn black_body() -> Self {
+        Self::new(vec![
+            Color::BLACK,
+            Color::rgb(0.5, 0.0, 0.0),
+            Color::RED,
+            Color::ORANGE,
+            Color::YELLOW,
+            Color::WHITE,
+        ])
+    }
+
+    /// The color at the cold end of the gradient
+    pub fn cold(&self) -> Color {
+        self.stops[0]
+    }
+
+    /// The color at the hot end of the gradient
+    pub fn hot(&self) -> Color {
+        self.stops[self.stops.len() - 1]
+    }
+
+    /// Sample the gradient, where 0 is the cold end and 1 is the hot end
+    /// Values outside of that are clamped
+    pub fn sample(&self, fraction: f32) -> Color {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let scaled = fraction * (self.stops.len() - 1) as f32;
+        let idx = (scaled.floor() as usize).min(self.stops.len() - 2);
+        let t = scaled - idx as f32;
+        let [r0, g0, b0, a0] = self.stops[idx].as_rgba_f32();
+        let [r1, g1, b1, a1] = self.stops[idx + 1].as_rgba_f32();
+        Color::rgba(
+            r0 + (r1 - r0) * t,
+            g0 + (g1 - g0) * t,
+            b0 + (b1 - b0) * t,
+            a0 + (a1 - a0) * t,
+        )
+    }
+
+    /// The color of a temperature, given the range of temperatures being drawn
+    /// A flat field has nothing to contrast, so it is drawn cold
+    pub fn color_for(
+        &self,
+        temperature: ThermodynamicTemperature,
+        min: ThermodynamicTemperature,
+        max: ThermodynamicTemperature,
+    ) -> Color {
+        let range = max.0 - min.0;
+        if range <= 0.0 {
+            return self.cold();
+        }
+        self.sample((temperature.0 - min.0) / range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_black_body_goes_red_to_blue() {
+        let red = black_body_color(ThermodynamicTemperature(1500.0)).as_rgba_f32();
+        assert_eq!(red[0], 1.0);
+        assert!(red[1] < 0.5 && red[2] == 0.0, "{:?}", red);
+        let white = black_body_color(ThermodynamicTemperature(6600.0)).as_rgba_f32();
+        assert!(white.iter().all(|&c| c > 0.95), "{:?}", white);
+        let blue = black_body_color(ThermodynamicTemperature(20000.0)).as_rgba_f32();
+        assert!(blue[2] == 1.0 && blue[0] < blue[2], "{:?}", blue);
+        assert_eq!(
+            black_body_color(ThermodynamicTemperature(300.0)),
+            Color::BLACK
+        );
+    }
+
+    #[test]
+    fn test_color_for_ends_of_range() {
+        let gradient = HeatGradient::black_body();
+        let min = ThermodynamicTemperature(290.0);
+        let max = ThermodynamicTemperature(1500.0);
+        assert_eq!(gradient.color_for(max, min, max), gradient.hot());
+        assert_eq!(gradient.color_for(min, min, max), gradient.cold());
+        // Out of range clamps to the ends
+        assert_eq!(
+            gradient.color_for(ThermodynamicTemperature(0.0), min, max),
+            gradient.cold()
+        );
+        assert_eq!(
+            gradient.color_for(ThermodynamicTemperature(5800.0), min, max),
+            gradient.hot()
+        );
+    }
+}
@@ -48,17 +48,28 @@ impl GizmoDrawableLoop {
     /// This is useful for chunk outlines and for the brush
     /// This draw mode "loops" like you would for an enclosed shape
     pub fn draw_bevy_gizmo_loop(&self, gizmos: &mut Gizmos, transform: &Transform) {
+        self.draw_bevy_gizmo_loop_with_color(gizmos, transform, self.color);
+    }
+
+    /// Same as [Self::draw_bevy_gizmo_loop], but overrides the color of the loop
+    /// Useful for debug overlays that recolor an existing outline
+    pub fn draw_bevy_gizmo_loop_with_color(
+        &self,
+        gizmos: &mut Gizmos,
+        transform: &Transform,
+        color: Color,
+    ) {
         for idx in 0..(self.mesh.indices.len() - 1) {
             let idx0 = self.mesh.indices[idx] as usize;
             let idx1 = self.mesh.indices[idx + 1] as usize;
             self.mesh
-                .draw_bevy_gizmo_line(idx0, idx1, transform, gizmos, self.color);
+                .draw_bevy_gizmo_line(idx0, idx1, transform, gizmos, color);
         }
         // Now the final line to close the loop
         let idx0 = self.mesh.indices[self.mesh.indices.len() - 1] as usize;
         let idx1 = self.mesh.indices[0] as usize;
         self.mesh
-            .draw_bevy_gizmo_line(idx0, idx1, transform, gizmos, self.color);
+            .draw_bevy_gizmo_line(idx0, idx1, transform, gizmos, color);
     }
 }
 
@@ -127,6 +138,15 @@ impl OwnedMeshData {
         Self { vertices, indices }
     }
 
+    /// Add another mesh's vertices and triangles onto the end of this one
+    /// Its indices are shifted to keep pointing at its own vertices
+    pub fn extend(&mut self, other: OwnedMeshData) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.indices
+            .extend(other.indices.into_iter().map(|idx| idx + offset));
+    }
+
     /// Get the uv bounds of a list of vertices
     pub fn calc_bounds(&self) -> MeshBoundingBox {
         let width: f32 = self
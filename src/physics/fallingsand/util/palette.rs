@@ -0,0 +1,143 @@
+//! The colors elements are drawn in, so the sim can be reskinned at runtime
+
+use bevy::{ecs::system::Resource, render::color::Color};
+use hashbrown::HashMap;
+use strum::IntoEnumIterator;
+
+use crate::physics::fallingsand::elements::element::{Element, ElementType, StateOfMatter};
+
+/// Overrides for the color each kind of element is drawn in
+/// Kinds without an override are drawn in their own [Element::get_color], so the default palette
+/// is just the elements' colors
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct ElementPalette {
+    colors: HashMap<ElementType, Color>,
+}
+
+impl ElementPalette {
+    /// Draws `kind` in `color` from now on
+    pub fn set_color(&mut self, kind: ElementType, color: Color) {
+        self.colors.insert(kind, color);
+    }
+
+    /// The same palette, but with `kind` drawn in `color`
+    pub fn with_color(mut self, kind: ElementType, color: Color) -> Self {
+        self.set_color(kind, color);
+        self
+    }
+
+    /// The color to draw an element in
+    pub fn color_of(&self, element: &dyn Element) -> Color {
+        self.colors
+            .get(&element.get_type())
+            .copied()
+            .unwrap_or_else(|| element.get_color())
+    }
+
+    /// Which kind of element each color is drawn as, the reverse of [Self::color_of]
+    /// Keyed by the rgba bytes as they appear in a texture. When two kinds are drawn in the same color,
+    /// the one later in [ElementType] wins. Custom kinds are only in it once given a color with
+    /// [Self::set_color], since their own colors aren't known without making one, and they come last
+    pub fn kinds_by_color(&self) -> HashMap<[u8; 4], ElementType> {
+        let mut custom: Vec<(ElementType, Color)> = self
+            .colors
+            .iter()
+            .filter(|(kind, _)| matches!(kind, ElementType::Custom(_)))
+            .map(|(&kind, &color)| (kind, color))
+            .collect();
+        custom.sort_by_key(|(kind, _)| kind.id());
+        ElementType::iter()
+            .map(|kind| {
+                (
+                    self.color_of(kind.get_element().as_ref()).as_rgba_u8(),
+                    kind,
+                )
+            })
+            .chain(
+                custom
+                    .into_iter()
+                    .map(|(kind, color)| (color.as_rgba_u8(), kind)),
+            )
+            .collect()
+    }
+
+    /// White lines on blue paper, every element drawn by its state of matter alone
+    pub fn blueprint() -> Self {
+        let mut out = Self::default();
+        for kind in ElementType::iter() {
+            let color = match kind.get_element().get_state_of_matter() {
+                StateOfMatter::Empty => Color::rgb(0.1, 0.2, 0.5),
+                StateOfMatter::Gas => Color::rgb(0.4, 0.5, 0.8),
+                StateOfMatter::Liquid => Color::rgb(0.7, 0.8, 1.0),
+                StateOfMatter::Solid => Color::WHITE,
+            };
+            out.set_color(kind, color);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::fallingsand::data::element_directory::ElementGridDir;
+    use crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder;
+    use crate::physics::fallingsand::util::image::RawImage;
+    use crate::physics::fallingsand::util::vectors::IjkVector;
+    use crate::physics::orbits::components::Length;
+    use crate::physics::util::clock::Clock;
+
+    /// The color of a cell in a chunk texture, see [ElementGrid::get_texture](crate::physics::fallingsand::data::element_grid::ElementGrid::get_texture)
+    fn pixel(element_grid_dir: &ElementGridDir, texture: &RawImage, cell: IjkVector) -> [u8; 4] {
+        let coords = element_grid_dir.get_coordinate_dir();
+        let (chunk, pos) = coords.cell_idx_to_chunk_idx(cell);
+        let width = coords.get_chunk_num_radial_lines(chunk);
+        let idx = (pos.j * width + pos.k) * 4;
+        texture.pixels[idx..idx + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_changing_the_palette_recolors_only_its_kinds() {
+        let coordinate_dir = CoordinateDirBuilder::new()
+            .cell_radius(Length(1.0))
+            .num_layers(5)
+            .first_num_radial_lines(6)
+            .second_num_concentric_circles(3)
+            .max_concentric_circles_per_chunk(64)
+            .max_radial_lines_per_chunk(64)
+            .build();
+        let mut element_grid_dir = ElementGridDir::new_empty(coordinate_dir);
+        let stone = IjkVector::new(3, 1, 4);
+        let sand = IjkVector::new(3, 1, 5);
+        element_grid_dir.set_element(stone, ElementType::Stone.get_element(), Clock::default());
+        element_grid_dir.set_element(sand, ElementType::Sand.get_element(), Clock::default());
+        let chunk = element_grid_dir
+            .get_coordinate_dir()
+            .cell_idx_to_chunk_idx(stone)
+            .0;
+        let sand_color = ElementType::Sand.get_element().get_color().as_rgba_u8();
+
+        let before = element_grid_dir.get_dirty_textures();
+        let before = &before.iter().find(|(coord, _)| *coord == chunk).unwrap().1;
+        assert_eq!(
+            pixel(&element_grid_dir, before, stone),
+            ElementType::Stone.get_element().get_color().as_rgba_u8()
+        );
+
+        element_grid_dir
+            .set_palette(ElementPalette::default().with_color(ElementType::Stone, Color::RED));
+        let after = element_grid_dir.get_dirty_textures();
+        assert_eq!(after.len(), element_grid_dir.get_num_chunks());
+        let after = &after.iter().find(|(coord, _)| *coord == chunk).unwrap().1;
+        assert_eq!(
+            pixel(&element_grid_dir, after, stone),
+            Color::RED.as_rgba_u8()
+        );
+        assert_eq!(pixel(&element_grid_dir, after, sand), sand_color);
+
+        // Setting the same palette again has nothing to redraw
+        element_grid_dir
+            .set_palette(ElementPalette::default().with_color(ElementType::Stone, Color::RED));
+        assert!(element_grid_dir.get_dirty_textures().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Drawing the pressure field with iso-contour lines, see
+//! [ElementGridDir::pressure_field](crate::physics::fallingsand::data::element_directory::ElementGridDir::pressure_field)
+
+use bevy::{ecs::system::Resource, render::color::Color};
+use ndarray::Array2;
+
+use super::heatmap::HeatGradient;
+
+/// How the pressure overlay is drawn
+/// Cells are colored along the gradient from no pressure up to the highest pressure in the directory,
+/// and a line is drawn every `contour_interval` of pressure
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct PressureOverlay {
+    /// From no pressure to the most
+    pub gradient: HeatGradient,
+    /// The pressure between two contour lines, 0 or less draws none
+    pub contour_interval: f32,
+    /// What the contour lines are drawn in
+    pub contour_color: Color,
+}
+
+impl Default for PressureOverlay {
+    fn default() -> Self {
+        Self {
+            gradient: HeatGradient::new(vec![
+                Color::rgb(0.0, 0.0, 0.3),
+                Color::BLUE,
+                Color::CYAN,
+                Color::WHITE,
+            ]),
+            contour_interval: 1.0e4,
+            contour_color: Color::BLACK,
+        }
+    }
+}
+
+impl PressureOverlay {
+    /// The color of a pressure, given the highest pressure being drawn
+    pub fn color_for(&self, pressure: f32, max: f32) -> Color {
+        if max <= 0.0 {
+            return self.gradient.cold();
+        }
+        self.gradient.sample(pressure / max)
+    }
+
+    /// Which band between contour lines a pressure falls in, the first one starts at 0
+    pub fn contour_band(&self, pressure: f32) -> i64 {
+        (pressure / self.contour_interval).floor() as i64
+    }
+
+    /// Marks every cell of a layer that a contour line runs through
+    /// That is any cell with a neighbor in a lower band, along its ring or in the ring above it,
+    /// so each line is one cell thick and sits on its high pressure side
+    /// `field` is indexed `[j, k]` and its rings wrap all the way around
+    pub fn contour_cells(&self, field: &Array2<f32>) -> Array2<bool> {
+        let (num_concentric_circles, num_radial_lines) = field.dim();
+        let mut out = Array2::from_elem(field.dim(), false);
+        if self.contour_interval <= 0.0 {
+            return out;
+        }
+        for ((j, k), &pressure) in field.indexed_iter() {
+            let band = self.contour_band(pressure);
+            let left = field[[j, (k + 1) % num_radial_lines]];
+            let right = field[[j, (k + num_radial_lines - 1) % num_radial_lines]];
+            let above = (j + 1 < num_concentric_circles).then(|| field[[j + 1, k]]);
+            out[[j, k]] = [Some(left), Some(right), above]
+                .into_iter()
+                .flatten()
+                .any(|neighbor| self.contour_band(neighbor) < band);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contours_mark_the_high_side_of_each_crossing() {
+        let overlay = PressureOverlay {
+            contour_interval: 10.0,
+            ..Default::default()
+        };
+        // Pressure growing with depth, so the rings at j 2 and 4 sit just past a contour
+        let field = Array2::from_shape_fn((8, 6), |(j, _)| (7 - j) as f32 * 4.0);
+        let contours = overlay.contour_cells(&field);
+        for ((j, k), &marked) in contours.indexed_iter() {
+            assert_eq!(marked, j == 2 || j == 4, "j {} k {}", j, k);
+        }
+        // A bump along a ring is outlined from above and both sides
+        let mut field = Array2::zeros((3, 6));
+        field[[1, 3]] = 25.0;
+        let contours = overlay.contour_cells(&field);
+        assert!(contours[[1, 3]]);
+        assert_eq!(contours.iter().filter(|&&marked| marked).count(), 1);
+
+        assert!(!PressureOverlay {
+            contour_interval: 0.0,
+            ..Default::default()
+        }
+        .contour_cells(&field)
+        .iter()
+        .any(|&marked| marked));
+    }
+}
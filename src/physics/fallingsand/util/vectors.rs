@@ -1,17 +1,29 @@
 //! A collection of coordinate types and their conversions
 //! Mostly for the [ChunkCoords] [crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir]
+//!
+//! # Winding
+//! There is one winding everywhere: k starts at 3 o'clock and by default increases **clockwise**.
+//! The mesh vertexes, the textures drawn onto them, and the cell under a world position all agree on it.
+//! `atan2` measures angles counter clockwise, so anything going from a position to a k,
+//! like [crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDir::rel_pos_to_cell_idx],
+//! has to flip the angle, it is not the k that is inverted.
+//! A directory can be built the other way around with
+//! [CoordinateDirBuilder::winding](crate::physics::fallingsand::mesh::coordinate_directory::CoordinateDirBuilder::winding),
+//! so every angle goes through [Winding] rather than flipping it by hand.
+//! Within a chunk texture k runs across the rows and j runs down them, see [ChunkCoords::cell_to_texel].
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 
+use std::f32::consts::PI;
+
 use bevy::{math::Vec2, render::color::Color};
 
 use crate::physics::fallingsand::mesh::chunk_coords::ChunkCoords;
 use derive_more::{Add, AddAssign, Sub, SubAssign};
 
-/// A coordinate system for [ndarray]
-/// [ndarray] is row-major, so the jk vector is flipped
-/// Top left is (0, 0)
-/// ![ndarray coords](../../../../../assets/docs/wireframe/ndarray_coords.png)
+/// A coordinate system for [ndarray], as used by [Grid::get_data](super::grid::Grid::get_data)
+/// [ndarray] is row-major and the grid keeps one row per concentric circle,
+/// so x is k and y is j, and the index is `[y, x]`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NdArrayCoords([usize; 2]);
 
@@ -19,29 +31,30 @@ pub struct NdArrayCoords([usize; 2]);
 impl NdArrayCoords {
     /// Create a new [NdArrayCoords]
     pub fn new(x: usize, y: usize) -> Self {
-        Self([x, y])
+        Self([y, x])
     }
 }
 
 impl NdArrayCoords {
     /// Convert to a [JkVector]
-    /// ndarray is row-major, so the jk vector is flipped
-    /// Bottom Right is (0, 0)
+    /// The chunk coords are only used to check the bounds
     pub fn to_jk_vector(self, coords: &ChunkCoords) -> JkVector {
+        debug_assert!(self.get_y() < coords.get_num_concentric_circles());
+        debug_assert!(self.get_x() < coords.get_num_radial_lines());
         JkVector {
-            j: coords.get_num_concentric_circles() - 1 - self.get_y(),
-            k: coords.get_num_radial_lines() - 1 - self.get_x(),
+            j: self.get_y(),
+            k: self.get_x(),
         }
     }
 
     /// Get the column index
     pub fn get_x(&self) -> usize {
-        self.0[0]
+        self.0[1]
     }
 
     /// Get the row index
     pub fn get_y(&self) -> usize {
-        self.0[1]
+        self.0[0]
     }
 }
 
@@ -51,6 +64,70 @@ impl From<NdArrayCoords> for [usize; 2] {
     }
 }
 
+/// Which way k increases around a layer, starting from 3 o'clock
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Winding {
+    /// k increases clockwise, against the unit circle
+    #[default]
+    Clockwise,
+    /// k increases counter clockwise, along the unit circle
+    CounterClockwise,
+}
+
+impl Winding {
+    /// The angle of radial line `k` out of `num_radial_lines`, counter clockwise from 3 o'clock
+    /// Takes fractions of a radial line, so `k + 0.5` is the middle of cell k
+    pub fn angle(self, k: f32, num_radial_lines: usize) -> f32 {
+        let theta = 2.0 * PI / num_radial_lines as f32;
+        match self {
+            Winding::Clockwise => -k * theta,
+            Winding::CounterClockwise => k * theta,
+        }
+    }
+
+    /// The k of the cell an angle falls in, the inverse of [Self::angle]
+    /// The angle is counter clockwise from 3 o'clock, like `atan2`, and can be any number of turns
+    pub fn radial_line_at(self, angle: f32, num_radial_lines: usize) -> usize {
+        let turns = angle.rem_euclid(2.0 * PI) / (2.0 * PI);
+        let k_ccw = ((turns * num_radial_lines as f32) as usize).min(num_radial_lines - 1);
+        match self {
+            Winding::Clockwise => num_radial_lines - k_ccw - 1,
+            Winding::CounterClockwise => k_ccw,
+        }
+    }
+
+    /// Like [Self::radial_line_at], but how far around the layer the angle is in radial lines,
+    /// fractions and all, from 0 up to `num_radial_lines`
+    pub fn fractional_radial_line(self, angle: f32, num_radial_lines: usize) -> f32 {
+        let lines = angle / (2.0 * PI) * num_radial_lines as f32;
+        match self {
+            Winding::Clockwise => (-lines).rem_euclid(num_radial_lines as f32),
+            Winding::CounterClockwise => lines.rem_euclid(num_radial_lines as f32),
+        }
+    }
+
+    /// The k one cell further counter clockwise, wrapping around the layer
+    pub fn counter_clockwise_of(self, k: usize, num_radial_lines: usize) -> usize {
+        match self {
+            Winding::Clockwise => (k + num_radial_lines - 1) % num_radial_lines,
+            Winding::CounterClockwise => (k + 1) % num_radial_lines,
+        }
+    }
+
+    /// The k one cell further clockwise, wrapping around the layer
+    pub fn clockwise_of(self, k: usize, num_radial_lines: usize) -> usize {
+        self.reversed().counter_clockwise_of(k, num_radial_lines)
+    }
+
+    /// The other winding
+    pub fn reversed(self) -> Self {
+        match self {
+            Winding::Clockwise => Winding::CounterClockwise,
+            Winding::CounterClockwise => Winding::Clockwise,
+        }
+    }
+}
+
 /// Constants
 impl NdArrayCoords {
     /// The zero vector
@@ -59,14 +136,14 @@ impl NdArrayCoords {
 
 /// My personal coordinate type for the circular grids
 /// basically radius-theta coordinates, with integer radius and theta
-/// "counter clockwise" is positive just like in the unit circle
+/// "clockwise" is positive, unlike the unit circle, see the winding section of the [module docs](self)
 ///
 /// ![jk vector](../../../../../assets/docs/wireframe/jk_coords.png)
 ///
 /// j is the "concentric circle" or "radial" axis, kinda like y,
 ///   towards the core is 0
 /// k is the "tangential" axis, kinda like x,
-///   positive is counter clockwise from unit circle 0 degrees which is starting from 3 o'clock east
+///   positive is clockwise from unit circle 0 degrees which is starting from 3 o'clock east
 ///
 /// Can also be used to describe a grid, like a chunk taken from the circle
 /// In this case j is the height and k is the width
@@ -77,21 +154,19 @@ impl NdArrayCoords {
 pub struct JkVector {
     /// The j coordinate, as in the radial dimension, towards the core is negative, away from the core is positive
     pub j: usize,
-    /// The k coordinate, as in the tangential dimension, positive is counter clockwise from unit circle 0 degrees which is starting from 3 o'clock east
+    /// The k coordinate, as in the tangential dimension, positive is clockwise from unit circle 0 degrees which is starting from 3 o'clock east, unless the [Winding] is reversed
     pub k: usize,
 }
 
 /// To [NdArrayCoords]
-/// ndarray is row-major, so the jk vector is flipped
-/// Top left is (0, 0)
-/// Whereas in a Jk Vector, the bottom right is (0, 0)
+/// Both put (0, 0) at the first radial line of the innermost concentric circle
 impl JkVector {
     /// Convert to a [NdArrayCoords]
+    /// The chunk coords are only used to check the bounds
     pub fn to_ndarray_coords(self, coords: &ChunkCoords) -> NdArrayCoords {
-        NdArrayCoords::new(
-            coords.get_num_radial_lines() - 1 - self.k,
-            coords.get_num_concentric_circles() - 1 - self.j,
-        )
+        debug_assert!(self.j < coords.get_num_concentric_circles());
+        debug_assert!(self.k < coords.get_num_radial_lines());
+        NdArrayCoords::new(self.k, self.j)
     }
 }
 
@@ -116,7 +191,7 @@ impl JkVector {
 pub struct RelJkVector {
     /// The relative j coordinate, as in the radial dimension, towards the core is negative, away from the core is positive
     pub rj: isize,
-    /// The relative k coordinate, as in the tangential dimension, positive is counter clockwise from unit circle 0 degrees which is starting from 3 o'clock east
+    /// The relative k coordinate, as in the tangential dimension, positive is clockwise from unit circle 0 degrees which is starting from 3 o'clock east, unless the [Winding] is reversed
     pub rk: isize,
 }
 
@@ -135,7 +210,7 @@ impl RelJkVector {
 pub struct TempJkVector {
     /// The j coordinate, as in the radial dimension, towards the core is negative, away from the core is positive
     pub j: isize,
-    /// The k coordinate, as in the tangential dimension, positive is counter clockwise from unit circle 0 degrees which is starting from 3 o'clock east
+    /// The k coordinate, as in the tangential dimension, positive is clockwise from unit circle 0 degrees which is starting from 3 o'clock east, unless the [Winding] is reversed
     pub k: isize,
 }
 
@@ -176,7 +251,7 @@ pub struct IjkVector {
     pub i: usize,
     /// The j coordinate, as in the radial dimension, towards the core is negative, away from the core is positive
     pub j: usize,
-    /// The k coordinate, as in the tangential dimension, positive is counter clockwise from unit circle 0 degrees which is starting from 3 o'clock east
+    /// The k coordinate, as in the tangential dimension, positive is clockwise from unit circle 0 degrees which is starting from 3 o'clock east, unless the [Winding] is reversed
     pub k: usize,
 }
 
@@ -219,7 +294,7 @@ pub struct ChunkIjkVector {
     pub i: usize,
     /// The j coordinate, as in the radial dimension, towards the core is negative, away from the core is positive
     pub j: usize,
-    /// The k coordinate, as in the tangential dimension, positive is counter clockwise from unit circle 0 degrees which is starting from 3 o'clock east
+    /// The k coordinate, as in the tangential dimension, positive is clockwise from unit circle 0 degrees which is starting from 3 o'clock east, unless the [Winding] is reversed
     pub k: usize,
 }
 
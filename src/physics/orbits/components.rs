@@ -18,6 +18,11 @@ pub struct Mass(pub f32);
 #[derive(Component, Debug, Clone, Copy, Add, Sub, AddAssign, SubAssign)]
 pub struct Velocity(pub Vec2);
 
+/// How far the center of mass of an entity is from its origin, in its own unrotated frame
+/// Gravity pulls towards and from the center of mass, see [gravitational_position](super::nbody::gravitational_position)
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq)]
+pub struct CenterOfMassOffset(pub Vec2);
+
 /// The force applied to an entity with its direction $\vec{N}$
 #[derive(Component, Debug, Clone, Copy)]
 pub struct ForceVec(pub Vec2);
@@ -39,8 +44,12 @@ impl Force {
     }
 }
 
+/// The spin of an entity in radians per second, positive is counter clockwise.
+#[derive(Component, Default, Debug, Clone, Copy, Add, Sub, AddAssign, SubAssign)]
+pub struct AngularVelocity(pub f32);
+
 /// A length in meters.
-#[derive(Component, Debug, Clone, Copy, Add, Sub, AddAssign, SubAssign, Sum)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Add, Sub, AddAssign, SubAssign, Sum)]
 pub struct Length(pub f32);
 
 impl Default for Length {
@@ -66,6 +75,10 @@ impl Default for Area {
     }
 }
 
+/// A temperature in Kelvin.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, PartialOrd, Add, Sub)]
+pub struct ThermodynamicTemperature(pub f32);
+
 /// The acceleration due to gravity
 #[derive(Component, Debug, Clone, Copy)]
 pub struct GravitationalAcceleration(pub f32);
@@ -76,3 +89,7 @@ impl GravitationalAcceleration {
         GravitationalAcceleration(G * total_mass.0)
     }
 }
+
+/// The acceleration due to gravity felt at the surface of a celestial, at its equator
+#[derive(Component, Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct SurfaceGravity(pub f32);
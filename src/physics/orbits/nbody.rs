@@ -52,13 +52,11 @@ use bevy::{
         system::{Query, Res},
     },
     math::{Vec2, Vec3Swizzles},
-    time::{Fixed, Time},
+    time::Time,
     transform::components::Transform,
 };
 
-use crate::physics::PHYSICS_FRAME_RATE;
-
-use super::components::{ForceVec, GravitationalField, Mass, Velocity};
+use super::components::{CenterOfMassOffset, ForceVec, GravitationalField, Mass, Velocity};
 
 /// It's important that we don't compute the gravitational force between two bodies that are too
 /// close together, because the force will be very large and the simulation will be unstable.
@@ -74,6 +72,20 @@ pub const MIN_DISTANCE_SQUARED: f32 = 100.0;
 /// act at the scale of gravity we want.
 pub const G: f32 = 1.0e3;
 
+/// Where gravity acts from for an entity, its center of mass
+/// The offset is turned with the entity, so a lopsided planet's pull swings around as it spins
+/// Entities without an offset pull from their origin
+pub fn gravitational_position(
+    transform: &Transform,
+    offset: Option<&CenterOfMassOffset>,
+) -> Transform {
+    match offset {
+        Some(offset) => transform
+            .with_translation(transform.translation + transform.rotation * offset.0.extend(0.0)),
+        None => *transform,
+    }
+}
+
 /// Returns the gravitational force between two entities
 fn compute_gravitational_force(
     pos1: &Transform,
@@ -142,7 +154,6 @@ impl Plugin for NBodyPlugin {
                 Self::no_grav_bodies_system.after(Self::grav_bodies_system),
             ),
         );
-        app.insert_resource(Time::<Fixed>::from_seconds(1.0 / PHYSICS_FRAME_RATE));
     }
 }
 
@@ -150,9 +161,16 @@ impl Plugin for NBodyPlugin {
 impl NBodyPlugin {
     /// Updates the locations and velocities of the entities with gravitational fields
     /// based on each other entity with a gravitational field
+    #[allow(clippy::type_complexity)]
     fn grav_bodies_system(
         mut grav_bodies: Query<
-            (Entity, &mut Transform, &mut Velocity, &Mass),
+            (
+                Entity,
+                &mut Transform,
+                &mut Velocity,
+                &Mass,
+                Option<&CenterOfMassOffset>,
+            ),
             With<GravitationalField>,
         >,
         time: Res<Time>,
@@ -160,34 +178,58 @@ impl NBodyPlugin {
         let dt = time.delta_seconds();
         let grav_bodies_copy = grav_bodies
             .iter()
-            .map(|(entity, transform, velocity, mass)| (entity, *transform, *velocity, *mass))
+            .map(|(entity, transform, velocity, mass, offset)| {
+                (
+                    entity,
+                    gravitational_position(transform, offset),
+                    *velocity,
+                    *mass,
+                )
+            })
             .collect::<Vec<_>>();
-        grav_bodies
-            .par_iter_mut()
-            .for_each(|(entity, mut transform, mut velocity, mass)| {
+        grav_bodies.par_iter_mut().for_each(
+            |(entity, mut transform, mut velocity, mass, offset)| {
                 half_step_velocity_update(
-                    (entity, &transform, &mut velocity, mass),
+                    (
+                        entity,
+                        &gravitational_position(&transform, offset),
+                        &mut velocity,
+                        mass,
+                    ),
                     &grav_bodies_copy,
                     dt,
                 );
                 full_position_update((entity, &mut transform, &velocity, mass), dt);
                 half_step_velocity_update(
-                    (entity, &transform, &mut velocity, mass),
+                    (
+                        entity,
+                        &gravitational_position(&transform, offset),
+                        &mut velocity,
+                        mass,
+                    ),
                     &grav_bodies_copy,
                     dt,
                 );
-            });
+            },
+        );
     }
 
     /// Updates the locations and velocities of the entities without gravitational fields
     /// based on the entities with gravitational fields
+    #[allow(clippy::type_complexity)]
     fn no_grav_bodies_system(
         mut no_grav_bodies: Query<
             (Entity, &mut Transform, &mut Velocity, &Mass),
             Without<GravitationalField>,
         >,
         grav_bodies: Query<
-            (Entity, &mut Transform, &mut Velocity, &Mass),
+            (
+                Entity,
+                &mut Transform,
+                &mut Velocity,
+                &Mass,
+                Option<&CenterOfMassOffset>,
+            ),
             With<GravitationalField>,
         >,
         time: Res<Time>,
@@ -195,7 +237,14 @@ impl NBodyPlugin {
         let dt = time.delta_seconds();
         let grav_bodies_copy = grav_bodies
             .iter()
-            .map(|(entity, transform, velocity, mass)| (entity, *transform, *velocity, *mass))
+            .map(|(entity, transform, velocity, mass, offset)| {
+                (
+                    entity,
+                    gravitational_position(transform, offset),
+                    *velocity,
+                    *mass,
+                )
+            })
             .collect::<Vec<_>>();
         no_grav_bodies
             .par_iter_mut()
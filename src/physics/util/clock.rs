@@ -36,6 +36,19 @@ impl Clock {
     pub fn get_current_frame(&self) -> u32 {
         self.frame.0
     }
+    /// The same moment and frame, but as if the last step had taken `factor` times as long
+    /// For anything updated only every few frames, which has to make up for the ones it sat out
+    pub fn with_delta_scaled(&self, factor: u32) -> Self {
+        // Early on the scaled step can be longer than all the time so far, it only covers what has passed
+        let delta = (self.time.delta() * factor).min(self.time.elapsed());
+        let mut time = Time::default();
+        time.advance_by(self.time.elapsed() - delta);
+        time.advance_by(delta);
+        Self {
+            time,
+            frame: self.frame,
+        }
+    }
     /// Advances the clock by the given delta and one frame.
     /// Mostly used for testing
     pub fn update(&mut self, delta: Duration) {